@@ -0,0 +1,140 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use vector::{
+    event::{LogEvent, Value},
+    sinks::{
+        infino::{
+            BulkAction, DocumentMetadata, InfinoEncoder, InfinoRequestBuilder, ProcessedEvent,
+        },
+        util::{Compression, RequestBuilder},
+    },
+};
+
+const BATCH_SIZE: usize = 1_000;
+
+fn small_log(i: usize) -> LogEvent {
+    let mut log = LogEvent::from(format!("request {} completed", i));
+    log.insert("status", 200);
+    log
+}
+
+fn wide_log(i: usize) -> LogEvent {
+    let mut log = LogEvent::from(format!("request {} completed", i));
+    for field in 0..50 {
+        log.insert(
+            format!("field_{}", field).as_str(),
+            format!("value-{}-{}", i, field),
+        );
+    }
+    log
+}
+
+fn nested_log(i: usize) -> LogEvent {
+    let mut log = LogEvent::from(format!("request {} completed", i));
+    log.insert("http.request.method", "GET");
+    log.insert("http.request.path", format!("/api/v1/items/{}", i));
+    log.insert("http.response.status", 200);
+    log.insert("http.response.bytes", 1024 * i as i64);
+    log.insert(
+        "tags",
+        Value::Array(vec!["alpha".into(), "beta".into(), "gamma".into()]),
+    );
+    log
+}
+
+fn batch(shape: fn(usize) -> LogEvent) -> Vec<ProcessedEvent> {
+    (0..BATCH_SIZE)
+        .map(|i| ProcessedEvent {
+            index: "vector".to_owned(),
+            bulk_action: BulkAction::Index,
+            log: shape(i),
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+        })
+        .collect()
+}
+
+fn shapes() -> [(&'static str, fn(usize) -> LogEvent); 3] {
+    [
+        ("small", small_log),
+        ("wide", wide_log),
+        ("nested", nested_log),
+    ]
+}
+
+fn request_builder(compression: Compression) -> InfinoRequestBuilder {
+    InfinoRequestBuilder {
+        compression,
+        encoder: InfinoEncoder {
+            doc_type: "_doc".to_owned(),
+            suppress_type_name: true,
+            ..Default::default()
+        },
+        ordered: false,
+    }
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("infino/encode");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    let builder = request_builder(Compression::None);
+    for (name, shape) in shapes() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched(
+                || batch(shape),
+                |events| builder.encode_events(events).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+fn bench_compression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("infino/compression");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    for compression in [
+        Compression::None,
+        Compression::gzip_default(),
+        Compression::zlib_default(),
+        Compression::zstd_default(),
+        Compression::Snappy,
+    ] {
+        let builder = request_builder(compression);
+        group.bench_function(BenchmarkId::from_parameter(compression), |b| {
+            b.iter_batched(
+                || batch(nested_log),
+                |events| builder.encode_events(events).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+fn bench_batch_assembly(c: &mut Criterion) {
+    let mut group = c.benchmark_group("infino/batch_assembly");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    let builder = request_builder(Compression::None);
+    for (name, shape) in shapes() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched(
+                || batch(shape),
+                |events| {
+                    let (metadata, metadata_builder, events) = builder.split_input(events);
+                    let payload = builder.encode_events(events).unwrap();
+                    let request_metadata = metadata_builder.build(&payload);
+                    builder.build_request(metadata, request_metadata, payload)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().noise_threshold(0.05);
+    targets = bench_encode, bench_compression, bench_batch_assembly
+);
+criterion_main!(benches);
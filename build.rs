@@ -116,6 +116,7 @@ fn main() {
 
     #[cfg(feature = "protobuf-build")]
     {
+        println!("cargo:rerun-if-changed=proto/infino/ingest.proto");
         println!("cargo:rerun-if-changed=proto/third-party/dnstap.proto");
         println!("cargo:rerun-if-changed=proto/third-party/google/pubsub/v1/pubsub.proto");
         println!("cargo:rerun-if-changed=proto/third-party/google/rpc/status.proto");
@@ -136,6 +137,7 @@ fn main() {
         let mut prost_build = prost_build::Config::new();
         prost_build
             .btree_map(["."])
+            .bytes([".infino"])
             .file_descriptor_set_path(protobuf_fds_path);
 
         tonic_build::configure()
@@ -151,8 +153,10 @@ fn main() {
                     "proto/third-party/google/pubsub/v1/pubsub.proto",
                     "proto/third-party/google/rpc/status.proto",
                     "proto/vector/vector.proto",
+                    "proto/infino/ingest.proto",
                 ],
                 &[
+                    "proto/infino",
                     "proto/third-party",
                     "proto/vector",
                     "lib/vector-core/proto/",
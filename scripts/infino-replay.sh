@@ -0,0 +1,50 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# infino-replay.sh
+#
+# SUMMARY
+#
+#   Re-sends requests recorded by the `infino` sink's `record` option to an Infino
+#   endpoint, in the order they were recorded, to the path and query string they
+#   were recorded with. Any extra arguments are passed to curl, for example to
+#   supply credentials:
+#
+#     scripts/infino-replay.sh /var/lib/vector/infino-recording https://test:9200 -u user:pass
+#
+#   Requires curl and jq.
+
+if [ $# -lt 2 ]; then
+  echo "usage: $0 <recording-dir> <endpoint> [curl args...]" >&2
+  exit 1
+fi
+
+RECORDING_DIR="$1"
+ENDPOINT="${2%/}"
+shift 2
+
+shopt -s nullglob
+METADATA_FILES=("${RECORDING_DIR}"/*.json)
+shopt -u nullglob
+
+if [ ${#METADATA_FILES[@]} -eq 0 ]; then
+  echo "No recorded requests found in ${RECORDING_DIR}" >&2
+  exit 1
+fi
+
+for METADATA in "${METADATA_FILES[@]}"; do
+  PAYLOAD="${METADATA%.json}.bulk"
+  # Recordings made before the path and Content-Type were recorded only hold bulk requests.
+  REQUEST_PATH=$(jq -r '.path // "/_bulk"' "${METADATA}")
+  CONTENT_TYPE=$(jq -r '.content_type // "application/x-ndjson"' "${METADATA}")
+  HEADERS=(-H "Content-Type: ${CONTENT_TYPE}")
+
+  CONTENT_ENCODING=$(jq -r '.content_encoding // empty' "${METADATA}")
+  if [ -n "${CONTENT_ENCODING}" ]; then
+    HEADERS+=(-H "Content-Encoding: ${CONTENT_ENCODING}")
+  fi
+
+  STATUS=$(curl -sS -o /dev/null -w '%{http_code}' -X POST "${HEADERS[@]}" \
+    --data-binary @"${PAYLOAD}" "$@" "${ENDPOINT}${REQUEST_PATH}")
+  echo "$(basename "${PAYLOAD}"): recorded $(jq -r '.status // "error"' "${METADATA}"), replayed ${STATUS}"
+done
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use async_graphql::Object;
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{
+    api::schema::metrics::{self, MetricsFilter},
+    event::{Metric, MetricValue},
+};
+
+#[derive(Clone)]
+pub struct InfinoSinkMetricIndex<'a> {
+    name: String,
+    metrics: Vec<&'a Metric>,
+}
+
+impl<'a> InfinoSinkMetricIndex<'a> {
+    /// Returns a new InfinoSinkMetricIndex from a (name, Vec<&Metric>) tuple
+    #[allow(clippy::missing_const_for_fn)] // const cannot run destructor
+    fn from_tuple((name, metrics): (String, Vec<&'a Metric>)) -> Self {
+        Self { name, metrics }
+    }
+
+    /// Returns the sum of the values of the metrics named `name`.
+    fn value(&self, name: &str) -> f64 {
+        self.metrics
+            .iter()
+            .filter(|m| m.name() == name)
+            .map(|m| match m.value() {
+                MetricValue::Counter { value } | MetricValue::Gauge { value } => *value,
+                _ => 0.00,
+            })
+            .sum()
+    }
+
+    fn get_rejection_rate(&self) -> Option<f64> {
+        let flushed = self.value("infino_index_events_flushed_total");
+        (flushed > 0.00).then(|| self.value("infino_index_events_rejected_total") / flushed)
+    }
+}
+
+#[Object]
+impl<'a> InfinoSinkMetricIndex<'a> {
+    /// Index name, or `_other` for the indexes beyond the first 1000 written to
+    async fn name(&self) -> &str {
+        &*self.name
+    }
+
+    /// Events accepted for the current index, and not yet flushed
+    async fn pending_events(&self) -> f64 {
+        self.value("infino_index_pending_events")
+    }
+
+    /// Time the latest request to the current index completed
+    async fn last_flush_at(&self) -> Option<DateTime<Utc>> {
+        let seconds = self.value("infino_index_last_flush_timestamp_seconds");
+        (seconds > 0.00)
+            .then(|| Utc.timestamp_millis_opt((seconds * 1000.0) as i64).single())
+            .flatten()
+    }
+
+    /// Total events flushed to the current index
+    async fn flushed_events_total(&self) -> f64 {
+        self.value("infino_index_events_flushed_total")
+    }
+
+    /// Total events flushed to the current index that were not indexed
+    async fn rejected_events_total(&self) -> f64 {
+        self.value("infino_index_events_rejected_total")
+    }
+
+    /// Share of the flushed events of the current index that were not indexed
+    async fn rejection_rate(&self) -> Option<f64> {
+        self.get_rejection_rate()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InfinoSinkMetrics(Vec<Metric>);
+
+impl InfinoSinkMetrics {
+    pub fn new(metrics: Vec<Metric>) -> Self {
+        Self(metrics)
+    }
+
+    pub fn get_indexes(&self) -> Vec<InfinoSinkMetricIndex<'_>> {
+        self.0
+            .iter()
+            .filter_map(|m| m.tag_value("index").map(|index| (index, m)))
+            .fold(
+                BTreeMap::new(),
+                |mut map: BTreeMap<String, Vec<&Metric>>, (index, m)| {
+                    map.entry(index).or_default().push(m);
+                    map
+                },
+            )
+            .into_iter()
+            .map(InfinoSinkMetricIndex::from_tuple)
+            .collect()
+    }
+}
+
+#[Object]
+impl InfinoSinkMetrics {
+    /// Index metrics
+    pub async fn indexes(&self) -> Vec<InfinoSinkMetricIndex<'_>> {
+        self.get_indexes()
+    }
+
+    /// Total received events for the current sink
+    pub async fn received_events_total(&self) -> Option<metrics::ReceivedEventsTotal> {
+        self.0.received_events_total()
+    }
+
+    /// Total sent bytes for the current sink
+    pub async fn sent_bytes_total(&self) -> Option<metrics::SentBytesTotal> {
+        self.0.sent_bytes_total()
+    }
+
+    /// Total sent events for the current sink
+    pub async fn sent_events_total(&self) -> Option<metrics::SentEventsTotal> {
+        self.0.sent_events_total()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_lib::metric_tags;
+
+    use super::*;
+    use crate::event::MetricKind;
+
+    fn metric(name: &str, index: &str, value: MetricValue) -> Metric {
+        Metric::new(name, MetricKind::Absolute, value)
+            .with_tags(Some(metric_tags!("index" => index)))
+    }
+
+    #[test]
+    fn groups_metrics_by_index() {
+        let metrics = InfinoSinkMetrics::new(vec![
+            metric(
+                "infino_index_events_flushed_total",
+                "logs",
+                MetricValue::Counter { value: 40.0 },
+            ),
+            metric(
+                "infino_index_events_rejected_total",
+                "logs",
+                MetricValue::Counter { value: 10.0 },
+            ),
+            metric(
+                "infino_index_pending_events",
+                "metrics",
+                MetricValue::Gauge { value: 3.0 },
+            ),
+            Metric::new(
+                "component_sent_events_total",
+                MetricKind::Absolute,
+                MetricValue::Counter { value: 40.0 },
+            ),
+        ]);
+
+        let indexes = metrics.get_indexes();
+        assert_eq!(
+            indexes
+                .iter()
+                .map(|index| index.name.as_str())
+                .collect::<Vec<_>>(),
+            ["logs", "metrics"]
+        );
+        assert_eq!(indexes[0].get_rejection_rate(), Some(0.25));
+        assert_eq!(indexes[1].value("infino_index_pending_events"), 3.0);
+        assert_eq!(indexes[1].get_rejection_rate(), None);
+    }
+}
@@ -1,4 +1,5 @@
 mod generic;
+pub mod infino;
 
 use async_graphql::Interface;
 
@@ -13,6 +14,7 @@ use crate::event::Metric;
 )]
 pub enum SinkMetrics {
     GenericSinkMetrics(generic::GenericSinkMetrics),
+    InfinoSinkMetrics(infino::InfinoSinkMetrics),
 }
 
 pub trait IntoSinkMetrics {
@@ -20,7 +22,10 @@ pub trait IntoSinkMetrics {
 }
 
 impl IntoSinkMetrics for Vec<Metric> {
-    fn into_sink_metrics(self, _component_type: &str) -> SinkMetrics {
-        SinkMetrics::GenericSinkMetrics(generic::GenericSinkMetrics::new(self))
+    fn into_sink_metrics(self, component_type: &str) -> SinkMetrics {
+        match component_type {
+            "Infino" => SinkMetrics::InfinoSinkMetrics(infino::InfinoSinkMetrics::new(self)),
+            _ => SinkMetrics::GenericSinkMetrics(generic::GenericSinkMetrics::new(self)),
+        }
     }
 }
@@ -1,5 +1,5 @@
 //! Authentication settings for AWS components.
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use aws_config::{
     default_provider::credentials::DefaultCredentialsChain,
@@ -11,6 +11,7 @@ use aws_config::{
     },
     provider_config::ProviderConfig,
     sts::AssumeRoleProviderBuilder,
+    web_identity_token::{StaticConfiguration, WebIdentityTokenCredentialsProvider},
 };
 use aws_credential_types::{provider::SharedCredentialsProvider, Credentials};
 use aws_smithy_async::time::SystemTimeSource;
@@ -24,6 +25,7 @@ use vector_lib::{config::proxy::ProxyConfig, sensitive_string::SensitiveString,
 // default rather than relying on the SDK default to not change
 const DEFAULT_LOAD_TIMEOUT: Duration = Duration::from_secs(5);
 const DEFAULT_PROFILE_NAME: &str = "default";
+const DEFAULT_SESSION_NAME: &str = "vector";
 
 /// IMDS Client Configuration for authenticating with AWS.
 #[serde_as]
@@ -88,6 +90,13 @@ pub enum AwsAuthentication {
         #[configurable(metadata(docs::examples = "randomEXAMPLEidString"))]
         external_id: Option<String>,
 
+        /// The name of the session of the role to assume.
+        ///
+        /// Defaults to `vector`. Setting it tells the sessions of different deployments apart in
+        /// AWS CloudTrail.
+        #[configurable(metadata(docs::examples = "vector-infino"))]
+        session_name: Option<String>,
+
         /// The [AWS region][aws_region] to send STS requests to.
         ///
         /// If not set, this will default to the configured region
@@ -116,6 +125,66 @@ pub enum AwsAuthentication {
         profile: String,
     },
 
+    /// Authenticate with a web identity token, such as the token of an EKS service account used
+    /// for [IAM roles for service accounts][irsa].
+    ///
+    /// [irsa]: https://docs.aws.amazon.com/eks/latest/userguide/iam-roles-for-service-accounts.html
+    WebIdentity {
+        /// Path to the web identity token file.
+        ///
+        /// In EKS, this is the path set in the `AWS_WEB_IDENTITY_TOKEN_FILE` environment variable.
+        #[configurable(metadata(
+            docs::examples = "/var/run/secrets/eks.amazonaws.com/serviceaccount/token"
+        ))]
+        web_identity_token_file: PathBuf,
+
+        /// The ARN of the [IAM role][iam_role] the web identity token is exchanged for.
+        ///
+        /// In EKS, this is the ARN set in the `AWS_ROLE_ARN` environment variable.
+        ///
+        /// [iam_role]: https://docs.aws.amazon.com/IAM/latest/UserGuide/id_roles.html
+        #[configurable(metadata(docs::examples = "arn:aws:iam::123456789098:role/my_role"))]
+        role_arn: String,
+
+        /// The name of the role session.
+        #[serde(default = "default_session_name")]
+        #[configurable(metadata(docs::examples = "vector-infino"))]
+        session_name: String,
+
+        /// The ARN of an [IAM role][iam_role] to assume with the credentials of `role_arn`.
+        ///
+        /// [iam_role]: https://docs.aws.amazon.com/IAM/latest/UserGuide/id_roles.html
+        #[configurable(metadata(docs::examples = "arn:aws:iam::123456789098:role/my_role"))]
+        assume_role: Option<String>,
+
+        /// The optional unique external ID in conjunction with role to assume.
+        ///
+        /// [external_id]: https://docs.aws.amazon.com/IAM/latest/UserGuide/id_roles_create_for-user_externalid.html
+        #[configurable(metadata(docs::examples = "randomEXAMPLEidString"))]
+        external_id: Option<String>,
+
+        /// The name of the session of the role given by `assume_role`.
+        ///
+        /// Defaults to `vector`. It is kept apart from `session_name`, the session of `role_arn`.
+        #[configurable(metadata(docs::examples = "vector-infino"))]
+        assume_role_session_name: Option<String>,
+
+        /// Timeout for exchanging the token, and assuming the role, in seconds.
+        #[configurable(metadata(docs::type_unit = "seconds"))]
+        #[configurable(metadata(docs::examples = 30))]
+        #[configurable(metadata(docs::human_name = "Load Timeout"))]
+        load_timeout_secs: Option<u64>,
+
+        /// The [AWS region][aws_region] to send STS requests to.
+        ///
+        /// If not set, this defaults to the configured region
+        /// for the service itself.
+        ///
+        /// [aws_region]: https://docs.aws.amazon.com/general/latest/gr/rande.html#regional-endpoints
+        #[configurable(metadata(docs::examples = "us-west-2"))]
+        region: Option<String>,
+    },
+
     /// Assume the given role ARN.
     Role {
         /// The ARN of an [IAM role][iam_role] to assume.
@@ -130,6 +199,13 @@ pub enum AwsAuthentication {
         #[configurable(metadata(docs::examples = "randomEXAMPLEidString"))]
         external_id: Option<String>,
 
+        /// The name of the session of the role to assume.
+        ///
+        /// Defaults to `vector`. Setting it tells the sessions of different deployments apart in
+        /// AWS CloudTrail.
+        #[configurable(metadata(docs::examples = "vector-infino"))]
+        session_name: Option<String>,
+
         /// Timeout for assuming the role, in seconds.
         ///
         /// Relevant when the default credentials chain or `assume_role` is used.
@@ -182,6 +258,10 @@ fn default_profile() -> String {
     DEFAULT_PROFILE_NAME.to_string()
 }
 
+fn default_session_name() -> String {
+    DEFAULT_SESSION_NAME.to_string()
+}
+
 impl AwsAuthentication {
     /// Creates the identity cache to store credentials based on the authentication mechanism chosen.
     pub(super) async fn credentials_cache(&self) -> crate::Result<SharedIdentityCache> {
@@ -189,6 +269,9 @@ impl AwsAuthentication {
             AwsAuthentication::Role {
                 load_timeout_secs, ..
             }
+            | AwsAuthentication::WebIdentity {
+                load_timeout_secs, ..
+            }
             | AwsAuthentication::Default {
                 load_timeout_secs, ..
             } => {
@@ -214,6 +297,7 @@ impl AwsAuthentication {
         region: &Region,
         assume_role: &str,
         external_id: Option<&str>,
+        session_name: Option<&str>,
     ) -> crate::Result<AssumeRoleProviderBuilder> {
         let connector = super::connector(proxy, tls_options)?;
         let config = SdkConfig::builder()
@@ -230,6 +314,10 @@ impl AwsAuthentication {
             builder = builder.external_id(external_id)
         }
 
+        if let Some(session_name) = session_name {
+            builder = builder.session_name(session_name)
+        }
+
         Ok(builder)
     }
 
@@ -246,6 +334,7 @@ impl AwsAuthentication {
                 secret_access_key,
                 assume_role,
                 external_id,
+                session_name,
                 region,
             } => {
                 let provider = SharedCredentialsProvider::new(Credentials::from_keys(
@@ -261,6 +350,7 @@ impl AwsAuthentication {
                         &auth_region,
                         assume_role,
                         external_id.as_deref(),
+                        session_name.as_deref(),
                     )?;
 
                     let provider = builder.build_from_provider(provider).await;
@@ -290,9 +380,52 @@ impl AwsAuthentication {
                     .build();
                 Ok(SharedCredentialsProvider::new(profile_provider))
             }
+            AwsAuthentication::WebIdentity {
+                web_identity_token_file,
+                role_arn,
+                session_name,
+                assume_role,
+                external_id,
+                assume_role_session_name,
+                region,
+                ..
+            } => {
+                let auth_region = region.clone().map(Region::new).unwrap_or(service_region);
+                let connector = super::connector(proxy, tls_options)?;
+                let provider_config = ProviderConfig::empty()
+                    .with_region(Some(auth_region.clone()))
+                    .with_http_client(connector);
+
+                let provider = SharedCredentialsProvider::new(
+                    WebIdentityTokenCredentialsProvider::builder()
+                        .static_configuration(StaticConfiguration {
+                            web_identity_token_file: web_identity_token_file.clone(),
+                            role_arn: role_arn.clone(),
+                            session_name: session_name.clone(),
+                        })
+                        .configure(&provider_config)
+                        .build(),
+                );
+                if let Some(assume_role) = assume_role {
+                    let builder = Self::assume_role_provider_builder(
+                        proxy,
+                        tls_options,
+                        &auth_region,
+                        assume_role,
+                        external_id.as_deref(),
+                        assume_role_session_name.as_deref(),
+                    )?;
+
+                    let provider = builder.build_from_provider(provider).await;
+
+                    return Ok(SharedCredentialsProvider::new(provider));
+                }
+                Ok(provider)
+            }
             AwsAuthentication::Role {
                 assume_role,
                 external_id,
+                session_name,
                 imds,
                 region,
                 ..
@@ -304,6 +437,7 @@ impl AwsAuthentication {
                     &auth_region,
                     assume_role,
                     external_id.as_deref(),
+                    session_name.as_deref(),
                 )?;
 
                 let provider = builder
@@ -335,6 +469,7 @@ impl AwsAuthentication {
             secret_access_key: "dummy".to_string().into(),
             assume_role: None,
             external_id: None,
+            session_name: None,
             region: None,
         }
     }
@@ -457,6 +592,49 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parsing_web_identity() {
+        let config = toml::from_str::<ComponentConfig>(
+            r#"
+            auth.web_identity_token_file = "/var/run/secrets/eks.amazonaws.com/serviceaccount/token"
+            auth.role_arn = "arn:aws:iam::123456789098:role/irsa"
+            auth.assume_role = "arn:aws:iam::210987654321:role/ingest"
+            auth.external_id = "id"
+            auth.region = "us-west-2"
+        "#,
+        )
+        .unwrap();
+
+        match config.auth {
+            AwsAuthentication::WebIdentity {
+                web_identity_token_file,
+                role_arn,
+                session_name,
+                assume_role,
+                external_id,
+                assume_role_session_name,
+                load_timeout_secs,
+                region,
+            } => {
+                assert_eq!(
+                    web_identity_token_file,
+                    PathBuf::from("/var/run/secrets/eks.amazonaws.com/serviceaccount/token")
+                );
+                assert_eq!(role_arn, "arn:aws:iam::123456789098:role/irsa");
+                assert_eq!(session_name, "vector");
+                assert_eq!(
+                    assume_role.as_deref(),
+                    Some("arn:aws:iam::210987654321:role/ingest")
+                );
+                assert_eq!(external_id.as_deref(), Some("id"));
+                assert_eq!(assume_role_session_name, None);
+                assert_eq!(load_timeout_secs, None);
+                assert_eq!(region.as_deref(), Some("us-west-2"));
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn parsing_old_assume_role() {
         let config = toml::from_str::<ComponentConfig>(
@@ -512,12 +690,14 @@ mod tests {
             AwsAuthentication::Role {
                 assume_role,
                 external_id,
+                session_name,
                 load_timeout_secs,
                 imds,
                 region,
             } => {
                 assert_eq!(&assume_role, "root");
                 assert_eq!(external_id, None);
+                assert_eq!(session_name, None);
                 assert_eq!(load_timeout_secs, None);
                 assert!(matches!(
                     imds,
@@ -549,12 +729,14 @@ mod tests {
             AwsAuthentication::Role {
                 assume_role,
                 external_id,
+                session_name,
                 load_timeout_secs,
                 imds,
                 region,
             } => {
                 assert_eq!(&assume_role, "auth.root");
                 assert_eq!(external_id, None);
+                assert_eq!(session_name, None);
                 assert_eq!(load_timeout_secs, Some(10));
                 assert!(matches!(imds, ImdsAuthentication { .. }));
                 assert_eq!(region.unwrap(), "us-west-2");
@@ -563,6 +745,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parsing_assume_role_with_session_name() {
+        let config = toml::from_str::<ComponentConfig>(
+            r#"
+            auth.access_key_id = "key"
+            auth.secret_access_key = "other"
+            auth.assume_role = "root"
+            auth.session_name = "vector-infino"
+        "#,
+        )
+        .unwrap();
+
+        match config.auth {
+            AwsAuthentication::AccessKey { session_name, .. } => {
+                assert_eq!(session_name.as_deref(), Some("vector-infino"));
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn parsing_static() {
         let config = toml::from_str::<ComponentConfig>(
@@ -41,7 +41,7 @@ mod source;
 mod transform;
 pub mod unit_test;
 mod validation;
-mod vars;
+pub(crate) mod vars;
 pub mod watcher;
 
 pub use builder::ConfigBuilder;
@@ -50,6 +50,8 @@ pub use diff::ConfigDiff;
 pub use enrichment_table::{EnrichmentTableConfig, EnrichmentTableOuter};
 pub use format::{Format, FormatHint};
 pub use id::{ComponentKey, Inputs};
+#[cfg(test)]
+pub(crate) use loading::interpolate as interpolate_secrets;
 pub use loading::{
     load, load_builder_from_paths, load_from_paths, load_from_paths_with_provider_and_secrets,
     load_from_str, load_source_from_paths, merge_path_lists, process_paths, COLLECTOR,
@@ -19,6 +19,7 @@ use hyper::{
 };
 use hyper_openssl::HttpsConnector;
 use hyper_proxy::ProxyConnector;
+use openssl::ssl::SslConnectorBuilder;
 use rand::Rng;
 use serde_with::serde_as;
 use snafu::{ResultExt, Snafu};
@@ -98,6 +99,32 @@ where
         client_builder: &mut client::Builder,
     ) -> Result<HttpClient<B>, HttpError> {
         let proxy_connector = build_proxy_connector(tls_settings.into(), proxy_config)?;
+        Ok(HttpClient::new_with_proxy_connector(
+            proxy_connector,
+            client_builder,
+        ))
+    }
+
+    /// Creates a client whose TLS connectors are built by `tls_connector`, such as to apply
+    /// settings of a component on top of `tls_settings`.
+    pub fn new_with_tls_connector(
+        tls_settings: impl Into<MaybeTlsSettings>,
+        tls_connector: impl Fn(&MaybeTlsSettings) -> Result<SslConnectorBuilder, HttpError>,
+        proxy_config: &ProxyConfig,
+        client_builder: &mut client::Builder,
+    ) -> Result<HttpClient<B>, HttpError> {
+        let proxy_connector =
+            build_proxy_connector_with(tls_settings.into(), tls_connector, proxy_config)?;
+        Ok(HttpClient::new_with_proxy_connector(
+            proxy_connector,
+            client_builder,
+        ))
+    }
+
+    fn new_with_proxy_connector(
+        proxy_connector: HttpProxyConnector,
+        client_builder: &mut client::Builder,
+    ) -> HttpClient<B> {
         let client = client_builder.build(proxy_connector.clone());
 
         let app_name = crate::get_app_name();
@@ -105,11 +132,11 @@ where
         let user_agent = HeaderValue::from_str(&format!("{}/{}", app_name, version))
             .expect("Invalid header value for user-agent!");
 
-        Ok(HttpClient {
+        HttpClient {
             client,
             user_agent,
             proxy_connector,
-        })
+        }
     }
 
     pub fn send(
@@ -177,12 +204,18 @@ where
 pub fn build_proxy_connector(
     tls_settings: MaybeTlsSettings,
     proxy_config: &ProxyConfig,
+) -> Result<ProxyConnector<HttpsConnector<HttpConnector>>, HttpError> {
+    build_proxy_connector_with(tls_settings, default_tls_connector, proxy_config)
+}
+
+fn build_proxy_connector_with(
+    tls_settings: MaybeTlsSettings,
+    tls_connector: impl Fn(&MaybeTlsSettings) -> Result<SslConnectorBuilder, HttpError>,
+    proxy_config: &ProxyConfig,
 ) -> Result<ProxyConnector<HttpsConnector<HttpConnector>>, HttpError> {
     // Create dedicated TLS connector for the proxied connection with user TLS settings.
-    let tls = tls_connector_builder(&tls_settings)
-        .context(BuildTlsConnectorSnafu)?
-        .build();
-    let https = build_tls_connector(tls_settings)?;
+    let tls = tls_connector(&tls_settings)?.build();
+    let https = build_tls_connector_with(tls_settings, tls_connector)?;
     let mut proxy = ProxyConnector::new(https).unwrap();
     // Make proxy connector aware of user TLS settings by setting the TLS connector:
     // https://github.com/vectordotdev/vector/issues/13683
@@ -195,11 +228,18 @@ pub fn build_proxy_connector(
 
 pub fn build_tls_connector(
     tls_settings: MaybeTlsSettings,
+) -> Result<HttpsConnector<HttpConnector>, HttpError> {
+    build_tls_connector_with(tls_settings, default_tls_connector)
+}
+
+fn build_tls_connector_with(
+    tls_settings: MaybeTlsSettings,
+    tls_connector: impl Fn(&MaybeTlsSettings) -> Result<SslConnectorBuilder, HttpError>,
 ) -> Result<HttpsConnector<HttpConnector>, HttpError> {
     let mut http = HttpConnector::new();
     http.enforce_http(false);
 
-    let tls = tls_connector_builder(&tls_settings).context(BuildTlsConnectorSnafu)?;
+    let tls = tls_connector(&tls_settings)?;
     let mut https = HttpsConnector::with_connector(http, tls).context(MakeHttpsConnectorSnafu)?;
 
     let settings = tls_settings.tls().cloned();
@@ -213,6 +253,12 @@ pub fn build_tls_connector(
     Ok(https)
 }
 
+fn default_tls_connector(
+    tls_settings: &MaybeTlsSettings,
+) -> Result<SslConnectorBuilder, HttpError> {
+    tls_connector_builder(tls_settings).context(BuildTlsConnectorSnafu)
+}
+
 fn default_request_headers<B>(request: &mut Request<B>, user_agent: &HeaderValue) {
     if !request.headers().contains_key("User-Agent") {
         request
@@ -0,0 +1,593 @@
+use metrics::{counter, decrement_gauge, gauge, histogram, increment_gauge};
+use vector_lib::internal_event::{
+    error_stage, error_type, ComponentEventsDropped, InternalEvent, INTENTIONAL, UNINTENTIONAL,
+};
+
+#[derive(Debug)]
+pub struct InfinoDuplicateEventsDropped {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoDuplicateEventsDropped {
+    fn emit(self) {
+        emit!(ComponentEventsDropped::<INTENTIONAL> {
+            count: self.count,
+            reason: "Duplicate events suppressed by the deduplication window.",
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoMetricsDropped {
+    pub count: usize,
+    pub reason: &'static str,
+}
+
+impl InternalEvent for InfinoMetricsDropped {
+    fn emit(self) {
+        emit!(ComponentEventsDropped::<INTENTIONAL> {
+            count: self.count,
+            reason: self.reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoMetricTimestampMissing {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoMetricTimestampMissing {
+    fn emit(self) {
+        debug!(
+            message = "Metric has no timestamp, stamping it with the current time.",
+            count = self.count,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_metric_timestamps_stamped_total", self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoInvalidUtf8FieldsDropped {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoInvalidUtf8FieldsDropped {
+    fn emit(self) {
+        debug!(
+            message = "Dropped fields that are not valid UTF-8.",
+            count = self.count,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "infino_invalid_utf8_fields_dropped_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoIndexRejected {
+    pub index: String,
+    pub reason: &'static str,
+}
+
+impl InternalEvent for InfinoIndexRejected {
+    fn emit(self) {
+        error!(
+            message = "Event targets an index that is not allowed.",
+            index = %self.index,
+            reason = self.reason,
+            error_type = error_type::CONDITION_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::CONDITION_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: 1,
+            reason: self.reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRequestAudited<'a> {
+    pub endpoint: &'a str,
+    pub indexes: &'a [String],
+    pub documents: usize,
+    pub payload_bytes: usize,
+    pub principal: Option<&'a str>,
+    pub status: Option<u16>,
+    pub error: Option<&'a str>,
+}
+
+impl InternalEvent for InfinoRequestAudited<'_> {
+    fn emit(self) {
+        info!(
+            message = "Request sent.",
+            endpoint = self.endpoint,
+            indexes = ?self.indexes,
+            documents = self.documents,
+            payload_bytes = self.payload_bytes,
+            principal = self.principal,
+            status = self.status,
+            error = self.error,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoEncryptionKeyInvalid;
+
+impl InternalEvent for InfinoEncryptionKeyInvalid {
+    fn emit(self) {
+        let reason = "Encryption key is not a valid header value.";
+        error!(
+            message = reason,
+            error_type = error_type::ENCODER_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::ENCODER_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> { count: 1, reason });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoBatchHeaderInvalid<'a> {
+    pub header: &'a str,
+}
+
+impl InternalEvent for InfinoBatchHeaderInvalid<'_> {
+    fn emit(self) {
+        let reason = "Batch header is not a valid header value.";
+        error!(
+            message = reason,
+            header = %self.header,
+            error_type = error_type::ENCODER_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::ENCODER_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> { count: 1, reason });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoBulkActionInvalid<'a> {
+    pub action: &'a str,
+    pub reason: &'static str,
+}
+
+impl InternalEvent for InfinoBulkActionInvalid<'_> {
+    fn emit(self) {
+        error!(
+            message = self.reason,
+            action = %self.action,
+            error_type = error_type::TEMPLATE_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::TEMPLATE_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: 1,
+            reason: self.reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoAlertRuleInvalid {
+    pub reason: &'static str,
+}
+
+impl InternalEvent for InfinoAlertRuleInvalid {
+    fn emit(self) {
+        error!(
+            message = self.reason,
+            error_type = error_type::PARSER_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::PARSER_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: 1,
+            reason: self.reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoAlertRuleFailed {
+    pub id: String,
+    pub error: String,
+}
+
+impl InternalEvent for InfinoAlertRuleFailed {
+    fn emit(self) {
+        let reason = "Failed to apply alert rule.";
+        error!(
+            message = reason,
+            id = %self.id,
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> { count: 1, reason });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoIndexEventsPending<'a> {
+    pub index: &'a str,
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoIndexEventsPending<'_> {
+    fn emit(self) {
+        increment_gauge!(
+            "infino_index_pending_events", self.count as f64,
+            "index" => self.index.to_owned(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoIndexEventsCompleted<'a> {
+    pub index: &'a str,
+    pub count: usize,
+    /// Whether the request reached Infino, rather than failing to be sent.
+    pub flushed: bool,
+    /// Whether Infino did not index the events.
+    pub rejected: bool,
+}
+
+impl InternalEvent for InfinoIndexEventsCompleted<'_> {
+    fn emit(self) {
+        decrement_gauge!(
+            "infino_index_pending_events", self.count as f64,
+            "index" => self.index.to_owned(),
+        );
+        if !self.flushed {
+            return;
+        }
+        gauge!(
+            "infino_index_last_flush_timestamp_seconds",
+            chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+            "index" => self.index.to_owned(),
+        );
+        counter!(
+            "infino_index_events_flushed_total", self.count as u64,
+            "index" => self.index.to_owned(),
+        );
+        if self.rejected {
+            counter!(
+                "infino_index_events_rejected_total", self.count as u64,
+                "index" => self.index.to_owned(),
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoEndpointRequestCompleted<'a> {
+    pub endpoint: &'a str,
+    /// The status of the response, if the request got one.
+    pub status: Option<http::StatusCode>,
+    pub byte_size: usize,
+    pub duration: std::time::Duration,
+}
+
+impl InternalEvent for InfinoEndpointRequestCompleted<'_> {
+    fn emit(self) {
+        let status = self
+            .status
+            .map_or_else(|| "error".to_owned(), |status| status.as_u16().to_string());
+        counter!(
+            "infino_endpoint_requests_total", 1,
+            "endpoint" => self.endpoint.to_owned(),
+            "status" => status,
+        );
+        counter!(
+            "infino_endpoint_sent_bytes_total", self.byte_size as u64,
+            "endpoint" => self.endpoint.to_owned(),
+        );
+        histogram!(
+            "infino_endpoint_request_duration_seconds", self.duration,
+            "endpoint" => self.endpoint.to_owned(),
+        );
+        if !self.status.map_or(false, |status| status.is_success()) {
+            counter!(
+                "infino_endpoint_errors_total", 1,
+                "endpoint" => self.endpoint.to_owned(),
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRetryQueued {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for InfinoRetryQueued {
+    fn emit(self) {
+        debug!(
+            message = "Request failed and was written to the retry queue.",
+            count = self.count,
+            byte_size = self.byte_size,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_retry_queued_events_total", self.count as u64);
+        counter!("infino_retry_queued_bytes_total", self.byte_size as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRetryQueueFull {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoRetryQueueFull {
+    fn emit(self) {
+        warn!(
+            message = "Retry queue is full, failed request was not queued.",
+            count = self.count,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_retry_queue_full_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRetryQueueExpired {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoRetryQueueExpired {
+    fn emit(self) {
+        let reason = "Request expired in the retry queue.";
+        error!(
+            message = reason,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: self.count,
+            reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRetryQueueRejected {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoRetryQueueRejected {
+    fn emit(self) {
+        let reason = "Request from the retry queue was rejected.";
+        error!(
+            message = reason,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: self.count,
+            reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoRetryQueueReadError {
+    pub count: usize,
+    pub error: crate::Error,
+}
+
+impl InternalEvent for InfinoRetryQueueReadError {
+    fn emit(self) {
+        let reason = "Unable to read request from the retry queue.";
+        error!(
+            message = reason,
+            error = %self.error,
+            error_type = error_type::READER_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::READER_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> {
+            count: self.count,
+            reason,
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoDocumentsDeadLettered {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoDocumentsDeadLettered {
+    fn emit(self) {
+        debug!(
+            message = "Rejected documents were written to the dead-letter file.",
+            count = self.count,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_dead_lettered_documents_total", self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoDeadLetterFull {
+    pub count: usize,
+}
+
+impl InternalEvent for InfinoDeadLetterFull {
+    fn emit(self) {
+        warn!(
+            message = "Dead-letter file is full, rejected documents were not written.",
+            count = self.count,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_dead_letter_full_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoCrossZoneRequest {
+    pub reason: &'static str,
+}
+
+impl InternalEvent for InfinoCrossZoneRequest {
+    fn emit(self) {
+        debug!(
+            message = "Sending request to the endpoints of other zones.",
+            reason = self.reason,
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_cross_zone_requests_total", 1, "reason" => self.reason);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoPartitionRerouted;
+
+impl InternalEvent for InfinoPartitionRerouted {
+    fn emit(self) {
+        debug!(
+            message = "Sending request to the next endpoint of its partition.",
+            internal_log_rate_limit = true,
+        );
+        counter!("infino_partition_reroutes_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoEndpointAddressesChanged<'a> {
+    pub endpoint: &'a str,
+    pub addresses: usize,
+}
+
+impl InternalEvent for InfinoEndpointAddressesChanged<'_> {
+    fn emit(self) {
+        info!(
+            message = "Addresses of the endpoint changed, opening new connections.",
+            endpoint = %self.endpoint,
+            addresses = %self.addresses,
+        );
+        counter!("infino_endpoint_address_changes_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoClientCertificateReloaded<'a> {
+    pub endpoint: &'a str,
+}
+
+impl InternalEvent for InfinoClientCertificateReloaded<'_> {
+    fn emit(self) {
+        info!(
+            message = "Client certificate changed, opening new connections.",
+            endpoint = %self.endpoint,
+        );
+        counter!("infino_client_certificate_reloads_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoNodeDiscovered<'a> {
+    pub endpoint: &'a str,
+}
+
+impl InternalEvent for InfinoNodeDiscovered<'_> {
+    fn emit(self) {
+        info!(
+            message = "Discovered a data node, sending it requests.",
+            endpoint = %self.endpoint,
+        );
+        counter!("infino_nodes_discovered_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoNodeRemoved<'a> {
+    pub endpoint: &'a str,
+}
+
+impl InternalEvent for InfinoNodeRemoved<'_> {
+    fn emit(self) {
+        info!(
+            message = "Data node left the cluster, no longer sending it requests.",
+            endpoint = %self.endpoint,
+        );
+        counter!("infino_nodes_removed_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct InfinoBulkResponse {
+    /// Whether the request succeeded, partially failed, or failed.
+    pub outcome: &'static str,
+    /// The number of items that failed in a partially failed request.
+    pub failed_items: usize,
+}
+
+impl InternalEvent for InfinoBulkResponse {
+    fn emit(self) {
+        counter!("infino_bulk_responses_total", 1, "outcome" => self.outcome);
+        if self.failed_items > 0 {
+            warn!(
+                message = "Request succeeded, but some of its items failed.",
+                failed_items = self.failed_items,
+                internal_log_rate_limit = true,
+            );
+            counter!("infino_bulk_failed_items_total", self.failed_items as u64);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+#![allow(clippy::clone_on_ref_ptr)]
+#![allow(warnings, clippy::pedantic, clippy::nursery)]
+
+tonic::include_proto!("infino");
+
+pub use ingest_service_client::IngestServiceClient as Client;
@@ -0,0 +1,227 @@
+use std::task::{Context, Poll};
+
+use futures::{future::BoxFuture, FutureExt};
+use http::StatusCode;
+use tower::{Layer, Service};
+
+use super::{
+    retry::{is_retriable, InfinoResultResponse},
+    service::{InfinoRequest, InfinoResponse},
+};
+use crate::event::{EventFinalizers, EventStatus};
+
+/// Acknowledges the events of requests one document at a time, once requests are done with.
+///
+/// Infino writes the documents of a bulk request independently, so the events of a request that
+/// partially failed are acknowledged according to the outcome of their own document: delivered if
+/// it was written, errored if it was rejected with a retriable status, and rejected otherwise.
+/// When the outcome of each document is not known, every event takes the status of the request.
+#[derive(Clone, Copy)]
+pub struct AckLayer {
+    ignore_conflicts: bool,
+}
+
+impl AckLayer {
+    /// Documents rejected with a version conflict are delivered when `ignore_conflicts` is set.
+    pub const fn new(ignore_conflicts: bool) -> Self {
+        Self { ignore_conflicts }
+    }
+}
+
+impl<S> Layer<S> for AckLayer {
+    type Service = AckService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AckService {
+            ignore_conflicts: self.ignore_conflicts,
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AckService<S> {
+    ignore_conflicts: bool,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for AckService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: InfinoRequest) -> Self::Future {
+        // Taken out, so that the retries and copies of the request do not hold on to them.
+        let finalizers = std::mem::take(&mut req.finalizers);
+        let ignore_conflicts = self.ignore_conflicts;
+        self.inner
+            .call(req)
+            .map(move |result| {
+                match &result {
+                    Ok(response) => acknowledge(finalizers, response, ignore_conflicts),
+                    Err(_) => {
+                        for finalizers in finalizers {
+                            finalizers.update_status(EventStatus::Errored);
+                        }
+                    }
+                }
+                result
+            })
+            .boxed()
+    }
+}
+
+fn acknowledge(
+    finalizers: Vec<EventFinalizers>,
+    response: &InfinoResponse,
+    ignore_conflicts: bool,
+) {
+    match document_statuses(response, finalizers.len(), ignore_conflicts) {
+        Some(statuses) => {
+            for (finalizers, status) in finalizers.into_iter().zip(statuses) {
+                finalizers.update_status(status);
+            }
+        }
+        None => {
+            for finalizers in finalizers {
+                finalizers.update_status(response.event_status);
+            }
+        }
+    }
+}
+
+/// Returns the status of each of the `documents` of the request `response` answers, or `None` if
+/// they all take the status of the request.
+fn document_statuses(
+    response: &InfinoResponse,
+    documents: usize,
+    ignore_conflicts: bool,
+) -> Option<Vec<EventStatus>> {
+    if response.event_status == EventStatus::Delivered
+        || !response.http_response.status().is_success()
+    {
+        return None;
+    }
+    let body = String::from_utf8_lossy(response.http_response.body());
+    let result = InfinoResultResponse::parse(&body).ok()?;
+    let statuses = result
+        .item_statuses()
+        .map(|status| match status {
+            Some(status) if status.is_success() => EventStatus::Delivered,
+            Some(StatusCode::CONFLICT) if ignore_conflicts => EventStatus::Delivered,
+            Some(status) if is_retriable(status) => EventStatus::Errored,
+            _ => EventStatus::Rejected,
+        })
+        .collect::<Vec<_>>();
+    if statuses.len() != documents {
+        error!(
+            message = "Bulk response items do not match the documents, using the request status.",
+            items = statuses.len(),
+            documents,
+            internal_log_rate_limit = true
+        );
+        return None;
+    }
+    Some(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::Response;
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+
+    fn response(status: u16, body: &'static str, event_status: EventStatus) -> InfinoResponse {
+        InfinoResponse {
+            http_response: Response::builder()
+                .status(status)
+                .body(Bytes::from_static(body.as_bytes()))
+                .unwrap(),
+            event_status,
+            batch_size: 3,
+            events_byte_size: CountByteSize(3, JsonSize::new(3)).into(),
+        }
+    }
+
+    #[test]
+    fn tells_documents_apart() {
+        let body = r#"{"errors":true,"items":[{"index":{"status":201}},{"create":{"status":409}},{"index":{"status":429}},{"delete":{"status":404}}]}"#;
+        let partial = response(200, body, EventStatus::Rejected);
+        assert_eq!(
+            document_statuses(&partial, 4, false),
+            Some(vec![
+                EventStatus::Delivered,
+                EventStatus::Rejected,
+                EventStatus::Errored,
+                EventStatus::Rejected,
+            ])
+        );
+        assert_eq!(
+            document_statuses(&partial, 4, true).unwrap()[1],
+            EventStatus::Delivered
+        );
+        // The items must match the documents of the request.
+        assert_eq!(document_statuses(&partial, 3, false), None);
+    }
+
+    #[test]
+    fn falls_back_to_request_status() {
+        let delivered = response(
+            200,
+            r#"{"errors":false,"items":[]}"#,
+            EventStatus::Delivered,
+        );
+        assert_eq!(document_statuses(&delivered, 3, false), None);
+        let failed = response(503, "unavailable", EventStatus::Errored);
+        assert_eq!(document_statuses(&failed, 3, false), None);
+    }
+
+    #[tokio::test]
+    async fn acknowledges_each_document() {
+        use tower::ServiceExt;
+
+        use crate::event::{BatchNotifier, BatchStatus, EventFinalizer};
+
+        let body = r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400}}]}"#;
+        let service = tower::service_fn(|_: InfinoRequest| {
+            futures::future::ok::<_, crate::Error>(response(200, body, EventStatus::Rejected))
+        });
+
+        let (delivered, delivered_receiver) = BatchNotifier::new_with_receiver();
+        let (rejected, rejected_receiver) = BatchNotifier::new_with_receiver();
+        let request = InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: vec![
+                EventFinalizers::new(EventFinalizer::new(delivered)),
+                EventFinalizers::new(EventFinalizer::new(rejected)),
+            ],
+            batch_size: 2,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        };
+        AckLayer::new(false)
+            .layer(service)
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(delivered_receiver.await, BatchStatus::Delivered);
+        assert_eq!(rejected_receiver.await, BatchStatus::Rejected);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use tokio::time::Instant;
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    batch::BatchTarget,
+    service::{InfinoRequest, InfinoResponse},
+};
+use crate::event::EventStatus;
+
+/// Adaptive batching configuration.
+///
+/// Watches the outcome and latency of the requests sent to Infino, and while the cluster is under
+/// pressure (during a rolling restart, for example) temporarily halves the batch size and flush
+/// interval, one step per window of requests. They are restored the same way once the cluster
+/// recovers.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AdaptiveBatchConfig {
+    /// The number of requests evaluated together.
+    #[serde(default = "default_window")]
+    #[configurable(metadata(docs::type_unit = "requests"))]
+    pub window: usize,
+
+    /// The ratio of failed requests, between `0.0` and `1.0`, above which batches are scaled down.
+    #[serde(default = "default_error_rate_threshold")]
+    pub error_rate_threshold: f64,
+
+    /// The average request latency above which batches are scaled down.
+    #[serde(default = "default_latency_threshold_ms")]
+    #[configurable(metadata(docs::type_unit = "milliseconds"))]
+    pub latency_threshold_ms: u64,
+
+    /// The maximum number of times batches are halved, at most `16`.
+    #[serde(default = "default_max_steps")]
+    pub max_steps: u32,
+}
+
+/// The most times batches can be halved, which already takes them down to their minimum.
+const MAX_STEPS: u32 = 16;
+
+impl Default for AdaptiveBatchConfig {
+    fn default() -> Self {
+        Self {
+            window: default_window(),
+            error_rate_threshold: default_error_rate_threshold(),
+            latency_threshold_ms: default_latency_threshold_ms(),
+            max_steps: default_max_steps(),
+        }
+    }
+}
+
+impl AdaptiveBatchConfig {
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.max_steps > MAX_STEPS {
+            return Err(
+                format!("`adaptive_batching.max_steps` must be at most {MAX_STEPS}").into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+const fn default_window() -> usize {
+    20
+}
+
+const fn default_error_rate_threshold() -> f64 {
+    0.1
+}
+
+const fn default_latency_threshold_ms() -> u64 {
+    5_000
+}
+
+const fn default_max_steps() -> u32 {
+    3
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    requests: usize,
+    errors: usize,
+    latency: Duration,
+}
+
+/// Scales the shared [`BatchTarget`] according to the requests observed.
+#[derive(Debug)]
+pub struct PressureMonitor {
+    config: AdaptiveBatchConfig,
+    target: Arc<BatchTarget>,
+    window: Mutex<Window>,
+}
+
+impl PressureMonitor {
+    pub fn new(config: AdaptiveBatchConfig, target: Arc<BatchTarget>) -> Self {
+        Self {
+            config,
+            target,
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    fn observe(&self, failed: bool, latency: Duration) {
+        let completed = {
+            let mut window = self.window.lock().expect("pressure window lock poisoned");
+            window.requests += 1;
+            window.errors += usize::from(failed);
+            window.latency += latency;
+            if window.requests < self.config.window.max(1) {
+                return;
+            }
+            std::mem::take(&mut *window)
+        };
+
+        let error_rate = completed.errors as f64 / completed.requests as f64;
+        let latency = completed.latency / completed.requests as u32;
+        let latency_threshold = Duration::from_millis(self.config.latency_threshold_ms);
+
+        if error_rate > self.config.error_rate_threshold || latency > latency_threshold {
+            if self.target.scale_down(self.config.max_steps) {
+                warn!(
+                    message = "Infino is under pressure, scaling batches down.",
+                    error_rate,
+                    latency_ms = latency.as_millis() as u64,
+                    max_bytes = self.target.max_bytes(),
+                    timeout_ms = self.target.timeout().as_millis() as u64,
+                );
+            }
+        } else if completed.errors == 0
+            && latency <= latency_threshold / 2
+            && self.target.scale_up()
+        {
+            info!(
+                message = "Infino has recovered, scaling batches up.",
+                max_bytes = self.target.max_bytes(),
+                timeout_ms = self.target.timeout().as_millis() as u64,
+            );
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PressureLayer {
+    monitor: Arc<PressureMonitor>,
+}
+
+impl PressureLayer {
+    pub const fn new(monitor: Arc<PressureMonitor>) -> Self {
+        Self { monitor }
+    }
+}
+
+impl<S> Layer<S> for PressureLayer {
+    type Service = PressureService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PressureService {
+            monitor: Arc::clone(&self.monitor),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PressureService<S> {
+    monitor: Arc<PressureMonitor>,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for PressureService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let monitor = Arc::clone(&self.monitor);
+        let started = Instant::now();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let response = future.await;
+            let failed = match &response {
+                Ok(response) => response.event_status == EventStatus::Errored,
+                Err(_) => true,
+            };
+            monitor.observe(failed, started.elapsed());
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> PressureMonitor {
+        PressureMonitor::new(
+            AdaptiveBatchConfig {
+                window: 4,
+                max_steps: 2,
+                ..Default::default()
+            },
+            Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(8))),
+        )
+    }
+
+    #[test]
+    fn validates_max_steps() {
+        let config = |max_steps| AdaptiveBatchConfig {
+            max_steps,
+            ..Default::default()
+        };
+        assert!(config(MAX_STEPS).validate().is_ok());
+        assert!(config(MAX_STEPS + 1).validate().is_err());
+    }
+
+    #[test]
+    fn scales_down_on_errors_and_back_up() {
+        let monitor = monitor();
+        let fast = Duration::from_millis(10);
+
+        for _ in 0..8 {
+            monitor.observe(true, fast);
+        }
+        assert_eq!(monitor.target.level(), 2);
+        for _ in 0..4 {
+            monitor.observe(true, fast);
+        }
+        assert_eq!(monitor.target.level(), 2);
+
+        for _ in 0..4 {
+            monitor.observe(false, fast);
+        }
+        assert_eq!(monitor.target.level(), 1);
+        for _ in 0..4 {
+            monitor.observe(false, fast);
+        }
+        assert_eq!(monitor.target.level(), 0);
+    }
+
+    #[test]
+    fn scales_down_on_latency() {
+        let monitor = monitor();
+        for _ in 0..4 {
+            monitor.observe(false, Duration::from_secs(6));
+        }
+        assert_eq!(monitor.target.level(), 1);
+        assert_eq!(monitor.target.timeout(), Duration::from_secs(4));
+    }
+}
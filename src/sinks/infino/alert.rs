@@ -0,0 +1,335 @@
+//! Alert rule management.
+//!
+//! In `alert_rules` mode, events are not indexed. Each event defines an alert rule, which is
+//! created, updated, or deleted through the alerting API of Infino, so that alerts kept under
+//! version control are applied by the same pipelines as the data they alert on.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use http::{Method, StatusCode};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use vector_lib::{
+    configurable::configurable_component,
+    internal_event::{CountByteSize, EventsSent, InternalEventHandle as _, Output, Registered},
+    lookup::lookup_v2::ConfigValuePath,
+    EstimatedJsonEncodedSizeOf,
+};
+use vrl::path::PathPrefix;
+
+use super::{redact::redact, InfinoCommon};
+use crate::{
+    event::{Event, EventFinalizers, EventStatus, Finalizable, LogEvent, Value},
+    http::HttpClient,
+    internal_events::{InfinoAlertRuleFailed, InfinoAlertRuleInvalid},
+    sinks::util::StreamSink,
+};
+
+/// Alert rule configuration.
+///
+/// Used in `alert_rules` mode, where each event holds the ID of a rule, the action to apply to it,
+/// and, unless the rule is deleted, its definition. The definition is sent to Infino as it is.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRulesConfig {
+    /// The field holding the ID of the rule.
+    #[serde(default = "default_id_field")]
+    #[configurable(metadata(docs::examples = "rule_id"))]
+    pub id_field: ConfigValuePath,
+
+    /// The field holding the action to apply to the rule.
+    ///
+    /// The action is one of `create`, `update`, or `delete`.
+    #[serde(default = "default_action_field")]
+    #[configurable(metadata(docs::examples = "op"))]
+    pub action_field: ConfigValuePath,
+
+    /// The field holding the definition of the rule.
+    #[serde(default = "default_rule_field")]
+    #[configurable(metadata(docs::examples = "spec"))]
+    pub rule_field: ConfigValuePath,
+}
+
+impl Default for AlertRulesConfig {
+    fn default() -> Self {
+        Self {
+            id_field: default_id_field(),
+            action_field: default_action_field(),
+            rule_field: default_rule_field(),
+        }
+    }
+}
+
+fn default_id_field() -> ConfigValuePath {
+    "id".into()
+}
+
+fn default_action_field() -> ConfigValuePath {
+    "action".into()
+}
+
+fn default_rule_field() -> ConfigValuePath {
+    "rule".into()
+}
+
+/// A request to the alerting API.
+#[derive(Debug, PartialEq)]
+struct AlertRuleRequest {
+    id: String,
+    method: Method,
+    path: String,
+    body: Bytes,
+}
+
+impl AlertRulesConfig {
+    /// Returns the request applying the rule defined by `log`.
+    fn request(&self, log: &LogEvent) -> Result<AlertRuleRequest, &'static str> {
+        let id = match log.get((PathPrefix::Event, &self.id_field)) {
+            Some(Value::Bytes(id)) if !id.is_empty() => String::from_utf8_lossy(id).into_owned(),
+            Some(Value::Integer(id)) => id.to_string(),
+            _ => return Err("Alert rule has no ID."),
+        };
+        let method = match log.get((PathPrefix::Event, &self.action_field)) {
+            Some(Value::Bytes(action)) => match action.as_ref() {
+                b"create" => Method::POST,
+                b"update" => Method::PUT,
+                b"delete" => Method::DELETE,
+                _ => {
+                    return Err("Alert rule action is not one of `create`, `update`, or `delete`.")
+                }
+            },
+            _ => return Err("Alert rule has no action."),
+        };
+        let body = if method == Method::DELETE {
+            Bytes::new()
+        } else {
+            match log.get((PathPrefix::Event, &self.rule_field)) {
+                Some(rule @ Value::Object(_)) => serde_json::to_vec(rule)
+                    .map_err(|_| "Alert rule definition can not be serialized.")?
+                    .into(),
+                _ => return Err("Alert rule has no definition."),
+            }
+        };
+        let path = format!(
+            "/_alerting/rules/{}",
+            utf8_percent_encode(&id, NON_ALPHANUMERIC)
+        );
+        Ok(AlertRuleRequest {
+            id,
+            method,
+            path,
+            body,
+        })
+    }
+}
+
+/// Applies the alert rules defined by events, instead of indexing them.
+pub struct AlertRuleSink {
+    config: AlertRulesConfig,
+    commons: Vec<InfinoCommon>,
+    client: HttpClient,
+    events_sent: Registered<EventsSent>,
+}
+
+impl AlertRuleSink {
+    pub fn new(config: AlertRulesConfig, commons: Vec<InfinoCommon>, client: HttpClient) -> Self {
+        Self {
+            config,
+            commons,
+            client,
+            events_sent: register!(EventsSent::from(Output(None))),
+        }
+    }
+
+    async fn process_event(&self, mut event: Event) {
+        let finalizers = event.take_finalizers();
+        let size = event.estimated_json_encoded_size_of();
+        let Event::Log(log) = event else {
+            reject(finalizers, "Alert rules must be defined by logs.");
+            return;
+        };
+        let request = match self.config.request(&log) {
+            Ok(request) => request,
+            Err(reason) => {
+                reject(finalizers, reason);
+                return;
+            }
+        };
+
+        match self.apply(&request).await {
+            Ok(()) => {
+                finalizers.update_status(EventStatus::Delivered);
+                self.events_sent.emit(CountByteSize(1, size));
+            }
+            Err((status, error)) => {
+                finalizers.update_status(status);
+                emit!(InfinoAlertRuleFailed {
+                    id: request.id,
+                    error,
+                });
+            }
+        }
+    }
+
+    /// Applies `request` on the first endpoint that handles it.
+    ///
+    /// Endpoints that can not be reached, or that fail with a server error, are skipped. Rules
+    /// rejected by an endpoint are not sent to the next one, as they would be rejected again.
+    async fn apply(&self, request: &AlertRuleRequest) -> Result<(), (EventStatus, String)> {
+        let mut last_error = String::from("no endpoint");
+        for common in &self.commons {
+            let response = common
+                .request(
+                    self.client.clone(),
+                    request.method.clone(),
+                    &request.path,
+                    request.body.clone(),
+                )
+                .await;
+            match response.map(|response| response.status()) {
+                Ok(status) if is_applied(&request.method, status) => return Ok(()),
+                Ok(status) if status.is_client_error() => {
+                    return Err((
+                        EventStatus::Rejected,
+                        format!(
+                            "{} rejected with status {}",
+                            redact(&common.base_url),
+                            status
+                        ),
+                    ))
+                }
+                Ok(status) => {
+                    last_error =
+                        format!("{} failed with status {}", redact(&common.base_url), status);
+                }
+                Err(error) => last_error = redact(&error.to_string()).into_owned(),
+            }
+        }
+        Err((EventStatus::Errored, last_error))
+    }
+}
+
+/// Returns whether a response with `status` means the rule is in the requested state.
+///
+/// Deleting a rule that does not exist succeeds, so that deletions can be replayed.
+fn is_applied(method: &Method, status: StatusCode) -> bool {
+    status.is_success() || (method == Method::DELETE && status == StatusCode::NOT_FOUND)
+}
+
+fn reject(finalizers: EventFinalizers, reason: &'static str) {
+    finalizers.update_status(EventStatus::Rejected);
+    emit!(InfinoAlertRuleInvalid { reason });
+}
+
+#[async_trait]
+impl StreamSink<Event> for AlertRuleSink {
+    async fn run(self: Box<Self>, mut input: BoxStream<'_, Event>) -> Result<(), ()> {
+        while let Some(event) = input.next().await {
+            self.process_event(event).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::ObjectMap;
+
+    fn log(fields: &[(&str, Value)]) -> LogEvent {
+        LogEvent::from(
+            fields
+                .iter()
+                .map(|(key, value)| ((*key).into(), value.clone()))
+                .collect::<ObjectMap>(),
+        )
+    }
+
+    fn rule() -> Value {
+        let mut rule = ObjectMap::new();
+        rule.insert("query".into(), "level:error".into());
+        rule.insert("threshold".into(), Value::Integer(10));
+        Value::Object(rule)
+    }
+
+    #[test]
+    fn builds_requests_per_action() {
+        let config = AlertRulesConfig::default();
+
+        let request = config
+            .request(&log(&[
+                ("id", "errors/high".into()),
+                ("action", "create".into()),
+                ("rule", rule()),
+            ]))
+            .unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.path, "/_alerting/rules/errors%2Fhigh");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&request.body).unwrap(),
+            serde_json::json!({"query": "level:error", "threshold": 10})
+        );
+
+        let request = config
+            .request(&log(&[
+                ("id", "errors".into()),
+                ("action", "update".into()),
+                ("rule", rule()),
+            ]))
+            .unwrap();
+        assert_eq!(request.method, Method::PUT);
+
+        let request = config
+            .request(&log(&[
+                ("id", Value::Integer(7)),
+                ("action", "delete".into()),
+            ]))
+            .unwrap();
+        assert_eq!(request.method, Method::DELETE);
+        assert_eq!(request.path, "/_alerting/rules/7");
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let config = AlertRulesConfig::default();
+        assert!(config
+            .request(&log(&[("action", "create".into()), ("rule", rule())]))
+            .is_err());
+        assert!(config
+            .request(&log(&[
+                ("id", "errors".into()),
+                ("action", "upsert".into()),
+                ("rule", rule())
+            ]))
+            .is_err());
+        assert!(config
+            .request(&log(&[
+                ("id", "errors".into()),
+                ("action", "create".into())
+            ]))
+            .is_err());
+    }
+
+    #[test]
+    fn uses_configured_fields() {
+        let config = AlertRulesConfig {
+            id_field: "meta.name".into(),
+            action_field: "op".into(),
+            rule_field: "spec".into(),
+        };
+        let mut event = log(&[("op", "create".into()), ("spec", rule())]);
+        event.insert("meta.name", "errors");
+        let request = config.request(&event).unwrap();
+        assert_eq!(request.id, "errors");
+        assert_eq!(request.method, Method::POST);
+    }
+
+    #[test]
+    fn replayed_deletions_are_applied() {
+        assert!(is_applied(&Method::DELETE, StatusCode::NOT_FOUND));
+        assert!(!is_applied(&Method::PUT, StatusCode::NOT_FOUND));
+        assert!(is_applied(&Method::POST, StatusCode::CREATED));
+    }
+}
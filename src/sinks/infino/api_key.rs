@@ -0,0 +1,81 @@
+//! API key authentication, with a static key or bearer token sent in a header of every request.
+
+use bytes::Bytes;
+use http::{HeaderName, HeaderValue, Request};
+
+use super::InfinoAuthConfig;
+
+#[derive(Clone, Debug)]
+pub struct ApiKey {
+    header: HeaderName,
+    value: HeaderValue,
+}
+
+impl ApiKey {
+    /// Returns the API key authentication of `auth`, if it is an API key.
+    pub fn new(auth: Option<&InfinoAuthConfig>) -> crate::Result<Option<Self>> {
+        let Some(InfinoAuthConfig::ApiKey {
+            key,
+            header,
+            scheme,
+        }) = auth
+        else {
+            return Ok(None);
+        };
+        let header = HeaderName::try_from(header.as_str())
+            .map_err(|_| format!("Invalid API key header {:?}", header))?;
+        let value = if scheme.is_empty() {
+            key.inner().to_owned()
+        } else {
+            format!("{} {}", scheme, key.inner())
+        };
+        let mut value = HeaderValue::from_str(&value)
+            .map_err(|_| "API key is not a valid header value".to_owned())?;
+        value.set_sensitive(true);
+        Ok(Some(Self { header, value }))
+    }
+
+    /// Authenticates `request` with the key.
+    pub fn apply(&self, request: &mut Request<Bytes>) {
+        request
+            .headers_mut()
+            .insert(self.header.clone(), self.value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_key(header: &str, scheme: &str) -> crate::Result<Option<ApiKey>> {
+        ApiKey::new(Some(&InfinoAuthConfig::ApiKey {
+            key: "a2V5".to_string().into(),
+            header: header.into(),
+            scheme: scheme.into(),
+        }))
+    }
+
+    #[test]
+    fn sends_key_in_header() {
+        let mut request = Request::new(Bytes::new());
+        api_key("Authorization", "ApiKey")
+            .unwrap()
+            .unwrap()
+            .apply(&mut request);
+        assert_eq!(request.headers()["authorization"], "ApiKey a2V5");
+        assert!(request.headers()["authorization"].is_sensitive());
+
+        let mut request = Request::new(Bytes::new());
+        api_key("X-Api-Key", "")
+            .unwrap()
+            .unwrap()
+            .apply(&mut request);
+        assert_eq!(request.headers()["x-api-key"], "a2V5");
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert!(api_key("X Api Key", "").is_err());
+        assert!(ApiKey::new(None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    redact::redact,
+    service::{InfinoRequest, InfinoResponse},
+    InfinoAuthConfig,
+};
+use crate::internal_events::InfinoRequestAudited;
+
+/// Request audit configuration.
+///
+/// Every request sent to Infino is audited with its time, endpoint, indexes, number of documents,
+/// payload size, authenticated principal, and outcome, as evidence of the data that left the
+/// host. Retries are audited as separate requests. Payloads and credentials are never audited.
+#[configurable_component]
+#[derive(Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    /// The file that audit records are appended to, as JSON lines.
+    ///
+    /// It is created if it does not exist. By default, audit records are emitted as internal
+    /// logs instead.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "/var/log/vector/infino-audit.log"))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp: String,
+    endpoint: &'a str,
+    indexes: &'a [String],
+    documents: usize,
+    payload_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Auditor {
+    file: Option<Mutex<File>>,
+}
+
+impl Auditor {
+    pub async fn new(config: &AuditConfig) -> crate::Result<Self> {
+        let file = match config.path.as_ref() {
+            Some(path) => Some(Mutex::new(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|error| {
+                        format!("Unable to open request audit file {:?}: {}", path, error)
+                    })?,
+            )),
+            None => None,
+        };
+        Ok(Self { file })
+    }
+
+    async fn audit(
+        &self,
+        endpoint: &str,
+        principal: Option<&str>,
+        request: &AuditedRequest,
+        response: &Result<InfinoResponse, crate::Error>,
+    ) -> std::io::Result<()> {
+        let record = AuditRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            endpoint,
+            indexes: &request.indexes,
+            documents: request.documents,
+            payload_bytes: request.payload_bytes,
+            principal,
+            status: response
+                .as_ref()
+                .ok()
+                .map(|response| response.http_response.status().as_u16()),
+            error: response
+                .as_ref()
+                .err()
+                .map(|error| redact(&error.to_string()).into_owned()),
+        };
+
+        match self.file.as_ref() {
+            Some(file) => {
+                let mut line = serde_json::to_vec(&record)?;
+                line.push(b'\n');
+                file.lock().await.write_all(&line).await
+            }
+            None => {
+                emit!(InfinoRequestAudited {
+                    endpoint,
+                    indexes: record.indexes,
+                    documents: record.documents,
+                    payload_bytes: record.payload_bytes,
+                    principal: record.principal,
+                    status: record.status,
+                    error: record.error.as_deref(),
+                });
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the principal requests are authenticated as.
+pub fn principal(auth: &InfinoAuthConfig) -> String {
+    match auth {
+        InfinoAuthConfig::Basic { user, .. } => user.clone(),
+        #[cfg(feature = "aws-core")]
+        InfinoAuthConfig::Aws(_) => "aws".to_owned(),
+        InfinoAuthConfig::Kerberos { principal, .. } => principal.clone(),
+        InfinoAuthConfig::ApiKey { .. } => "api_key".to_owned(),
+        InfinoAuthConfig::OAuth2 { client_id, .. } => client_id.clone(),
+    }
+}
+
+/// The part of a request that is audited, kept aside as the inner service consumes the request.
+struct AuditedRequest {
+    indexes: Vec<String>,
+    documents: usize,
+    payload_bytes: usize,
+}
+
+#[derive(Clone)]
+pub struct AuditLayer {
+    auditor: Arc<Auditor>,
+    endpoint: String,
+    principal: Option<String>,
+}
+
+impl AuditLayer {
+    /// Audits the requests sent to `endpoint`, which are authenticated as `principal`.
+    pub const fn new(auditor: Arc<Auditor>, endpoint: String, principal: Option<String>) -> Self {
+        Self {
+            auditor,
+            endpoint,
+            principal,
+        }
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = AuditService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuditService {
+            auditor: Arc::clone(&self.auditor),
+            endpoint: self.endpoint.clone(),
+            principal: self.principal.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuditService<S> {
+    auditor: Arc<Auditor>,
+    endpoint: String,
+    principal: Option<String>,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for AuditService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let auditor = Arc::clone(&self.auditor);
+        let endpoint = self.endpoint.clone();
+        let principal = self.principal.clone();
+        let audited = AuditedRequest {
+            indexes: req.indexes.keys().cloned().collect(),
+            documents: req.batch_size,
+            payload_bytes: req.payload.len(),
+        };
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let response = future.await;
+            if let Err(error) = auditor
+                .audit(&endpoint, principal.as_deref(), &audited, &response)
+                .await
+            {
+                error!(
+                    message = "Failed to write request audit record.",
+                    %error,
+                    internal_log_rate_limit = true
+                );
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::{Response, StatusCode};
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::*;
+    use crate::event::EventStatus;
+
+    #[tokio::test]
+    async fn appends_records_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let auth = InfinoAuthConfig::Basic {
+            user: "ingest".into(),
+            password: "secret".to_string().into(),
+        };
+        let auditor = Auditor::new(&AuditConfig {
+            path: Some(path.clone()),
+        })
+        .await
+        .unwrap();
+        let principal = principal(&auth);
+
+        let request = AuditedRequest {
+            indexes: vec!["logs".into()],
+            documents: 3,
+            payload_bytes: 120,
+        };
+        let response = Ok(InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::OK)
+                .body(Bytes::new())
+                .unwrap(),
+            event_status: EventStatus::Delivered,
+            batch_size: 3,
+            events_byte_size: GroupedCountByteSize::new_untagged(),
+        });
+        auditor
+            .audit(
+                "http://localhost:9200",
+                Some(&principal),
+                &request,
+                &response,
+            )
+            .await
+            .unwrap();
+        auditor
+            .audit(
+                "http://localhost:9200",
+                Some(&principal),
+                &request,
+                &Err("timed out".into()),
+            )
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let records = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["endpoint"], "http://localhost:9200");
+        assert_eq!(records[0]["indexes"], serde_json::json!(["logs"]));
+        assert_eq!(records[0]["documents"], 3);
+        assert_eq!(records[0]["payload_bytes"], 120);
+        assert_eq!(records[0]["principal"], "ingest");
+        assert_eq!(records[0]["status"], 200);
+        assert_eq!(records[1]["error"], "timed out");
+        assert!(!content.contains("secret"));
+    }
+}
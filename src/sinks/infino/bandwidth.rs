@@ -0,0 +1,417 @@
+//! Bandwidth throttling.
+//!
+//! The compressed bytes of the requests sent to all of the endpoints, retries included, are
+//! limited by a token bucket shared between the endpoints. A request larger than the bucket is
+//! still sent, and the endpoints then wait until the bytes it overdrew are paid back.
+//!
+//! The limit may change with the time of day, following a schedule of windows, such as to slow
+//! down backfills during business hours. The first window containing the current time sets the
+//! limit, and `request.rate_limit_bytes_per_sec` applies outside of the windows.
+
+use std::{
+    future::Future,
+    num::NonZeroU64,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, Utc};
+use tokio::time::Sleep;
+use tower::{Layer, Service};
+use vector_lib::{configurable::configurable_component, TimeZone};
+
+use super::{service::InfinoRequest, ParseError};
+
+/// Throttle schedule configuration.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ThrottleScheduleConfig {
+    /// The time zone of the windows.
+    ///
+    /// This overrides the [global `timezone`][global_timezone] option. The time zone name may be
+    /// any name in the [TZ database][tz_database] or `local` to indicate system local time.
+    ///
+    /// [global_timezone]: https://vector.dev/docs/reference/configuration//global-options#timezone
+    /// [tz_database]: https://en.wikipedia.org/wiki/List_of_tz_database_time_zones
+    #[serde(default)]
+    #[configurable(metadata(docs::advanced))]
+    pub timezone: Option<TimeZone>,
+
+    /// The windows of the schedule, in order of precedence.
+    pub windows: Vec<ThrottleWindow>,
+}
+
+/// A window of the throttle schedule.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ThrottleWindow {
+    /// The days of the week the window applies to.
+    ///
+    /// The window applies to every day by default.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+
+    /// The time of day the window starts at, as `HH:MM`.
+    #[configurable(metadata(docs::examples = "09:00"))]
+    pub start: String,
+
+    /// The time of day the window ends at, as `HH:MM`.
+    ///
+    /// Windows ending before they start span midnight, and apply to the day they start on.
+    #[configurable(metadata(docs::examples = "17:00"))]
+    pub end: String,
+
+    /// The maximum number of bytes sent to the endpoints per second during the window.
+    ///
+    /// Traffic is not limited during the window if unset.
+    #[serde(default)]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 1048576))]
+    pub rate_limit_bytes_per_sec: Option<NonZeroU64>,
+}
+
+/// A day of the week.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    /// Monday.
+    Monday,
+    /// Tuesday.
+    Tuesday,
+    /// Wednesday.
+    Wednesday,
+    /// Thursday.
+    Thursday,
+    /// Friday.
+    Friday,
+    /// Saturday.
+    Saturday,
+    /// Sunday.
+    Sunday,
+}
+
+impl From<Weekday> for chrono::Weekday {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Monday => Self::Mon,
+            Weekday::Tuesday => Self::Tue,
+            Weekday::Wednesday => Self::Wed,
+            Weekday::Thursday => Self::Thu,
+            Weekday::Friday => Self::Fri,
+            Weekday::Saturday => Self::Sat,
+            Weekday::Sunday => Self::Sun,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Window {
+    days: Vec<chrono::Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    bytes_per_sec: Option<NonZeroU64>,
+}
+
+impl Window {
+    fn new(config: &ThrottleWindow) -> Result<Self, ParseError> {
+        let time = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| ParseError::InvalidThrottleTime {
+                time: time.to_owned(),
+            })
+        };
+        Ok(Self {
+            days: config.days.iter().copied().map(Into::into).collect(),
+            start: time(&config.start)?,
+            end: time(&config.end)?,
+            bytes_per_sec: config.rate_limit_bytes_per_sec,
+        })
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let (day, contained) = if self.start <= self.end {
+            (now.weekday(), self.start <= time && time < self.end)
+        } else if time >= self.start {
+            (now.weekday(), true)
+        } else {
+            // Spanning midnight, the window started the day before.
+            (now.weekday().pred(), time < self.end)
+        };
+        contained && (self.days.is_empty() || self.days.contains(&day))
+    }
+}
+
+/// The bandwidth limit, by time of day.
+#[derive(Clone, Debug)]
+pub struct Bandwidth {
+    default: Option<NonZeroU64>,
+    windows: Vec<Window>,
+    timezone: TimeZone,
+}
+
+impl Bandwidth {
+    /// Returns the bandwidth limit, or `None` if traffic is never limited.
+    pub fn new(
+        default: Option<NonZeroU64>,
+        schedule: Option<&ThrottleScheduleConfig>,
+        timezone: TimeZone,
+    ) -> Result<Option<Self>, ParseError> {
+        let windows = schedule
+            .map_or(&[][..], |schedule| &schedule.windows)
+            .iter()
+            .map(Window::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        if default.is_none() && windows.iter().all(|window| window.bytes_per_sec.is_none()) {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            default,
+            windows,
+            timezone: schedule
+                .and_then(|schedule| schedule.timezone)
+                .unwrap_or(timezone),
+        }))
+    }
+
+    fn bytes_per_sec(&self, now: DateTime<Utc>) -> Option<NonZeroU64> {
+        if self.windows.is_empty() {
+            return self.default;
+        }
+        let now = match self.timezone {
+            TimeZone::Local => now.with_timezone(&chrono::Local).naive_local(),
+            TimeZone::Named(tz) => now.with_timezone(&tz).naive_local(),
+        };
+        self.windows
+            .iter()
+            .find(|window| window.contains(now))
+            .map_or(self.default, |window| window.bytes_per_sec)
+    }
+}
+
+/// A token bucket of bytes, holding up to one second of bytes at the current limit.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, bytes_per_sec: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * bytes_per_sec).min(bytes_per_sec);
+        self.updated_at = now;
+    }
+
+    /// Returns how long to wait until the bucket is no longer overdrawn.
+    fn wait(&mut self, bytes_per_sec: Option<NonZeroU64>, now: Instant) -> Duration {
+        let Some(bytes_per_sec) = bytes_per_sec.map(|limit| limit.get() as f64) else {
+            return Duration::ZERO;
+        };
+        self.refill(bytes_per_sec, now);
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / bytes_per_sec)
+        }
+    }
+
+    fn take(&mut self, bytes: usize, bytes_per_sec: Option<NonZeroU64>, now: Instant) {
+        if let Some(bytes_per_sec) = bytes_per_sec {
+            self.refill(bytes_per_sec.get() as f64, now);
+            self.tokens -= bytes as f64;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Limiter {
+    bandwidth: Bandwidth,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl Limiter {
+    fn wait(&self) -> Duration {
+        let bytes_per_sec = self.bandwidth.bytes_per_sec(Utc::now());
+        self.bucket
+            .lock()
+            .expect("bandwidth lock poisoned")
+            .wait(bytes_per_sec, Instant::now())
+    }
+
+    fn take(&self, bytes: usize) {
+        let bytes_per_sec = self.bandwidth.bytes_per_sec(Utc::now());
+        self.bucket.lock().expect("bandwidth lock poisoned").take(
+            bytes,
+            bytes_per_sec,
+            Instant::now(),
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BandwidthLayer {
+    limiter: Arc<Limiter>,
+}
+
+impl BandwidthLayer {
+    pub fn new(bandwidth: Bandwidth) -> Self {
+        let tokens = bandwidth
+            .bytes_per_sec(Utc::now())
+            .map_or(0.0, |limit| limit.get() as f64);
+        Self {
+            limiter: Arc::new(Limiter {
+                bandwidth,
+                bucket: Mutex::new(TokenBucket {
+                    tokens,
+                    updated_at: Instant::now(),
+                }),
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for BandwidthLayer {
+    type Service = BandwidthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BandwidthService {
+            inner,
+            limiter: Arc::clone(&self.limiter),
+            sleep: None,
+        }
+    }
+}
+
+pub struct BandwidthService<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S: Clone> Clone for BandwidthService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            limiter: Arc::clone(&self.limiter),
+            sleep: None,
+        }
+    }
+}
+
+impl<S> Service<InfinoRequest> for BandwidthService<S>
+where
+    S: Service<InfinoRequest>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            let wait = self.limiter.wait();
+            if wait.is_zero() {
+                break;
+            }
+            self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        self.limiter.take(req.payload.len());
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn limit(bytes_per_sec: u64) -> Option<NonZeroU64> {
+        NonZeroU64::new(bytes_per_sec)
+    }
+
+    #[test]
+    fn limits_bytes_per_second() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket {
+            tokens: 1000.0,
+            updated_at: start,
+        };
+        bucket.take(600, limit(1000), start);
+        assert_eq!(bucket.wait(limit(1000), start), Duration::ZERO);
+
+        // Requests larger than the bucket overdraw it.
+        bucket.take(900, limit(1000), start);
+        assert_eq!(bucket.wait(limit(1000), start), Duration::from_millis(500));
+        assert_eq!(
+            bucket.wait(limit(1000), start + Duration::from_millis(500)),
+            Duration::ZERO
+        );
+
+        // The bucket holds at most a second of bytes.
+        assert_eq!(
+            bucket.wait(limit(1000), start + Duration::from_secs(10)),
+            Duration::ZERO
+        );
+        assert_eq!(bucket.tokens, 1000.0);
+
+        // Unlimited traffic never waits.
+        bucket.take(100_000, None, start);
+        assert_eq!(bucket.wait(None, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn follows_the_schedule() {
+        let window = |days: Vec<Weekday>, start: &str, end: &str, bytes_per_sec| ThrottleWindow {
+            days,
+            start: start.to_owned(),
+            end: end.to_owned(),
+            rate_limit_bytes_per_sec: bytes_per_sec,
+        };
+        let schedule = ThrottleScheduleConfig {
+            timezone: None,
+            windows: vec![
+                window(vec![Weekday::Monday], "09:00", "17:00", limit(100)),
+                window(Vec::new(), "22:00", "06:00", None),
+            ],
+        };
+        let timezone = TimeZone::Named(chrono_tz::UTC);
+        let bandwidth = Bandwidth::new(limit(1000), Some(&schedule), timezone)
+            .unwrap()
+            .unwrap();
+
+        // 2024-01-01 is a Monday.
+        let at = |day, hour| Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+        assert_eq!(bandwidth.bytes_per_sec(at(1, 10)), limit(100));
+        assert_eq!(bandwidth.bytes_per_sec(at(2, 10)), limit(1000));
+        assert_eq!(bandwidth.bytes_per_sec(at(1, 17)), limit(1000));
+        assert_eq!(bandwidth.bytes_per_sec(at(1, 23)), None);
+        assert_eq!(bandwidth.bytes_per_sec(at(2, 5)), None);
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        let schedule = ThrottleScheduleConfig {
+            timezone: None,
+            windows: vec![ThrottleWindow {
+                days: Vec::new(),
+                start: "9am".to_owned(),
+                end: "17:00".to_owned(),
+                rate_limit_bytes_per_sec: None,
+            }],
+        };
+        assert!(Bandwidth::new(None, Some(&schedule), TimeZone::Local).is_err());
+    }
+}
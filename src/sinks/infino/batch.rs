@@ -0,0 +1,629 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{stream::Fuse, Future, Stream, StreamExt};
+use tokio::time::{Instant, Sleep};
+use vector_lib::configurable::configurable_component;
+use vector_lib::stream::BatcherSettings;
+use vector_lib::ByteSizeOf;
+
+use super::{dispatch::Lane, encoder::ProcessedEvent, sink::PartitionKey};
+
+/// The smallest byte target the batcher is shrunk to.
+const MIN_BATCH_BYTES: usize = 64 * 1024;
+
+/// The shortest flush interval the batcher is scaled down to.
+const MIN_BATCH_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The byte size target and flush interval of batches, shared between the batcher and the
+/// services so that they can be adjusted while the sink is running.
+///
+/// The byte target has a ceiling, which is lowered every time Infino rejects a request as too
+/// large, and a pressure level: every level halves both the byte target and the flush interval.
+#[derive(Debug)]
+pub struct BatchTarget {
+    ceiling_bytes: AtomicUsize,
+    timeout: Duration,
+    level: AtomicU32,
+}
+
+impl BatchTarget {
+    pub const fn new(max_bytes: usize, timeout: Duration) -> Self {
+        Self {
+            ceiling_bytes: AtomicUsize::new(max_bytes),
+            timeout,
+            level: AtomicU32::new(0),
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        let ceiling = self.ceiling_bytes.load(Ordering::Relaxed);
+        let level = self.level.load(Ordering::Relaxed);
+        // Levels beyond the width of the target take it down to its minimum.
+        let scaled = ceiling.checked_shr(level).unwrap_or_default();
+        scaled.max(MIN_BATCH_BYTES.min(ceiling))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        let level = self.level.load(Ordering::Relaxed);
+        let scaled = 2u32
+            .checked_pow(level)
+            .map_or(Duration::ZERO, |divisor| self.timeout / divisor);
+        scaled.max(MIN_BATCH_TIMEOUT.min(self.timeout))
+    }
+
+    /// Halves the byte ceiling after Infino rejected a request as too large, and returns the new
+    /// byte target.
+    pub fn shrink(&self) -> usize {
+        // Never grows a target that was configured below the minimum.
+        let halved = |max_bytes: usize| (max_bytes / 2).max(MIN_BATCH_BYTES.min(max_bytes));
+        _ = self
+            .ceiling_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |max_bytes| {
+                Some(halved(max_bytes))
+            });
+        self.max_bytes()
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level.load(Ordering::Relaxed)
+    }
+
+    /// Moves one pressure level up, to at most `max_level`. Returns `true` if the level changed.
+    pub fn scale_down(&self, max_level: u32) -> bool {
+        self.level
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |level| {
+                (level < max_level).then_some(level + 1)
+            })
+            .is_ok()
+    }
+
+    /// Moves one pressure level down. Returns `true` if the level changed.
+    pub fn scale_up(&self) -> bool {
+        self.level
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |level| {
+                level.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
+
+/// Cost-based batching configuration.
+///
+/// Batches are flushed once their estimated cost for Infino reaches `max_cost`, rather than only
+/// by their number of events and bytes, so that every request carries a similar amount of work.
+/// The cost of a batch is the weighted sum of its documents, its kibibytes, and its distinct
+/// indexes, since each index of a bulk request is written on its own.
+///
+/// The `batch` limits still apply.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BatchCostConfig {
+    /// The cost batches are flushed at.
+    #[configurable(metadata(docs::examples = 10000.0))]
+    pub max_cost: f64,
+
+    /// The cost of each document.
+    #[serde(default = "default_document_cost")]
+    pub document_cost: f64,
+
+    /// The cost of each kibibyte of documents.
+    #[serde(default = "default_kibibyte_cost")]
+    pub kibibyte_cost: f64,
+
+    /// The cost of each distinct index.
+    #[serde(default = "default_index_cost")]
+    pub index_cost: f64,
+}
+
+const fn default_document_cost() -> f64 {
+    1.0
+}
+
+const fn default_kibibyte_cost() -> f64 {
+    1.0
+}
+
+const fn default_index_cost() -> f64 {
+    100.0
+}
+
+impl BatchCostConfig {
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.max_cost.is_nan() || self.max_cost <= 0.0 {
+            return Err("`batch_cost.max_cost` must be greater than zero".into());
+        }
+        if [self.document_cost, self.kibibyte_cost, self.index_cost]
+            .iter()
+            .any(|cost| cost.is_nan() || *cost < 0.0)
+        {
+            return Err("`batch_cost` costs must not be negative".into());
+        }
+        Ok(())
+    }
+
+    fn cost(&self, documents: usize, size: usize, indexes: usize) -> f64 {
+        documents as f64 * self.document_cost
+            + size as f64 / 1024.0 * self.kibibyte_cost
+            + indexes as f64 * self.index_cost
+    }
+}
+
+struct Batch {
+    events: Vec<ProcessedEvent>,
+    size: usize,
+    /// The distinct indexes of the events, only tracked for the cost of the batch.
+    indexes: HashSet<String>,
+    opened_at: Instant,
+    pushed_at: Instant,
+}
+
+impl Batch {
+    fn new(now: Instant) -> Self {
+        Self {
+            events: Vec::new(),
+            size: 0,
+            indexes: HashSet::new(),
+            opened_at: now,
+            pushed_at: now,
+        }
+    }
+
+    /// The instant the batch has to be flushed at, whether or not it is full.
+    fn deadline(&self, timeout: Duration, idle_timeout: Option<Duration>) -> Instant {
+        let deadline = self.opened_at + timeout;
+        match idle_timeout {
+            Some(idle_timeout) => deadline.min(self.pushed_at + idle_timeout),
+            None => deadline,
+        }
+    }
+}
+
+type BatchKey = (
+    Option<PartitionKey>,
+    Lane,
+    Option<String>,
+    Vec<(String, String)>,
+    Vec<(String, String)>,
+);
+
+/// Batches processed events per lane, encryption key, headers and query parameters, and optionally
+/// per partition, with limits read from a [`BatchTarget`] every time they are checked.
+///
+/// Like the batchers of `vector_lib`, the first event of a batch is never subject to the byte
+/// limit, so that any event fits in a batch.
+pub struct InfinoBatcher<S> {
+    stream: Fuse<S>,
+    item_limit: usize,
+    target: Arc<BatchTarget>,
+    idle_timeout: Option<Duration>,
+    max_delay: Option<Duration>,
+    cost: Option<BatchCostConfig>,
+    partitioned: bool,
+    ordered: bool,
+    batches: HashMap<BatchKey, Batch>,
+    closed: VecDeque<Vec<ProcessedEvent>>,
+    timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> InfinoBatcher<S>
+where
+    S: Stream<Item = ProcessedEvent> + Unpin,
+{
+    /// When `partitioned` is set, every batch only holds events sharing the same [`PartitionKey`].
+    pub fn new(
+        stream: S,
+        settings: &BatcherSettings,
+        target: Arc<BatchTarget>,
+        partitioned: bool,
+    ) -> Self {
+        Self {
+            stream: stream.fuse(),
+            item_limit: settings.item_limit,
+            target,
+            idle_timeout: None,
+            max_delay: None,
+            cost: None,
+            partitioned,
+            ordered: false,
+            batches: HashMap::new(),
+            closed: VecDeque::new(),
+            timer: None,
+        }
+    }
+
+    /// Partitions batches by index only, whatever the bulk action of their events.
+    pub fn with_ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Also flushes batches that have not received any event for `idle_timeout`, so that quiet
+    /// partitions are not held back for the whole batch timeout.
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Flushes every batch at most `max_delay` after its first event arrived, however the batch
+    /// timeout is configured or scaled.
+    pub fn with_max_delay(mut self, max_delay: Option<Duration>) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Also flushes batches once their estimated cost reaches the `max_cost` of `cost`.
+    pub fn with_cost(mut self, cost: Option<BatchCostConfig>) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    fn push(&mut self, event: ProcessedEvent) {
+        let now = Instant::now();
+        let key = (
+            self.partitioned
+                .then(|| PartitionKey::of(&event, self.ordered)),
+            event.lane,
+            event.encryption_key.clone(),
+            event.headers.clone(),
+            event.query.clone(),
+        );
+        let size = event.size_of();
+        let max_bytes = self.target.max_bytes();
+        let timeout = self.timeout();
+
+        let batch = self
+            .batches
+            .entry(key.clone())
+            .or_insert_with(|| Batch::new(now));
+        let is_new_index = self.cost.is_some() && !batch.indexes.contains(&event.index);
+        let overflows = |cost: &BatchCostConfig| {
+            let indexes = batch.indexes.len() + usize::from(is_new_index);
+            cost.cost(batch.events.len() + 1, batch.size + size, indexes) > cost.max_cost
+        };
+        if !batch.events.is_empty()
+            && (batch.size + size > max_bytes || self.cost.as_ref().map_or(false, overflows))
+        {
+            let full = std::mem::replace(batch, Batch::new(now));
+            self.closed.push_back(full.events);
+        }
+
+        if self.cost.is_some() {
+            batch.indexes.insert(event.index.clone());
+        }
+        batch.events.push(event);
+        batch.size += size;
+        batch.pushed_at = now;
+        let costly = self.cost.as_ref().map_or(false, |cost| {
+            cost.cost(batch.events.len(), batch.size, batch.indexes.len()) >= cost.max_cost
+        });
+        if batch.events.len() >= self.item_limit || batch.size >= max_bytes || costly {
+            let full = self.batches.remove(&key).expect("batch exists");
+            self.closed.push_back(full.events);
+        } else {
+            let deadline = batch.deadline(timeout, self.idle_timeout);
+            self.schedule(deadline);
+        }
+    }
+
+    /// The flush interval of batches.
+    fn timeout(&self) -> Duration {
+        match self.max_delay {
+            Some(max_delay) => self.target.timeout().min(max_delay),
+            None => self.target.timeout(),
+        }
+    }
+
+    /// Makes sure that the timer goes off by `deadline`.
+    fn schedule(&mut self, deadline: Instant) {
+        match self.timer.as_mut() {
+            Some(timer) if timer.deadline() <= deadline => {}
+            Some(timer) => timer.as_mut().reset(deadline),
+            None => self.timer = Some(Box::pin(tokio::time::sleep_until(deadline))),
+        }
+    }
+
+    /// Closes the batches that have expired, and returns the deadline of the next one to expire.
+    fn close_expired(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        let timeout = self.timeout();
+        let idle_timeout = self.idle_timeout;
+
+        let expired = self
+            .batches
+            .iter()
+            .filter(|(_, batch)| batch.deadline(timeout, idle_timeout) <= now)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in expired {
+            let batch = self.batches.remove(&key).expect("batch exists");
+            self.closed.push_back(batch.events);
+        }
+
+        self.batches
+            .values()
+            .map(|batch| batch.deadline(timeout, idle_timeout))
+            .min()
+    }
+}
+
+impl<S> Stream for InfinoBatcher<S>
+where
+    S: Stream<Item = ProcessedEvent> + Unpin,
+{
+    type Item = Vec<ProcessedEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(batch) = this.closed.pop_front() {
+                return Poll::Ready(Some(batch));
+            }
+
+            // Expired batches are closed before more events are pulled, as the input may never run
+            // dry, and the clock is checked directly since the timer can only fire once the task
+            // yields.
+            let expired = this
+                .timer
+                .as_ref()
+                .map_or(false, |timer| timer.deadline() <= Instant::now());
+            if expired {
+                this.timer = None;
+                if let Some(deadline) = this.close_expired() {
+                    this.schedule(deadline);
+                }
+                continue;
+            }
+
+            match this.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(event)) => this.push(event),
+                Poll::Ready(None) => {
+                    if this.batches.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    this.closed
+                        .extend(this.batches.drain().map(|(_, batch)| batch.events));
+                }
+                // Woken up by the timer once the next batch expires.
+                Poll::Pending => match this.timer.as_mut() {
+                    Some(timer) if timer.as_mut().poll(cx).is_ready() => {}
+                    _ => return Poll::Pending,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::{
+        event::LogEvent,
+        sinks::infino::{BulkAction, DocumentMetadata},
+    };
+
+    fn event(index: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            index: index.to_owned(),
+            bulk_action: BulkAction::Index,
+            log: LogEvent::from("hello"),
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    fn settings(item_limit: usize) -> BatcherSettings {
+        BatcherSettings {
+            timeout: Duration::from_secs(1),
+            size_limit: 10 * 1024 * 1024,
+            item_limit,
+        }
+    }
+
+    #[test]
+    fn scales_with_pressure_level() {
+        let target = BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(8));
+        assert!(target.scale_down(2));
+        assert!(target.scale_down(2));
+        assert!(!target.scale_down(2));
+        assert_eq!(target.max_bytes(), 10 * 1024 * 1024 / 4);
+        assert_eq!(target.timeout(), Duration::from_secs(2));
+
+        assert!(target.scale_up());
+        assert!(target.scale_up());
+        assert!(!target.scale_up());
+        assert_eq!(target.timeout(), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn saturates_at_high_levels() {
+        let target = BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(8));
+        while target.scale_down(64) {}
+        assert_eq!(target.level(), 64);
+        assert_eq!(target.max_bytes(), MIN_BATCH_BYTES);
+        assert_eq!(target.timeout(), MIN_BATCH_TIMEOUT);
+    }
+
+    #[test]
+    fn shrinks_down_to_minimum() {
+        let target = BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1));
+        assert_eq!(target.shrink(), 5 * 1024 * 1024);
+        for _ in 0..16 {
+            target.shrink();
+        }
+        assert_eq!(target.max_bytes(), MIN_BATCH_BYTES);
+
+        let small = BatchTarget::new(1024, Duration::from_secs(1));
+        assert_eq!(small.shrink(), 1024);
+    }
+
+    #[tokio::test]
+    async fn batches_per_partition() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let events = stream::iter(["a", "b", "a", "b", "a"].map(event));
+        let batches = InfinoBatcher::new(events, &settings(2), target, true)
+            .map(|batch| {
+                (
+                    batch[0].index.clone(),
+                    batch.iter().all(|event| event.index == batch[0].index),
+                    batch.len(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(batches.len(), 3);
+        assert!(batches
+            .iter()
+            .all(|(_, single_partition, _)| *single_partition));
+        assert_eq!(batches.iter().map(|(_, _, len)| len).sum::<usize>(), 5);
+    }
+
+    #[tokio::test]
+    async fn ordered_batches_mix_bulk_actions() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let delete = ProcessedEvent {
+            bulk_action: BulkAction::Delete,
+            ..event("a")
+        };
+        let events = stream::iter([event("a"), delete, event("a")]);
+        let batches = InfinoBatcher::new(events, &settings(3), target, true)
+            .with_ordered(true)
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|event| event.bulk_action)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            batches,
+            vec![vec![
+                BulkAction::Index,
+                BulkAction::Delete,
+                BulkAction::Index
+            ]]
+        );
+    }
+
+    #[tokio::test]
+    async fn batches_by_cost() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let cost = BatchCostConfig {
+            max_cost: 3.0,
+            document_cost: 1.0,
+            kibibyte_cost: 0.0,
+            index_cost: 1.0,
+        };
+        let events = stream::iter(["a", "a", "a", "b", "c", "c"].map(event));
+        let batches = InfinoBatcher::new(events, &settings(100), target, false)
+            .with_cost(Some(cost))
+            .map(|batch| batch.len())
+            .collect::<Vec<_>>()
+            .await;
+
+        // Two documents of one index, then one document of each of two indexes.
+        assert_eq!(batches, vec![2, 1, 1, 2]);
+    }
+
+    #[test]
+    fn rejects_invalid_costs() {
+        let cost = |max_cost, index_cost| BatchCostConfig {
+            max_cost,
+            document_cost: default_document_cost(),
+            kibibyte_cost: default_kibibyte_cost(),
+            index_cost,
+        };
+        assert!(cost(1000.0, 100.0).validate().is_ok());
+        assert!(cost(0.0, 100.0).validate().is_err());
+        assert!(cost(1000.0, -1.0).validate().is_err());
+        assert!(cost(1000.0, f64::NAN).validate().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_on_timeout() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut batcher = InfinoBatcher::new(rx, &settings(100), target, false);
+
+        tx.unbounded_send(event("a")).unwrap();
+        let started = Instant::now();
+        let batch = batcher.next().await.unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn flushes_on_timeout_under_steady_input() {
+        let target = Arc::new(BatchTarget::new(
+            10 * 1024 * 1024,
+            Duration::from_millis(100),
+        ));
+        // Events keep coming for about half a second, without the input ever being pending.
+        let events = stream::iter(0..500)
+            .map(|_| {
+                std::thread::sleep(Duration::from_millis(1));
+                event("a")
+            })
+            .chain(stream::pending());
+        let mut batcher = InfinoBatcher::new(events, &settings(1000), target, false);
+
+        let batch = batcher.next().await.unwrap();
+        assert!(batch.len() < 500);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_idle_batches() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(60)));
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut batcher = InfinoBatcher::new(rx, &settings(100), target, true)
+            .with_idle_timeout(Some(Duration::from_secs(2)));
+
+        let started = Instant::now();
+        tx.unbounded_send(event("a")).unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        tx.unbounded_send(event("a")).unwrap();
+
+        let batch = batcher.next().await.unwrap();
+        assert_eq!(batch.len(), 2);
+        // Two seconds after the last event, long before the batch timeout.
+        assert_eq!(started.elapsed(), Duration::from_secs(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn caps_event_delay() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(60)));
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut batcher = InfinoBatcher::new(rx, &settings(100), target, false)
+            .with_idle_timeout(Some(Duration::from_secs(10)))
+            .with_max_delay(Some(Duration::from_secs(5)));
+
+        let started = Instant::now();
+        let sender = tokio::spawn(async move {
+            loop {
+                tx.unbounded_send(event("a")).unwrap();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        });
+
+        batcher.next().await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+        sender.abort();
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Response, StatusCode};
+use serde_json::{json, Value as JsonValue};
+use tower::{Layer, Service, ServiceExt};
+use vector_lib::request_metadata::RequestMetadata;
+
+use super::{
+    batch::BatchTarget,
+    partial,
+    service::{BulkOutcome, InfinoRequest, InfinoResponse},
+};
+use crate::{
+    event::EventStatus,
+    sinks::util::{Compression, Compressor},
+};
+
+/// Splits requests rejected with `413 Payload Too Large` in half, and sends each half on its own.
+///
+/// Halves that are still too large are split again, until a request holds a single document. Each
+/// rejection also shrinks the batcher's byte target, so that later batches fit on the first try.
+///
+/// Once the first half has been written, the request can no longer be sent again as a whole, so
+/// the outcomes of the halves are merged item by item into a single bulk response, where the
+/// documents of a half that failed carry the status of its failure. `request_retry_partial` then
+/// sends those documents again on their own.
+#[derive(Clone)]
+pub struct BisectLayer {
+    compression: Compression,
+    ignore_conflicts: bool,
+    target: Arc<BatchTarget>,
+}
+
+impl BisectLayer {
+    pub const fn new(
+        compression: Compression,
+        ignore_conflicts: bool,
+        target: Arc<BatchTarget>,
+    ) -> Self {
+        Self {
+            compression,
+            ignore_conflicts,
+            target,
+        }
+    }
+}
+
+impl<S> Layer<S> for BisectLayer {
+    type Service = BisectService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BisectService {
+            compression: self.compression,
+            ignore_conflicts: self.ignore_conflicts,
+            target: Arc::clone(&self.target),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BisectService<S> {
+    compression: Compression,
+    ignore_conflicts: bool,
+    target: Arc<BatchTarget>,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for BisectService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        // Only the service that was driven to readiness may be called, so take it and leave the
+        // clone behind, as recommended by `tower`.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let compression = self.compression;
+        let ignore_conflicts = self.ignore_conflicts;
+        let target = Arc::clone(&self.target);
+
+        Box::pin(send(
+            inner,
+            req,
+            compression,
+            ignore_conflicts,
+            target,
+            true,
+        ))
+    }
+}
+
+/// Sends `req`, bisecting it for as long as Infino rejects it as too large.
+///
+/// `ready` tells whether `inner` was already driven to readiness.
+fn send<S>(
+    mut inner: S,
+    req: InfinoRequest,
+    compression: Compression,
+    ignore_conflicts: bool,
+    target: Arc<BatchTarget>,
+    ready: bool,
+) -> BoxFuture<'static, Result<InfinoResponse, crate::Error>>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    Box::pin(async move {
+        // Kept aside, as the inner service consumes the request.
+        let retained = (req.batch_size > 1).then(|| req.payload.clone());
+        let partition_key = req.partition_key.clone();
+        let indexes = req.indexes.clone();
+        let lane = req.lane;
+        let encryption_key = req.encryption_key.clone();
+        let headers = req.headers.clone();
+        let query = req.query.clone();
+
+        let response = if ready {
+            inner.call(req).await?
+        } else {
+            inner.ready().await?.call(req).await?
+        };
+
+        let Some(payload) = retained else {
+            return Ok(response);
+        };
+        if response.http_response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+            return Ok(response);
+        }
+
+        let (first, second) = match split_payload(&payload, compression) {
+            Ok(Some(halves)) => halves,
+            Ok(None) => return Ok(response),
+            Err(error) => {
+                warn!(
+                    message = "Unable to split request rejected as too large.",
+                    %error,
+                    internal_log_rate_limit = true
+                );
+                return Ok(response);
+            }
+        };
+
+        let max_bytes = target.shrink();
+        debug!(
+            message = "Request too large, splitting it in half.",
+            batch_size = response.batch_size,
+            payload_bytes = payload.len(),
+            max_bytes,
+        );
+
+        let batch_size = response.batch_size;
+        let events_byte_size = response.events_byte_size;
+        let mut merged: Option<InfinoResponse> = None;
+        let mut items = Vec::with_capacity(batch_size);
+        for half in [first, second] {
+            let actions = half.actions;
+            let half = InfinoRequest {
+                payload: half.payload,
+                finalizers: Default::default(),
+                batch_size: actions.len(),
+                events_byte_size: Default::default(),
+                partition_key: partition_key.clone(),
+                indexes: indexes.clone(),
+                lane,
+                encryption_key: encryption_key.clone(),
+                headers: headers.clone(),
+                query: query.clone(),
+                metadata: RequestMetadata::default(),
+            };
+            let result = send(
+                inner.clone(),
+                half,
+                compression,
+                ignore_conflicts,
+                Arc::clone(&target),
+                false,
+            )
+            .await;
+            match (merged.take(), result) {
+                // Nothing was written yet, so the request is retried or rejected as a whole.
+                (None, Err(error)) => return Err(error),
+                (None, Ok(response)) if !response.http_response.status().is_success() => {
+                    return Ok(response)
+                }
+                (None, Ok(response)) => {
+                    items.extend(half_items(Ok(&response), &actions));
+                    merged = Some(response);
+                }
+                (Some(first), result) => {
+                    items.extend(half_items(result.as_ref(), &actions));
+                    merged = Some(first);
+                }
+            }
+        }
+
+        let mut merged = merged.expect("both halves were sent");
+        let errors = items
+            .iter()
+            .any(|item| !partial::status(item).map_or(false, |s| s.is_success()));
+        let body = json!({ "errors": errors, "items": items });
+        let (parts, _) = merged.http_response.into_parts();
+        merged.http_response = Response::from_parts(parts, Bytes::from(serde_json::to_vec(&body)?));
+        merged.event_status = match BulkOutcome::of(&merged.http_response, ignore_conflicts) {
+            BulkOutcome::Success => EventStatus::Delivered,
+            _ => EventStatus::Rejected,
+        };
+        merged.batch_size = batch_size;
+        merged.events_byte_size = events_byte_size;
+        Ok(merged)
+    })
+}
+
+/// Returns the items of the response to a half, one per document. When the half failed, or its
+/// response holds no items, every document is given the status of the response, or
+/// `503 Service Unavailable` if the half could not be sent, so that it can be retried on its own.
+fn half_items(
+    result: Result<&InfinoResponse, &crate::Error>,
+    actions: &[String],
+) -> Vec<JsonValue> {
+    let (status, reason) = match result {
+        Ok(response) => {
+            let body = serde_json::from_slice::<JsonValue>(response.http_response.body()).ok();
+            match body.and_then(|mut body| body.get_mut("items").map(JsonValue::take)) {
+                Some(JsonValue::Array(items)) if items.len() == actions.len() => return items,
+                _ => {}
+            }
+            let status = response.http_response.status();
+            (status, format!("request failed with status {}", status))
+        }
+        Err(error) => (StatusCode::SERVICE_UNAVAILABLE, error.to_string()),
+    };
+
+    actions
+        .iter()
+        .map(|action| {
+            let mut result = json!({ "status": status.as_u16() });
+            if !status.is_success() {
+                result["error"] = json!({ "type": "request_failed", "reason": reason });
+            }
+            json!({ action.as_str(): result })
+        })
+        .collect()
+}
+
+/// One half of a bisected payload, with the bulk action of each of its documents.
+struct Half {
+    payload: Bytes,
+    actions: Vec<String>,
+}
+
+/// Splits an encoded bulk payload into two payloads holding half of its documents each, or returns
+/// `None` if it holds a single document.
+fn split_payload(payload: &Bytes, compression: Compression) -> io::Result<Option<(Half, Half)>> {
+    let decompressed = decompress(payload, compression)?;
+    let documents = split_documents(&decompressed);
+    if documents.len() < 2 {
+        return Ok(None);
+    }
+
+    let (first, second) = documents.split_at(documents.len() / 2);
+    let half = |documents: &[&[u8]]| {
+        Ok::<_, io::Error>(Half {
+            payload: compress(documents, compression)?,
+            actions: documents.iter().map(|document| action(document)).collect(),
+        })
+    };
+    Ok(Some((half(first)?, half(second)?)))
+}
+
+/// Returns the bulk action of an encoded document, which is the key of its action line.
+fn action(document: &[u8]) -> String {
+    let line = document
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    serde_json::from_slice::<JsonValue>(line)
+        .ok()
+        .and_then(|action| action.as_object()?.keys().next().cloned())
+        .unwrap_or_else(|| "index".to_owned())
+}
+
+/// Splits a decompressed bulk payload into its documents, each made of its action line followed
+/// by its source line, if any, as deletions have none.
+pub(super) fn split_documents(payload: &[u8]) -> Vec<&[u8]> {
+    let mut documents = Vec::new();
+    let mut rest = payload;
+    while !rest.is_empty() {
+        let lines = if rest.starts_with(br#"{"delete":"#) {
+            1
+        } else {
+            2
+        };
+        let end = rest
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .nth(lines - 1)
+            .map_or(rest.len(), |(index, _)| index + 1);
+        let (document, remaining) = rest.split_at(end);
+        documents.push(document);
+        rest = remaining;
+    }
+    documents
+}
+
+pub(super) fn decompress(payload: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    match compression {
+        Compression::None => decompressed.extend_from_slice(payload),
+        Compression::Gzip(_) => {
+            flate2::read::MultiGzDecoder::new(payload).read_to_end(&mut decompressed)?;
+        }
+        Compression::Zlib(_) => {
+            flate2::read::ZlibDecoder::new(payload).read_to_end(&mut decompressed)?;
+        }
+        Compression::Zstd(_) => decompressed = zstd::decode_all(payload)?,
+        Compression::Snappy => {
+            decompressed = snap::raw::Decoder::new()
+                .decompress_vec(payload)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        }
+    }
+    Ok(decompressed)
+}
+
+pub(super) fn compress(lines: &[&[u8]], compression: Compression) -> io::Result<Bytes> {
+    let mut compressor = Compressor::from(compression);
+    for line in lines {
+        compressor.write_all(line)?;
+    }
+    Ok(compressor.finish()?.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+
+    const PAYLOAD: &str = r#"{"index":{"_index":"vector"}}
+{"message":"one"}
+{"index":{"_index":"vector"}}
+{"message":"two"}
+{"index":{"_index":"vector"}}
+{"message":"three"}
+"#;
+
+    #[test]
+    fn splits_payload_in_half() {
+        for compression in [
+            Compression::None,
+            Compression::gzip_default(),
+            Compression::Snappy,
+        ] {
+            let payload = compress(
+                &PAYLOAD
+                    .split_inclusive('\n')
+                    .map(str::as_bytes)
+                    .collect::<Vec<_>>(),
+                compression,
+            )
+            .unwrap();
+
+            let (first, second) = split_payload(&payload, compression).unwrap().unwrap();
+            assert_eq!((first.actions.len(), second.actions.len()), (1, 2));
+
+            let first = decompress(&first.payload, compression).unwrap();
+            let second = decompress(&second.payload, compression).unwrap();
+            assert_eq!(
+                String::from_utf8(first).unwrap(),
+                "{\"index\":{\"_index\":\"vector\"}}\n{\"message\":\"one\"}\n"
+            );
+            assert!(String::from_utf8(second)
+                .unwrap()
+                .ends_with("{\"message\":\"three\"}\n"));
+        }
+    }
+
+    #[test]
+    fn splits_deletions_alone() {
+        let payload = b"{\"delete\":{\"_id\":\"1\"}}\n{\"index\":{}}\n{\"message\":\"one\"}\n";
+        assert_eq!(
+            split_documents(payload),
+            [
+                &b"{\"delete\":{\"_id\":\"1\"}}\n"[..],
+                &b"{\"index\":{}}\n{\"message\":\"one\"}\n"[..],
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_split_single_document() {
+        let payload = Bytes::from_static(b"{\"index\":{}}\n{\"message\":\"one\"}\n");
+        assert!(split_payload(&payload, Compression::None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn merges_the_items_of_halves() {
+        let response = |status: StatusCode, body: &'static str, event_status| InfinoResponse {
+            http_response: Response::builder()
+                .status(status)
+                .body(Bytes::from_static(body.as_bytes()))
+                .unwrap(),
+            event_status,
+            batch_size: 3,
+            events_byte_size: CountByteSize(3, JsonSize::new(3)).into(),
+        };
+        let sent = Arc::new(Mutex::new(0));
+        let service = {
+            let sent = Arc::clone(&sent);
+            tower::service_fn(move |_: InfinoRequest| {
+                let mut sent = sent.lock().unwrap();
+                *sent += 1;
+                let result: Result<_, crate::Error> = match *sent {
+                    1 => Ok(response(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "",
+                        EventStatus::Rejected,
+                    )),
+                    2 => Ok(response(
+                        StatusCode::OK,
+                        r#"{"errors":false,"items":[{"index":{"status":201}}]}"#,
+                        EventStatus::Delivered,
+                    )),
+                    _ => Err("connection reset".into()),
+                };
+                futures::future::ready(result)
+            })
+        };
+        let request = InfinoRequest {
+            payload: Bytes::from_static(PAYLOAD.as_bytes()),
+            finalizers: Default::default(),
+            batch_size: 3,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: RequestMetadata::default(),
+        };
+
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let response = BisectLayer::new(Compression::None, false, target)
+            .layer(service)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), 3);
+
+        // The first half was written, so only the documents of the second half are failed.
+        assert_eq!(response.http_response.status(), StatusCode::OK);
+        assert_eq!(response.event_status, EventStatus::Rejected);
+        let body = serde_json::from_slice::<JsonValue>(response.http_response.body()).unwrap();
+        assert_eq!(body["errors"], JsonValue::Bool(true));
+        let statuses = body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["index"]["status"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [201, 503, 503]);
+    }
+
+    #[tokio::test]
+    async fn returns_failed_first_half() {
+        let target = Arc::new(BatchTarget::new(10 * 1024 * 1024, Duration::from_secs(1)));
+        let sent = Arc::new(Mutex::new(0));
+        let service = {
+            let sent = Arc::clone(&sent);
+            tower::service_fn(move |_: InfinoRequest| {
+                let mut sent = sent.lock().unwrap();
+                *sent += 1;
+                let status = match *sent {
+                    1 => StatusCode::PAYLOAD_TOO_LARGE,
+                    _ => StatusCode::SERVICE_UNAVAILABLE,
+                };
+                futures::future::ok::<_, crate::Error>(InfinoResponse {
+                    http_response: Response::builder()
+                        .status(status)
+                        .body(Bytes::new())
+                        .unwrap(),
+                    event_status: EventStatus::Errored,
+                    batch_size: 3,
+                    events_byte_size: CountByteSize(3, JsonSize::new(3)).into(),
+                })
+            })
+        };
+        let request = InfinoRequest {
+            payload: Bytes::from_static(PAYLOAD.as_bytes()),
+            finalizers: Default::default(),
+            batch_size: 3,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: RequestMetadata::default(),
+        };
+
+        // Nothing was written, so the request is retried as a whole, without its second half.
+        let response = BisectLayer::new(Compression::None, false, target)
+            .layer(service)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), 2);
+        assert_eq!(
+            response.http_response.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
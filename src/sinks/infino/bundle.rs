@@ -0,0 +1,262 @@
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    time::Duration,
+};
+
+use async_stream::stream;
+use futures::{stream::BoxStream, StreamExt};
+use indexmap::IndexMap;
+use vector_lib::{configurable::configurable_component, lookup::lookup_v2::ConfigValuePath};
+use vrl::path::PathPrefix;
+
+use crate::event::{Event, EventMetadata, LogEvent, Value};
+
+/// Bundle configuration.
+///
+/// Packs the logs that share a key, such as the ID of a request, received within a window into a
+/// single document, which holds the key and the logs in an array field. This suits queries that
+/// favor one document per request over one document per log line.
+///
+/// Bundles are written as a single log would be, with the timestamp of their first log. Logs
+/// without the key, and metrics, are written as they are.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BundleConfig {
+    /// The field logs are grouped by.
+    #[configurable(metadata(docs::examples = "request_id"))]
+    pub key: ConfigValuePath,
+
+    /// The array field the logs of a bundle are written to.
+    #[serde(default = "default_field")]
+    #[configurable(metadata(docs::examples = "events"))]
+    pub field: ConfigValuePath,
+
+    /// The maximum number of logs in a bundle.
+    ///
+    /// A bundle is written as soon as it is full, and the next logs with its key start another.
+    #[serde(default = "default_max_events")]
+    pub max_events: NonZeroUsize,
+
+    /// The duration of the window logs are bundled in.
+    #[serde(default = "default_window_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub window_secs: NonZeroU64,
+}
+
+fn default_field() -> ConfigValuePath {
+    "events".into()
+}
+
+const fn default_max_events() -> NonZeroUsize {
+    match NonZeroUsize::new(100) {
+        Some(max_events) => max_events,
+        None => unreachable!(),
+    }
+}
+
+const fn default_window_secs() -> NonZeroU64 {
+    match NonZeroU64::new(5) {
+        Some(window) => window,
+        None => unreachable!(),
+    }
+}
+
+struct Group {
+    key: Value,
+    timestamp: Option<Value>,
+    events: Vec<Value>,
+    metadata: EventMetadata,
+}
+
+struct Bundler {
+    config: BundleConfig,
+    groups: IndexMap<String, Group>,
+}
+
+impl Bundler {
+    fn new(config: BundleConfig) -> Self {
+        Self {
+            config,
+            groups: IndexMap::new(),
+        }
+    }
+
+    /// Adds `log` to the bundle of its key, returning the bundle if it is full, or `log` itself
+    /// if it has no key.
+    fn record(&mut self, log: LogEvent) -> Option<Event> {
+        let Some(key) = log.get((PathPrefix::Event, &self.config.key)).cloned() else {
+            return Some(log.into());
+        };
+        let timestamp = log.get_timestamp().cloned();
+        let (value, metadata) = log.into_parts();
+
+        let id = key.to_string_lossy().into_owned();
+        match self.groups.get_mut(&id) {
+            Some(group) => {
+                group.events.push(value);
+                // Keep the finalizers of every log until the bundle is delivered.
+                group.metadata.merge(metadata);
+            }
+            None => {
+                self.groups.insert(
+                    id.clone(),
+                    Group {
+                        key,
+                        timestamp,
+                        events: vec![value],
+                        metadata,
+                    },
+                );
+            }
+        }
+
+        if self.groups[&id].events.len() < self.config.max_events.get() {
+            return None;
+        }
+        self.groups
+            .shift_remove(&id)
+            .map(|group| self.assemble(group).into())
+    }
+
+    fn assemble(&self, group: Group) -> LogEvent {
+        let mut log = LogEvent::from_parts(Value::Object(Default::default()), group.metadata);
+        if let Some(timestamp) = group.timestamp {
+            if let Some(path) = log.timestamp_path().cloned() {
+                log.insert(&path, timestamp);
+            }
+        }
+        log.insert((PathPrefix::Event, &self.config.key), group.key);
+        log.insert(
+            (PathPrefix::Event, &self.config.field),
+            Value::Array(group.events),
+        );
+        log
+    }
+
+    fn flush_into(&mut self, output: &mut Vec<Event>) {
+        let groups = std::mem::take(&mut self.groups);
+        output.extend(
+            groups
+                .into_values()
+                .map(|group| self.assemble(group).into()),
+        );
+    }
+}
+
+/// Bundles the logs of `input` over windows, passing other events through.
+pub fn bundle(mut input: BoxStream<'_, Event>, config: BundleConfig) -> BoxStream<'_, Event> {
+    Box::pin(stream! {
+        let mut ticks = tokio::time::interval(Duration::from_secs(config.window_secs.get()));
+        let mut bundler = Bundler::new(config);
+        // The first tick completes immediately.
+        ticks.tick().await;
+
+        let mut output = Vec::new();
+        let mut done = false;
+        while !done {
+            tokio::select! {
+                _ = ticks.tick() => bundler.flush_into(&mut output),
+                event = input.next() => match event {
+                    Some(Event::Log(log)) => output.extend(bundler.record(log)),
+                    Some(event) => output.push(event),
+                    None => {
+                        bundler.flush_into(&mut output);
+                        done = true;
+                    }
+                },
+            }
+            for event in output.drain(..) {
+                yield event;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    fn config(max_events: usize) -> BundleConfig {
+        BundleConfig {
+            key: "request_id".into(),
+            field: default_field(),
+            max_events: NonZeroUsize::new(max_events).unwrap(),
+            window_secs: default_window_secs(),
+        }
+    }
+
+    fn log(request_id: Option<&str>, message: &str) -> Event {
+        let mut log = LogEvent::from(message);
+        if let Some(request_id) = request_id {
+            log.insert("request_id", request_id);
+        }
+        log.into()
+    }
+
+    fn messages(event: &Event) -> Vec<String> {
+        match event.as_log().get("events") {
+            Some(Value::Array(events)) => events
+                .iter()
+                .map(|event| {
+                    let message = &event.as_object().unwrap()["message"];
+                    message.to_string_lossy().into_owned()
+                })
+                .collect(),
+            _ => panic!("not a bundle"),
+        }
+    }
+
+    #[tokio::test]
+    async fn bundles_logs_by_key() {
+        let input = stream::iter(vec![
+            log(Some("a"), "a1"),
+            log(Some("b"), "b1"),
+            log(None, "passes through"),
+            log(Some("a"), "a2"),
+        ])
+        .boxed();
+
+        let output = bundle(input, config(100)).collect::<Vec<_>>().await;
+        assert_eq!(output.len(), 3);
+        assert_eq!(
+            output[0].as_log().get_message(),
+            Some(&Value::from("passes through"))
+        );
+        assert_eq!(
+            output[1].as_log().get("request_id"),
+            Some(&Value::from("a"))
+        );
+        assert_eq!(messages(&output[1]), ["a1", "a2"]);
+        assert_eq!(messages(&output[2]), ["b1"]);
+    }
+
+    #[tokio::test]
+    async fn writes_full_bundles() {
+        let input = stream::iter(vec![
+            log(Some("a"), "a1"),
+            log(Some("a"), "a2"),
+            log(Some("a"), "a3"),
+        ])
+        .boxed();
+
+        let output = bundle(input, config(2)).collect::<Vec<_>>().await;
+        assert_eq!(output.len(), 2);
+        assert_eq!(messages(&output[0]), ["a1", "a2"]);
+        assert_eq!(messages(&output[1]), ["a3"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_every_window() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut output = bundle(rx.boxed(), config(100));
+
+        tx.unbounded_send(log(Some("a"), "a1")).unwrap();
+        let started = tokio::time::Instant::now();
+        let event = output.next().await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+        assert_eq!(messages(&event), ["a1"]);
+    }
+}
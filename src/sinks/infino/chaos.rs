@@ -0,0 +1,360 @@
+//! Fault injection for soak testing the `infino` sink.
+//!
+//! The [`ChaosLayer`] wraps the Infino service and randomly delays requests, fails them with
+//! connection resets, answers them with bursts of throttling/unavailability statuses, or rewrites
+//! successful responses into partial failures. It is only compiled in with the `infino-chaos`
+//! feature and must never be enabled against a production cluster.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Response, StatusCode};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+use vector_lib::request_metadata::MetaDescriptive;
+
+use super::{
+    redact::ResponseDiagnostics,
+    service::{get_event_status, InfinoRequest, InfinoResponse},
+};
+use crate::http::HttpError;
+
+/// Fault injection configuration.
+///
+/// All probabilities are between `0.0` and `1.0` and default to `0.0`, which disables the
+/// corresponding fault.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChaosConfig {
+    /// The probability that a request is delayed before being sent.
+    #[serde(default)]
+    pub latency_probability: f64,
+
+    /// The maximum latency injected into a delayed request.
+    ///
+    /// The actual delay is chosen uniformly between zero and this value.
+    #[serde(default = "default_max_latency_ms")]
+    #[configurable(metadata(docs::type_unit = "milliseconds"))]
+    pub max_latency_ms: u64,
+
+    /// The probability that a request fails with a connection reset instead of being sent.
+    #[serde(default)]
+    pub connection_reset_probability: f64,
+
+    /// The probability that a burst of error statuses starts on a given request.
+    #[serde(default)]
+    pub status_burst_probability: f64,
+
+    /// The number of consecutive requests answered with an error status once a burst starts.
+    #[serde(default = "default_status_burst_length")]
+    pub status_burst_length: usize,
+
+    /// The HTTP status codes returned during a burst, chosen at random for each request.
+    #[serde(default = "default_status_burst_codes")]
+    pub status_burst_codes: Vec<u16>,
+
+    /// The probability that a successful response is rewritten to report item-level failures.
+    ///
+    /// Roughly half of the items in a rewritten response are reported as rejected with a
+    /// `429` status.
+    #[serde(default)]
+    pub partial_failure_probability: f64,
+
+    /// The seed for the random number generator, for reproducible runs.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            latency_probability: 0.0,
+            max_latency_ms: default_max_latency_ms(),
+            connection_reset_probability: 0.0,
+            status_burst_probability: 0.0,
+            status_burst_length: default_status_burst_length(),
+            status_burst_codes: default_status_burst_codes(),
+            partial_failure_probability: 0.0,
+            seed: None,
+        }
+    }
+}
+
+const fn default_max_latency_ms() -> u64 {
+    1_000
+}
+
+const fn default_status_burst_length() -> usize {
+    5
+}
+
+fn default_status_burst_codes() -> Vec<u16> {
+    vec![429, 503]
+}
+
+#[derive(Debug)]
+struct ChaosState {
+    config: ChaosConfig,
+    rng: Mutex<SmallRng>,
+    burst_remaining: AtomicUsize,
+}
+
+impl ChaosState {
+    fn roll(&self, probability: f64) -> bool {
+        probability > 0.0
+            && self
+                .rng
+                .lock()
+                .expect("chaos rng lock poisoned")
+                .gen_bool(probability.min(1.0))
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        if self.config.max_latency_ms == 0 || !self.roll(self.config.latency_probability) {
+            return None;
+        }
+        let millis = self
+            .rng
+            .lock()
+            .expect("chaos rng lock poisoned")
+            .gen_range(0..=self.config.max_latency_ms);
+        Some(Duration::from_millis(millis))
+    }
+
+    /// Returns the status to answer with if a burst is in progress or starts with this request.
+    fn burst_status(&self) -> Option<StatusCode> {
+        let codes = &self.config.status_burst_codes;
+        if codes.is_empty() {
+            return None;
+        }
+
+        let in_burst = self
+            .burst_remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok();
+        if !in_burst {
+            if !self.roll(self.config.status_burst_probability) {
+                return None;
+            }
+            self.burst_remaining.store(
+                self.config.status_burst_length.saturating_sub(1),
+                Ordering::Relaxed,
+            );
+        }
+
+        let code = codes[self
+            .rng
+            .lock()
+            .expect("chaos rng lock poisoned")
+            .gen_range(0..codes.len())];
+        StatusCode::from_u16(code).ok()
+    }
+
+    fn partial_failure_body(&self, batch_size: usize) -> Bytes {
+        let mut rng = self.rng.lock().expect("chaos rng lock poisoned");
+        let items = (0..batch_size)
+            .map(|_| {
+                if rng.gen_bool(0.5) {
+                    r#"{"index":{"status":429,"error":{"type":"es_rejected_execution_exception","reason":"injected by chaos layer"}}}"#
+                } else {
+                    r#"{"index":{"status":201}}"#
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        Bytes::from(format!(r#"{{"took":0,"errors":true,"items":[{}]}}"#, items))
+    }
+}
+
+/// Builds a genuine `hyper::Error` so that injected resets take the same retry path as real ones.
+async fn connection_reset() -> crate::Error {
+    let body = hyper::Body::wrap_stream(futures::stream::once(async {
+        Err::<Bytes, _>(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "connection reset injected by chaos layer",
+        ))
+    }));
+    match hyper::body::to_bytes(body).await {
+        Err(source) => HttpError::CallRequest { source }.into(),
+        Ok(_) => unreachable!("stream always yields an error"),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChaosLayer {
+    state: Arc<ChaosState>,
+}
+
+impl ChaosLayer {
+    pub fn new(config: ChaosConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => SmallRng::seed_from_u64(seed),
+            None => SmallRng::from_entropy(),
+        };
+        warn!(
+            message =
+                "Fault injection is enabled for the infino sink. Do not use this in production."
+        );
+        Self {
+            state: Arc::new(ChaosState {
+                config,
+                rng: Mutex::new(rng),
+                burst_remaining: AtomicUsize::new(0),
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for ChaosLayer {
+    type Service = ChaosService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ChaosService {
+            state: Arc::clone(&self.state),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChaosService<S> {
+    state: Arc<ChaosState>,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for ChaosService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: InfinoRequest) -> Self::Future {
+        let state = Arc::clone(&self.state);
+        let latency = state.latency();
+        let reset = state.roll(state.config.connection_reset_probability);
+        let burst_status = if reset { None } else { state.burst_status() };
+
+        // Only the service that was driven to readiness may be called, so take it and leave the
+        // clone behind, as recommended by `tower`.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if let Some(latency) = latency {
+                tokio::time::sleep(latency).await;
+            }
+
+            if reset {
+                return Err(connection_reset().await);
+            }
+
+            if let Some(status) = burst_status {
+                let http_response = Response::builder()
+                    .status(status)
+                    .body(Bytes::new())
+                    .expect("valid response");
+                return Ok(InfinoResponse {
+                    event_status: get_event_status(
+                        &http_response,
+                        false,
+                        &ResponseDiagnostics::default(),
+                    ),
+                    http_response,
+                    batch_size: req.batch_size,
+                    events_byte_size: std::mem::take(req.metadata_mut())
+                        .into_events_estimated_json_encoded_byte_size(),
+                });
+            }
+
+            let mut response = inner.call(req).await?;
+            if response.http_response.status().is_success()
+                && state.roll(state.config.partial_failure_probability)
+            {
+                *response.http_response.body_mut() =
+                    state.partial_failure_body(response.batch_size);
+                response.event_status = get_event_status(
+                    &response.http_response,
+                    false,
+                    &ResponseDiagnostics::default(),
+                );
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(config: ChaosConfig) -> ChaosState {
+        ChaosState {
+            config,
+            rng: Mutex::new(SmallRng::seed_from_u64(42)),
+            burst_remaining: AtomicUsize::new(0),
+        }
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let state = state(ChaosConfig::default());
+        for _ in 0..100 {
+            assert!(state.latency().is_none());
+            assert!(!state.roll(state.config.connection_reset_probability));
+            assert!(state.burst_status().is_none());
+        }
+    }
+
+    #[test]
+    fn bursts_last_configured_length() {
+        let state = state(ChaosConfig {
+            status_burst_probability: 1.0,
+            status_burst_length: 3,
+            status_burst_codes: vec![503],
+            ..Default::default()
+        });
+        for _ in 0..3 {
+            assert_eq!(state.burst_status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        }
+        assert_eq!(state.burst_remaining.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn partial_failure_body_reports_errors() {
+        let state = state(ChaosConfig::default());
+        let body = state.partial_failure_body(4);
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["errors"], true);
+        assert_eq!(value["items"].as_array().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn connection_reset_is_retriable() {
+        let error = connection_reset().await;
+        let error = error.downcast_ref::<HttpError>().unwrap();
+        assert!(error.is_retriable());
+    }
+}
@@ -0,0 +1,76 @@
+use http::HeaderName;
+use sha2::{Digest, Sha256};
+use vector_lib::configurable::configurable_component;
+
+use super::ParseError;
+
+/// Payload checksum configuration.
+///
+/// Every request is sent with the hex-encoded SHA-256 digest of its body, after compression, so
+/// that Infino can reject uploads corrupted on their way, such as by a faulty proxy.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksumConfig {
+    /// The header the checksum is sent in.
+    #[serde(default = "default_header")]
+    #[configurable(metadata(docs::examples = "X-Amz-Content-Sha256"))]
+    pub header: String,
+}
+
+impl Default for ChecksumConfig {
+    fn default() -> Self {
+        Self {
+            header: default_header(),
+        }
+    }
+}
+
+fn default_header() -> String {
+    "X-Infino-Content-SHA256".to_owned()
+}
+
+impl ChecksumConfig {
+    pub fn header_name(&self) -> Result<HeaderName, ParseError> {
+        HeaderName::try_from(self.header.as_str()).map_err(|_| ParseError::InvalidChecksumHeader {
+            header: self.header.clone(),
+        })
+    }
+}
+
+/// Returns the hex-encoded SHA-256 digest of `payload`.
+pub fn checksum(payload: &[u8]) -> String {
+    hex::encode(Sha256::digest(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_payload() {
+        assert_eq!(
+            checksum(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            checksum(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn validates_header() {
+        assert_eq!(
+            ChecksumConfig::default().header_name().unwrap(),
+            "x-infino-content-sha256"
+        );
+        let config = ChecksumConfig {
+            header: "Content SHA".to_owned(),
+        };
+        assert!(matches!(
+            config.header_name(),
+            Err(ParseError::InvalidChecksumHeader { .. })
+        ));
+    }
+}
@@ -1,7 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use bytes::{Buf, Bytes};
-use http::{Response, StatusCode, Uri};
+use http::{
+    header::{CONTENT_TYPE, HOST},
+    Response, StatusCode, Uri,
+};
 use hyper::{body, Body};
 use serde::Deserialize;
 use snafu::ResultExt;
@@ -9,18 +16,30 @@ use vector_lib::config::proxy::ProxyConfig;
 use vector_lib::config::LogNamespace;
 
 use super::{
-    request_builder::InfinoRequestBuilder, InfinoApiVersion, InfinoEncoder, InvalidHostSnafu,
-    Request, VersionType,
+    config::dynamic_template,
+    mapping::IndexTemplateConfig,
+    request_builder::InfinoRequestBuilder,
+    tls::{tls_settings, InfinoTlsConfig, InfinoTlsSettings},
+    ApiVersionCheck, InfinoApiVersion, InfinoEncoder, InfinoMode, InvalidHostSnafu, Request,
+    VersionType,
 };
 use crate::{
+    config::vars,
     http::{HttpClient, MaybeAuth},
     sinks::{
+        infino::{
+            api_key::ApiKey,
+            audit,
+            endpoint::{check_tls, host_header, normalize_endpoint},
+            kerberos::Spnego,
+            oauth2::OAuth2,
+            redact::redact,
+            InfinoAuthConfig, InfinoCommonMode, InfinoConfig, ParseError,
+        },
         util::auth::Auth,
         util::{http::RequestConfig, UriSerde},
         HealthcheckError,
-        Infino::{InfinoAuthConfig, InfinoCommonMode, InfinoConfig, ParseError},
     },
-    tls::TlsSettings,
     transforms::metric_to_log::MetricToLog,
 };
 
@@ -28,10 +47,15 @@ use crate::{
 pub struct InfinoCommon {
     pub mode: InfinoCommonMode,
     pub request_builder: InfinoRequestBuilder,
-    pub tls_settings: TlsSettings,
+    pub tls_settings: InfinoTlsSettings,
+    pub tls: Option<InfinoTlsConfig>,
     pub request: RequestConfig,
     pub query_params: HashMap<String, String>,
     pub metric_to_log: MetricToLog,
+    pub kerberos: Option<Spnego>,
+    pub oauth2: Option<Arc<OAuth2>>,
+    pub api_key: Option<ApiKey>,
+    pub principal: Option<String>,
 }
 
 impl InfinoCommon {
@@ -41,7 +65,10 @@ impl InfinoCommon {
         proxy_config: &ProxyConfig,
         version: &mut Option<usize>,
     ) -> crate::Result<Self> {
-        let mode = config.common_mode()?;
+        let endpoint = &interpolate_endpoint(config, endpoint)?;
+        let endpoint = &normalize_endpoint(endpoint)?;
+        check_tls(endpoint, config.tls.as_ref())?;
+        let mut mode = config.common_mode()?;
 
         let tower_request = config.request.tower.into_settings();
 
@@ -55,6 +82,12 @@ impl InfinoCommon {
         {
             return Err(ParseError::ExternalVersioningWithoutDocumentID.into());
         }
+        if let Some(configured) = config.api_version.major() {
+            if configured < 8 && allows_data_stream_index_op(config) {
+                return Err(ParseError::DataStreamIndexOpUnsupported { configured }.into());
+            }
+        }
+        config.request.content_type.validate()?;
         if config.bulk.version.is_none()
             && (config.bulk.version_type == VersionType::External
                 || config.bulk.version_type == VersionType::ExternalGte)
@@ -62,11 +95,17 @@ impl InfinoCommon {
             return Err(ParseError::ExternalVersioningWithoutVersion.into());
         }
 
-        let mut query_params = config.query.clone().unwrap_or_default();
-        query_params.insert(
-            "timeout".into(),
-            format!("{}s", tower_request.timeout.as_secs()),
-        );
+        // Templated parameters are rendered per request instead.
+        let mut query_params = config
+            .query
+            .iter()
+            .flatten()
+            .filter(|(_, value)| dynamic_template(value).is_none())
+            .map(|(param, value)| (param.clone(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        query_params
+            .entry("timeout".into())
+            .or_insert_with(|| format!("{}s", tower_request.timeout.as_secs()));
 
         if let Some(pipeline) = &config.pipeline {
             if !pipeline.is_empty() {
@@ -83,9 +122,12 @@ impl InfinoCommon {
         };
         let bulk_uri = bulk_url.parse::<Uri>().unwrap();
 
-        let tls_settings = TlsSettings::from_options(&config.tls)?;
+        let tls_settings = tls_settings(&config.tls)?;
+        let kerberos = Spnego::new(config.auth.as_ref())?;
+        let oauth2 = OAuth2::new(config.auth.as_ref(), &tls_settings, proxy_config)?.map(Arc::new);
+        let api_key = ApiKey::new(config.auth.as_ref())?;
         let config = config.clone();
-        let request = config.request;
+        let request = config.request.http();
 
         let metric_config = config.metrics.clone().unwrap_or_default();
         let metric_to_log = MetricToLog::new(
@@ -99,18 +141,50 @@ impl InfinoCommon {
             version
         } else {
             let ver = match config.api_version {
-                InfinoApiVersion::V6 => 6,
-                InfinoApiVersion::V7 => 7,
-                InfinoApiVersion::V8 => 8,
+                InfinoApiVersion::V6 | InfinoApiVersion::V7 | InfinoApiVersion::V8 => {
+                    if config.api_version_check != ApiVersionCheck::Disabled {
+                        let server_version = get_version(
+                            &base_url,
+                            &auth,
+                            kerberos.as_ref(),
+                            oauth2.as_deref(),
+                            api_key.as_ref(),
+                            &request,
+                            &tls_settings,
+                            proxy_config,
+                        )
+                        .await
+                        .map_err(|error| error.to_string());
+                        preflight_version(config, server_version)?;
+                    }
+                    config.api_version.major().expect("explicit api version")
+                }
                 InfinoApiVersion::Auto => {
-                    match get_version(&base_url, &auth, &request, &tls_settings, proxy_config).await
+                    match get_version(
+                        &base_url,
+                        &auth,
+                        kerberos.as_ref(),
+                        oauth2.as_deref(),
+                        api_key.as_ref(),
+                        &request,
+                        &tls_settings,
+                        proxy_config,
+                    )
+                    .await
                     {
                         Ok(version) => {
                             debug!(message = "Auto-detected Infino API version.", %version);
+                            preflight_version(config, Ok(version))?;
                             version
                         }
                         // This error should be fatal, but for now we only emit it as a warning
                         // to make the transition smoother.
+                        Err(error) if config.api_version_check == ApiVersionCheck::Strict => {
+                            return Err(ParseError::ServerVersionUnavailable {
+                                reason: redact(&error.to_string()).into_owned(),
+                            }
+                            .into());
+                        }
                         Err(error) => {
                             // For now, estimate version.
                             // The `suppress_type_name` option is only valid up to V6, so if a user
@@ -125,7 +199,7 @@ impl InfinoCommon {
                             );
                             warn!(message = "Failed to determine Infino API version. Please fix the reported error or set an API version explicitly via `api_version`.",
                                   %assumed_version,
-                                  %error
+                                  error = %redact(&error.to_string())
                             );
                             assumed_version
                         }
@@ -136,6 +210,37 @@ impl InfinoCommon {
             ver
         };
 
+        if let InfinoCommonMode::Bulk { data_streams, .. } = &mut mode {
+            // Data streams only exist from version 7 on.
+            if config.mode == InfinoMode::Auto && version >= 7 {
+                match get_data_streams(
+                    &base_url,
+                    &auth,
+                    kerberos.as_ref(),
+                    oauth2.as_deref(),
+                    api_key.as_ref(),
+                    &request,
+                    &tls_settings,
+                    proxy_config,
+                )
+                .await
+                {
+                    Ok(names) => {
+                        debug!(
+                            message = "Detected Infino data streams.",
+                            count = names.len()
+                        );
+                        *data_streams = Arc::new(names);
+                    }
+                    Err(error) => {
+                        warn!(message = "Failed to list Infino data streams, all documents use the bulk action.",
+                              error = %redact(&error.to_string())
+                        );
+                    }
+                }
+            }
+        }
+
         let doc_type = config.doc_type.clone();
         let suppress_type_name = if config.suppress_type_name {
             warn!(message = "DEPRECATION, use of deprecated option `suppress_type_name`. Please use `api_version` option instead.");
@@ -149,7 +254,11 @@ impl InfinoCommon {
                 transformer: config.encoding.clone(),
                 doc_type,
                 suppress_type_name,
+                float_precision: config.float_precision,
+                non_finite_floats: config.non_finite_floats,
+                invalid_utf8: config.invalid_utf8,
             },
+            ordered: config.ordered,
         };
 
         Ok(Self {
@@ -161,7 +270,12 @@ impl InfinoCommon {
             query_params,
             request,
             tls_settings,
+            tls: config.tls.clone(),
             metric_to_log,
+            kerberos,
+            oauth2,
+            api_key,
+            principal: config.auth.as_ref().map(audit::principal),
         })
     }
 
@@ -171,11 +285,16 @@ impl InfinoCommon {
         proxy_config: &ProxyConfig,
     ) -> crate::Result<Vec<Self>> {
         let mut version = None;
+        if config.endpoint_overrides.keys().any(|endpoint| {
+            !config.endpoints.contains(endpoint) && config.endpoint.as_ref() != Some(endpoint)
+        }) {
+            return Err(ParseError::UnknownOverriddenEndpoint.into());
+        }
         if let Some(endpoint) = config.endpoint.as_ref() {
-            warn!(message = "DEPRECATION, use of deprecated option `endpoint`. Please use `endpoints` option instead.");
             if config.endpoints.is_empty() {
+                let config = config.for_endpoint(endpoint);
                 Ok(vec![
-                    Self::parse_config(config, endpoint, proxy_config, &mut version).await?,
+                    Self::parse_config(&config, endpoint, proxy_config, &mut version).await?,
                 ])
             } else {
                 Err(ParseError::EndpointsExclusive.into())
@@ -185,8 +304,9 @@ impl InfinoCommon {
         } else {
             let mut commons = Vec::new();
             for endpoint in config.endpoints.iter() {
+                let config = config.for_endpoint(endpoint);
                 commons
-                    .push(Self::parse_config(config, endpoint, proxy_config, &mut version).await?);
+                    .push(Self::parse_config(&config, endpoint, proxy_config, &mut version).await?);
             }
             Ok(commons)
         }
@@ -205,6 +325,9 @@ impl InfinoCommon {
         match get(
             &self.base_url,
             &self.auth,
+            self.kerberos.as_ref(),
+            self.oauth2.as_deref(),
+            self.api_key.as_ref(),
             &self.request,
             client,
             "/_cluster/health",
@@ -216,6 +339,146 @@ impl InfinoCommon {
             status => Err(HealthcheckError::UnexpectedStatus { status }.into()),
         }
     }
+
+    /// Sends a request to `path` on the endpoint, authenticated as the bulk requests are.
+    pub async fn request(
+        &self,
+        client: HttpClient,
+        method: http::Method,
+        path: &str,
+        body: Bytes,
+    ) -> crate::Result<Response<Body>> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.base_url, path));
+        if !body.is_empty() {
+            builder = builder.header(CONTENT_TYPE, "application/json");
+        }
+        send(
+            builder,
+            body,
+            &self.auth,
+            self.kerberos.as_ref(),
+            self.oauth2.as_deref(),
+            self.api_key.as_ref(),
+            &self.request,
+            client,
+        )
+        .await
+    }
+
+    /// Installs `template` on the endpoint, replacing any template of the same name.
+    pub async fn install_index_template(
+        &self,
+        client: HttpClient,
+        template: &IndexTemplateConfig,
+    ) -> crate::Result<()> {
+        let body = serde_json::to_vec(&template.body())?;
+        let response = put(
+            &self.base_url,
+            &self.auth,
+            self.kerberos.as_ref(),
+            self.oauth2.as_deref(),
+            self.api_key.as_ref(),
+            &self.request,
+            client,
+            &format!("/_index_template/{}", template.name),
+            body.into(),
+        )
+        .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Failed to install Infino index template {:?} on {}: unexpected status {}",
+                template.name,
+                redact(&self.base_url),
+                status
+            )
+            .into())
+        }
+    }
+}
+
+/// Interpolates the environment variables of `endpoint` if `interpolate_endpoints` is enabled.
+///
+/// The environment is that of the process, as when the configuration was loaded.
+fn interpolate_endpoint<'a>(
+    config: &InfinoConfig,
+    endpoint: &'a str,
+) -> Result<Cow<'a, str>, ParseError> {
+    if !config.interpolate_endpoints {
+        return Ok(Cow::Borrowed(endpoint));
+    }
+    let vars = std::env::vars().collect::<HashMap<_, _>>();
+    vars::interpolate(endpoint, &vars)
+        .map(Cow::Owned)
+        .map_err(|errors| ParseError::EndpointInterpolation {
+            errors: errors.join(", "),
+        })
+}
+
+/// Checks the configuration against the version reported by the server, reacting to any
+/// incompatibility as configured by `api_version_check`.
+fn preflight_version(
+    config: &InfinoConfig,
+    server_version: Result<usize, String>,
+) -> Result<(), ParseError> {
+    let result = server_version
+        .map_err(|reason| ParseError::ServerVersionUnavailable { reason })
+        .and_then(|server| check_version_compatibility(config, server));
+
+    match (result, config.api_version_check) {
+        (Ok(()), _) | (Err(_), ApiVersionCheck::Disabled) => Ok(()),
+        (Err(error), ApiVersionCheck::Strict) => Err(error),
+        (Err(error), ApiVersionCheck::Warn) => {
+            warn!(
+                message = "Infino server version is not compatible with the sink configuration.",
+                %error
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Returns the first incompatibility between the configuration and the server's major version.
+pub(super) fn check_version_compatibility(
+    config: &InfinoConfig,
+    server: usize,
+) -> Result<(), ParseError> {
+    if let Some(configured) = config.api_version.major() {
+        if configured != server {
+            return Err(ParseError::ApiVersionMismatch { configured, server });
+        }
+    }
+
+    if config.mode == InfinoMode::DataStream && server < 7 {
+        return Err(ParseError::UnsupportedFeature {
+            feature: "Data stream mode",
+            required: 7,
+            server,
+        });
+    }
+
+    if allows_data_stream_index_op(config) && server < 8 {
+        return Err(ParseError::UnsupportedFeature {
+            feature: "`data_stream.allow_index_op`",
+            required: 8,
+            server,
+        });
+    }
+
+    Ok(())
+}
+
+fn allows_data_stream_index_op(config: &InfinoConfig) -> bool {
+    config.mode == InfinoMode::DataStream
+        && config
+            .data_stream
+            .as_ref()
+            .map_or(false, |data_stream| data_stream.allow_index_op)
 }
 
 #[cfg(feature = "aws-core")]
@@ -227,11 +490,15 @@ pub async fn sign_request(
     crate::aws::sign_request("es", request, credentials_provider, region).await
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn get_version(
     base_url: &str,
     auth: &Option<Auth>,
+    kerberos: Option<&Spnego>,
+    oauth2: Option<&OAuth2>,
+    api_key: Option<&ApiKey>,
     request: &RequestConfig,
-    tls_settings: &TlsSettings,
+    tls_settings: &InfinoTlsSettings,
     proxy_config: &ProxyConfig,
 ) -> crate::Result<usize> {
     #[derive(Deserialize)]
@@ -243,10 +510,12 @@ async fn get_version(
         version: Option<Version>,
     }
 
-    let client = HttpClient::new(tls_settings.clone(), proxy_config)?;
-    let response = get(base_url, auth, request, client, "/")
-        .await
-        .map_err(|error| format!("Failed to get Infino API version: {}", error))?;
+    let client = tls_settings.http_client(proxy_config)?;
+    let response = get(
+        base_url, auth, kerberos, oauth2, api_key, request, client, "/",
+    )
+    .await
+    .map_err(|error| format!("Failed to get Infino API version: {}", error))?;
 
     let (_, body) = response.into_parts();
     let mut body = body::aggregate(body).await?;
@@ -268,19 +537,113 @@ async fn get_version(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
+async fn get_data_streams(
+    base_url: &str,
+    auth: &Option<Auth>,
+    kerberos: Option<&Spnego>,
+    oauth2: Option<&OAuth2>,
+    api_key: Option<&ApiKey>,
+    request: &RequestConfig,
+    tls_settings: &InfinoTlsSettings,
+    proxy_config: &ProxyConfig,
+) -> crate::Result<HashSet<String>> {
+    #[derive(Deserialize)]
+    struct DataStream {
+        name: String,
+    }
+    #[derive(Deserialize)]
+    struct ResponsePayload {
+        data_streams: Vec<DataStream>,
+    }
+
+    let client = tls_settings.http_client(proxy_config)?;
+    let response = get(
+        base_url,
+        auth,
+        kerberos,
+        oauth2,
+        api_key,
+        request,
+        client,
+        "/_data_stream",
+    )
+    .await?;
+    let status = response.status();
+    if status != StatusCode::OK {
+        return Err(HealthcheckError::UnexpectedStatus { status }.into());
+    }
+
+    let (_, body) = response.into_parts();
+    let mut body = body::aggregate(body).await?;
+    let body = body.copy_to_bytes(body.remaining());
+    let ResponsePayload { data_streams } = serde_json::from_slice(&body)?;
+    Ok(data_streams.into_iter().map(|stream| stream.name).collect())
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn get(
     base_url: &str,
     auth: &Option<Auth>,
+    kerberos: Option<&Spnego>,
+    oauth2: Option<&OAuth2>,
+    api_key: Option<&ApiKey>,
     request: &RequestConfig,
     client: HttpClient,
     path: &str,
 ) -> crate::Result<Response<Body>> {
-    let mut builder = Request::get(format!("{}{}", base_url, path));
+    let builder = Request::get(format!("{}{}", base_url, path));
+    send(
+        builder,
+        Bytes::new(),
+        auth,
+        kerberos,
+        oauth2,
+        api_key,
+        request,
+        client,
+    )
+    .await
+}
 
+#[allow(clippy::too_many_arguments)]
+async fn put(
+    base_url: &str,
+    auth: &Option<Auth>,
+    kerberos: Option<&Spnego>,
+    oauth2: Option<&OAuth2>,
+    api_key: Option<&ApiKey>,
+    request: &RequestConfig,
+    client: HttpClient,
+    path: &str,
+    body: Bytes,
+) -> crate::Result<Response<Body>> {
+    let builder =
+        Request::put(format!("{}{}", base_url, path)).header(CONTENT_TYPE, "application/json");
+    send(
+        builder, body, auth, kerberos, oauth2, api_key, request, client,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn send(
+    mut builder: http::request::Builder,
+    body: Bytes,
+    auth: &Option<Auth>,
+    kerberos: Option<&Spnego>,
+    oauth2: Option<&OAuth2>,
+    api_key: Option<&ApiKey>,
+    request: &RequestConfig,
+    client: HttpClient,
+) -> crate::Result<Response<Body>> {
+    if let Some(host) = builder.uri_ref().and_then(host_header) {
+        builder = builder.header(HOST, host);
+    }
     for (header, value) in &request.headers {
         builder = builder.header(&header[..], &value[..]);
     }
-    let mut request = builder.body(Bytes::new())?;
+    let mut request = builder.body(body)?;
 
     if let Some(auth) = auth {
         match auth {
@@ -297,6 +660,15 @@ async fn get(
             }
         }
     }
+    if let Some(kerberos) = kerberos {
+        kerberos.apply(&mut request).await?;
+    }
+    if let Some(oauth2) = oauth2 {
+        oauth2.apply(&mut request).await?;
+    }
+    if let Some(api_key) = api_key {
+        api_key.apply(&mut request);
+    }
 
     client
         .send(request.map(hyper::Body::from))
@@ -0,0 +1,365 @@
+//! Dead-letter file for rejected documents.
+//!
+//! Documents that Infino rejects with a status that retrying would not change, such as a mapping
+//! conflict or a malformed document, are appended to a file along with the reason they were
+//! rejected, so that they can be inspected and sent again rather than being lost.
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Response, StatusCode};
+use serde_json::{json, Value as JsonValue};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    bisect::{decompress, split_documents},
+    partial::status,
+    redact::ResponseDiagnostics,
+    retry::is_retriable,
+    service::{InfinoRequest, InfinoResponse},
+};
+use crate::{
+    internal_events::{InfinoDeadLetterFull, InfinoDocumentsDeadLettered},
+    sinks::util::Compression,
+};
+
+/// Dead-letter configuration.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DeadLetterConfig {
+    /// The file rejected documents are appended to, one JSON object per line.
+    ///
+    /// Each line holds the action and source of the document, the status and error it was
+    /// rejected with, and the ID the server gave to its request. It must not be shared with
+    /// another sink.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/infino-rejected.ndjson"))]
+    pub path: PathBuf,
+
+    /// The maximum size of the file, in bytes.
+    ///
+    /// Documents rejected once the file is full are dropped.
+    #[serde(default = "default_max_bytes")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub max_bytes: u64,
+}
+
+const fn default_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// The dead-letter file, and the number of bytes written to it.
+struct DeadLetterFile {
+    file: File,
+    bytes: u64,
+    max_bytes: u64,
+}
+
+impl DeadLetterFile {
+    /// Appends `records`, unless the file is full.
+    async fn append(&mut self, records: &[JsonValue]) -> crate::Result<bool> {
+        let mut contents = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut contents, record)?;
+            contents.push(b'\n');
+        }
+        let size = contents.len() as u64;
+        if self.bytes + size > self.max_bytes {
+            return Ok(false);
+        }
+        self.file.write_all(&contents).await?;
+        self.file.flush().await?;
+        self.bytes += size;
+        Ok(true)
+    }
+}
+
+#[derive(Clone)]
+pub struct DeadLetterLayer {
+    file: Arc<Mutex<DeadLetterFile>>,
+    compression: Compression,
+    ignore_conflicts: bool,
+    diagnostics: ResponseDiagnostics,
+}
+
+impl DeadLetterLayer {
+    pub fn new(
+        config: &DeadLetterConfig,
+        compression: Compression,
+        ignore_conflicts: bool,
+        diagnostics: ResponseDiagnostics,
+    ) -> crate::Result<Self> {
+        let open = || {
+            if let Some(parent) = config.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&config.path)
+        };
+        let file = open().map_err(|error| {
+            format!(
+                "Unable to open the dead-letter file {:?}: {}",
+                config.path, error
+            )
+        })?;
+        let bytes = file.metadata()?.len();
+        Ok(Self {
+            file: Arc::new(Mutex::new(DeadLetterFile {
+                file: File::from_std(file),
+                bytes,
+                max_bytes: config.max_bytes,
+            })),
+            compression,
+            ignore_conflicts,
+            diagnostics,
+        })
+    }
+}
+
+impl<S> Layer<S> for DeadLetterLayer {
+    type Service = DeadLetterService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadLetterService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DeadLetterService<S> {
+    layer: DeadLetterLayer,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for DeadLetterService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let payload = req.payload.clone();
+        let layer = self.layer.clone();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let result = future.await;
+            if let Ok(response) = &result {
+                let records = rejected(&payload, &response.http_response, &layer);
+                if !records.is_empty() {
+                    write(&layer, &records).await;
+                }
+            }
+            result
+        })
+    }
+}
+
+async fn write(layer: &DeadLetterLayer, records: &[JsonValue]) {
+    let count = records.len();
+    match layer.file.lock().await.append(records).await {
+        Ok(true) => emit!(InfinoDocumentsDeadLettered { count }),
+        Ok(false) => emit!(InfinoDeadLetterFull { count }),
+        Err(error) => warn!(
+            message = "Unable to write rejected documents to the dead-letter file.",
+            %error,
+            internal_log_rate_limit = true,
+        ),
+    }
+}
+
+/// Returns the records of the documents of `payload` that `response` rejected for good.
+fn rejected(
+    payload: &Bytes,
+    response: &Response<Bytes>,
+    layer: &DeadLetterLayer,
+) -> Vec<JsonValue> {
+    if !response.status().is_success() {
+        return Vec::new();
+    }
+    let Ok(mut body) = serde_json::from_slice::<JsonValue>(response.body()) else {
+        return Vec::new();
+    };
+    if body.get("errors") != Some(&JsonValue::Bool(true)) {
+        return Vec::new();
+    }
+    let Some(items) = body.get_mut("items").and_then(JsonValue::as_array_mut) else {
+        return Vec::new();
+    };
+    let Ok(decompressed) = decompress(payload, layer.compression) else {
+        return Vec::new();
+    };
+    let documents = split_documents(&decompressed);
+    if documents.len() != items.len() {
+        return Vec::new();
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let request_id = layer.diagnostics.request_id(response);
+    documents
+        .into_iter()
+        .zip(items.iter_mut())
+        .filter_map(|(document, item)| {
+            let status = status(item)?;
+            let rejected = !status.is_success()
+                && !is_retriable(status)
+                && !(layer.ignore_conflicts && status == StatusCode::CONFLICT);
+            if !rejected {
+                return None;
+            }
+            let error = item
+                .as_object_mut()?
+                .values_mut()
+                .next()?
+                .get_mut("error")
+                .map_or(JsonValue::Null, JsonValue::take);
+            Some(record(document, status, error, &timestamp, request_id))
+        })
+        .collect()
+}
+
+/// Returns the record of a rejected `document`, made of its action line and source line, if any.
+fn record(
+    document: &[u8],
+    status: StatusCode,
+    error: JsonValue,
+    timestamp: &str,
+    request_id: Option<&str>,
+) -> JsonValue {
+    let mut lines = document
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty());
+    let (action, metadata) = lines
+        .next()
+        .and_then(|line| serde_json::from_slice::<JsonValue>(line).ok())
+        .and_then(|line| match line {
+            JsonValue::Object(line) => line.into_iter().next(),
+            _ => None,
+        })
+        .unwrap_or_else(|| (String::new(), JsonValue::Null));
+    // Sources that are not JSON, such as those of raw formats, are kept as strings.
+    let source = lines.next().map(|line| {
+        serde_json::from_slice(line)
+            .unwrap_or_else(|_| JsonValue::String(String::from_utf8_lossy(line).into_owned()))
+    });
+    json!({
+        "timestamp": timestamp,
+        "action": action,
+        "metadata": metadata,
+        "source": source,
+        "status": status.as_u16(),
+        "error": error,
+        "request_id": request_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+    use crate::event::EventStatus;
+
+    const PAYLOAD: &str = "{\"index\":{\"_index\":\"logs\"}}\n{\"message\":\"one\"}\n{\"index\":{\"_index\":\"logs\"}}\n{\"message\":\"two\"}\n{\"create\":{\"_index\":\"logs\",\"_id\":\"3\"}}\n{\"message\":\"three\"}\n{\"delete\":{\"_index\":\"logs\",\"_id\":\"4\"}}\n";
+    const BODY: &str = r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse field [message]"}}},{"create":{"status":409}},{"delete":{"status":503}}]}"#;
+
+    fn layer(path: PathBuf, max_bytes: u64) -> DeadLetterLayer {
+        DeadLetterLayer::new(
+            &DeadLetterConfig { path, max_bytes },
+            Compression::None,
+            true,
+            ResponseDiagnostics::default(),
+        )
+        .unwrap()
+    }
+
+    fn response() -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("x-request-id", "abc")
+            .body(Bytes::from_static(BODY.as_bytes()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn keeps_only_rejected_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let layer = layer(dir.path().join("rejected.ndjson"), default_max_bytes());
+
+        // Conflicts are ignored, and server errors may succeed when retried.
+        let records = rejected(&Bytes::from_static(PAYLOAD.as_bytes()), &response(), &layer);
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record["action"], "index");
+        assert_eq!(record["metadata"], json!({"_index": "logs"}));
+        assert_eq!(record["source"], json!({"message": "two"}));
+        assert_eq!(record["status"], 400);
+        assert_eq!(record["error"]["type"], "mapper_parsing_exception");
+        assert_eq!(record["request_id"], "abc");
+    }
+
+    #[tokio::test]
+    async fn appends_rejected_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead-letter").join("rejected.ndjson");
+        let service = tower::service_fn(|_: InfinoRequest| {
+            futures::future::ok::<_, crate::Error>(InfinoResponse {
+                http_response: response(),
+                event_status: EventStatus::Rejected,
+                batch_size: 4,
+                events_byte_size: CountByteSize(4, JsonSize::new(4)).into(),
+            })
+        });
+        let mut service = layer(path.clone(), default_max_bytes()).layer(service);
+
+        for _ in 0..2 {
+            let request = InfinoRequest {
+                payload: Bytes::from_static(PAYLOAD.as_bytes()),
+                finalizers: Default::default(),
+                batch_size: 4,
+                events_byte_size: Default::default(),
+                partition_key: None,
+                indexes: Default::default(),
+                lane: Default::default(),
+                encryption_key: None,
+                headers: Vec::new(),
+                query: Vec::new(),
+                metadata: Default::default(),
+            };
+            service.call(request).await.unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let record = serde_json::from_str::<JsonValue>(lines[1]).unwrap();
+        assert_eq!(record["source"]["message"], "two");
+    }
+
+    #[tokio::test]
+    async fn drops_records_once_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rejected.ndjson");
+        let layer = layer(path.clone(), 16);
+        let records = rejected(&Bytes::from_static(PAYLOAD.as_bytes()), &response(), &layer);
+        assert!(!layer.file.lock().await.append(&records).await.unwrap());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+    }
+}
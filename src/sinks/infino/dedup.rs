@@ -0,0 +1,299 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use lru::LruCache;
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vrl::path::PathPrefix;
+
+use crate::{
+    event::{BatchNotifier, BatchStatus, EventFinalizer, EventStatus, Finalizable},
+    internal_events::InfinoDuplicateEventsDropped,
+    sinks::infino::encoder::ProcessedEvent,
+};
+
+/// Deduplication configuration.
+///
+/// Keeps a hash of the recently delivered events and suppresses any event whose hash is already in
+/// the window, before it is encoded. Events are only remembered once Infino accepted them, so that
+/// events redelivered after a failed request are sent again. Suppressed events are acknowledged as
+/// delivered. This is intended for sources that redeliver events, such as Kafka during consumer
+/// group rebalances.
+///
+/// At most `max_entries` events are awaiting delivery at any time. Events sent while that many are
+/// in flight are not remembered.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DedupConfig {
+    /// The fields that identify an event.
+    ///
+    /// Two events with the same bulk action, targeting the same index, with equal values for all of
+    /// these fields are duplicates. By default, the whole event is compared.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "message"))]
+    #[configurable(metadata(docs::examples = "request_id"))]
+    pub fields: Vec<ConfigValuePath>,
+
+    /// The number of recently seen events to remember.
+    #[serde(default = "default_max_entries")]
+    #[configurable(metadata(docs::type_unit = "events"))]
+    pub max_entries: NonZeroUsize,
+
+    /// How long an event is remembered for.
+    #[serde(default = "default_ttl_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub ttl_secs: u64,
+}
+
+const fn default_max_entries() -> NonZeroUsize {
+    match NonZeroUsize::new(5_000) {
+        Some(size) => size,
+        None => unreachable!(),
+    }
+}
+
+const fn default_ttl_secs() -> u64 {
+    300
+}
+
+/// The window of recently delivered event hashes.
+pub struct Dedup {
+    fields: Vec<ConfigValuePath>,
+    ttl: Duration,
+    cache: LruCache<u64, Instant>,
+    /// The hashes of the events that have been sent, resolved with their delivery status. Holds at
+    /// most as many events as the cache.
+    pending: FuturesUnordered<BoxFuture<'static, (u64, BatchStatus)>>,
+}
+
+impl Dedup {
+    pub fn new(config: &DedupConfig) -> Self {
+        Self {
+            fields: config.fields.clone(),
+            ttl: Duration::from_secs(config.ttl_secs),
+            cache: LruCache::new(config.max_entries),
+            pending: FuturesUnordered::new(),
+        }
+    }
+
+    fn hash(&self, event: &ProcessedEvent) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        event.index.hash(&mut hasher);
+        event.bulk_action.hash(&mut hasher);
+        if self.fields.is_empty() {
+            if let Ok(document) = serde_json::to_vec(event.log.value()) {
+                document.hash(&mut hasher);
+            }
+        } else {
+            for field in &self.fields {
+                match event.log.get((PathPrefix::Event, field)) {
+                    Some(value) => value.coerce_to_bytes().hash(&mut hasher),
+                    // Keep missing fields distinct from fields holding an empty value.
+                    None => 0xffu8.hash(&mut hasher),
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Returns the event, or `None` if it is a duplicate of an event seen within the window.
+    pub fn check(&mut self, mut event: ProcessedEvent) -> Option<ProcessedEvent> {
+        let now = Instant::now();
+        self.record_delivered(now);
+
+        let hash = self.hash(&event);
+        if self.is_new(hash, now) {
+            // Past the capacity of the cache, events are sent without being remembered, as their
+            // delivery would evict the hashes of those before them anyway.
+            if self.pending.len() < self.cache.cap().get() {
+                let (batch, receiver) = BatchNotifier::new_with_receiver();
+                event.log.add_finalizer(EventFinalizer::new(batch));
+                self.pending
+                    .push(receiver.map(move |status| (hash, status)).boxed());
+            }
+            Some(event)
+        } else {
+            event
+                .take_finalizers()
+                .update_status(EventStatus::Delivered);
+            emit!(InfinoDuplicateEventsDropped { count: 1 });
+            None
+        }
+    }
+
+    /// Remembers the events that have been delivered since the last check.
+    fn record_delivered(&mut self, now: Instant) {
+        while let Some(Some((hash, status))) = self.pending.next().now_or_never() {
+            if status == BatchStatus::Delivered {
+                self.cache.put(hash, now);
+            }
+        }
+    }
+
+    fn is_new(&mut self, hash: u64, now: Instant) -> bool {
+        match self.cache.get(&hash) {
+            Some(seen) => now.duration_since(*seen) >= self.ttl,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::LogEvent,
+        sinks::infino::{BulkAction, DocumentMetadata},
+    };
+
+    fn event(message: &str, request_id: &str) -> ProcessedEvent {
+        let mut log = LogEvent::from(message);
+        log.insert("request_id", request_id);
+        ProcessedEvent {
+            index: "vector".to_owned(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    fn dedup(fields: &[&str], ttl_secs: u64) -> Dedup {
+        Dedup::new(&DedupConfig {
+            fields: fields
+                .iter()
+                .map(|field| ConfigValuePath::try_from(field.to_string()).unwrap())
+                .collect(),
+            max_entries: default_max_entries(),
+            ttl_secs,
+        })
+    }
+
+    /// Sends `event` through `dedup`, and completes its delivery with `status`.
+    fn send(dedup: &mut Dedup, event: ProcessedEvent, status: EventStatus) -> bool {
+        match dedup.check(event) {
+            Some(mut event) => {
+                event.log.take_finalizers().update_status(status);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn suppresses_delivered_duplicates() {
+        let mut dedup = dedup(&[], 60);
+        assert!(send(
+            &mut dedup,
+            event("hello", "1"),
+            EventStatus::Delivered
+        ));
+        assert!(!send(
+            &mut dedup,
+            event("hello", "1"),
+            EventStatus::Delivered
+        ));
+        assert!(send(
+            &mut dedup,
+            event("hello", "2"),
+            EventStatus::Delivered
+        ));
+    }
+
+    #[test]
+    fn resends_failed_events() {
+        let mut dedup = dedup(&[], 60);
+        assert!(send(&mut dedup, event("hello", "1"), EventStatus::Rejected));
+        assert!(send(&mut dedup, event("hello", "1"), EventStatus::Errored));
+        assert!(send(
+            &mut dedup,
+            event("hello", "1"),
+            EventStatus::Delivered
+        ));
+        assert!(!send(
+            &mut dedup,
+            event("hello", "1"),
+            EventStatus::Delivered
+        ));
+    }
+
+    #[test]
+    fn expires_after_ttl() {
+        let mut dedup = dedup(&[], 60);
+        let hash = dedup.hash(&event("hello", "1"));
+        let now = Instant::now();
+        dedup.cache.put(hash, now);
+        assert!(!dedup.is_new(hash, now));
+        assert!(dedup.is_new(hash, now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn compares_configured_fields() {
+        let mut dedup = dedup(&["request_id"], 60);
+        assert!(send(
+            &mut dedup,
+            event("first", "1"),
+            EventStatus::Delivered
+        ));
+        assert!(!send(
+            &mut dedup,
+            event("second", "1"),
+            EventStatus::Delivered
+        ));
+    }
+
+    #[test]
+    fn compares_bulk_actions() {
+        let mut dedup = dedup(&["request_id"], 60);
+        assert!(send(
+            &mut dedup,
+            event("first", "1"),
+            EventStatus::Delivered
+        ));
+        let delete = ProcessedEvent {
+            bulk_action: BulkAction::Delete,
+            ..event("first", "1")
+        };
+        assert!(send(&mut dedup, delete, EventStatus::Delivered));
+    }
+
+    #[test]
+    fn bounds_pending_events() {
+        let mut dedup = Dedup::new(&DedupConfig {
+            fields: Vec::new(),
+            max_entries: NonZeroUsize::new(1).unwrap(),
+            ttl_secs: 60,
+        });
+        let mut in_flight = dedup.check(event("hello", "1")).unwrap();
+        assert!(send(
+            &mut dedup,
+            event("hello", "2"),
+            EventStatus::Delivered
+        ));
+        assert_eq!(dedup.pending.len(), 1);
+        assert!(send(
+            &mut dedup,
+            event("hello", "2"),
+            EventStatus::Delivered
+        ));
+
+        in_flight
+            .log
+            .take_finalizers()
+            .update_status(EventStatus::Delivered);
+        assert!(!send(
+            &mut dedup,
+            event("hello", "1"),
+            EventStatus::Delivered
+        ));
+    }
+}
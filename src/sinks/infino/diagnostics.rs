@@ -0,0 +1,146 @@
+//! Clearer configuration errors than the ones of `serde`.
+
+use super::{InfinoConfig, InfinoMode};
+
+/// Rewrites an unknown field error to suggest the closest valid option, if one is close enough.
+///
+/// `serde` reports unknown fields by listing every valid option, which is hard to read for a
+/// configuration with as many options as this sink's.
+pub fn suggest_field(message: &str) -> Option<String> {
+    let (_, rest) = message.split_once("unknown field `")?;
+    let (field, rest) = rest.split_once('`')?;
+    let (_, expected) = rest.split_once("expected ")?;
+
+    let max_distance = (field.chars().count() / 3).max(2);
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|option| (distance(field, option), option))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, option)| format!("unknown field `{}`, did you mean `{}`?", field, option))
+}
+
+/// The Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Returns an error for the options that are set but not used in the configured mode.
+pub fn check_mode_options(config: &InfinoConfig) -> Result<(), String> {
+    match config.mode {
+        InfinoMode::Bulk | InfinoMode::Auto | InfinoMode::DataStream
+            if config.alert_rules.is_some() =>
+        {
+            Err(format!(
+                "`alert_rules` options are only used when `mode` is `alert_rules`, but it is \
+                 `{}`",
+                mode_name(&config.mode)
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns a warning for the options that are set but ignored in the configured mode.
+///
+/// These options have always been accepted in any mode, so existing configurations keep working.
+pub fn unused_mode_options(config: &InfinoConfig) -> Option<String> {
+    match config.mode {
+        InfinoMode::Bulk | InfinoMode::Auto | InfinoMode::AlertRules
+            if config.data_stream.is_some() =>
+        {
+            Some(format!(
+                "`data_stream` options are only used when `mode` is `data_stream`, but it is \
+                 `{}`, so they are ignored",
+                mode_name(&config.mode)
+            ))
+        }
+        InfinoMode::DataStream if config.bulk.version.is_some() => Some(
+            "`bulk.version` is ignored when `mode` is `data_stream`, as data streams only \
+             support the `create` action"
+                .to_owned(),
+        ),
+        _ => None,
+    }
+}
+
+const fn mode_name(mode: &InfinoMode) -> &'static str {
+    match mode {
+        InfinoMode::Bulk => "bulk",
+        InfinoMode::DataStream => "data_stream",
+        InfinoMode::Auto => "auto",
+        InfinoMode::AlertRules => "alert_rules",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_closest_field() {
+        assert_eq!(
+            suggest_field(
+                "unknown field `endpont`, expected one of `endpoint`, `endpoints`, `auth`"
+            )
+            .as_deref(),
+            Some("unknown field `endpont`, did you mean `endpoint`?")
+        );
+        assert_eq!(
+            suggest_field("unknown field `idnex`, expected one of `action`, `index`, `version`")
+                .as_deref(),
+            Some("unknown field `idnex`, did you mean `index`?")
+        );
+    }
+
+    #[test]
+    fn keeps_errors_without_close_field() {
+        assert_eq!(
+            suggest_field("unknown field `foo`, expected one of `endpoint`, `endpoints`"),
+            None
+        );
+        assert_eq!(
+            suggest_field("invalid type: string, expected a boolean"),
+            None
+        );
+    }
+
+    #[test]
+    fn warns_about_unused_mode_options() {
+        let config = InfinoConfig {
+            data_stream: Some(Default::default()),
+            ..Default::default()
+        };
+        assert!(check_mode_options(&config).is_ok());
+        assert!(unused_mode_options(&config)
+            .unwrap()
+            .starts_with("`data_stream` options are only used when `mode` is `data_stream`"));
+
+        let config = InfinoConfig {
+            mode: InfinoMode::DataStream,
+            data_stream: Some(Default::default()),
+            ..Default::default()
+        };
+        assert_eq!(unused_mode_options(&config), None);
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("index", "index"), 0);
+    }
+}
@@ -0,0 +1,673 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    num::{NonZeroU32, NonZeroUsize},
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::PollSemaphore;
+use tower::{Layer, Service, ServiceExt};
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::event_path;
+
+use super::{
+    encoder::ProcessedEvent,
+    service::{InfinoRequest, InfinoResponse},
+    sink::PartitionKey,
+    DATA_STREAM_TIMESTAMP_KEY,
+};
+use crate::event::Value;
+
+/// The maximum number of requests waiting in the lanes for their turn, beyond which no more
+/// requests are taken until one is sent.
+const MAX_QUEUED_REQUESTS: usize = 64;
+
+/// The lane a batch is dispatched in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Lane {
+    /// Recent events.
+    #[default]
+    Realtime,
+
+    /// Events that are older than the configured backfill age.
+    Backfill,
+}
+
+impl Lane {
+    const fn index(self) -> usize {
+        match self {
+            Self::Realtime => 0,
+            Self::Backfill => 1,
+        }
+    }
+}
+
+/// Priority lanes configuration.
+///
+/// Events are sent in one of two lanes, real-time or backfill, depending on the age of their
+/// timestamp. Batches never mix lanes, and when both lanes have batches waiting to be sent, the
+/// drain policy decides which one goes first.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityConfig {
+    /// The age above which an event is sent in the backfill lane.
+    ///
+    /// Events without a timestamp are always sent in the real-time lane.
+    #[serde(default = "default_backfill_after_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub backfill_after_secs: u64,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub drain: DrainPolicy,
+
+    /// The number of real-time batches sent for every `backfill_weight` backfill batches, when
+    /// using the `weighted_fair` drain policy.
+    #[serde(default = "default_realtime_weight")]
+    pub realtime_weight: NonZeroU32,
+
+    /// The number of backfill batches sent for every `realtime_weight` real-time batches, when
+    /// using the `weighted_fair` drain policy.
+    #[serde(default = "default_backfill_weight")]
+    pub backfill_weight: NonZeroU32,
+}
+
+/// The policy deciding which lane is drained first when both have batches waiting.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainPolicy {
+    /// Real-time batches are always sent first.
+    ///
+    /// Backfill batches are only sent while no real-time batch is waiting.
+    Strict,
+
+    /// Lanes take turns according to their weights, so that neither lane is starved.
+    #[default]
+    WeightedFair,
+}
+
+const fn default_backfill_after_secs() -> u64 {
+    3_600
+}
+
+const fn default_realtime_weight() -> NonZeroU32 {
+    match NonZeroU32::new(4) {
+        Some(weight) => weight,
+        None => unreachable!(),
+    }
+}
+
+const fn default_backfill_weight() -> NonZeroU32 {
+    match NonZeroU32::new(1) {
+        Some(weight) => weight,
+        None => unreachable!(),
+    }
+}
+
+impl PriorityConfig {
+    /// Returns the lane of `event`, according to the age of its timestamp.
+    pub fn lane(&self, event: &ProcessedEvent) -> Lane {
+        let timestamp = event
+            .log
+            .get_timestamp()
+            .or_else(|| event.log.get(event_path!(DATA_STREAM_TIMESTAMP_KEY)))
+            .and_then(Value::as_timestamp);
+        match timestamp {
+            Some(timestamp)
+                if u64::try_from((Utc::now() - *timestamp).num_seconds())
+                    .is_ok_and(|age| age > self.backfill_after_secs) =>
+            {
+                Lane::Backfill
+            }
+            _ => Lane::Realtime,
+        }
+    }
+}
+
+/// Partition fairness configuration.
+///
+/// When many partitions contend for the requests in flight, decides which partition sends next so
+/// that a single very busy index cannot monopolize dispatch while smaller indexes back up. Events
+/// are batched per partition when this is enabled.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FairnessConfig {
+    #[configurable(derived)]
+    #[serde(default)]
+    pub policy: FairnessPolicy,
+
+    /// The number of bytes each partition is allowed to send per round, when using the `deficit`
+    /// policy.
+    #[serde(default = "default_quantum_bytes")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub quantum_bytes: NonZeroUsize,
+}
+
+/// The policy sharing dispatch between partitions.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FairnessPolicy {
+    /// Partitions take turns, one request each.
+    #[default]
+    RoundRobin,
+
+    /// Partitions take turns, each sending up to `quantum_bytes` per round, so that partitions
+    /// sending large requests do not get a larger share than partitions sending small ones.
+    Deficit,
+}
+
+const fn default_quantum_bytes() -> NonZeroUsize {
+    match NonZeroUsize::new(1024 * 1024) {
+        Some(quantum) => quantum,
+        None => unreachable!(),
+    }
+}
+
+struct Waiter {
+    size: usize,
+    turn: oneshot::Sender<Turn>,
+}
+
+/// The requests waiting in a lane, grouped by partition.
+#[derive(Default)]
+struct LaneQueue {
+    partitions: HashMap<Option<PartitionKey>, VecDeque<Waiter>>,
+    /// The partitions with waiting requests, in the order they are visited.
+    active: VecDeque<Option<PartitionKey>>,
+    deficits: HashMap<Option<PartitionKey>, usize>,
+}
+
+impl LaneQueue {
+    fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    fn push(&mut self, partition: Option<PartitionKey>, waiter: Waiter) {
+        let queue = self.partitions.entry(partition.clone()).or_default();
+        if queue.is_empty() {
+            self.active.push_back(partition);
+        }
+        queue.push_back(waiter);
+    }
+
+    fn pop(&mut self, fairness: Option<&FairnessConfig>) -> Option<Waiter> {
+        loop {
+            let partition = self.active.front()?.clone();
+            let queue = self
+                .partitions
+                .get_mut(&partition)
+                .expect("active partitions have waiters");
+
+            let policy = fairness.map(|fairness| fairness.policy);
+            if let Some(FairnessPolicy::Deficit) = policy {
+                let quantum = fairness.map_or(1, |fairness| fairness.quantum_bytes.get());
+                let size = queue.front().map_or(0, |waiter| waiter.size);
+                let deficit = self.deficits.entry(partition.clone()).or_default();
+                if *deficit < size {
+                    // Not enough credit left in this round, move on to the next partition.
+                    *deficit += quantum;
+                    self.active.rotate_left(1);
+                    continue;
+                }
+                *deficit -= size;
+            }
+
+            let waiter = queue.pop_front().expect("active partitions have waiters");
+            if queue.is_empty() {
+                self.partitions.remove(&partition);
+                self.deficits.remove(&partition);
+                self.active.pop_front();
+            } else if let Some(FairnessPolicy::RoundRobin) = policy {
+                self.active.rotate_left(1);
+            }
+            return Some(waiter);
+        }
+    }
+}
+
+/// Hands the right to send a request to the requests waiting in each lane.
+struct Scheduler {
+    busy: bool,
+    lanes: [LaneQueue; 2],
+    policy: DrainPolicy,
+    fairness: Option<FairnessConfig>,
+    weights: [u32; 2],
+    credits: [u32; 2],
+}
+
+impl Scheduler {
+    fn new(priority: Option<&PriorityConfig>, fairness: Option<&FairnessConfig>) -> Self {
+        let weights = priority.map_or([1, 1], |priority| {
+            [
+                priority.realtime_weight.get(),
+                priority.backfill_weight.get(),
+            ]
+        });
+        Self {
+            busy: false,
+            lanes: Default::default(),
+            policy: priority.map(|priority| priority.drain).unwrap_or_default(),
+            fairness: fairness.cloned(),
+            weights,
+            credits: weights,
+        }
+    }
+
+    fn enqueue(
+        &mut self,
+        lane: Lane,
+        partition: Option<PartitionKey>,
+        size: usize,
+    ) -> oneshot::Receiver<Turn> {
+        // Without fairness, every request of a lane waits in the same queue.
+        let partition = partition.filter(|_| self.fairness.is_some());
+        let (turn, rx) = oneshot::channel();
+        self.lanes[lane.index()].push(partition, Waiter { size, turn });
+        rx
+    }
+
+    /// Picks the lane whose request goes next, if any is waiting.
+    fn next_lane(&mut self) -> Option<Lane> {
+        let lane = match (
+            self.lanes[Lane::Realtime.index()].is_empty(),
+            self.lanes[Lane::Backfill.index()].is_empty(),
+        ) {
+            (true, true) => return None,
+            (false, true) => Lane::Realtime,
+            (true, false) => Lane::Backfill,
+            (false, false) => match self.policy {
+                DrainPolicy::Strict => Lane::Realtime,
+                DrainPolicy::WeightedFair => {
+                    if self.credits == [0, 0] {
+                        self.credits = self.weights;
+                    }
+                    if self.credits[Lane::Realtime.index()] > 0 {
+                        Lane::Realtime
+                    } else {
+                        Lane::Backfill
+                    }
+                }
+            },
+        };
+        let credits = &mut self.credits[lane.index()];
+        *credits = credits.saturating_sub(1);
+        Some(lane)
+    }
+
+    /// Returns the waiter the turn is passed to, or releases the turn if nobody is waiting.
+    fn pass(&mut self) -> Option<oneshot::Sender<Turn>> {
+        match self.next_lane() {
+            Some(lane) => self.lanes[lane.index()]
+                .pop(self.fairness.as_ref())
+                .map(|waiter| waiter.turn),
+            None => {
+                self.busy = false;
+                None
+            }
+        }
+    }
+}
+
+/// The right to hand a request to the inner service, passed on to the next waiting request when
+/// dropped.
+struct Turn {
+    scheduler: Arc<Mutex<Scheduler>>,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let next = self
+            .scheduler
+            .lock()
+            .expect("dispatch scheduler lock poisoned")
+            .pass();
+        if let Some(next) = next {
+            // If the waiter is gone, the turn comes back and is passed on again when dropped.
+            _ = next.send(Turn {
+                scheduler: Arc::clone(&self.scheduler),
+            });
+        }
+    }
+}
+
+async fn acquire(
+    scheduler: &Arc<Mutex<Scheduler>>,
+    lane: Lane,
+    partition: Option<PartitionKey>,
+    size: usize,
+) -> Turn {
+    let waiting = {
+        let mut locked = scheduler.lock().expect("dispatch scheduler lock poisoned");
+        if locked.busy {
+            Some(locked.enqueue(lane, partition, size))
+        } else {
+            locked.busy = true;
+            None
+        }
+    };
+    match waiting {
+        Some(rx) => rx.await.expect("waiters are always handed a turn"),
+        None => Turn {
+            scheduler: Arc::clone(scheduler),
+        },
+    }
+}
+
+/// Sends waiting requests in the order decided by the drain policy of the priority lanes and by
+/// the partition fairness policy.
+#[derive(Clone)]
+pub struct DispatchLayer {
+    scheduler: Arc<Mutex<Scheduler>>,
+    slots: Arc<Semaphore>,
+}
+
+impl DispatchLayer {
+    pub fn new(priority: Option<&PriorityConfig>, fairness: Option<&FairnessConfig>) -> Self {
+        Self {
+            scheduler: Arc::new(Mutex::new(Scheduler::new(priority, fairness))),
+            slots: Arc::new(Semaphore::new(MAX_QUEUED_REQUESTS)),
+        }
+    }
+}
+
+impl<S> Layer<S> for DispatchLayer {
+    type Service = DispatchService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DispatchService {
+            scheduler: Arc::clone(&self.scheduler),
+            slots: PollSemaphore::new(Arc::clone(&self.slots)),
+            slot: None,
+            inner: Arc::new(tokio::sync::Mutex::new(inner)),
+        }
+    }
+}
+
+pub struct DispatchService<S> {
+    scheduler: Arc<Mutex<Scheduler>>,
+    slots: PollSemaphore,
+    slot: Option<OwnedSemaphorePermit>,
+    inner: Arc<tokio::sync::Mutex<S>>,
+}
+
+impl<S> Service<InfinoRequest> for DispatchService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Requests queue up in their lane and partition until the inner service is ready for them,
+        // so that the scheduler picks the next one among them, but only up to a bound.
+        if self.slot.is_none() {
+            match ready!(self.slots.poll_acquire(cx)) {
+                Some(slot) => self.slot = Some(slot),
+                None => {
+                    return Poll::Ready(Err("Dispatch queue semaphore unexpectedly closed".into()))
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let slot = self
+            .slot
+            .take()
+            .expect("poll_ready not called before invoking call");
+        let scheduler = Arc::clone(&self.scheduler);
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let partition = req.partition_key.clone();
+            let turn = acquire(&scheduler, req.lane, partition, req.payload.len()).await;
+            let future = {
+                let mut inner = inner.lock().await;
+                inner.ready().await?.call(req)
+            };
+            drop(turn);
+            drop(slot);
+            future.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::FutureExt;
+    use http::Response;
+    use tower_test::mock;
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::*;
+    use crate::{event::EventStatus, sinks::infino::BulkAction};
+
+    fn scheduler(drain: DrainPolicy, fairness: Option<FairnessPolicy>) -> Scheduler {
+        Scheduler::new(
+            Some(&PriorityConfig {
+                backfill_after_secs: default_backfill_after_secs(),
+                drain,
+                realtime_weight: NonZeroU32::new(2).unwrap(),
+                backfill_weight: NonZeroU32::new(1).unwrap(),
+            }),
+            fairness
+                .map(|policy| FairnessConfig {
+                    policy,
+                    quantum_bytes: NonZeroUsize::new(100).unwrap(),
+                })
+                .as_ref(),
+        )
+    }
+
+    fn key(index: &str) -> Option<PartitionKey> {
+        Some(PartitionKey {
+            index: index.to_owned(),
+            bulk_action: Some(BulkAction::Index),
+        })
+    }
+
+    /// Passes the turn until nobody waits, returning the order in which waiters were served.
+    fn drain<T>(scheduler: &mut Scheduler, waiters: Vec<(T, oneshot::Receiver<Turn>)>) -> Vec<T> {
+        let mut waiters = waiters;
+        let mut served = Vec::new();
+        while let Some(next) = scheduler.pass() {
+            // Dropping the sender closes the channel of the waiter that was served.
+            drop(next);
+            let position = waiters
+                .iter_mut()
+                .position(|(_, rx)| {
+                    matches!(rx.try_recv(), Err(oneshot::error::TryRecvError::Closed))
+                })
+                .expect("a waiter was served");
+            served.push(waiters.remove(position).0);
+        }
+        served
+    }
+
+    fn lanes(scheduler: &mut Scheduler, count: usize) -> Vec<Lane> {
+        let waiters = [Lane::Realtime, Lane::Backfill]
+            .into_iter()
+            .flat_map(|lane| std::iter::repeat(lane).take(count))
+            .map(|lane| (lane, scheduler.enqueue(lane, None, 0)))
+            .collect();
+        drain(scheduler, waiters)
+    }
+
+    #[test]
+    fn strict_drains_realtime_first() {
+        use Lane::*;
+        assert_eq!(
+            lanes(&mut scheduler(DrainPolicy::Strict, None), 2),
+            vec![Realtime, Realtime, Backfill, Backfill]
+        );
+    }
+
+    #[test]
+    fn weighted_fair_interleaves_lanes() {
+        use Lane::*;
+        assert_eq!(
+            lanes(&mut scheduler(DrainPolicy::WeightedFair, None), 4),
+            vec![Realtime, Realtime, Backfill, Realtime, Realtime, Backfill, Backfill, Backfill]
+        );
+    }
+
+    #[test]
+    fn round_robin_across_partitions() {
+        let mut scheduler = scheduler(DrainPolicy::Strict, Some(FairnessPolicy::RoundRobin));
+        let waiters = ["hot", "hot", "hot", "small", "other"]
+            .into_iter()
+            .map(|index| (index, scheduler.enqueue(Lane::Realtime, key(index), 10)))
+            .collect();
+        assert_eq!(
+            drain(&mut scheduler, waiters),
+            vec!["hot", "small", "other", "hot", "hot"]
+        );
+    }
+
+    #[test]
+    fn deficit_accounts_for_request_size() {
+        let mut scheduler = scheduler(DrainPolicy::Strict, Some(FairnessPolicy::Deficit));
+        let waiters = [("large", 200), ("large", 200), ("small", 50), ("small", 50)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (index, size))| {
+                let rx = scheduler.enqueue(Lane::Realtime, key(index), size);
+                ((i, index), rx)
+            })
+            .collect();
+        let served = drain(&mut scheduler, waiters)
+            .into_iter()
+            .map(|(_, index)| index)
+            .collect::<Vec<_>>();
+        // The small partition sends both of its requests in the first round, while the large one
+        // needs two rounds of credit per request.
+        assert_eq!(served, vec!["small", "small", "large", "large"]);
+    }
+
+    #[test]
+    fn fifo_without_fairness() {
+        let mut scheduler = scheduler(DrainPolicy::Strict, None);
+        let waiters = ["hot", "hot", "small"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, index)| (i, scheduler.enqueue(Lane::Realtime, key(index), 10)))
+            .collect();
+        assert_eq!(drain(&mut scheduler, waiters), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn passes_turns_in_lane_order() {
+        let scheduler = Arc::new(Mutex::new(scheduler(DrainPolicy::Strict, None)));
+        let first = acquire(&scheduler, Lane::Backfill, None, 0).await;
+
+        let backfill = tokio::spawn({
+            let scheduler = Arc::clone(&scheduler);
+            async move { drop(acquire(&scheduler, Lane::Backfill, None, 0).await) }
+        });
+        tokio::task::yield_now().await;
+        let realtime = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move { acquire(&scheduler, Lane::Realtime, None, 0).await })
+        };
+        tokio::task::yield_now().await;
+
+        drop(first);
+        let turn = realtime.await.unwrap();
+        assert!(!backfill.is_finished());
+        drop(turn);
+        backfill.await.unwrap();
+        assert!(!scheduler.lock().unwrap().busy);
+    }
+
+    fn request(lane: Lane) -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: Default::default(),
+            batch_size: 1,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane,
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn response() -> InfinoResponse {
+        InfinoResponse {
+            http_response: Response::new(Bytes::new()),
+            event_status: EventStatus::Delivered,
+            batch_size: 1,
+            events_byte_size: GroupedCountByteSize::new_untagged(),
+        }
+    }
+
+    #[tokio::test]
+    async fn bounds_queued_requests() {
+        let (inner, mut handle) = mock::pair::<InfinoRequest, InfinoResponse>();
+        handle.allow(0);
+        let mut service = DispatchLayer::new(None, None).layer(inner);
+
+        let mut responses = Vec::new();
+        for _ in 0..MAX_QUEUED_REQUESTS {
+            service.ready().await.unwrap();
+            responses.push(tokio::spawn(service.call(request(Lane::Realtime))));
+        }
+        // Every request waits in its lane, so no more are taken.
+        assert!(service.ready().now_or_never().is_none());
+
+        handle.allow(1);
+        let (_, send) = handle.next_request().await.unwrap();
+        service.ready().await.unwrap();
+        send.send_response(response());
+        assert!(responses.remove(0).await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn schedules_queued_requests_by_lane() {
+        use Lane::*;
+        let (inner, mut handle) = mock::pair::<InfinoRequest, InfinoResponse>();
+        handle.allow(0);
+        let priority = PriorityConfig {
+            backfill_after_secs: default_backfill_after_secs(),
+            drain: DrainPolicy::WeightedFair,
+            realtime_weight: NonZeroU32::new(2).unwrap(),
+            backfill_weight: NonZeroU32::new(1).unwrap(),
+        };
+        let mut service = DispatchLayer::new(Some(&priority), None).layer(inner);
+
+        // The first request takes the turn, and the others queue up behind it while the inner
+        // service is busy.
+        let mut responses = Vec::new();
+        for lane in [Backfill, Backfill, Backfill, Realtime, Realtime] {
+            service.ready().await.unwrap();
+            responses.push(tokio::spawn(service.call(request(lane))));
+            tokio::task::yield_now().await;
+        }
+
+        let mut sent = Vec::new();
+        for _ in 0..responses.len() {
+            handle.allow(1);
+            let (req, send) = handle.next_request().await.unwrap();
+            sent.push(req.lane);
+            send.send_response(response());
+        }
+        assert_eq!(sent, vec![Backfill, Realtime, Realtime, Backfill, Backfill]);
+        for result in responses {
+            assert!(result.await.unwrap().is_ok());
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::task::{ready, Context, Poll};
+
+use futures::{future::BoxFuture, FutureExt};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tower::{buffer::Buffer, Service, ServiceExt};
+use vector_lib::configurable::configurable_component;
+
+use super::service::{InfinoRequest, InfinoResponse};
+use crate::{
+    event::EventStatus, internal_events::InfinoPartitionRerouted,
+    sinks::util::service::HealthConfig,
+};
+
+/// The number of points each unit of weight of an endpoint has on the ring, so that partitions are
+/// spread evenly between endpoints.
+const POINTS_PER_WEIGHT: usize = 64;
+
+/// The requests queued for each endpoint. Readiness reserves one for the next request, which leaves
+/// room for the requests rerouted from other endpoints.
+const QUEUED_REQUESTS: usize = 2;
+
+/// Distribution configuration.
+#[configurable_component]
+#[derive(Clone, Debug, Default)]
+pub struct DistributionConfig {
+    #[serde(flatten)]
+    #[configurable(derived)]
+    pub health: HealthConfig,
+
+    #[serde(default)]
+    #[configurable(derived)]
+    pub strategy: DistributionStrategy,
+}
+
+/// How requests are distributed between endpoints.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionStrategy {
+    /// Sends each request to the healthy endpoint with the least load.
+    ///
+    /// When endpoints are given a `weight`, each request is sent to a ready endpoint chosen at
+    /// random in proportion to its weight instead.
+    Balance,
+
+    /// Sends the requests of each index to the same endpoint, chosen by consistent hashing.
+    ///
+    /// All writes for an index land on the same node, which improves the locality of its caches,
+    /// and adding or removing an endpoint only moves the indexes of that endpoint. Requests are
+    /// sent to the next endpoint of the ring when their endpoint fails them. Batches only hold the
+    /// events of a single index. It cannot be combined with `locality` or `fan_out`.
+    Hash,
+}
+
+impl Default for DistributionStrategy {
+    fn default() -> Self {
+        Self::Balance
+    }
+}
+
+/// A consistent hash ring of endpoints.
+struct Ring {
+    /// The points of the ring, sorted, with the endpoint they belong to.
+    points: Vec<(u64, usize)>,
+    endpoints: usize,
+}
+
+impl Ring {
+    /// Builds the ring of `endpoints`, each with as many points as its weight.
+    fn new(endpoints: &[(String, usize)]) -> Self {
+        let mut points = endpoints
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (endpoint, weight))| {
+                (0..weight * POINTS_PER_WEIGHT)
+                    .map(move |point| (hash(&format!("{}#{}", endpoint, point)), i))
+            })
+            .collect::<Vec<_>>();
+        points.sort_unstable();
+        Self {
+            points,
+            endpoints: endpoints.len(),
+        }
+    }
+
+    /// Returns the endpoints that `key` is sent to, in order, starting with its own.
+    fn endpoints(&self, key: &str) -> Vec<usize> {
+        let start = self.points.partition_point(|(point, _)| *point < hash(key));
+        let mut endpoints = Vec::with_capacity(self.endpoints);
+        for (_, endpoint) in self.points[start..].iter().chain(&self.points[..start]) {
+            if !endpoints.contains(endpoint) {
+                endpoints.push(*endpoint);
+                if endpoints.len() == self.endpoints {
+                    break;
+                }
+            }
+        }
+        endpoints
+    }
+}
+
+/// A hash that is the same on every host and release, so that indexes keep their endpoint.
+fn hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Sends the requests of each index to the service of its endpoint on a consistent hash ring.
+pub struct HashService<S>
+where
+    S: Service<InfinoRequest>,
+{
+    services: Vec<Buffer<S, InfinoRequest>>,
+    ring: Ring,
+}
+
+impl<S> HashService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    /// Builds the service distributing to `services`, along with their endpoint and weight.
+    pub fn new(services: Vec<(String, usize, S)>) -> Self {
+        let endpoints = services
+            .iter()
+            .map(|(endpoint, weight, _)| (endpoint.clone(), *weight))
+            .collect::<Vec<_>>();
+        Self {
+            services: services
+                .into_iter()
+                .map(|(_, _, service)| Buffer::new(service, QUEUED_REQUESTS))
+                .collect(),
+            ring: Ring::new(&endpoints),
+        }
+    }
+}
+
+impl<S> Service<InfinoRequest> for HashService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    /// The endpoint of a request is only known once it is called, so every endpoint must be ready.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for service in &mut self.services {
+            ready!(service.poll_ready(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let key = req
+            .partition_key
+            .as_ref()
+            .map_or("", |partition| partition.index.as_str());
+        let mut endpoints = self.ring.endpoints(key).into_iter();
+        // The first endpoint was driven to readiness, so it takes the request right away.
+        let first = endpoints.next().map(|i| self.services[i].call(req.clone()));
+        let fallbacks = endpoints
+            .map(|i| self.services[i].clone())
+            .collect::<Vec<_>>();
+        async move {
+            let mut result = match first {
+                Some(response) => response.await,
+                None => Err("no endpoint to send the request to".into()),
+            };
+            for service in fallbacks {
+                match &result {
+                    Ok(response) if response.event_status != EventStatus::Errored => break,
+                    _ => {
+                        emit!(InfinoPartitionRerouted);
+                        result = service.oneshot(req.clone()).await;
+                    }
+                }
+            }
+            result
+        }
+        .boxed()
+    }
+}
+
+/// Sends each request to one of the ready services, chosen at random in proportion to the weight of
+/// its endpoint.
+pub struct WeightedService<S>
+where
+    S: Service<InfinoRequest>,
+{
+    services: Vec<(usize, Buffer<S, InfinoRequest>)>,
+    /// The service chosen for the next request, once it is ready.
+    chosen: Option<usize>,
+}
+
+impl<S> WeightedService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    /// Builds the service distributing to `services`, along with their weight.
+    pub fn new(services: Vec<(usize, S)>) -> Self {
+        Self {
+            services: services
+                .into_iter()
+                .map(|(weight, service)| (weight, Buffer::new(service, QUEUED_REQUESTS)))
+                .collect(),
+            chosen: None,
+        }
+    }
+}
+
+impl<S> Service<InfinoRequest> for WeightedService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.chosen.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        // Each ready service replaces the chosen one with a probability of its share of the weight
+        // of the ready services so far, which chooses them in proportion to their weight.
+        let mut rng = rand::thread_rng();
+        let mut total = 0;
+        for (i, (weight, service)) in self.services.iter_mut().enumerate() {
+            if service.poll_ready(cx)?.is_ready() {
+                total += *weight;
+                if rng.gen_range(0..total) < *weight {
+                    self.chosen = Some(i);
+                }
+            }
+        }
+        match self.chosen {
+            Some(_) => Poll::Ready(Ok(())),
+            None => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let i = self.chosen.take().expect("poll_ready must be called first");
+        self.services[i].1.call(req).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+    use futures::future;
+    use http::Response;
+    use tower::{service_fn, util::BoxService};
+    use tower_test::mock;
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::*;
+    use crate::sinks::infino::{sink::PartitionKey, BulkAction};
+
+    type Endpoint = BoxService<InfinoRequest, InfinoResponse, crate::Error>;
+
+    fn endpoints(count: usize) -> Vec<(String, usize)> {
+        (0..count)
+            .map(|i| (format!("http://{}:9000", i), 1))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_indexes_on_their_endpoint() {
+        let ring = Ring::new(&endpoints(3));
+        assert_eq!(ring.endpoints("logs").len(), 3);
+        assert_eq!(ring.endpoints("logs"), ring.endpoints("logs"));
+
+        // Only the indexes of a removed endpoint move.
+        let smaller = Ring::new(&endpoints(2));
+        for i in 0..100 {
+            let index = format!("logs-{}", i);
+            let endpoint = ring.endpoints(&index)[0];
+            if endpoint < 2 {
+                assert_eq!(smaller.endpoints(&index)[0], endpoint);
+            }
+        }
+    }
+
+    #[test]
+    fn weighs_endpoints() {
+        let ring = Ring::new(&[
+            ("http://a:9000".to_owned(), 3),
+            ("http://b:9000".to_owned(), 1),
+        ]);
+        let heavy = (0..1000)
+            .filter(|i| ring.endpoints(&format!("logs-{}", i))[0] == 0)
+            .count();
+        assert!((650..850).contains(&heavy), "{}", heavy);
+    }
+
+    fn endpoint(status: EventStatus, calls: Arc<Mutex<Vec<String>>>) -> Endpoint {
+        BoxService::new(service_fn(move |req: InfinoRequest| {
+            let index = req.partition_key.map(|partition| partition.index);
+            calls.lock().unwrap().extend(index);
+            future::ok(InfinoResponse {
+                http_response: Response::new(Bytes::new()),
+                event_status: status,
+                batch_size: 1,
+                events_byte_size: GroupedCountByteSize::new_untagged(),
+            })
+        }))
+    }
+
+    fn request(index: &str) -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: Default::default(),
+            batch_size: 1,
+            events_byte_size: Default::default(),
+            partition_key: Some(PartitionKey {
+                index: index.to_owned(),
+                bulk_action: Some(BulkAction::Index),
+            }),
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_and_reroutes_partitions() {
+        let calls = (0..2)
+            .map(|_| Arc::new(Mutex::new(Vec::new())))
+            .collect::<Vec<_>>();
+        let mut service = HashService::new(vec![
+            (
+                "http://0:9000".to_owned(),
+                1,
+                endpoint(EventStatus::Delivered, Arc::clone(&calls[0])),
+            ),
+            (
+                "http://1:9000".to_owned(),
+                1,
+                endpoint(EventStatus::Errored, Arc::clone(&calls[1])),
+            ),
+        ]);
+        let ring = Ring::new(&endpoints(2));
+        let index = (0..)
+            .map(|i| format!("logs-{}", i))
+            .find(|index| ring.endpoints(index)[0] == 1)
+            .unwrap();
+
+        let response = service.ready().await.unwrap().call(request(&index)).await;
+        assert_eq!(response.unwrap().event_status, EventStatus::Delivered);
+        assert_eq!(*calls[1].lock().unwrap(), [index.clone()]);
+        assert_eq!(*calls[0].lock().unwrap(), [index]);
+    }
+
+    #[tokio::test]
+    async fn weighs_ready_endpoints() {
+        let calls = (0..2)
+            .map(|_| Arc::new(Mutex::new(Vec::new())))
+            .collect::<Vec<_>>();
+        let mut service = WeightedService::new(vec![
+            (3, endpoint(EventStatus::Delivered, Arc::clone(&calls[0]))),
+            (1, endpoint(EventStatus::Delivered, Arc::clone(&calls[1]))),
+        ]);
+        for i in 0..1000 {
+            let response = service.ready().await.unwrap().call(request(&i.to_string()));
+            response.await.unwrap();
+        }
+        let heavy = calls[0].lock().unwrap().len();
+        assert_eq!(heavy + calls[1].lock().unwrap().len(), 1000);
+        assert!((650..850).contains(&heavy), "{}", heavy);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_endpoints_that_are_not_ready() {
+        let (inner, mut handle) = mock::pair::<InfinoRequest, InfinoResponse>();
+        handle.allow(0);
+        let mut service = WeightedService::new(vec![
+            (100, BoxService::new(inner)),
+            (1, endpoint(EventStatus::Delivered, Default::default())),
+        ]);
+
+        // The endpoint that takes no requests only holds those it queued.
+        let mut delivered = 0;
+        for i in 0..10 {
+            let response = service.ready().await.unwrap().call(request(&i.to_string()));
+            if tokio::time::timeout(Duration::from_secs(1), response)
+                .await
+                .is_ok()
+            {
+                delivered += 1;
+            }
+        }
+        assert!(delivered >= 10 - QUEUED_REQUESTS, "{}", delivered);
+    }
+
+    #[tokio::test]
+    async fn waits_for_endpoints() {
+        let (inner, mut handle) = mock::pair::<InfinoRequest, InfinoResponse>();
+        handle.allow(0);
+        let mut service = HashService::new(vec![("http://0:9000".to_owned(), 1, inner)]);
+
+        let mut responses = Vec::new();
+        for i in 0..QUEUED_REQUESTS {
+            service.ready().await.unwrap();
+            responses.push(tokio::spawn(service.call(request(&i.to_string()))));
+        }
+        // The endpoint takes no requests, so neither does the service.
+        assert!(service.ready().now_or_never().is_none());
+    }
+}
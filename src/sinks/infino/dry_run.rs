@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeSet,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::{self, BoxFuture};
+use http::{Response, StatusCode};
+use tower::Service;
+use vector_lib::request_metadata::MetaDescriptive;
+
+use super::{
+    bisect::split_documents,
+    service::{InfinoRequest, InfinoResponse},
+};
+use crate::{event::EventStatus, sinks::util::Compression};
+
+/// The maximum number of bytes of the first document that is logged for each request.
+const SAMPLE_DOCUMENT_BYTES: usize = 1024;
+
+/// Stands in for the Infino service when `dry_run` is enabled.
+///
+/// Requests are fully built, but instead of being sent they are summarized in the logs and
+/// acknowledged as delivered.
+#[derive(Clone, Debug)]
+pub struct DryRunService {
+    compression: Compression,
+}
+
+impl DryRunService {
+    pub const fn new(compression: Compression) -> Self {
+        Self { compression }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct RequestSummary {
+    documents: usize,
+    indexes: BTreeSet<String>,
+    sample_document: Option<String>,
+}
+
+impl RequestSummary {
+    /// Walks the documents of an uncompressed payload, each made of its action line followed by
+    /// its source line, if any.
+    fn from_payload(payload: &[u8]) -> Self {
+        let documents = split_documents(payload);
+        let mut summary = Self {
+            documents: documents.len(),
+            ..Self::default()
+        };
+
+        for document in documents {
+            let mut lines = document
+                .split(|byte| *byte == b'\n')
+                .filter(|line| !line.is_empty());
+
+            if let Some(Ok(serde_json::Value::Object(action))) =
+                lines.next().map(serde_json::from_slice)
+            {
+                if let Some(index) = action
+                    .values()
+                    .next()
+                    .and_then(|metadata| metadata.get("_index"))
+                    .and_then(|index| index.as_str())
+                {
+                    summary.indexes.insert(index.to_owned());
+                }
+            }
+
+            if summary.sample_document.is_none() {
+                summary.sample_document = lines.next().map(|source| {
+                    let len = source.len().min(SAMPLE_DOCUMENT_BYTES);
+                    String::from_utf8_lossy(&source[..len]).into_owned()
+                });
+            }
+        }
+
+        summary
+    }
+}
+
+impl Service<InfinoRequest> for DryRunService {
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: InfinoRequest) -> Self::Future {
+        if self.compression.is_compressed() {
+            info!(
+                message = "Dry run, request not sent.",
+                batch_size = req.batch_size,
+                payload_bytes = req.payload.len(),
+                compression = %self.compression,
+            );
+        } else {
+            let summary = RequestSummary::from_payload(&req.payload);
+            info!(
+                message = "Dry run, request not sent.",
+                batch_size = req.batch_size,
+                documents = summary.documents,
+                payload_bytes = req.payload.len(),
+                indexes = ?summary.indexes,
+                sample_document = summary.sample_document.as_deref().unwrap_or_default(),
+            );
+        }
+
+        let http_response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from_static(
+                br#"{"took":0,"errors":false,"items":[]}"#,
+            ))
+            .expect("valid response");
+        let response = InfinoResponse {
+            http_response,
+            event_status: EventStatus::Delivered,
+            batch_size: req.batch_size,
+            events_byte_size: std::mem::take(req.metadata_mut())
+                .into_events_estimated_json_encoded_byte_size(),
+        };
+        Box::pin(future::ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_payload() {
+        let payload = br#"{"index":{"_index":"logs-a"}}
+{"message":"first"}
+{"create":{"_index":"logs-b","_id":"1"}}
+{"message":"second"}
+{"index":{"_index":"logs-a"}}
+{"message":"third"}
+"#;
+        let summary = RequestSummary::from_payload(payload);
+        assert_eq!(summary.documents, 3);
+        assert_eq!(
+            summary.indexes.into_iter().collect::<Vec<_>>(),
+            vec!["logs-a".to_owned(), "logs-b".to_owned()]
+        );
+        assert_eq!(
+            summary.sample_document.as_deref(),
+            Some(r#"{"message":"first"}"#)
+        );
+    }
+
+    #[test]
+    fn counts_deletions_as_documents() {
+        let payload = br#"{"delete":{"_index":"logs-a","_id":"1"}}
+{"index":{"_index":"logs-b"}}
+{"message":"first"}
+{"delete":{"_index":"logs-c","_id":"2"}}
+"#;
+        let summary = RequestSummary::from_payload(payload);
+        assert_eq!(summary.documents, 3);
+        assert_eq!(
+            summary.indexes.into_iter().collect::<Vec<_>>(),
+            vec![
+                "logs-a".to_owned(),
+                "logs-b".to_owned(),
+                "logs-c".to_owned()
+            ]
+        );
+        assert_eq!(
+            summary.sample_document.as_deref(),
+            Some(r#"{"message":"first"}"#)
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use std::{io, io::Write};
 
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use ordered_float::NotNan;
 use serde::Serialize;
 use vector_lib::buffers::EventCount;
+use vector_lib::configurable::configurable_component;
 use vector_lib::{config::telemetry, event::Event, ByteSizeOf, EstimatedJsonEncodedSizeOf};
 use vector_lib::{
     internal_event::TaggedEventsSent,
@@ -11,14 +14,15 @@ use vector_lib::{
 
 use crate::{
     codecs::Transformer,
-    event::{EventFinalizers, Finalizable, LogEvent},
+    event::{EventFinalizers, Finalizable, LogEvent, Value},
+    internal_events::InfinoInvalidUtf8FieldsDropped,
     sinks::{
+        infino::{dispatch::Lane, BulkAction, VersionType},
         util::encoding::{as_tracked_write, Encoder},
-        Infino::{BulkAction, VersionType},
     },
 };
 
-#[derive(Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub enum DocumentVersionType {
     External,
     ExternalGte,
@@ -33,13 +37,13 @@ impl DocumentVersionType {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct DocumentVersion {
     pub kind: DocumentVersionType,
     pub value: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub enum DocumentMetadata {
     WithoutId,
     Id(String),
@@ -52,6 +56,13 @@ pub struct ProcessedEvent {
     pub bulk_action: BulkAction,
     pub log: LogEvent,
     pub document_metadata: DocumentMetadata,
+    pub lane: Lane,
+    /// The key Infino encrypts the event with, if any.
+    pub encryption_key: Option<String>,
+    /// The headers of the request the event is sent in, rendered from the event.
+    pub headers: Vec<(String, String)>,
+    /// The query parameters of the request the event is sent in, rendered from the event.
+    pub query: Vec<(String, String)>,
 }
 
 impl Finalizable for ProcessedEvent {
@@ -92,11 +103,73 @@ impl GetEventCountTags for ProcessedEvent {
     }
 }
 
+/// How to write floats that are NaN or infinite.
+///
+/// JSON cannot represent these, and Infino rejects the documents holding them. This applies to
+/// metric values and to the float fields of logs.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NonFiniteFloats {
+    /// Drop the metric, or the field of the log.
+    Drop,
+
+    /// Write the float as `null`.
+    #[default]
+    Null,
+
+    /// Write infinite floats as the largest finite float of the same sign, and NaN as `null`.
+    Clamp,
+
+    /// Write the float as the string `NaN`, `Infinity`, or `-Infinity`.
+    Stringify,
+}
+
+impl NonFiniteFloats {
+    /// Returns the value `float` is written as, or `None` if it is dropped.
+    pub fn value(self, float: f64) -> Option<Value> {
+        if float.is_finite() {
+            return Some(Value::from(float));
+        }
+        match self {
+            Self::Drop => None,
+            Self::Null => Some(Value::Null),
+            Self::Clamp if float.is_nan() => Some(Value::Null),
+            Self::Clamp => Some(Value::from(f64::MAX.copysign(float))),
+            Self::Stringify if float.is_nan() => Some(Value::from("NaN")),
+            Self::Stringify if float > 0.0 => Some(Value::from("Infinity")),
+            Self::Stringify => Some(Value::from("-Infinity")),
+        }
+    }
+}
+
+/// How to write string fields that are not valid UTF-8.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidUtf8 {
+    /// Replace the invalid sequences with the Unicode replacement character.
+    #[default]
+    Lossy,
+
+    /// Write the field as base64.
+    Base64,
+
+    /// Write the field as hexadecimal.
+    Hex,
+
+    /// Drop the field.
+    Drop,
+}
+
 #[derive(PartialEq, Eq, Default, Clone, Debug)]
 pub struct InfinoEncoder {
     pub transformer: Transformer,
     pub doc_type: String,
     pub suppress_type_name: bool,
+    pub float_precision: Option<u8>,
+    pub non_finite_floats: NonFiniteFloats,
+    pub invalid_utf8: InvalidUtf8,
 }
 
 impl Encoder<Vec<ProcessedEvent>> for InfinoEncoder {
@@ -113,7 +186,19 @@ impl Encoder<Vec<ProcessedEvent>> for InfinoEncoder {
                 self.transformer.transform(&mut event);
                 byte_size.add_event(&event, event.estimated_json_encoded_size_of());
 
-                event.into_log()
+                let mut log = event.into_log();
+                // Non-finite floats are already serialized as `null`, and invalid UTF-8 lossily.
+                if self.float_precision.is_some()
+                    || self.non_finite_floats != NonFiniteFloats::Null
+                    || self.invalid_utf8 != InvalidUtf8::Lossy
+                {
+                    let mut dropped = 0;
+                    self.normalize(log.value_mut(), &mut dropped);
+                    if dropped > 0 {
+                        emit!(InfinoInvalidUtf8FieldsDropped { count: dropped });
+                    }
+                }
+                log
             };
             written_bytes += write_bulk_action(
                 writer,
@@ -123,19 +208,78 @@ impl Encoder<Vec<ProcessedEvent>> for InfinoEncoder {
                 self.suppress_type_name,
                 &event.document_metadata,
             )?;
-            written_bytes +=
-                as_tracked_write::<_, _, io::Error>(writer, &log, |mut writer, log| {
+            written_bytes += as_tracked_write::<_, _, io::Error>(
+                writer,
+                (&log, event.bulk_action),
+                |mut writer, (log, bulk_action)| {
                     writer.write_all(&[b'\n'])?;
-                    serde_json::to_writer(&mut writer, log)?;
+                    match bulk_action {
+                        // Deletions have no source.
+                        BulkAction::Delete => return Ok(()),
+                        BulkAction::Update => {
+                            writer.write_all(br#"{"doc":"#)?;
+                            serde_json::to_writer(&mut writer, log)?;
+                            writer.write_all(b"}")?;
+                        }
+                        BulkAction::Index | BulkAction::Create => {
+                            serde_json::to_writer(&mut writer, log)?
+                        }
+                    }
                     writer.write_all(&[b'\n'])?;
                     Ok(())
-                })?;
+                },
+            )?;
         }
 
         Ok((written_bytes, byte_size))
     }
 }
 
+impl InfinoEncoder {
+    /// Rounds every float of `value` to `float_precision` decimal places, and applies
+    /// `non_finite_floats` and `invalid_utf8` to the values they cover. Returns `false` if `value`
+    /// is dropped, counting the strings dropped for being invalid UTF-8 in `dropped`.
+    ///
+    /// Floats that are too large to have any digit at that precision are left as they are.
+    fn normalize(&self, value: &mut Value, dropped: &mut usize) -> bool {
+        // Beyond this, every float is an integer.
+        const MAX_SCALED: f64 = 9_007_199_254_740_992.0;
+
+        match value {
+            Value::Float(float) if !float.is_finite() => {
+                match self.non_finite_floats.value(float.into_inner()) {
+                    Some(replacement) => *value = replacement,
+                    None => return false,
+                }
+            }
+            Value::Float(float) => {
+                if let Some(precision) = self.float_precision {
+                    let scale = 10f64.powi(i32::from(precision));
+                    let scaled = float.into_inner() * scale;
+                    if scaled.abs() < MAX_SCALED {
+                        if let Ok(rounded) = NotNan::new(scaled.round() / scale) {
+                            *float = rounded;
+                        }
+                    }
+                }
+            }
+            Value::Bytes(bytes) if std::str::from_utf8(bytes).is_err() => match self.invalid_utf8 {
+                InvalidUtf8::Lossy => {}
+                InvalidUtf8::Base64 => *value = Value::from(BASE64_STANDARD.encode(&bytes[..])),
+                InvalidUtf8::Hex => *value = Value::from(hex::encode(&bytes[..])),
+                InvalidUtf8::Drop => {
+                    *dropped += 1;
+                    return false;
+                }
+            },
+            Value::Object(map) => map.retain(|_, value| self.normalize(value, dropped)),
+            Value::Array(array) => array.retain_mut(|value| self.normalize(value, dropped)),
+            _ => {}
+        }
+        true
+    }
+}
+
 fn write_bulk_action(
     writer: &mut dyn Write,
     bulk_action: &str,
@@ -206,6 +350,108 @@ fn write_bulk_action(
 mod tests {
     use super::*;
 
+    #[test]
+    fn rounds_floats() {
+        let mut log = LogEvent::default();
+        log.insert("value", 0.1 + 0.2);
+        log.insert("nested.ratio", 2.0 / 3.0);
+        log.insert(
+            "list",
+            Value::Array(vec![Value::from(1.23456), Value::from(7_i64)]),
+        );
+        log.insert("huge", 1e300);
+
+        let encoder = InfinoEncoder {
+            float_precision: Some(3),
+            ..Default::default()
+        };
+        encoder.normalize(log.value_mut(), &mut 0);
+        assert_eq!(
+            serde_json::to_string(&log).unwrap(),
+            r#"{"huge":1e300,"list":[1.235,7],"nested":{"ratio":0.667},"value":0.3}"#
+        );
+    }
+
+    #[test]
+    fn handles_non_finite_floats() {
+        let mut log = LogEvent::default();
+        log.insert("up", f64::INFINITY);
+        log.insert("nested.down", f64::NEG_INFINITY);
+        log.insert(
+            "list",
+            Value::Array(vec![Value::from(f64::INFINITY), Value::from(1.5)]),
+        );
+        log.insert("finite", 0.5);
+
+        for (non_finite, expected) in [
+            (
+                NonFiniteFloats::Drop,
+                r#"{"finite":0.5,"list":[1.5],"nested":{}}"#,
+            ),
+            (
+                NonFiniteFloats::Null,
+                r#"{"finite":0.5,"list":[null,1.5],"nested":{"down":null},"up":null}"#,
+            ),
+            (
+                NonFiniteFloats::Clamp,
+                r#"{"finite":0.5,"list":[1.7976931348623157e308,1.5],"nested":{"down":-1.7976931348623157e308},"up":1.7976931348623157e308}"#,
+            ),
+            (
+                NonFiniteFloats::Stringify,
+                r#"{"finite":0.5,"list":["Infinity",1.5],"nested":{"down":"-Infinity"},"up":"Infinity"}"#,
+            ),
+        ] {
+            let encoder = InfinoEncoder {
+                non_finite_floats: non_finite,
+                ..Default::default()
+            };
+            let mut log = log.clone();
+            encoder.normalize(log.value_mut(), &mut 0);
+            assert_eq!(
+                serde_json::to_string(&log).unwrap(),
+                expected,
+                "{non_finite:?}"
+            );
+        }
+        assert_eq!(NonFiniteFloats::Clamp.value(f64::NAN), Some(Value::Null));
+        assert_eq!(
+            NonFiniteFloats::Stringify.value(f64::NAN),
+            Some(Value::from("NaN"))
+        );
+    }
+
+    #[test]
+    fn handles_invalid_utf8() {
+        let mut log = LogEvent::default();
+        log.insert("valid", "hi");
+        log.insert("invalid", Value::Bytes(vec![0xff, 0xfe].into()));
+
+        for (invalid_utf8, expected, expected_dropped) in [
+            (
+                InvalidUtf8::Lossy,
+                "{\"invalid\":\"\u{fffd}\u{fffd}\",\"valid\":\"hi\"}",
+                0,
+            ),
+            (InvalidUtf8::Base64, r#"{"invalid":"//4=","valid":"hi"}"#, 0),
+            (InvalidUtf8::Hex, r#"{"invalid":"fffe","valid":"hi"}"#, 0),
+            (InvalidUtf8::Drop, r#"{"valid":"hi"}"#, 1),
+        ] {
+            let encoder = InfinoEncoder {
+                invalid_utf8,
+                ..Default::default()
+            };
+            let mut log = log.clone();
+            let mut dropped = 0;
+            encoder.normalize(log.value_mut(), &mut dropped);
+            assert_eq!(
+                serde_json::to_string(&log).unwrap(),
+                expected,
+                "{invalid_utf8:?}"
+            );
+            assert_eq!(dropped, expected_dropped);
+        }
+    }
+
     #[test]
     fn suppress_type_with_id() {
         let mut writer = Vec::new();
@@ -0,0 +1,127 @@
+use http::{HeaderName, HeaderValue};
+use vector_lib::configurable::configurable_component;
+
+use super::{encoder::ProcessedEvent, ParseError};
+use crate::{
+    internal_events::{InfinoEncryptionKeyInvalid, TemplateRenderingError},
+    template::Template,
+};
+
+/// Customer-managed encryption key configuration.
+///
+/// Every request is sent with the key Infino encrypts its documents with, such as the ARN of a
+/// KMS key, so that the data of each tenant is encrypted with the tenant's own key. Events are
+/// batched per key, so that a request never mixes the data of several keys.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// The key to encrypt events with.
+    ///
+    /// Events for which the template fails to render, or renders a value that is not valid in a
+    /// header, are dropped.
+    #[configurable(metadata(docs::examples = "{{ tenant.kms_key_arn }}"))]
+    #[configurable(metadata(
+        docs::examples = "arn:aws:kms:us-east-1:111122223333:key/1234abcd-12ab-34cd-56ef-1234567890ab"
+    ))]
+    pub key: Template,
+
+    /// The header the key is sent in.
+    #[serde(default = "default_header")]
+    #[configurable(metadata(docs::examples = "X-Amz-Server-Side-Encryption-Aws-Kms-Key-Id"))]
+    pub header: String,
+}
+
+fn default_header() -> String {
+    "X-Infino-Encryption-Key".to_owned()
+}
+
+impl EncryptionConfig {
+    pub fn header_name(&self) -> Result<HeaderName, ParseError> {
+        HeaderName::try_from(self.header.as_str()).map_err(|_| {
+            ParseError::InvalidEncryptionHeader {
+                header: self.header.clone(),
+            }
+        })
+    }
+
+    /// Renders the key of `event`, or returns `None` if the event has to be dropped.
+    pub fn key(&self, event: &ProcessedEvent) -> Option<String> {
+        let key = self
+            .key
+            .render_string(&event.log)
+            .map_err(|error| {
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("encryption.key"),
+                    drop_event: true,
+                });
+            })
+            .ok()?;
+        if HeaderValue::from_str(&key).is_err() {
+            emit!(InfinoEncryptionKeyInvalid);
+            return None;
+        }
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::LogEvent,
+        sinks::infino::{BulkAction, DocumentMetadata},
+    };
+
+    fn config() -> EncryptionConfig {
+        EncryptionConfig {
+            key: Template::try_from("{{ kms_key }}").unwrap(),
+            header: default_header(),
+        }
+    }
+
+    fn event(key: Option<&str>) -> ProcessedEvent {
+        let mut log = LogEvent::from("hello");
+        if let Some(key) = key {
+            log.insert("kms_key", key);
+        }
+        ProcessedEvent {
+            index: "logs".into(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn renders_key() {
+        assert_eq!(
+            config().key(&event(Some("alias/tenant-a"))).as_deref(),
+            Some("alias/tenant-a")
+        );
+    }
+
+    #[test]
+    fn drops_events_without_valid_key() {
+        assert_eq!(config().key(&event(None)), None);
+        assert_eq!(config().key(&event(Some("tenant\na"))), None);
+    }
+
+    #[test]
+    fn validates_header() {
+        assert!(config().header_name().is_ok());
+        let config = EncryptionConfig {
+            header: "Encryption Key".to_owned(),
+            ..config()
+        };
+        assert!(matches!(
+            config.header_name(),
+            Err(ParseError::InvalidEncryptionHeader { .. })
+        ));
+    }
+}
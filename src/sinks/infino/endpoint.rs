@@ -0,0 +1,174 @@
+//! Validation of endpoints, and of the IPv6 literals they may hold.
+//!
+//! IPv6 addresses are written in brackets, such as `https://[2001:db8::1]:9200`, and may carry the
+//! zone ID of a link-local address, such as `http://[fe80::1%25eth0]:9200`. As in RFC 6874, the
+//! `%` before a zone ID is percent-encoded in URLs, but a bare `%` is accepted and encoded.
+
+use std::{borrow::Cow, net::Ipv6Addr};
+
+use http::Uri;
+
+use super::{tls::InfinoTlsConfig, ParseError};
+
+/// Validates `endpoint`, and returns it with the zone ID of its IPv6 literal, if any, encoded.
+pub fn normalize_endpoint(endpoint: &str) -> Result<Cow<'_, str>, ParseError> {
+    let invalid = |reason| ParseError::InvalidEndpoint {
+        endpoint: endpoint.to_owned(),
+        reason,
+    };
+
+    // Endpoints without a scheme are left to the parsing of URLs.
+    let Some(scheme_end) = endpoint.find("://").map(|index| index + 3) else {
+        return Ok(Cow::Borrowed(endpoint));
+    };
+    let authority_end = endpoint[scheme_end..]
+        .find(&['/', '?', '#'][..])
+        .map_or(endpoint.len(), |index| scheme_end + index);
+    let authority = &endpoint[scheme_end..authority_end];
+    let host_start = authority
+        .rfind('@')
+        .map_or(scheme_end, |index| scheme_end + index + 1);
+    let host_port = &endpoint[host_start..authority_end];
+
+    let Some(literal) = host_port.strip_prefix('[') else {
+        if host_port.matches(':').count() > 1 {
+            return Err(invalid("IPv6 addresses must be enclosed in brackets"));
+        }
+        return Ok(Cow::Borrowed(endpoint));
+    };
+    let Some((literal, port)) = literal.split_once(']') else {
+        return Err(invalid("the IPv6 address is missing its closing bracket"));
+    };
+    if !port.is_empty()
+        && port
+            .strip_prefix(':')
+            .and_then(|port| port.parse::<u16>().ok())
+            .is_none()
+    {
+        return Err(invalid("the port of the IPv6 address is invalid"));
+    }
+
+    let (address, zone) = match literal.split_once('%') {
+        Some((address, zone)) => (address, Some(zone.strip_prefix("25").unwrap_or(zone))),
+        None => (literal, None),
+    };
+    if address.parse::<Ipv6Addr>().is_err() {
+        return Err(invalid("the IPv6 address is malformed"));
+    }
+    match zone {
+        None => Ok(Cow::Borrowed(endpoint)),
+        Some(zone) if zone.is_empty() || !zone.bytes().all(is_unreserved) => {
+            Err(invalid("the zone ID of the IPv6 address is malformed"))
+        }
+        Some(zone) => Ok(Cow::Owned(format!(
+            "{}[{}%25{}]{}{}",
+            &endpoint[..host_start],
+            address,
+            zone,
+            port,
+            &endpoint[authority_end..]
+        ))),
+    }
+}
+
+/// Whether `byte` is unreserved in URLs, as the characters of zone IDs need to be.
+const fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// Returns the `Host` header of requests to `uri` when it differs from its authority, which is
+/// when its host is an IPv6 literal with a zone ID. Zone IDs only have a meaning on the host that
+/// sends the request, so they are not sent.
+pub fn host_header(uri: &Uri) -> Option<String> {
+    let host = uri.host()?;
+    let (address, _) = host.strip_prefix('[')?.split_once('%')?;
+    Some(match uri.port_u16() {
+        Some(port) => format!("[{}]:{}", address, port),
+        None => format!("[{}]", address),
+    })
+}
+
+/// Checks that TLS connections can be made to `endpoint`.
+///
+/// No server name is indicated to IP literals, and their certificates are verified against their
+/// address, which can not hold a zone ID, so hostname verification has to be disabled to connect
+/// to an address with one.
+pub fn check_tls(endpoint: &str, tls: Option<&InfinoTlsConfig>) -> Result<(), ParseError> {
+    let Ok(uri) = endpoint.parse::<Uri>() else {
+        return Ok(());
+    };
+    let verifies_hostname = tls.and_then(|tls| tls.options.verify_hostname) != Some(false);
+    if uri.scheme_str() == Some("https") && host_header(&uri).is_some() && verifies_hostname {
+        return Err(ParseError::InvalidEndpoint {
+            endpoint: endpoint.to_owned(),
+            reason: "the certificate of an IPv6 address with a zone ID can not be verified, \
+                     set `tls.verify_hostname` to `false`",
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(endpoint: &str) -> Result<String, String> {
+        normalize_endpoint(endpoint)
+            .map(Cow::into_owned)
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn accepts_ipv6_literals() {
+        for endpoint in [
+            "http://[::1]:9200",
+            "https://[2001:db8::1]",
+            "http://user:pass@[2001:db8::1]:9200/prefix",
+            "http://[fe80::1%25eth0]:9200",
+            "http://10.0.0.1:9200",
+            "http://infino:9200",
+        ] {
+            assert_eq!(normalize(endpoint).as_deref(), Ok(endpoint));
+        }
+        assert_eq!(
+            normalize("http://[fe80::1%eth0]:9200/").as_deref(),
+            Ok("http://[fe80::1%25eth0]:9200/")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ipv6_literals() {
+        for (endpoint, reason) in [
+            ("http://2001:db8::1:9200", "enclosed in brackets"),
+            ("http://[2001:db8::1:9200", "closing bracket"),
+            ("http://[2001:db8::1]9200", "port"),
+            ("http://[2001:db8::1]:99999", "port"),
+            ("http://[2001:db8::g]:9200", "malformed"),
+            ("http://[fe80::1%25]:9200", "zone ID"),
+            ("http://[fe80::1%25eth!0]:9200", "zone ID"),
+        ] {
+            let error = normalize(endpoint).unwrap_err();
+            assert!(error.contains(reason), "{}: {}", endpoint, error);
+        }
+    }
+
+    #[test]
+    fn omits_zone_ids_from_host() {
+        let uri = "http://[fe80::1%25eth0]:9200".parse::<Uri>().unwrap();
+        assert_eq!(host_header(&uri).as_deref(), Some("[fe80::1]:9200"));
+        let uri = "http://[2001:db8::1]:9200".parse::<Uri>().unwrap();
+        assert_eq!(host_header(&uri), None);
+    }
+
+    #[test]
+    fn requires_disabled_hostname_verification_with_zone_ids() {
+        let endpoint = "https://[fe80::1%25eth0]:9200";
+        assert!(check_tls(endpoint, None).is_err());
+        assert!(check_tls("http://[fe80::1%25eth0]:9200", None).is_ok());
+        assert!(check_tls("https://[2001:db8::1]:9200", None).is_ok());
+
+        let mut tls = InfinoTlsConfig::default();
+        tls.options.verify_hostname = Some(false);
+        assert!(check_tls(endpoint, Some(&tls)).is_ok());
+    }
+}
@@ -0,0 +1,288 @@
+use std::task::{Context, Poll};
+
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
+use tower::Service;
+use vector_lib::configurable::configurable_component;
+
+use super::service::{InfinoRequest, InfinoResponse};
+use crate::event::EventStatus;
+
+/// Fan-out configuration.
+///
+/// Sends every request to all of the `endpoints`, such as to replicate events into several
+/// clusters, instead of distributing requests between them. Each endpoint is retried on its own,
+/// and the `weight` of endpoints is ignored.
+///
+/// Without fan-out, each request is sent to a single healthy endpoint, and sent again to another
+/// one if it fails, and its events are acknowledged once an endpoint accepts them.
+#[configurable_component]
+#[derive(Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FanOutConfig {
+    #[serde(default)]
+    #[configurable(derived)]
+    pub ack: FanOutAck,
+}
+
+/// When the events of a request sent to every endpoint are acknowledged.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FanOutAck {
+    /// Acknowledges events once all of the endpoints accepted them.
+    ///
+    /// Events are rejected if any endpoint rejects them, so that their source can tell that an
+    /// endpoint is missing them.
+    All,
+
+    /// Acknowledges events once the first endpoint accepted them.
+    ///
+    /// The request is still sent to the other endpoints, but their failures are only logged.
+    First,
+}
+
+impl Default for FanOutAck {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+/// Sends every request to all of the endpoint services.
+pub struct FanOutService<S> {
+    services: Vec<S>,
+    ack: FanOutAck,
+}
+
+impl<S> FanOutService<S> {
+    pub const fn new(services: Vec<S>, ack: FanOutAck) -> Self {
+        Self { services, ack }
+    }
+}
+
+impl<S> Service<InfinoRequest> for FanOutService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for service in &mut self.services {
+            if service.poll_ready(cx)?.is_pending() {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: InfinoRequest) -> Self::Future {
+        let responses = self
+            .services
+            .iter_mut()
+            .map(|service| service.call(request.clone()))
+            .collect::<FuturesUnordered<_>>();
+        match self.ack {
+            FanOutAck::All => Box::pin(all(responses)),
+            FanOutAck::First => Box::pin(first(responses)),
+        }
+    }
+}
+
+/// Waits for every endpoint, then returns the errors of those that failed, or the first response
+/// not accepted, or the first response if all of them accepted the request.
+///
+/// No endpoint is given up on early, so that the request is not cancelled on the others.
+async fn all<F>(responses: FuturesUnordered<F>) -> Result<InfinoResponse, crate::Error>
+where
+    F: std::future::Future<Output = Result<InfinoResponse, crate::Error>>,
+{
+    let mut errors = Vec::new();
+    let mut rejected = None;
+    let mut accepted = None;
+    for result in responses.collect::<Vec<_>>().await {
+        match result {
+            Err(error) => errors.push(error),
+            Ok(response) if response.event_status != EventStatus::Delivered => {
+                rejected.get_or_insert(response);
+            }
+            Ok(response) => {
+                accepted.get_or_insert(response);
+            }
+        }
+    }
+
+    match errors.len() {
+        0 => rejected
+            .or(accepted)
+            .ok_or_else(|| "No endpoint to send the request to.".into()),
+        1 => Err(errors.remove(0)),
+        failed => {
+            let errors = errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ");
+            Err(format!("{} endpoints failed: {}", failed, errors).into())
+        }
+    }
+}
+
+/// Returns the first response accepted by an endpoint, or the last failure if none accepted it.
+async fn first<F>(mut responses: FuturesUnordered<F>) -> Result<InfinoResponse, crate::Error>
+where
+    F: std::future::Future<Output = Result<InfinoResponse, crate::Error>> + Send + 'static,
+{
+    let mut failure = None;
+    while let Some(result) = responses.next().await {
+        match result {
+            Ok(response) if response.event_status == EventStatus::Delivered => {
+                if !responses.is_empty() {
+                    tokio::spawn(responses.for_each(|result| async move {
+                        match result {
+                            Ok(response) if response.event_status == EventStatus::Delivered => {}
+                            Ok(response) => warn!(
+                                message = "Endpoint did not accept a request already acknowledged.",
+                                status = %response.http_response.status(),
+                            ),
+                            Err(error) => warn!(
+                                message = "Endpoint failed a request already acknowledged.",
+                                %error,
+                            ),
+                        }
+                    }));
+                }
+                return Ok(response);
+            }
+            other => failure = Some(other),
+        }
+    }
+    failure.unwrap_or_else(|| Err("No endpoint to send the request to.".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use bytes::Bytes;
+    use futures::{future, FutureExt};
+    use http::{Response, StatusCode};
+    use tower::{service_fn, ServiceExt};
+    use vector_lib::{json_size::JsonSize, request_metadata::GroupedCountByteSize};
+
+    use super::*;
+
+    fn endpoint(
+        status: EventStatus,
+    ) -> impl Service<
+        InfinoRequest,
+        Response = InfinoResponse,
+        Error = crate::Error,
+        Future = future::Ready<Result<InfinoResponse, crate::Error>>,
+    > {
+        service_fn(move |request: InfinoRequest| {
+            future::ready(Ok(InfinoResponse {
+                http_response: Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Bytes::new())
+                    .unwrap(),
+                event_status: status,
+                batch_size: request.batch_size,
+                events_byte_size: GroupedCountByteSize::new_untagged(),
+            }))
+        })
+    }
+
+    fn request() -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: Default::default(),
+            batch_size: 1,
+            events_byte_size: JsonSize::new(1),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    async fn status(ack: FanOutAck, statuses: &[EventStatus]) -> EventStatus {
+        let services = statuses.iter().map(|status| endpoint(*status)).collect();
+        FanOutService::new(services, ack)
+            .oneshot(request())
+            .await
+            .unwrap()
+            .event_status
+    }
+
+    #[tokio::test]
+    async fn all_requires_every_endpoint() {
+        let statuses = [EventStatus::Delivered, EventStatus::Rejected];
+        assert_eq!(
+            status(FanOutAck::All, &statuses).await,
+            EventStatus::Rejected
+        );
+        assert_eq!(
+            status(FanOutAck::All, &[EventStatus::Delivered; 2]).await,
+            EventStatus::Delivered
+        );
+    }
+
+    #[tokio::test]
+    async fn all_waits_for_every_endpoint() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let failing = service_fn(|_: InfinoRequest| {
+            future::ready(Err::<InfinoResponse, crate::Error>(
+                "connection refused".into(),
+            ))
+        });
+        let slow = {
+            let sent = Arc::clone(&sent);
+            service_fn(move |_: InfinoRequest| {
+                let sent = Arc::clone(&sent);
+                async move {
+                    tokio::task::yield_now().await;
+                    sent.fetch_add(1, Ordering::Relaxed);
+                    Ok::<_, crate::Error>(InfinoResponse {
+                        http_response: Response::new(Bytes::new()),
+                        event_status: EventStatus::Delivered,
+                        batch_size: 1,
+                        events_byte_size: GroupedCountByteSize::new_untagged(),
+                    })
+                }
+                .boxed()
+            })
+        };
+        let services = vec![
+            tower::util::BoxService::new(failing),
+            tower::util::BoxService::new(slow),
+        ];
+
+        let error = FanOutService::new(services, FanOutAck::All)
+            .oneshot(request())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "connection refused");
+        assert_eq!(sent.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn first_requires_one_endpoint() {
+        let statuses = [EventStatus::Rejected, EventStatus::Delivered];
+        assert_eq!(
+            status(FanOutAck::First, &statuses).await,
+            EventStatus::Delivered
+        );
+        assert_eq!(
+            status(FanOutAck::First, &[EventStatus::Errored; 2]).await,
+            EventStatus::Errored
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vrl::path::PathPrefix;
+
+use crate::event::{LogEvent, ObjectMap, Value};
+
+/// The characters of geohashes.
+const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Normalizes the geo-points in the fields at `keys` into the shapes Infino accepts.
+///
+/// Points given as an object with `lat` and `lon` (or `latitude` and `longitude`) members, as a
+/// `[lon, lat]` array, or as a `"lat,lon"` string are written as an object with `lat` and `lon`
+/// members, while geohashes are kept as they are. Fields that hold none of these, or a point out
+/// of range, are removed, as Infino would reject the document holding them.
+pub(super) fn normalize_geo_points(log: &mut LogEvent, keys: &[ConfigValuePath]) {
+    for key in keys {
+        let Some(value) = log.get((PathPrefix::Event, key)) else {
+            continue;
+        };
+        match geo_point(value) {
+            Some(point) => {
+                log.insert((PathPrefix::Event, key), point);
+            }
+            None => {
+                debug!(
+                    message = "Removing field holding an invalid geo-point.",
+                    field = %key.0,
+                    internal_log_rate_limit = true,
+                );
+                log.remove((PathPrefix::Event, key));
+            }
+        }
+    }
+}
+
+fn geo_point(value: &Value) -> Option<Value> {
+    let (lat, lon) = match value {
+        Value::Object(object) => {
+            let member = |names: [&str; 2]| {
+                names
+                    .iter()
+                    .find_map(|name| object.get(*name))
+                    .and_then(coordinate)
+            };
+            (member(["lat", "latitude"])?, member(["lon", "longitude"])?)
+        }
+        Value::Array(array) => match array.as_slice() {
+            [lon, lat] => (coordinate(lat)?, coordinate(lon)?),
+            _ => return None,
+        },
+        Value::Bytes(bytes) => {
+            let point = std::str::from_utf8(bytes).ok()?.trim();
+            match point.split_once(',') {
+                Some((lat, lon)) => (lat.trim().parse().ok()?, lon.trim().parse().ok()?),
+                None if !point.is_empty()
+                    && point
+                        .chars()
+                        .all(|c| GEOHASH_ALPHABET.contains(c.to_ascii_lowercase())) =>
+                {
+                    return Some(Value::from(point.to_ascii_lowercase()));
+                }
+                None => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then(|| {
+        Value::Object(ObjectMap::from([
+            ("lat".into(), Value::from(lat)),
+            ("lon".into(), Value::from(lon)),
+        ]))
+    })
+}
+
+fn coordinate(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(float) => Some(float.into_inner()),
+        Value::Integer(integer) => Some(*integer as f64),
+        Value::Bytes(bytes) => std::str::from_utf8(bytes).ok()?.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, lon: f64) -> Value {
+        Value::Object(ObjectMap::from([
+            ("lat".into(), Value::from(lat)),
+            ("lon".into(), Value::from(lon)),
+        ]))
+    }
+
+    #[test]
+    fn normalizes_shapes() {
+        let mut object = ObjectMap::new();
+        object.insert("latitude".into(), Value::from(48.85));
+        object.insert("longitude".into(), Value::from("2.35"));
+
+        for (value, expected) in [
+            (Value::Object(object), Some(point(48.85, 2.35))),
+            (
+                Value::Array(vec![Value::from(2.35), Value::from(48.85)]),
+                Some(point(48.85, 2.35)),
+            ),
+            (Value::from("48.85, 2.35"), Some(point(48.85, 2.35))),
+            (Value::from("u09TVW"), Some(Value::from("u09tvw"))),
+            (Value::from("91,0"), None),
+            (Value::from("paris"), None),
+            (Value::Array(vec![Value::from(1)]), None),
+        ] {
+            assert_eq!(geo_point(&value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn removes_invalid_points() {
+        let mut log = LogEvent::default();
+        log.insert("location", "48.85,2.35");
+        log.insert("origin", "nowhere");
+
+        normalize_geo_points(&mut log, &["location".into(), "origin".into()]);
+        assert_eq!(log.get("location"), Some(&point(48.85, 2.35)));
+        assert!(!log.contains("origin"));
+    }
+}
@@ -0,0 +1,315 @@
+//! gRPC ingest, with the ingest service of Infino instead of the bulk API.
+//!
+//! Requests carry the same payload as bulk requests, and responses the same status and body, so
+//! that the retry, acknowledgement, and dead-letter logic is shared with the HTTP protocol. Calls
+//! are multiplexed on HTTP/2 connections, which spares the per-request overhead of HTTP/1.1 at
+//! high event rates.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, HOST},
+    HeaderName, Response, StatusCode, Uri,
+};
+use tonic::{body::BoxBody, metadata::MetadataMap, Code, Status};
+use tower::Service;
+use vector_lib::request_metadata::MetaDescriptive;
+use vector_lib::{config::proxy::ProxyConfig, configurable::configurable_component};
+
+use super::{
+    redact::ResponseDiagnostics,
+    service::{get_event_status, HttpRequestBuilder, InfinoRequest, InfinoResponse},
+    tls::InfinoTlsConfig,
+    InfinoAuthConfig, InfinoCommon, InfinoConfig, ParseError,
+};
+use crate::{http::HttpClient, proto::infino as proto};
+
+/// The protocol documents are sent to Infino with.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestProtocol {
+    /// Sends documents to the bulk API, over HTTP.
+    Http,
+
+    /// Sends documents to the gRPC ingest service of Infino, over HTTP/2.
+    ///
+    /// The ingest service is reached at the endpoint, and the other requests, such as health
+    /// checks, are still sent over HTTP. AWS authentication, `presigned_url`, `dns_refresh_secs`,
+    /// and `tls.reload_interval_secs` are not supported.
+    Grpc,
+}
+
+impl Default for IngestProtocol {
+    fn default() -> Self {
+        Self::Http
+    }
+}
+
+/// Fails unless every option of `config` is supported with the gRPC protocol.
+pub fn check_grpc_options(config: &InfinoConfig) -> Result<(), ParseError> {
+    let overrides = || config.endpoint_overrides.values();
+    let option = if aws_auth(config.auth.as_ref())
+        || overrides().any(|overrides| aws_auth(overrides.auth.as_ref()))
+    {
+        Some("auth.strategy = \"aws\"")
+    } else if config.presigned_url.is_some() {
+        Some("presigned_url")
+    } else if config.dns_refresh_secs.is_some() {
+        Some("dns_refresh_secs")
+    } else if reloads_certificates(config.tls.as_ref())
+        || overrides().any(|overrides| reloads_certificates(overrides.tls.as_ref()))
+    {
+        Some("tls.reload_interval_secs")
+    } else {
+        None
+    };
+    match option {
+        Some(option) => Err(ParseError::GrpcUnsupportedOption { option }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "aws-core")]
+const fn aws_auth(auth: Option<&InfinoAuthConfig>) -> bool {
+    matches!(auth, Some(InfinoAuthConfig::Aws(_)))
+}
+
+#[cfg(not(feature = "aws-core"))]
+const fn aws_auth(_auth: Option<&InfinoAuthConfig>) -> bool {
+    false
+}
+
+fn reloads_certificates(tls: Option<&InfinoTlsConfig>) -> bool {
+    tls.is_some_and(|tls| tls.reload_interval_secs.is_some())
+}
+
+/// Builds the gRPC requests of the bulk requests that [`HttpRequestBuilder`] builds.
+///
+/// The headers of the bulk request, including those of authentication, are sent as metadata,
+/// except those the ingest service sets in the message, or that gRPC sets on its own.
+pub struct GrpcRequestBuilder {
+    pub http: HttpRequestBuilder,
+}
+
+impl GrpcRequestBuilder {
+    pub async fn build_request(
+        &self,
+        req: InfinoRequest,
+    ) -> crate::Result<tonic::Request<proto::BulkRequest>> {
+        let (mut parts, payload) = self.http.build_request(req).await?.into_parts();
+        let mut header = |name: HeaderName| {
+            parts
+                .headers
+                .remove(name)
+                .and_then(|value| value.to_str().ok().map(str::to_owned))
+                .unwrap_or_default()
+        };
+        let content_type = header(CONTENT_TYPE);
+        let content_encoding = header(CONTENT_ENCODING);
+        for name in [HOST, ACCEPT_ENCODING, CONTENT_LENGTH] {
+            parts.headers.remove(name);
+        }
+
+        let message = proto::BulkRequest {
+            payload,
+            content_type,
+            content_encoding,
+            query: parts.uri.query().unwrap_or_default().to_owned(),
+        };
+        let mut request = tonic::Request::new(message);
+        *request.metadata_mut() = MetadataMap::from_headers(parts.headers);
+        Ok(request)
+    }
+}
+
+type GrpcClient = proto::Client<HttpClient<BoxBody>>;
+
+#[derive(Clone)]
+pub struct GrpcService {
+    client: GrpcClient,
+    request_builder: Arc<GrpcRequestBuilder>,
+    ignore_conflicts: bool,
+    diagnostics: ResponseDiagnostics,
+}
+
+impl GrpcService {
+    pub fn new(
+        common: &InfinoCommon,
+        request_builder: GrpcRequestBuilder,
+        proxy: &ProxyConfig,
+        ignore_conflicts: bool,
+        diagnostics: ResponseDiagnostics,
+    ) -> crate::Result<Self> {
+        let client = common
+            .tls_settings
+            .http_client_with(proxy, hyper::Client::builder().http2_only(true))?;
+        let origin = common.base_url.parse::<Uri>()?;
+        // Responses hold an item for each document, so they are as large as the batches allow.
+        let client = GrpcClient::with_origin(client, origin).max_decoding_message_size(usize::MAX);
+        Ok(Self {
+            client,
+            request_builder: Arc::new(request_builder),
+            ignore_conflicts,
+            diagnostics,
+        })
+    }
+}
+
+async fn send(
+    client: &mut GrpcClient,
+    request_builder: &GrpcRequestBuilder,
+    req: InfinoRequest,
+) -> crate::Result<Response<Bytes>> {
+    let request = request_builder.build_request(req).await?;
+    Ok(http_response(client.bulk(request).await))
+}
+
+/// Returns the bulk response of a call, so that it is handled as if it was sent over HTTP.
+///
+/// Failed calls have the HTTP status of their gRPC status, and their message as body.
+fn http_response(result: Result<tonic::Response<proto::BulkResponse>, Status>) -> Response<Bytes> {
+    let (metadata, status, body) = match result {
+        Ok(response) => {
+            let (metadata, message, _) = response.into_parts();
+            let status = u16::try_from(message.status)
+                .ok()
+                .and_then(|status| StatusCode::from_u16(status).ok())
+                .unwrap_or(StatusCode::BAD_GATEWAY);
+            (metadata, status, message.body)
+        }
+        Err(status) => (
+            status.metadata().clone(),
+            http_status(status.code()),
+            Bytes::copy_from_slice(status.message().as_bytes()),
+        ),
+    };
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = metadata.into_headers();
+    response
+}
+
+/// Returns the HTTP status matching a gRPC status code, so that failed calls are retried as the
+/// bulk requests failing the same way are.
+const fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Cancelled | Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl Service<InfinoRequest> for GrpcService {
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    // Readiness of the client is awaited by the call itself.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    // Emission of internal events for errors and dropped events is handled upstream by the caller.
+    fn call(&mut self, mut req: InfinoRequest) -> Self::Future {
+        let mut client = self.client.clone();
+        let request_builder = Arc::clone(&self.request_builder);
+        let ignore_conflicts = self.ignore_conflicts;
+        let diagnostics = self.diagnostics.clone();
+        let oauth2 = self.request_builder.http.oauth2.clone();
+        Box::pin(async move {
+            let batch_size = req.batch_size;
+            let events_byte_size =
+                std::mem::take(req.metadata_mut()).into_events_estimated_json_encoded_byte_size();
+            // Requests rejected with an expired or revoked token are sent once more with a new one.
+            let retry = oauth2.as_ref().map(|_| req.clone());
+            let mut http_response = send(&mut client, &request_builder, req).await?;
+            if let (Some(oauth2), Some(req)) = (oauth2, retry) {
+                if http_response.status() == StatusCode::UNAUTHORIZED {
+                    oauth2.invalidate().await;
+                    http_response = send(&mut client, &request_builder, req).await?;
+                }
+            }
+
+            let event_status = get_event_status(&http_response, ignore_conflicts, &diagnostics);
+            Ok(InfinoResponse {
+                event_status,
+                http_response,
+                batch_size,
+                events_byte_size,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::metadata::MetadataValue;
+
+    use super::*;
+
+    #[test]
+    fn handles_calls_as_bulk_responses() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            "x-request-id",
+            MetadataValue::from_static("4bf92f3577b34da6"),
+        );
+        let message = proto::BulkResponse {
+            status: 200,
+            body: Bytes::from_static(br#"{"errors":false}"#),
+        };
+        let response = http_response(Ok(tonic::Response::from_parts(
+            metadata,
+            message,
+            Default::default(),
+        )));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-request-id"], "4bf92f3577b34da6");
+        assert_eq!(response.body().as_ref(), br#"{"errors":false}"#);
+
+        let response = http_response(Err(Status::resource_exhausted("queue is full")));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.body().as_ref(), b"queue is full");
+
+        let response = http_response(Err(Status::unavailable("connection refused")));
+        assert!(response.status().is_server_error());
+        let response = http_response(Err(Status::invalid_argument("invalid payload")));
+        assert!(response.status().is_client_error());
+    }
+
+    #[test]
+    fn rejects_unsupported_options() {
+        let config = toml::from_str::<InfinoConfig>(
+            r#"
+            endpoints = [""]
+            protocol = "grpc"
+            presigned_url.provider.type = "command"
+            presigned_url.provider.command = ["infino-presign"]
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            check_grpc_options(&config),
+            Err(ParseError::GrpcUnsupportedOption {
+                option: "presigned_url"
+            })
+        ));
+        assert!(check_grpc_options(&InfinoConfig::default()).is_ok());
+    }
+}
@@ -0,0 +1,118 @@
+use http::{HeaderName, HeaderValue};
+use indexmap::IndexMap;
+
+use super::{encoder::ProcessedEvent, ParseError};
+use crate::{
+    internal_events::{InfinoBatchHeaderInvalid, TemplateRenderingError},
+    template::Template,
+};
+
+/// Renders the headers of requests from the events they hold.
+///
+/// Events are batched per rendered headers, so that the headers of a request hold for every one of
+/// its events.
+#[derive(Clone, Debug)]
+pub struct BatchHeaders {
+    headers: Vec<(String, Template)>,
+}
+
+impl BatchHeaders {
+    pub fn new(headers: &IndexMap<String, Template>) -> Result<Option<Self>, ParseError> {
+        if headers.is_empty() {
+            return Ok(None);
+        }
+        for header in headers.keys() {
+            if HeaderName::try_from(header.as_str()).is_err() {
+                return Err(ParseError::InvalidBatchHeader {
+                    header: header.clone(),
+                });
+            }
+        }
+        Ok(Some(Self {
+            headers: headers
+                .iter()
+                .map(|(header, template)| (header.clone(), template.clone()))
+                .collect(),
+        }))
+    }
+
+    /// Renders the headers of `event`, or returns `None` if the event has to be dropped.
+    pub fn render(&self, event: &ProcessedEvent) -> Option<Vec<(String, String)>> {
+        self.headers
+            .iter()
+            .map(|(header, template)| {
+                let value = template
+                    .render_string(&event.log)
+                    .map_err(|error| {
+                        emit!(TemplateRenderingError {
+                            error,
+                            field: Some("request.batch_headers"),
+                            drop_event: true,
+                        });
+                    })
+                    .ok()?;
+                if HeaderValue::from_str(&value).is_err() {
+                    emit!(InfinoBatchHeaderInvalid { header });
+                    return None;
+                }
+                Some((header.clone(), value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::LogEvent,
+        sinks::infino::{encoder::DocumentMetadata, BulkAction},
+    };
+
+    fn event(org_id: &str) -> ProcessedEvent {
+        let mut log = LogEvent::from("message");
+        log.insert("org_id", org_id);
+        ProcessedEvent {
+            index: "logs".to_owned(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    fn headers(template: &str) -> BatchHeaders {
+        let headers = IndexMap::from([(
+            "X-Scope-OrgID".to_owned(),
+            Template::try_from(template).unwrap(),
+        )]);
+        BatchHeaders::new(&headers).unwrap().unwrap()
+    }
+
+    #[test]
+    fn renders_headers() {
+        assert_eq!(
+            headers("{{ org_id }}").render(&event("tenant-a")),
+            Some(vec![("X-Scope-OrgID".to_owned(), "tenant-a".to_owned())])
+        );
+    }
+
+    #[test]
+    fn drops_events_with_invalid_headers() {
+        assert_eq!(headers("{{ missing }}").render(&event("tenant-a")), None);
+        assert_eq!(headers("{{ org_id }}").render(&event("tenant\na")), None);
+    }
+
+    #[test]
+    fn rejects_invalid_header_names() {
+        let headers = IndexMap::from([(
+            "X Scope".to_owned(),
+            Template::try_from("{{ org_id }}").unwrap(),
+        )]);
+        assert!(BatchHeaders::new(&headers).is_err());
+        assert!(BatchHeaders::new(&IndexMap::new()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+use tower::timeout::error::Elapsed;
+
+use crate::{
+    http::HttpError,
+    sinks::{infino::service::InfinoResponse, util::service::HealthLogic},
+};
+
+/// Tells whether an endpoint is healthy from the outcome of a request, so that requests are only
+/// distributed to the healthy endpoints, and unhealthy ones are probed again with a backoff.
+///
+/// Endpoints that fail with a server error, that cannot be reached, or that do not answer in time
+/// are unhealthy. Other failures, such as client errors, are those of the request rather than of
+/// the endpoint.
+#[derive(Clone)]
+pub struct InfinoHealthLogic;
+
+impl HealthLogic for InfinoHealthLogic {
+    type Error = crate::Error;
+    type Response = InfinoResponse;
+
+    fn is_healthy(&self, response: &Result<Self::Response, Self::Error>) -> Option<bool> {
+        match response {
+            Ok(response) => {
+                let status = response.http_response.status();
+                if status.is_success() {
+                    Some(true)
+                } else if status.is_server_error() {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            Err(error) if error.is::<Elapsed>() => Some(false),
+            Err(error) => match error.downcast_ref::<HttpError>() {
+                Some(HttpError::CallRequest { .. }) => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::Response;
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+    use crate::event::EventStatus;
+
+    fn response(status: u16) -> Result<InfinoResponse, crate::Error> {
+        Ok(InfinoResponse {
+            http_response: Response::builder()
+                .status(status)
+                .body(Bytes::new())
+                .unwrap(),
+            event_status: EventStatus::Delivered,
+            batch_size: 1,
+            events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+        })
+    }
+
+    #[test]
+    fn tells_unhealthy_endpoints() {
+        let logic = InfinoHealthLogic;
+        assert_eq!(logic.is_healthy(&response(200)), Some(true));
+        assert_eq!(logic.is_healthy(&response(503)), Some(false));
+        // Rejected requests say nothing of the endpoint.
+        assert_eq!(logic.is_healthy(&response(400)), None);
+        assert_eq!(logic.is_healthy(&response(429)), None);
+
+        assert_eq!(logic.is_healthy(&Err(Elapsed::new().into())), Some(false));
+        assert_eq!(logic.is_healthy(&Err("unknown".into())), None);
+    }
+}
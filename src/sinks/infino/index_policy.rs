@@ -0,0 +1,98 @@
+use snafu::ResultExt;
+
+use crate::{
+    event::{EventStatus, Finalizable},
+    internal_events::InfinoIndexRejected,
+    sinks::infino::{encoder::ProcessedEvent, InvalidIndexPatternSnafu, ParseError},
+};
+
+/// The indexes events may be written to, checked against the rendered index of each event.
+#[derive(Clone, Debug, Default)]
+pub struct IndexPolicy {
+    allowed: Vec<glob::Pattern>,
+    denied: Vec<glob::Pattern>,
+}
+
+impl IndexPolicy {
+    pub fn new(allowed: &[String], denied: &[String]) -> Result<Self, ParseError> {
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    glob::Pattern::new(pattern).context(InvalidIndexPatternSnafu {
+                        pattern: pattern.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            allowed: patterns(allowed)?,
+            denied: patterns(denied)?,
+        })
+    }
+
+    /// Returns the reason `index` is not allowed, if it is not.
+    fn violation(&self, index: &str) -> Option<&'static str> {
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|pattern| pattern.matches(index)) {
+            Some("Index is not in `allowed_indexes`.")
+        } else if self.denied.iter().any(|pattern| pattern.matches(index)) {
+            Some("Index is in `denied_indexes`.")
+        } else {
+            None
+        }
+    }
+
+    /// Returns the event, or `None` if its index is not allowed.
+    pub fn check(&self, mut event: ProcessedEvent) -> Option<ProcessedEvent> {
+        match self.violation(&event.index) {
+            None => Some(event),
+            Some(reason) => {
+                event.take_finalizers().update_status(EventStatus::Rejected);
+                emit!(InfinoIndexRejected {
+                    index: event.index,
+                    reason,
+                });
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn allows_everything_by_default() {
+        let policy = IndexPolicy::new(&[], &[]).unwrap();
+        assert_eq!(policy.violation("anything"), None);
+    }
+
+    #[test]
+    fn denies_indexes_outside_allowed() {
+        let policy = IndexPolicy::new(
+            &patterns(&["logs-*", "metrics-*"]),
+            &patterns(&["logs-debug-*"]),
+        )
+        .unwrap();
+        assert_eq!(policy.violation("logs-app-2024.01.01"), None);
+        assert_eq!(policy.violation("metrics-host"), None);
+        assert_eq!(
+            policy.violation("vector-2024.01.01"),
+            Some("Index is not in `allowed_indexes`.")
+        );
+        assert_eq!(
+            policy.violation("logs-debug-app"),
+            Some("Index is in `denied_indexes`.")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(IndexPolicy::new(&patterns(&["logs-[*"]), &[]).is_err());
+    }
+}
@@ -71,7 +71,9 @@ impl ElasticsearchCommon {
         }
 
         let proxy = ProxyConfig::default();
-        let client = HttpClient::new(self.tls_settings.clone(), &proxy)
+        let client = self
+            .tls_settings
+            .http_client(&proxy)
             .expect("Could not build client to flush");
         let response = client.send(request.map(SdkBody::from)).await?;
 
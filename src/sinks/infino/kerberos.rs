@@ -0,0 +1,179 @@
+//! Kerberos authentication, with SPNEGO (`Negotiate`) tokens.
+//!
+//! Every request is sent with a fresh token for the service principal of its endpoint, so that
+//! tokens never outlive the tickets they were issued from. Acquiring a token may reach the KDC, so
+//! it is done on a blocking thread.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use http::{header::AUTHORIZATION, HeaderValue, Request};
+
+use super::{InfinoAuthConfig, ParseError};
+
+/// The environment variable the Kerberos library reads the client keytab from.
+const CLIENT_KEYTAB: &str = "KRB5_CLIENT_KTNAME";
+
+/// Held for writing while `CLIENT_KEYTAB` is set, and for reading while credentials are acquired,
+/// so that the library never reads the variable as it changes.
+static CLIENT_KEYTAB_LOCK: RwLock<()> = RwLock::new(());
+
+#[derive(Clone, Debug)]
+pub struct Spnego {
+    principal: String,
+    service: String,
+}
+
+impl Spnego {
+    /// Returns the SPNEGO authentication of `auth`, if it is Kerberos.
+    pub fn new(auth: Option<&InfinoAuthConfig>) -> crate::Result<Option<Self>> {
+        let Some(InfinoAuthConfig::Kerberos {
+            principal,
+            keytab,
+            service,
+        }) = auth
+        else {
+            return Ok(None);
+        };
+        if !cfg!(feature = "infino-kerberos") {
+            return Err(
+                "Kerberos authentication requires Vector to be built with the `infino-kerberos` \
+                 feature"
+                    .into(),
+            );
+        }
+        if let Some(keytab) = keytab {
+            use_keytab(keytab)?;
+        }
+        Ok(Some(Self {
+            principal: principal.clone(),
+            service: service.clone(),
+        }))
+    }
+
+    /// Returns the host-based name of the service principal of `host`, such as `HTTP@infino`.
+    fn target(&self, host: &str) -> String {
+        format!("{}@{}", self.service, host)
+    }
+
+    /// Authenticates `request` with a token for the service principal of its host.
+    pub async fn apply(&self, request: &mut Request<Bytes>) -> crate::Result<()> {
+        let host = request
+            .uri()
+            .host()
+            .ok_or("Kerberos authentication requires the endpoint to have a host")?;
+        let target = self.target(host);
+        let principal = self.principal.clone();
+        let token = tokio::task::spawn_blocking(move || token(&principal, &target)).await??;
+        let value = format!("Negotiate {}", BASE64_STANDARD.encode(token));
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_str(&value)?);
+        Ok(())
+    }
+}
+
+/// Makes the Kerberos library acquire the initial credentials of the client from `keytab`.
+///
+/// The library only reads the client keytab from the environment, which is shared by the whole
+/// process, so a sink whose keytab differs from the one already in use fails to build.
+fn use_keytab(keytab: &Path) -> Result<(), ParseError> {
+    let _guard = CLIENT_KEYTAB_LOCK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    match std::env::var_os(CLIENT_KEYTAB) {
+        Some(current) if current != keytab.as_os_str() => Err(ParseError::KeytabConflict {
+            keytab: keytab.to_owned(),
+            current: PathBuf::from(current),
+        }),
+        Some(_) => Ok(()),
+        None => {
+            std::env::set_var(CLIENT_KEYTAB, keytab);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "infino-kerberos")]
+fn token(principal: &str, target: &str) -> crate::Result<Vec<u8>> {
+    use libgssapi::{
+        context::{ClientCtx, CtxFlags},
+        credential::{Cred, CredUsage},
+        name::Name,
+        oid::{OidSet, GSS_MECH_KRB5, GSS_NT_HOSTBASED_SERVICE, GSS_NT_KRB5_PRINCIPAL},
+    };
+
+    let mut mechanisms = OidSet::new()?;
+    mechanisms.add(&GSS_MECH_KRB5)?;
+    let principal = Name::new(principal.as_bytes(), Some(&GSS_NT_KRB5_PRINCIPAL))?;
+    let credentials = {
+        let _guard = CLIENT_KEYTAB_LOCK
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Cred::acquire(
+            Some(&principal),
+            None,
+            CredUsage::Initiate,
+            Some(&mechanisms),
+        )?
+    };
+    let target = Name::new(target.as_bytes(), Some(&GSS_NT_HOSTBASED_SERVICE))?;
+    let mut context = ClientCtx::new(
+        Some(credentials),
+        target,
+        CtxFlags::empty(),
+        Some(&GSS_MECH_KRB5),
+    );
+    let token = context
+        .step(None, None)?
+        .ok_or("Kerberos did not issue a token for the request")?;
+    Ok(token.to_vec())
+}
+
+#[cfg(not(feature = "infino-kerberos"))]
+fn token(_principal: &str, _target: &str) -> crate::Result<Vec<u8>> {
+    Err("Kerberos authentication requires the `infino-kerberos` feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_other_strategies() {
+        let auth = InfinoAuthConfig::Basic {
+            user: "user".into(),
+            password: "password".to_string().into(),
+        };
+        assert!(Spnego::new(Some(&auth)).unwrap().is_none());
+        assert!(Spnego::new(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn targets_service_of_host() {
+        let spnego = Spnego {
+            principal: "vector@EXAMPLE.COM".into(),
+            service: "HTTP".into(),
+        };
+        assert_eq!(
+            spnego.target("infino.example.com"),
+            "HTTP@infino.example.com"
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_keytabs() {
+        let keytab = std::env::var_os(CLIENT_KEYTAB)
+            .map_or_else(|| PathBuf::from("/etc/vector/vector.keytab"), PathBuf::from);
+        use_keytab(&keytab).unwrap();
+        use_keytab(&keytab).unwrap();
+        assert!(matches!(
+            use_keytab(&keytab.with_extension("other")),
+            Err(ParseError::KeytabConflict { .. })
+        ));
+    }
+}
@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+
+use vector_lib::configurable::configurable_component;
+
+use crate::{
+    sinks::infino::{config::dynamic_template, InfinoConfig, InfinoMode, ParseError},
+    template::Template,
+};
+
+/// The schema templates are checked against when the sink is built.
+///
+/// Each field referred to by the `bulk` templates, or by the `data_stream` templates in
+/// `data_stream` mode, or by `encryption.key`, `request.batch_headers`, or the templated
+/// `request.headers` and `query` parameters, that is not declared here is reported with a warning,
+/// or fails the build with `strict_templates`, so that typos are caught before events are dropped
+/// for failing to render.
+#[configurable_component]
+#[derive(Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateSchemaConfig {
+    /// The fields events reaching the sink are declared to have.
+    ///
+    /// Nested fields are written as paths, such as `kubernetes.namespace`. Templates may refer to
+    /// a declared field, to a field nested in one, or to an object holding one.
+    #[configurable(metadata(docs::examples = "application_id"))]
+    #[configurable(metadata(docs::examples = "kubernetes.namespace"))]
+    pub fields: Vec<String>,
+}
+
+/// A field referred to by a template but not declared by the schema.
+#[derive(Debug, Eq, PartialEq)]
+pub struct UndeclaredField {
+    pub option: &'static str,
+    pub field: String,
+}
+
+impl TemplateSchemaConfig {
+    fn declares(&self, reference: &str) -> bool {
+        let reference = reference.trim_start_matches('.');
+        self.fields.iter().any(|field| {
+            let field = field.trim_start_matches('.');
+            field == reference || is_nested(reference, field) || is_nested(field, reference)
+        })
+    }
+
+    /// Returns the fields referred to by the templates of `config` that are not declared.
+    pub fn undeclared_fields(&self, config: &InfinoConfig) -> Vec<UndeclaredField> {
+        templates(config)
+            .into_iter()
+            .flat_map(|(option, template)| {
+                template
+                    .get_fields()
+                    .unwrap_or_default()
+                    .into_iter()
+                    // Metadata references can not be checked against the fields of events.
+                    .filter(|field| !field.starts_with('%') && !self.declares(field))
+                    .map(move |field| UndeclaredField { option, field })
+            })
+            .collect()
+    }
+}
+
+fn is_nested(path: &str, parent: &str) -> bool {
+    path.strip_prefix(parent)
+        .map_or(false, |rest| rest.starts_with('.') || rest.starts_with('['))
+}
+
+/// Returns the templates rendered against events in the mode of `config`.
+fn templates(config: &InfinoConfig) -> Vec<(&'static str, Cow<'_, Template>)> {
+    let mut templates = mode_templates(config)
+        .into_iter()
+        .map(|(option, template)| (option, Cow::Borrowed(template)))
+        .collect::<Vec<_>>();
+    if let Some(encryption) = config.encryption.as_ref() {
+        templates.push(("encryption.key", Cow::Borrowed(&encryption.key)));
+    }
+    templates.extend(
+        config
+            .request
+            .headers
+            .values()
+            .filter_map(|value| dynamic_template(value))
+            .map(|template| ("request.headers", Cow::Owned(template))),
+    );
+    templates.extend(
+        config
+            .request
+            .batch_headers
+            .values()
+            .map(|template| ("request.batch_headers", Cow::Borrowed(template))),
+    );
+    templates.extend(
+        config
+            .query
+            .iter()
+            .flat_map(|query| query.values())
+            .filter_map(|value| dynamic_template(value))
+            .map(|template| ("query", Cow::Owned(template))),
+    );
+    templates
+}
+
+fn mode_templates(config: &InfinoConfig) -> Vec<(&'static str, &Template)> {
+    match config.mode {
+        InfinoMode::Bulk | InfinoMode::Auto => {
+            let mut templates = vec![
+                ("bulk.index", &config.bulk.index),
+                ("bulk.action", &config.bulk.action),
+            ];
+            if let Some(version) = config.bulk.version.as_ref() {
+                templates.push(("bulk.version", version));
+            }
+            templates
+        }
+        InfinoMode::DataStream => match config.data_stream.as_ref() {
+            Some(data_stream) => vec![
+                ("data_stream.type", &data_stream.dtype),
+                ("data_stream.dataset", &data_stream.dataset),
+                ("data_stream.namespace", &data_stream.namespace),
+            ],
+            None => Vec::new(),
+        },
+        InfinoMode::AlertRules => Vec::new(),
+    }
+}
+
+/// Reports the fields referred to by templates that are not declared by `template_schema`.
+///
+/// With `strict_templates`, the first of them is returned as an error, and no field is declared
+/// when `template_schema` is not set. Otherwise, they are logged as warnings.
+pub fn lint_templates(config: &InfinoConfig) -> Result<(), ParseError> {
+    let schema = match (config.template_schema.as_ref(), config.strict_templates) {
+        (Some(schema), _) => Cow::Borrowed(schema),
+        (None, true) => Cow::Owned(TemplateSchemaConfig::default()),
+        (None, false) => return Ok(()),
+    };
+    for UndeclaredField { option, field } in schema.undeclared_fields(config) {
+        if config.strict_templates {
+            return Err(ParseError::UndeclaredTemplateField { option, field });
+        }
+        warn!(
+            message = "Template refers to a field that is not declared in `template_schema`.",
+            option,
+            %field,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::sinks::infino::{BulkConfig, DataStreamConfig};
+
+    fn schema(fields: &[&str]) -> TemplateSchemaConfig {
+        TemplateSchemaConfig {
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn reports_undeclared_fields() {
+        let config = InfinoConfig {
+            bulk: BulkConfig {
+                index: Template::try_from("{{ kubernetes.namespace }}-{{ aplication }}-%Y")
+                    .unwrap(),
+                action: Template::try_from("{{ .action }}").unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let undeclared =
+            schema(&["kubernetes", "application", "action"]).undeclared_fields(&config);
+        assert_eq!(
+            undeclared,
+            [UndeclaredField {
+                option: "bulk.index",
+                field: "aplication".to_owned(),
+            }]
+        );
+
+        let undeclared = schema(&["kubernetes.namespace.name", "aplication", "action.kind"])
+            .undeclared_fields(&config);
+        assert!(undeclared.is_empty());
+    }
+
+    #[test]
+    fn checks_the_templates_of_the_mode() {
+        let config = InfinoConfig {
+            bulk: BulkConfig {
+                index: Template::try_from("{{ index }}").unwrap(),
+                ..Default::default()
+            },
+            mode: InfinoMode::DataStream,
+            data_stream: Some(DataStreamConfig {
+                dataset: Template::try_from("{{ service }}").unwrap(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let undeclared = schema(&[]).undeclared_fields(&config);
+        assert_eq!(
+            undeclared,
+            [UndeclaredField {
+                option: "data_stream.dataset",
+                field: "service".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn checks_request_templates() {
+        let mut config = InfinoConfig::default();
+        config
+            .request
+            .headers
+            .insert("X-Tenant".to_owned(), "{{ tenant }}".to_owned());
+        config
+            .request
+            .headers
+            .insert("X-Static".to_owned(), "100%".to_owned());
+        config.request.batch_headers.insert(
+            "X-Scope-OrgID".to_owned(),
+            Template::try_from("{{ org_id }}").unwrap(),
+        );
+        config.query = Some(HashMap::from([
+            ("routing".to_owned(), "{{ shard }}".to_owned()),
+            ("timeout".to_owned(), "30s".to_owned()),
+        ]));
+
+        let undeclared = schema(&[]).undeclared_fields(&config);
+        assert_eq!(
+            undeclared,
+            [
+                UndeclaredField {
+                    option: "request.headers",
+                    field: "tenant".to_owned(),
+                },
+                UndeclaredField {
+                    option: "request.batch_headers",
+                    field: "org_id".to_owned(),
+                },
+                UndeclaredField {
+                    option: "query",
+                    field: "shard".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_templates_fail() {
+        let config = InfinoConfig {
+            bulk: BulkConfig {
+                index: Template::try_from("logs-{{ service }}").unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(lint_templates(&config).is_ok());
+
+        let config = InfinoConfig {
+            strict_templates: true,
+            ..config
+        };
+        assert!(matches!(
+            lint_templates(&config),
+            Err(ParseError::UndeclaredTemplateField {
+                option: "bulk.index",
+                ..
+            })
+        ));
+
+        let config = InfinoConfig {
+            template_schema: Some(schema(&["service"])),
+            ..config
+        };
+        assert!(lint_templates(&config).is_ok());
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use tower::{buffer::Buffer, Service, ServiceExt};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    service::{InfinoRequest, InfinoResponse},
+    ParseError,
+};
+use crate::{event::EventStatus, internal_events::InfinoCrossZoneRequest};
+
+/// Locality configuration.
+///
+/// Requests are sent to the endpoints in the zone of Vector, to avoid the cost of transfers
+/// between zones, and only sent to the endpoints of other zones when those in the zone fail the
+/// request, or cannot take it, such as when they are all unhealthy.
+///
+/// Zones are labels, so they may equally be regions, or any other locality.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LocalityConfig {
+    /// The zone Vector runs in.
+    ///
+    /// It is usually injected by an orchestrator, through an environment variable.
+    #[configurable(metadata(docs::examples = "${ZONE}"))]
+    #[configurable(metadata(docs::examples = "us-east-1a"))]
+    pub zone: String,
+
+    /// The zone of endpoints, by endpoint.
+    ///
+    /// Endpoints that are not listed are in other zones.
+    #[serde(default)]
+    #[configurable(metadata(docs::additional_props_description = "The zone of the endpoint."))]
+    #[configurable(metadata(docs::examples = "endpoint_zones_examples()"))]
+    pub endpoint_zones: HashMap<String, String>,
+}
+
+fn endpoint_zones_examples() -> HashMap<String, String> {
+    HashMap::from([(
+        "http://10.24.32.122:9000".to_owned(),
+        "us-east-1a".to_owned(),
+    )])
+}
+
+impl LocalityConfig {
+    pub fn validate(&self, endpoints: &[String]) -> Result<(), ParseError> {
+        if self
+            .endpoint_zones
+            .keys()
+            .any(|endpoint| !endpoints.contains(endpoint))
+        {
+            return Err(ParseError::UnknownZonedEndpoint);
+        }
+        Ok(())
+    }
+
+    /// Returns whether the configured `endpoint` is in the zone of Vector.
+    pub fn is_local(&self, endpoint: &str) -> bool {
+        self.endpoint_zones.get(endpoint) == Some(&self.zone)
+    }
+}
+
+/// Sends requests to the service of the endpoints in the zone, and to the service of the other
+/// endpoints when it fails them or is not ready for them.
+pub struct LocalityService<S>
+where
+    S: Service<InfinoRequest>,
+{
+    local: S,
+    remote: Buffer<S, InfinoRequest>,
+    /// Whether the next request goes to the other zones, as the local service is not ready.
+    cross_zone: bool,
+}
+
+impl<S> LocalityService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    pub fn new(local: S, remote: S) -> Self {
+        Self {
+            local,
+            remote: Buffer::new(remote, 1),
+            cross_zone: false,
+        }
+    }
+}
+
+impl<S> Service<InfinoRequest> for LocalityService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.local.poll_ready(cx)?.is_ready() {
+            self.cross_zone = false;
+            return Poll::Ready(Ok(()));
+        }
+        self.cross_zone = true;
+        self.remote.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        if self.cross_zone {
+            emit!(InfinoCrossZoneRequest {
+                reason: "local_not_ready",
+            });
+            return self.remote.call(req).boxed();
+        }
+        let remote = self.remote.clone();
+        let retried = req.clone();
+        let local = self.local.call(req);
+        async move {
+            match local.await {
+                Ok(response) if response.event_status != EventStatus::Errored => Ok(response),
+                _ => {
+                    emit!(InfinoCrossZoneRequest {
+                        reason: "local_failed",
+                    });
+                    remote.oneshot(retried).await
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use bytes::Bytes;
+    use futures::future;
+    use http::Response;
+    use tower::{service_fn, util::BoxService};
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::*;
+
+    type Endpoint = BoxService<InfinoRequest, InfinoResponse, crate::Error>;
+
+    fn endpoint(status: EventStatus, calls: Arc<AtomicUsize>) -> Endpoint {
+        BoxService::new(service_fn(move |_: InfinoRequest| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            future::ok(InfinoResponse {
+                http_response: Response::new(Bytes::new()),
+                event_status: status,
+                batch_size: 1,
+                events_byte_size: GroupedCountByteSize::new_untagged(),
+            })
+        }))
+    }
+
+    fn request() -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: Default::default(),
+            batch_size: 1,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    async fn send(local: EventStatus) -> (EventStatus, usize) {
+        let remote_calls = Arc::new(AtomicUsize::new(0));
+        let service = LocalityService::new(
+            endpoint(local, Arc::new(AtomicUsize::new(0))),
+            endpoint(EventStatus::Delivered, Arc::clone(&remote_calls)),
+        );
+        let status = service.oneshot(request()).await.unwrap().event_status;
+        (status, remote_calls.load(Ordering::Relaxed))
+    }
+
+    #[tokio::test]
+    async fn prefers_the_local_zone() {
+        assert_eq!(
+            send(EventStatus::Delivered).await,
+            (EventStatus::Delivered, 0)
+        );
+        // Rejected requests would be rejected by the other zones too.
+        assert_eq!(
+            send(EventStatus::Rejected).await,
+            (EventStatus::Rejected, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_other_zones() {
+        assert_eq!(
+            send(EventStatus::Errored).await,
+            (EventStatus::Delivered, 1)
+        );
+    }
+
+    #[test]
+    fn finds_local_endpoints() {
+        let config = LocalityConfig {
+            zone: "a".to_owned(),
+            endpoint_zones: HashMap::from([
+                ("http://a:9000".to_owned(), "a".to_owned()),
+                ("http://b:9000".to_owned(), "b".to_owned()),
+            ]),
+        };
+        assert!(config.is_local("http://a:9000"));
+        assert!(!config.is_local("http://b:9000"));
+        assert!(!config.is_local("http://c:9000"));
+        assert!(config.validate(&["http://a:9000".to_owned()]).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+use vector_lib::configurable::configurable_component;
+
+/// Index template configuration.
+///
+/// Installs an index template on every endpoint when the sink starts, so that the indexes created
+/// by its writes map the given fields to their intended type. Otherwise, Infino infers the type of
+/// fields from their first value, which tokenizes high-cardinality identifiers as text.
+///
+/// A template that cannot be installed on an endpoint, such as one that is unreachable, is logged
+/// as a warning instead of failing the startup of the sink.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct IndexTemplateConfig {
+    /// The name of the index template.
+    ///
+    /// An existing template of the same name is replaced.
+    #[serde(default = "default_name")]
+    pub name: String,
+
+    /// The patterns of the names of the indexes the template applies to.
+    #[serde(default = "default_index_patterns")]
+    #[configurable(metadata(docs::examples = "logs-*"))]
+    pub index_patterns: Vec<String>,
+
+    /// The type of the fields of documents, by field name.
+    ///
+    /// Nested fields are named with dots, such as `user.id`. Other fields keep the type inferred by
+    /// Infino.
+    #[serde(default)]
+    #[configurable(metadata(docs::additional_props_description = "The type of the field."))]
+    #[configurable(metadata(docs::examples = "example_fields()"))]
+    pub fields: BTreeMap<String, FieldType>,
+}
+
+impl Default for IndexTemplateConfig {
+    fn default() -> Self {
+        Self {
+            name: default_name(),
+            index_patterns: default_index_patterns(),
+            fields: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_name() -> String {
+    "vector".to_owned()
+}
+
+fn default_index_patterns() -> Vec<String> {
+    vec!["vector-*".to_owned()]
+}
+
+fn example_fields() -> BTreeMap<String, FieldType> {
+    BTreeMap::from([
+        ("message".to_owned(), FieldType::Text),
+        ("trace_id".to_owned(), FieldType::Keyword),
+        ("duration_ms".to_owned(), FieldType::Numeric),
+    ])
+}
+
+/// The type of a field in Infino.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    /// An exact value, such as an identifier, which is not tokenized.
+    Keyword,
+
+    /// Full text, which is tokenized for full-text search.
+    Text,
+
+    /// A floating point number.
+    Numeric,
+
+    /// A date, as an RFC 3339 timestamp or a number of milliseconds since the Unix epoch.
+    Date,
+
+    /// A geo-point, as normalized by `geo_points`.
+    GeoPoint,
+}
+
+impl FieldType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Text => "text",
+            Self::Numeric => "double",
+            Self::Date => "date",
+            Self::GeoPoint => "geo_point",
+        }
+    }
+}
+
+impl IndexTemplateConfig {
+    /// Returns the body of the request installing the template.
+    pub fn body(&self) -> Value {
+        let mut properties = Map::new();
+        for (field, field_type) in &self.fields {
+            let mut segments = field.split('.').peekable();
+            let mut properties = &mut properties;
+            while let Some(segment) = segments.next() {
+                let property = properties
+                    .entry(segment)
+                    .or_insert_with(|| json!({}))
+                    .as_object_mut()
+                    .expect("properties are objects");
+                if segments.peek().is_none() {
+                    property.insert("type".to_owned(), json!(field_type.as_str()));
+                } else {
+                    properties = property
+                        .entry("properties")
+                        .or_insert_with(|| json!({}))
+                        .as_object_mut()
+                        .expect("properties are objects");
+                }
+            }
+        }
+
+        json!({
+            "index_patterns": self.index_patterns,
+            "template": {
+                "mappings": {
+                    "properties": properties,
+                },
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_template() {
+        let config = IndexTemplateConfig {
+            fields: BTreeMap::from([
+                ("trace_id".to_owned(), FieldType::Keyword),
+                ("user.id".to_owned(), FieldType::Keyword),
+                ("user.name".to_owned(), FieldType::Text),
+                ("took".to_owned(), FieldType::Numeric),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.body(),
+            json!({
+                "index_patterns": ["vector-*"],
+                "template": {
+                    "mappings": {
+                        "properties": {
+                            "took": { "type": "double" },
+                            "trace_id": { "type": "keyword" },
+                            "user": {
+                                "properties": {
+                                    "id": { "type": "keyword" },
+                                    "name": { "type": "text" },
+                                },
+                            },
+                        },
+                    },
+                },
+            })
+        );
+    }
+}
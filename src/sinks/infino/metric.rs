@@ -0,0 +1,773 @@
+use chrono::Utc;
+use regex::Regex;
+use snafu::ResultExt;
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::event_path;
+
+use super::{encoder::NonFiniteFloats, InvalidNamespacePatternSnafu, ParseError};
+use crate::{
+    config::log_schema,
+    event::{
+        metric::MetricSketch, EventStatus, Finalizable, LogEvent, Metric, MetricValue, ObjectMap,
+        Value,
+    },
+    internal_events::{InfinoMetricTimestampMissing, InfinoMetricsDropped, TemplateRenderingError},
+    template::Template,
+};
+
+/// Metric document configuration.
+///
+/// Controls how metrics are converted into Infino metric documents.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MetricEncodingConfig {
+    #[configurable(derived)]
+    #[serde(default)]
+    pub missing_timestamp: MissingTimestamp,
+
+    /// Whether or not to encode numeric and boolean tag values as typed JSON values.
+    ///
+    /// Tag values such as `42`, `0.5`, or `true` are written as a number or a boolean in the
+    /// `labels` of the document. Values are only converted when they are written exactly as their
+    /// typed counterpart would be, so that identifiers such as `007` stay strings. Disable this for
+    /// backends that expect all labels to be strings.
+    #[serde(default = "crate::serde::default_true")]
+    pub typed_labels: bool,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub multi_value_tags: MultiValueTags,
+
+    /// The separator between values of a tag, when `multi_value_tags` is set to `join`.
+    #[serde(default = "default_multi_value_separator")]
+    pub multi_value_separator: String,
+
+    /// Routes metrics to an index, or data stream, according to their namespace.
+    ///
+    /// Routes are tried in order, and metrics are sent to the index of the first route matching
+    /// their namespace. Metrics that match no route, or have no namespace, are sent to the index
+    /// of the sink.
+    #[serde(default)]
+    pub namespace_routes: Vec<NamespaceRoute>,
+
+    /// Whether or not to convert counters into their rate per second.
+    ///
+    /// Counters are written as gauges of their rate over the interval since the previous point of
+    /// their series, for dashboards built on rate series. Incremental counters carrying their
+    /// interval are converted on their own, while the first point of other counter series is
+    /// dropped, as it has no previous point to compute a rate from.
+    #[serde(default)]
+    pub counter_rates: bool,
+
+    /// The names of the metrics to send.
+    ///
+    /// Patterns are globs, or regular expressions when enclosed in slashes. By default, all
+    /// metrics are sent.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "http_*"))]
+    #[configurable(metadata(docs::examples = "/^(cpu|memory)_.+$/"))]
+    pub include_names: Vec<String>,
+
+    /// The names of the metrics not to send, even if they match `include_names`.
+    ///
+    /// Patterns are globs, or regular expressions when enclosed in slashes.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "*_bucket"))]
+    pub exclude_names: Vec<String>,
+
+    /// The tag holding the unit of metrics.
+    ///
+    /// The unit, such as `seconds` or `bytes`, is written in the `unit` field of the document
+    /// rather than in its `labels`, so that Infino can label the axes of charts. Metrics without
+    /// this tag are written without a unit.
+    #[serde(default = "default_unit_tag")]
+    #[configurable(metadata(docs::examples = "unit"))]
+    #[configurable(metadata(docs::examples = "otel.unit"))]
+    pub unit_tag: String,
+}
+
+/// A route from metric namespaces to an index.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceRoute {
+    /// The namespaces routed to `index`, as a glob pattern.
+    #[configurable(metadata(docs::examples = "host"))]
+    #[configurable(metadata(docs::examples = "app_*"))]
+    pub namespace: String,
+
+    /// The index, or data stream, metrics are written to.
+    ///
+    /// The template is rendered against the metric, so that it can refer to its tags.
+    #[configurable(metadata(docs::examples = "metrics-host"))]
+    #[configurable(metadata(docs::examples = "metrics-{{ tags.service }}"))]
+    pub index: Template,
+}
+
+impl Default for MetricEncodingConfig {
+    fn default() -> Self {
+        Self {
+            missing_timestamp: Default::default(),
+            typed_labels: true,
+            multi_value_tags: Default::default(),
+            multi_value_separator: default_multi_value_separator(),
+            namespace_routes: Vec::new(),
+            counter_rates: false,
+            include_names: Vec::new(),
+            exclude_names: Vec::new(),
+            unit_tag: default_unit_tag(),
+        }
+    }
+}
+
+fn default_multi_value_separator() -> String {
+    ",".to_owned()
+}
+
+fn default_unit_tag() -> String {
+    "unit".to_owned()
+}
+
+/// What to do with metrics that have no timestamp.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingTimestamp {
+    /// Stamp the metric with the time it is encoded at.
+    #[default]
+    Now,
+
+    /// Drop the metric.
+    Drop,
+}
+
+/// How to encode tags holding more than one value.
+///
+/// Tags holding a single value are always encoded as that value.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiValueTags {
+    /// Encode all of the values as an array.
+    #[default]
+    Array,
+
+    /// Join all of the values into a single string, separated by `multi_value_separator`.
+    Join,
+
+    /// Keep the first value only.
+    First,
+
+    /// Keep the last value only.
+    Last,
+}
+
+/// A metric name pattern.
+#[derive(Clone, Debug)]
+enum NamePattern {
+    Glob(glob::Pattern),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    fn new(pattern: &str) -> Result<Self, ParseError> {
+        let parsed = match pattern
+            .strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'))
+        {
+            Some(regex) => Regex::new(regex)
+                .map(Self::Regex)
+                .map_err(|error| error.to_string()),
+            None => glob::Pattern::new(pattern)
+                .map(Self::Glob)
+                .map_err(|error| error.to_string()),
+        };
+        parsed.map_err(|reason| ParseError::InvalidMetricNamePattern {
+            pattern: pattern.to_owned(),
+            reason,
+        })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => pattern.matches(name),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Converts metrics into Infino metric documents.
+#[derive(Clone, Debug, Default)]
+pub struct MetricEncoder {
+    config: MetricEncodingConfig,
+    routes: Vec<(glob::Pattern, Template)>,
+    include_names: Vec<NamePattern>,
+    exclude_names: Vec<NamePattern>,
+    non_finite_floats: NonFiniteFloats,
+}
+
+impl MetricEncoder {
+    pub fn new(config: MetricEncodingConfig) -> Result<Self, ParseError> {
+        let routes = config
+            .namespace_routes
+            .iter()
+            .map(|route| {
+                let pattern =
+                    glob::Pattern::new(&route.namespace).context(InvalidNamespacePatternSnafu {
+                        pattern: route.namespace.clone(),
+                    })?;
+                Ok((pattern, route.index.clone()))
+            })
+            .collect::<Result<_, ParseError>>()?;
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| NamePattern::new(pattern))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include_names: patterns(&config.include_names)?,
+            exclude_names: patterns(&config.exclude_names)?,
+            config,
+            routes,
+            non_finite_floats: Default::default(),
+        })
+    }
+
+    /// Sets how metric values that are NaN or infinite are written.
+    pub fn with_non_finite_floats(mut self, non_finite_floats: NonFiniteFloats) -> Self {
+        self.non_finite_floats = non_finite_floats;
+        self
+    }
+
+    /// Returns `metric` if its name is allowed by `include_names` and `exclude_names`.
+    pub fn filter(&self, mut metric: Metric) -> Option<Metric> {
+        let name = metric.name();
+        let included = self.include_names.is_empty()
+            || self
+                .include_names
+                .iter()
+                .any(|pattern| pattern.matches(name));
+        if included
+            && !self
+                .exclude_names
+                .iter()
+                .any(|pattern| pattern.matches(name))
+        {
+            Some(metric)
+        } else {
+            metric
+                .take_finalizers()
+                .update_status(EventStatus::Delivered);
+            emit!(InfinoMetricsDropped {
+                count: 1,
+                reason: "Metric name is excluded.",
+            });
+            None
+        }
+    }
+
+    /// Returns the index of the route matching the namespace of `metric`, if any.
+    ///
+    /// Returns `Err` if the index of the route failed to render, in which case the metric is
+    /// dropped.
+    pub fn route(&self, metric: &Metric) -> Result<Option<String>, ()> {
+        let Some(namespace) = metric.namespace() else {
+            return Ok(None);
+        };
+        let Some((_, index)) = self
+            .routes
+            .iter()
+            .find(|(pattern, _)| pattern.matches(namespace))
+        else {
+            return Ok(None);
+        };
+        index.render_string(metric).map(Some).map_err(|error| {
+            emit!(TemplateRenderingError {
+                error,
+                field: Some("index"),
+                drop_event: true,
+            });
+        })
+    }
+
+    /// Converts a metric into an Infino metric document, or returns `None` if it is dropped.
+    ///
+    /// The document holds the metric name, the time of the point in seconds, its value, its unit if
+    /// known, and the other metric tags as `labels`.
+    pub fn encode(&self, metric: Metric) -> Option<LogEvent> {
+        let timestamp = match metric.timestamp() {
+            Some(timestamp) => timestamp,
+            None => match self.config.missing_timestamp {
+                MissingTimestamp::Now => {
+                    emit!(InfinoMetricTimestampMissing { count: 1 });
+                    Utc::now()
+                }
+                MissingTimestamp::Drop => {
+                    emit!(InfinoMetricsDropped {
+                        count: 1,
+                        reason: "Metric has no timestamp.",
+                    });
+                    return None;
+                }
+            },
+        };
+
+        let value = match metric.value() {
+            MetricValue::Counter { value } => *value,
+            MetricValue::Gauge { value } => *value,
+            MetricValue::Set { values } => values.len() as f64,
+            MetricValue::Distribution { samples, .. } => samples.iter().map(|s| s.value).sum(),
+            MetricValue::AggregatedHistogram { sum, .. } => *sum,
+            MetricValue::AggregatedSummary { sum, .. } => *sum,
+            MetricValue::Sketch { sketch } => match sketch {
+                MetricSketch::AgentDDSketch(ddsketch) => ddsketch.sum().unwrap_or_default(),
+            },
+        };
+        let Some(value) = self.non_finite_floats.value(value) else {
+            emit!(InfinoMetricsDropped {
+                count: 1,
+                reason: "Metric value is not finite.",
+            });
+            return None;
+        };
+        let unit = metric.tag_value(&self.config.unit_tag);
+        let labels = metric
+            .tags()
+            .map(|tags| {
+                tags.iter_sets()
+                    .filter(|(key, _)| *key != self.config.unit_tag)
+                    .filter_map(|(key, values)| {
+                        let values = values.iter().flatten().collect::<Vec<_>>();
+                        self.label(&values).map(|label| (key.into(), label))
+                    })
+                    .collect::<ObjectMap>()
+            })
+            .unwrap_or_default();
+
+        let metric_name = metric.name().to_owned();
+        // Keep the metadata of the metric, including its finalizers and source.
+        let (_, _, metadata) = metric.into_parts();
+        let mut log = LogEvent::new_with_metadata(metadata);
+        log.insert(event_path!("metric_name"), metric_name);
+        log.insert(event_path!("time"), timestamp.timestamp());
+        log.insert(event_path!("value"), value);
+        log.insert(event_path!("labels"), labels);
+        if let Some(unit) = unit {
+            log.insert(event_path!("unit"), unit);
+        }
+        log.maybe_insert(log_schema().timestamp_key_target_path(), timestamp);
+        Some(log)
+    }
+
+    /// Returns the label for the values of a tag, or `None` if it has no value.
+    fn label(&self, values: &[&str]) -> Option<Value> {
+        let typed = self.config.typed_labels;
+        let label = match (values, self.config.multi_value_tags) {
+            ([], _) => return None,
+            ([value], _) | ([value, ..], MultiValueTags::First) => label_value(value, typed),
+            ([.., value], MultiValueTags::Last) => label_value(value, typed),
+            (values, MultiValueTags::Join) => {
+                Value::from(values.join(&self.config.multi_value_separator))
+            }
+            (values, MultiValueTags::Array) => Value::Array(
+                values
+                    .iter()
+                    .map(|value| label_value(value, typed))
+                    .collect(),
+            ),
+        };
+        Some(label)
+    }
+}
+
+/// Splits aggregated histograms and summaries into the points of their buckets or quantiles,
+/// count, and sum, as Prometheus writes them, so that percentiles can be queried from the
+/// documents. Other metrics are returned as they are.
+///
+/// Bucket points are named `<name>_bucket`, with their upper limit in the `le` tag and the
+/// cumulative count of their values, up to the `+Inf` bucket holding the count of every value.
+/// Quantile points keep the name of the summary, with their quantile in the `quantile` tag.
+pub fn split_aggregates(metric: Metric) -> Vec<Metric> {
+    let point = |suffix: Option<&str>, tag: Option<(&str, String)>, value: MetricValue| {
+        let mut point = metric.clone().with_value(value);
+        if let Some(suffix) = suffix {
+            point = point.with_name(format!("{}_{}", metric.name(), suffix));
+        }
+        if let Some((key, value)) = tag {
+            point.replace_tag(key.to_owned(), value);
+        }
+        point
+    };
+
+    let (mut points, count, sum) = match metric.value() {
+        MetricValue::AggregatedHistogram {
+            buckets,
+            count,
+            sum,
+        } => {
+            let mut cumulative = 0;
+            let mut points = buckets
+                .iter()
+                // The `+Inf` bucket is written from the total count.
+                .filter(|bucket| bucket.upper_limit.is_finite())
+                .map(|bucket| {
+                    cumulative += bucket.count;
+                    let le = ("le", bucket.upper_limit.to_string());
+                    let value = cumulative as f64;
+                    point(Some("bucket"), Some(le), MetricValue::Counter { value })
+                })
+                .collect::<Vec<_>>();
+            let le = ("le", "+Inf".to_owned());
+            let value = *count as f64;
+            points.push(point(
+                Some("bucket"),
+                Some(le),
+                MetricValue::Counter { value },
+            ));
+            (points, *count, *sum)
+        }
+        MetricValue::AggregatedSummary {
+            quantiles,
+            count,
+            sum,
+        } => {
+            let points = quantiles
+                .iter()
+                .map(|quantile| {
+                    let tag = ("quantile", quantile.quantile.to_string());
+                    let value = quantile.value;
+                    point(None, Some(tag), MetricValue::Gauge { value })
+                })
+                .collect::<Vec<_>>();
+            (points, *count, *sum)
+        }
+        _ => return vec![metric],
+    };
+    points.push(point(
+        Some("sum"),
+        None,
+        MetricValue::Counter { value: sum },
+    ));
+    let value = count as f64;
+    points.push(point(Some("count"), None, MetricValue::Counter { value }));
+    points
+}
+
+/// Returns the label value for a tag value, typed if `typed` is set and the tag value is the exact
+/// representation of a boolean or a number.
+fn label_value(value: &str, typed: bool) -> Value {
+    if typed {
+        if let Ok(boolean) = value.parse::<bool>() {
+            return Value::from(boolean);
+        }
+        if let Ok(integer) = value.parse::<i64>() {
+            if integer.to_string() == value {
+                return Value::from(integer);
+            }
+        }
+        if let Ok(float) = value.parse::<f64>() {
+            if float.is_finite() && float.to_string() == value {
+                return Value::from(float);
+            }
+        }
+    }
+    Value::from(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::event::{
+        metric::{Bucket, MetricTags, Quantile, TagValue},
+        MetricKind,
+    };
+
+    fn gauge() -> Metric {
+        Metric::new(
+            "cpu",
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 0.5 },
+        )
+    }
+
+    #[test]
+    fn encodes_timestamp() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let log = MetricEncoder::default()
+            .encode(gauge().with_timestamp(Some(timestamp)))
+            .unwrap();
+        assert_eq!(log.get("time"), Some(&Value::from(timestamp.timestamp())));
+        assert_eq!(log.get("value"), Some(&Value::from(0.5)));
+    }
+
+    #[test]
+    fn stamps_metrics_without_timestamp() {
+        let before = Utc::now().timestamp();
+        let log = MetricEncoder::default().encode(gauge()).unwrap();
+        let time = log.get("time").and_then(Value::as_integer).unwrap();
+        assert!(time >= before);
+        assert!(log.get_timestamp().is_some());
+    }
+
+    #[test]
+    fn encodes_unit() {
+        let mut metric = gauge();
+        metric.replace_tag("unit".to_owned(), "seconds".to_owned());
+        metric.replace_tag("host".to_owned(), "web-1".to_owned());
+        let log = MetricEncoder::default().encode(metric).unwrap();
+        assert_eq!(log.get("unit"), Some(&Value::from("seconds")));
+        assert_eq!(log.get("labels.host"), Some(&Value::from("web-1")));
+        assert!(!log.contains("labels.unit"));
+
+        let log = MetricEncoder::default().encode(gauge()).unwrap();
+        assert!(!log.contains("unit"));
+    }
+
+    #[test]
+    fn handles_non_finite_values() {
+        let nan = Metric::new(
+            "cpu",
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: f64::NAN },
+        );
+        let log = MetricEncoder::default().encode(nan.clone()).unwrap();
+        assert_eq!(log.get("value"), Some(&Value::Null));
+
+        let encoder = MetricEncoder::default().with_non_finite_floats(NonFiniteFloats::Stringify);
+        let log = encoder.encode(nan.clone()).unwrap();
+        assert_eq!(log.get("value"), Some(&Value::from("NaN")));
+
+        let encoder = MetricEncoder::default().with_non_finite_floats(NonFiniteFloats::Drop);
+        assert!(encoder.encode(nan).is_none());
+    }
+
+    #[test]
+    fn drops_metrics_without_timestamp() {
+        let encoder = MetricEncoder::new(MetricEncodingConfig {
+            missing_timestamp: MissingTimestamp::Drop,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(encoder.encode(gauge()).is_none());
+    }
+
+    #[test]
+    fn encodes_multi_value_tags() {
+        let metric = gauge().with_tags(Some({
+            let mut tags = MetricTags::default();
+            tags.set_multi_value("region".to_owned(), ["eu", "us"].map(TagValue::from));
+            tags.insert("host".to_owned(), "web-1");
+            tags.insert("bare".to_owned(), TagValue::Bare);
+            tags
+        }));
+
+        for (multi_value_tags, region) in [
+            (
+                MultiValueTags::Array,
+                Value::Array(vec![Value::from("eu"), Value::from("us")]),
+            ),
+            (MultiValueTags::Join, Value::from("eu|us")),
+            (MultiValueTags::First, Value::from("eu")),
+            (MultiValueTags::Last, Value::from("us")),
+        ] {
+            let encoder = MetricEncoder::new(MetricEncodingConfig {
+                multi_value_tags,
+                multi_value_separator: "|".to_owned(),
+                ..Default::default()
+            })
+            .unwrap();
+            let log = encoder
+                .encode(metric.clone().with_timestamp(Some(Utc::now())))
+                .unwrap();
+            assert_eq!(log.get("labels.region"), Some(&region));
+            assert_eq!(log.get("labels.host"), Some(&Value::from("web-1")));
+            assert!(log.get("labels.bare").is_none());
+        }
+    }
+
+    #[test]
+    fn routes_by_namespace() {
+        let route = |namespace: &str, index: &str| NamespaceRoute {
+            namespace: namespace.to_owned(),
+            index: Template::try_from(index).unwrap(),
+        };
+        let encoder = MetricEncoder::new(MetricEncodingConfig {
+            namespace_routes: vec![
+                route("host", "metrics-host"),
+                route("app_*", "metrics-{{ tags.service }}"),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let host = gauge().with_namespace(Some("host"));
+        assert_eq!(encoder.route(&host), Ok(Some("metrics-host".to_owned())));
+
+        let mut app = gauge().with_namespace(Some("app_checkout"));
+        app.replace_tag("service".to_owned(), "checkout".to_owned());
+        assert_eq!(encoder.route(&app), Ok(Some("metrics-checkout".to_owned())));
+
+        assert_eq!(encoder.route(&gauge()), Ok(None));
+        assert_eq!(
+            encoder.route(&gauge().with_namespace(Some("other"))),
+            Ok(None)
+        );
+        assert!(encoder
+            .route(&gauge().with_namespace(Some("app_web")))
+            .is_err());
+
+        assert!(MetricEncoder::new(MetricEncodingConfig {
+            namespace_routes: vec![route("[", "metrics")],
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn filters_metric_names() {
+        let encoder = MetricEncoder::new(MetricEncodingConfig {
+            include_names: vec!["http_*".to_owned(), "/^(cpu|memory)$/".to_owned()],
+            exclude_names: vec!["*_bucket".to_owned()],
+            ..Default::default()
+        })
+        .unwrap();
+        let named = |name: &str| gauge().with_name(name);
+
+        assert!(encoder.filter(named("http_requests")).is_some());
+        assert!(encoder.filter(named("cpu")).is_some());
+        assert!(encoder.filter(named("cpu_seconds")).is_none());
+        assert!(encoder.filter(named("http_latency_bucket")).is_none());
+        assert!(encoder.filter(named("disk")).is_none());
+
+        assert!(MetricEncoder::new(MetricEncodingConfig {
+            exclude_names: vec!["/(/".to_owned()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn types_label_values() {
+        for (tag, typed) in [
+            ("true", Value::from(true)),
+            ("42", Value::from(42_i64)),
+            ("-0.5", Value::from(-0.5)),
+            ("007", Value::from("007")),
+            ("1.50", Value::from("1.50")),
+            ("NaN", Value::from("NaN")),
+            ("web-1", Value::from("web-1")),
+        ] {
+            assert_eq!(label_value(tag, true), typed, "{tag}");
+            assert_eq!(label_value(tag, false), Value::from(tag));
+        }
+    }
+
+    #[test]
+    fn splits_histograms() {
+        let histogram = Metric::new(
+            "latency",
+            MetricKind::Absolute,
+            MetricValue::AggregatedHistogram {
+                buckets: vec![
+                    Bucket {
+                        upper_limit: 0.5,
+                        count: 2,
+                    },
+                    Bucket {
+                        upper_limit: 1.0,
+                        count: 3,
+                    },
+                    Bucket {
+                        upper_limit: f64::INFINITY,
+                        count: 1,
+                    },
+                ],
+                count: 6,
+                sum: 4.5,
+            },
+        );
+
+        let points = split_aggregates(histogram)
+            .iter()
+            .map(|point| {
+                let MetricValue::Counter { value } = point.value() else {
+                    panic!("not a counter");
+                };
+                (point.name().to_owned(), point.tag_value("le"), *value)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            [
+                ("latency_bucket".to_owned(), Some("0.5".to_owned()), 2.0),
+                ("latency_bucket".to_owned(), Some("1".to_owned()), 5.0),
+                ("latency_bucket".to_owned(), Some("+Inf".to_owned()), 6.0),
+                ("latency_sum".to_owned(), None, 4.5),
+                ("latency_count".to_owned(), None, 6.0),
+            ]
+        );
+        assert_eq!(split_aggregates(gauge()), [gauge()]);
+    }
+
+    #[test]
+    fn splits_summaries() {
+        let summary = Metric::new(
+            "latency",
+            MetricKind::Absolute,
+            MetricValue::AggregatedSummary {
+                quantiles: vec![
+                    Quantile {
+                        quantile: 0.5,
+                        value: 0.2,
+                    },
+                    Quantile {
+                        quantile: 0.99,
+                        value: 1.5,
+                    },
+                ],
+                count: 6,
+                sum: 4.5,
+            },
+        );
+
+        let points = split_aggregates(summary)
+            .iter()
+            .map(|point| {
+                (
+                    point.name().to_owned(),
+                    point.tag_value("quantile"),
+                    point.value().clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            [
+                (
+                    "latency".to_owned(),
+                    Some("0.5".to_owned()),
+                    MetricValue::Gauge { value: 0.2 }
+                ),
+                (
+                    "latency".to_owned(),
+                    Some("0.99".to_owned()),
+                    MetricValue::Gauge { value: 1.5 }
+                ),
+                (
+                    "latency_sum".to_owned(),
+                    None,
+                    MetricValue::Counter { value: 4.5 }
+                ),
+                (
+                    "latency_count".to_owned(),
+                    None,
+                    MetricValue::Counter { value: 6.0 }
+                ),
+            ]
+        );
+    }
+}
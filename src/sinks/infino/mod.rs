@@ -1,11 +1,64 @@
+mod ack;
+mod adaptive;
+mod alert;
+mod api_key;
+mod audit;
+mod bandwidth;
+mod batch;
+mod bisect;
+mod bundle;
+mod cert_reload;
+#[cfg(feature = "infino-chaos")]
+mod chaos;
+mod checksum;
 mod common;
 mod config;
+mod dead_letter;
+mod dedup;
+mod diagnostics;
+mod dispatch;
+mod distribution;
+mod dry_run;
 mod encoder;
+mod encryption;
+mod endpoint;
+mod fanout;
+mod geo;
+mod grpc;
+mod headers;
 mod health;
+mod index_policy;
+mod kerberos;
+mod lint;
+mod locality;
+mod mapping;
+mod metric;
+mod oauth2;
+mod ordered;
+mod partial;
+mod presign;
+mod probe;
+mod profile;
+mod provenance;
+mod query;
+mod rate;
+mod record;
+mod redact;
 mod request_builder;
+mod resolve;
 mod retry;
+mod retry_queue;
+mod rollup;
+mod sample;
+mod self_monitoring;
 mod service;
+mod severity;
 mod sink;
+mod sniff;
+mod stats;
+mod timestamp;
+mod tls;
+mod trace;
 
 #[cfg(test)]
 mod tests;
@@ -14,11 +67,13 @@ mod tests;
 #[cfg(feature = "es-integration-tests")]
 mod integration_tests;
 
-use std::{convert::TryFrom, fmt};
+use std::{collections::HashSet, convert::TryFrom, fmt, sync::Arc};
 
 pub use common::*;
 pub use config::*;
-pub use encoder::ElasticsearchEncoder;
+pub use encoder::{DocumentMetadata, InfinoEncoder, ProcessedEvent};
+pub use request_builder::InfinoRequestBuilder;
+
 use http::{uri::InvalidUri, Request};
 use snafu::Snafu;
 use vector_lib::sensitive_string::SensitiveString;
@@ -26,25 +81,31 @@ use vector_lib::{configurable::configurable_component, internal_event};
 
 use crate::{
     event::{EventRef, LogEvent},
-    internal_events::TemplateRenderingError,
+    internal_events::{InfinoBulkActionInvalid, TemplateRenderingError},
     template::{Template, TemplateParseError},
 };
 
-/// Elasticsearch Authentication strategies.
+/// Infino Authentication strategies.
+///
+/// Credentials can be read from a secrets backend with `SECRET[<backend>.<key>]`. They are read
+/// again whenever the configuration is reloaded, and the sink is rebuilt when they have changed,
+/// so that rotated credentials are used without restarting Vector.
 #[configurable_component]
 #[derive(Clone, Debug)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", tag = "strategy")]
 #[configurable(metadata(docs::enum_tag_description = "The authentication strategy to use."))]
-pub enum ElasticsearchAuthConfig {
+pub enum InfinoAuthConfig {
     /// HTTP Basic Authentication.
     Basic {
         /// Basic authentication username.
         #[configurable(metadata(docs::examples = "${ELASTICSEARCH_USERNAME}"))]
+        #[configurable(metadata(docs::examples = "SECRET[vault.infino_user]"))]
         #[configurable(metadata(docs::examples = "username"))]
         user: String,
 
         /// Basic authentication password.
         #[configurable(metadata(docs::examples = "${ELASTICSEARCH_PASSWORD}"))]
+        #[configurable(metadata(docs::examples = "SECRET[vault.infino_password]"))]
         #[configurable(metadata(docs::examples = "password"))]
         password: SensitiveString,
     },
@@ -52,13 +113,99 @@ pub enum ElasticsearchAuthConfig {
     #[cfg(feature = "aws-core")]
     /// Amazon OpenSearch Service-specific authentication.
     Aws(crate::aws::AwsAuthentication),
+
+    /// Kerberos authentication, with SPNEGO (`Negotiate`) tokens.
+    ///
+    /// Requires Vector to be built with the `infino-kerberos` feature.
+    Kerberos {
+        /// The principal to authenticate as.
+        #[configurable(metadata(docs::examples = "vector@EXAMPLE.COM"))]
+        principal: String,
+
+        /// Path to the keytab holding the keys of `principal`.
+        ///
+        /// By default, the client keytab, or the credential cache, of the Kerberos library is used.
+        /// The Kerberos library reads the keytab from the environment of the whole process, so
+        /// every sink setting a keytab has to set the same one, which also has to match
+        /// `KRB5_CLIENT_KTNAME` if it is set.
+        #[serde(default)]
+        #[configurable(metadata(docs::examples = "/etc/vector/vector.keytab"))]
+        keytab: Option<std::path::PathBuf>,
+
+        /// The service of the principals of the endpoints.
+        ///
+        /// The principal of an endpoint is `<service>@<host>`, such as `HTTP@infino.example.com`.
+        #[serde(default = "default_kerberos_service")]
+        #[configurable(metadata(docs::examples = "HTTP"))]
+        service: String,
+    },
+
+    /// API key authentication, with a static key or bearer token.
+    ///
+    /// The key is sent in a header of every request, such as
+    /// `Authorization: ApiKey <key>` or `Authorization: Bearer <token>`.
+    ApiKey {
+        /// The API key or token.
+        #[configurable(metadata(docs::examples = "${INFINO_API_KEY}"))]
+        #[configurable(metadata(docs::examples = "SECRET[vault.infino_api_key]"))]
+        key: SensitiveString,
+
+        /// The header the key is sent in.
+        #[serde(default = "default_api_key_header")]
+        #[configurable(metadata(docs::examples = "X-Api-Key"))]
+        header: String,
+
+        /// The scheme the key is prefixed with, followed by a space.
+        ///
+        /// Set to an empty string to send the key alone, as custom headers usually expect.
+        #[serde(default = "default_api_key_scheme")]
+        #[configurable(metadata(docs::examples = "Bearer"))]
+        scheme: String,
+    },
+
+    /// OAuth 2.0 authentication, with bearer tokens obtained with the client credentials grant.
+    ///
+    /// Tokens are obtained again shortly before they expire, and when a request is rejected as
+    /// unauthorized, in which case the request is sent once more with the new token.
+    #[serde(rename = "oauth2")]
+    OAuth2 {
+        /// The URL of the token endpoint of the authorization server.
+        #[configurable(metadata(docs::examples = "https://auth.example.com/oauth2/token"))]
+        token_url: String,
+
+        /// The client ID.
+        #[configurable(metadata(docs::examples = "vector"))]
+        client_id: String,
+
+        /// The client secret.
+        #[configurable(metadata(docs::examples = "${INFINO_CLIENT_SECRET}"))]
+        #[configurable(metadata(docs::examples = "SECRET[vault.infino_client_secret]"))]
+        client_secret: SensitiveString,
+
+        /// The scopes to request.
+        #[serde(default)]
+        #[configurable(metadata(docs::examples = "ingest"))]
+        scopes: Vec<String>,
+    },
 }
 
-/// Elasticsearch Indexing mode.
+fn default_kerberos_service() -> String {
+    "HTTP".to_owned()
+}
+
+fn default_api_key_header() -> String {
+    "Authorization".to_owned()
+}
+
+fn default_api_key_scheme() -> String {
+    "ApiKey".to_owned()
+}
+
+/// Infino Indexing mode.
 #[configurable_component]
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
-pub enum ElasticsearchMode {
+pub enum InfinoMode {
     /// Ingests documents in bulk, using the bulk API `index` action.
     #[serde(alias = "normal")]
     Bulk,
@@ -67,9 +214,22 @@ pub enum ElasticsearchMode {
     ///
     /// Elasticsearch Data Streams only support the `create` action.
     DataStream,
+
+    /// Ingests documents in bulk, choosing the action depending on the rendered index.
+    ///
+    /// The data streams of the cluster are listed when the sink starts. Documents whose
+    /// `bulk.index` renders to one of them use the `create` action, and the other documents use
+    /// `bulk.action`, as in `bulk` mode.
+    Auto,
+
+    /// Manages alert rules, instead of ingesting documents.
+    ///
+    /// Each event defines an alert rule, which is created, updated, or deleted through the
+    /// alerting API, as configured by `alert_rules`.
+    AlertRules,
 }
 
-impl Default for ElasticsearchMode {
+impl Default for InfinoMode {
     fn default() -> Self {
         Self::Bulk
     }
@@ -85,6 +245,16 @@ pub enum BulkAction {
 
     /// The `create` action.
     Create,
+
+    /// The `update` action.
+    ///
+    /// The event is written as the partial document of the update, and needs an `_id`.
+    Update,
+
+    /// The `delete` action.
+    ///
+    /// The event only identifies the document to delete, and needs an `_id`.
+    Delete,
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -93,13 +263,22 @@ impl BulkAction {
         match self {
             BulkAction::Index => "index",
             BulkAction::Create => "create",
+            BulkAction::Update => "update",
+            BulkAction::Delete => "delete",
         }
     }
 
+    /// Whether the action targets an existing document, which it then needs the `_id` of.
+    pub const fn needs_id(&self) -> bool {
+        matches!(self, BulkAction::Update | BulkAction::Delete)
+    }
+
     pub const fn as_json_pointer(&self) -> &'static str {
         match self {
             BulkAction::Index => "/index",
             BulkAction::Create => "/create",
+            BulkAction::Update => "/update",
+            BulkAction::Delete => "/delete",
         }
     }
 }
@@ -111,11 +290,27 @@ impl TryFrom<&str> for BulkAction {
         match input {
             "index" => Ok(BulkAction::Index),
             "create" => Ok(BulkAction::Create),
+            "update" => Ok(BulkAction::Update),
+            "delete" => Ok(BulkAction::Delete),
             _ => Err(format!("Invalid bulk action: {}", input)),
         }
     }
 }
 
+/// Strategies for idempotent ingestion.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum DedupStrategy {
+    /// Derive the document `_id` from a hash of its content and use the `create` action.
+    ///
+    /// Infino rejects documents that already exist with a `409 Conflict` status, which is then
+    /// treated as a successful delivery, so redelivered events are written only once. An `_id` set
+    /// through `id_key` takes precedence over the content hash. Events with the `update` or
+    /// `delete` action keep their action and `_id`.
+    CreateWithId,
+}
+
 /// Elasticsearch version types.
 #[configurable_component]
 #[derive(Clone, Copy, Debug, Derivative, Eq, Hash, PartialEq)]
@@ -155,15 +350,18 @@ impl TryFrom<&str> for VersionType {
     }
 }
 
-impl_generate_config_from_default!(ElasticsearchConfig);
+impl_generate_config_from_default!(InfinoConfig);
 
 #[derive(Debug, Clone)]
-pub enum ElasticsearchCommonMode {
+pub enum InfinoCommonMode {
     Bulk {
         index: Template,
         action: Template,
+        default_action: Option<BulkAction>,
         version: Option<Template>,
         version_type: VersionType,
+        /// The data streams found on the cluster in `auto` mode.
+        data_streams: Arc<HashSet<String>>,
     },
     DataStream(DataStreamConfig),
 }
@@ -184,7 +382,7 @@ impl fmt::Display for VersionValueParseError<'_> {
     }
 }
 
-impl ElasticsearchCommonMode {
+impl InfinoCommonMode {
     fn index(&self, log: &LogEvent) -> Option<String> {
         match self {
             Self::Bulk { index, .. } => index
@@ -201,30 +399,57 @@ impl ElasticsearchCommonMode {
         }
     }
 
+    /// Returns the action events are written to `index` with if it is a data stream, which only
+    /// accepts `create` unless overwrites are allowed.
+    fn data_stream_action(&self, index: &str) -> Option<BulkAction> {
+        match self {
+            Self::Bulk { data_streams, .. } => {
+                data_streams.contains(index).then_some(BulkAction::Create)
+            }
+            Self::DataStream(ds) => Some(ds.bulk_action()),
+        }
+    }
+
+    /// Renders the action of `event`, falling back to the default action, if any, when it does
+    /// not render to a supported action.
     fn bulk_action<'a>(&self, event: impl Into<EventRef<'a>>) -> Option<BulkAction> {
         match self {
-            ElasticsearchCommonMode::Bulk {
+            InfinoCommonMode::Bulk {
                 action: bulk_action_template,
+                default_action,
                 ..
-            } => bulk_action_template
-                .render_string(event)
-                .map_err(|error| {
-                    emit!(TemplateRenderingError {
-                        error,
-                        field: Some("bulk_action"),
-                        drop_event: true,
-                    });
-                })
-                .ok()
-                .and_then(|value| BulkAction::try_from(value.as_str()).ok()),
+            } => {
+                let value = match bulk_action_template.render_string(event) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        emit!(TemplateRenderingError {
+                            error,
+                            field: Some("bulk_action"),
+                            drop_event: default_action.is_none(),
+                        });
+                        return *default_action;
+                    }
+                };
+                match BulkAction::try_from(value.as_str()) {
+                    Ok(action) => Some(action),
+                    Err(_) if default_action.is_some() => *default_action,
+                    Err(_) => {
+                        emit!(InfinoBulkActionInvalid {
+                            action: &value,
+                            reason: "Bulk action is not supported.",
+                        });
+                        None
+                    }
+                }
+            }
             // avoid the interpolation
-            ElasticsearchCommonMode::DataStream(_) => Some(BulkAction::Create),
+            InfinoCommonMode::DataStream(ds) => Some(ds.bulk_action()),
         }
     }
 
     fn version<'a>(&self, event: impl Into<EventRef<'a>>) -> Option<u64> {
         match self {
-            ElasticsearchCommonMode::Bulk {
+            InfinoCommonMode::Bulk {
                 version: Some(version),
                 ..
             } => version
@@ -250,7 +475,7 @@ impl ElasticsearchCommonMode {
 
     const fn version_type(&self) -> Option<VersionType> {
         match self {
-            ElasticsearchCommonMode::Bulk { version_type, .. } => Some(*version_type),
+            InfinoCommonMode::Bulk { version_type, .. } => Some(*version_type),
             _ => Some(VersionType::Internal),
         }
     }
@@ -263,12 +488,12 @@ impl ElasticsearchCommonMode {
     }
 }
 
-/// Configuration for Elasticsearch API version.
+/// Configuration for Infino API version.
 #[configurable_component]
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "proptest", derive(proptest_derive::Arbitrary))]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
-pub enum ElasticsearchApiVersion {
+pub enum InfinoApiVersion {
     /// Auto-detect the API version.
     ///
     /// If the [cluster state version endpoint][es_version] isn't reachable, a warning is logged to
@@ -287,12 +512,48 @@ pub enum ElasticsearchApiVersion {
     V8,
 }
 
-impl Default for ElasticsearchApiVersion {
+impl Default for InfinoApiVersion {
     fn default() -> Self {
         Self::Auto
     }
 }
 
+impl InfinoApiVersion {
+    /// The major version configured explicitly, if any.
+    pub const fn major(&self) -> Option<usize> {
+        match self {
+            Self::Auto => None,
+            Self::V6 => Some(6),
+            Self::V7 => Some(7),
+            Self::V8 => Some(8),
+        }
+    }
+}
+
+/// Startup check of the Infino server version against the configuration.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum ApiVersionCheck {
+    /// Do not check the server version.
+    ///
+    /// When `api_version` is set explicitly, the server is not queried at all.
+    Disabled,
+
+    /// Log a warning when the server version is incompatible with the configuration.
+    Warn,
+
+    /// Fail to build the sink when the server version is incompatible with the configuration, or
+    /// when it cannot be determined.
+    Strict,
+}
+
+impl Default for ApiVersionCheck {
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
+
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
 pub enum ParseError {
@@ -310,7 +571,7 @@ pub enum ParseError {
     #[snafu(display("Endpoints option must be specified"))]
     EndpointRequired,
     #[snafu(display(
-        "`endpoint` and `endpoints` options are mutually exclusive. Please use `endpoints` option."
+        "`endpoint` and `endpoints` options are mutually exclusive. Please use only one of them."
     ))]
     EndpointsExclusive,
     #[snafu(display("Tried to use external versioning without specifying the version itself"))]
@@ -319,4 +580,105 @@ pub enum ParseError {
     ExternalVersioningWithoutDocumentID,
     #[snafu(display("Your version field will be ignored because you use internal versioning"))]
     ExternalVersionIgnoredWithInternalVersioning,
+    #[snafu(display(
+        "Configured `api_version` is v{} but the Infino server reports major version {}",
+        configured,
+        server
+    ))]
+    ApiVersionMismatch { configured: usize, server: usize },
+    #[snafu(display(
+        "{} requires Infino API version {} or later, but the server reports major version {}",
+        feature,
+        required,
+        server
+    ))]
+    UnsupportedFeature {
+        feature: &'static str,
+        required: usize,
+        server: usize,
+    },
+    #[snafu(display(
+        "`data_stream.allow_index_op` requires Infino API version 8 or later, but `api_version` is {}",
+        configured
+    ))]
+    DataStreamIndexOpUnsupported { configured: usize },
+    #[snafu(display("Unable to determine the Infino server version: {}", reason))]
+    ServerVersionUnavailable { reason: String },
+    #[snafu(display("`{}` must be a positive number of seconds", option))]
+    InvalidInterval { option: &'static str },
+    #[snafu(display("Invalid metric namespace pattern {:?}: {}", pattern, source))]
+    InvalidNamespacePattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+    #[snafu(display("Invalid index pattern {:?}: {}", pattern, source))]
+    InvalidIndexPattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+    #[snafu(display(
+        "`{}` refers to the field {:?}, which is not declared in `template_schema`",
+        option,
+        field
+    ))]
+    UndeclaredTemplateField { option: &'static str, field: String },
+    #[snafu(display("Invalid metric name pattern {:?}: {}", pattern, reason))]
+    InvalidMetricNamePattern { pattern: String, reason: String },
+    #[snafu(display("Unable to interpolate endpoint: {}", errors))]
+    EndpointInterpolation { errors: String },
+    #[snafu(display(
+        "`locality.endpoint_zones` refers to an endpoint that is not in `endpoints`"
+    ))]
+    UnknownZonedEndpoint,
+    #[snafu(display("`endpoint_overrides` refers to an endpoint that is not in `endpoints`"))]
+    UnknownOverriddenEndpoint,
+    #[snafu(display(
+        "`distribution.strategy = \"hash\"` cannot be combined with `locality` or `fan_out`"
+    ))]
+    HashDistributionConflict,
+    #[snafu(display("`reorder` cannot be combined with `ordered`"))]
+    ReorderConflict,
+    #[snafu(display(
+        "`sniffing` requires the `balance` distribution strategy, without `locality`, `fan_out`, or endpoint weights"
+    ))]
+    SniffingConflict,
+    #[snafu(display("Invalid throttle window time {:?}, expected HH:MM", time))]
+    InvalidThrottleTime { time: String },
+    #[snafu(display("Invalid TLS pin {:?}, expected a base64 SHA-256 hash", pin))]
+    InvalidTlsPin { pin: String },
+    #[snafu(display("`tls.min_version` is newer than `tls.max_version`"))]
+    InvalidTlsVersions,
+    #[snafu(display("`tls.fips` is enabled, but {}", reason))]
+    FipsViolation { reason: &'static str },
+    #[snafu(display("Invalid checksum header {:?}", header))]
+    InvalidChecksumHeader { header: String },
+    #[snafu(display("Invalid encryption key header {:?}", header))]
+    InvalidEncryptionHeader { header: String },
+    #[snafu(display("Invalid batch header {:?}", header))]
+    InvalidBatchHeader { header: String },
+    #[snafu(display("Invalid endpoint {:?}: {}", endpoint, reason))]
+    InvalidEndpoint {
+        endpoint: String,
+        reason: &'static str,
+    },
+    #[snafu(display("Invalid request ID header {:?}", header))]
+    InvalidRequestIdHeader { header: String },
+    #[snafu(display("Unsupported charset {:?}, payloads are encoded in UTF-8", charset))]
+    UnsupportedCharset { charset: String },
+    #[snafu(display("`{}` is not supported when `protocol` is `grpc`", option))]
+    GrpcUnsupportedOption { option: &'static str },
+    #[snafu(display("Invalid connection profile {:?}: {}", path, reason))]
+    InvalidProfile {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+    #[snafu(display(
+        "Kerberos keytab {:?} conflicts with the keytab {:?} already in use",
+        keytab,
+        current
+    ))]
+    KeytabConflict {
+        keytab: std::path::PathBuf,
+        current: std::path::PathBuf,
+    },
 }
@@ -0,0 +1,242 @@
+//! OAuth 2.0 client credentials authentication, with bearer tokens.
+//!
+//! Tokens are requested from the token URL of the authorization server with the client
+//! credentials grant, and cached until they are due to be refreshed, shortly before they expire.
+//! Tokens the server rejects are dropped, so that the next request fetches a new one.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use derivative::Derivative;
+use http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    HeaderValue, Request, StatusCode, Uri,
+};
+use hyper::Body;
+use serde::Deserialize;
+use tokio::{sync::Mutex, time::Instant};
+use vector_lib::config::proxy::ProxyConfig;
+
+use super::{tls::InfinoTlsSettings, InfinoAuthConfig};
+use crate::http::HttpClient;
+
+/// How long before a token expires to fetch a new one.
+const REFRESH_BEFORE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// The number of seconds the token is valid for, if it expires.
+    expires_in: Option<u64>,
+}
+
+struct Token {
+    value: HeaderValue,
+    refresh_at: Option<Instant>,
+}
+
+/// Fetches bearer tokens with the client credentials grant, and caches them until they are due to
+/// be refreshed.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct OAuth2 {
+    token_url: Uri,
+    client_id: String,
+    #[derivative(Debug = "ignore")]
+    client_secret: String,
+    scopes: Vec<String>,
+    #[derivative(Debug = "ignore")]
+    client: HttpClient,
+    #[derivative(Debug = "ignore")]
+    token: Mutex<Option<Token>>,
+}
+
+impl OAuth2 {
+    /// Returns the OAuth 2.0 authentication of `auth`, if it is OAuth 2.0.
+    pub fn new(
+        auth: Option<&InfinoAuthConfig>,
+        tls_settings: &InfinoTlsSettings,
+        proxy: &ProxyConfig,
+    ) -> crate::Result<Option<Self>> {
+        let Some(InfinoAuthConfig::OAuth2 {
+            token_url,
+            client_id,
+            client_secret,
+            scopes,
+        }) = auth
+        else {
+            return Ok(None);
+        };
+        let token_url = token_url
+            .parse::<Uri>()
+            .map_err(|error| format!("Invalid OAuth 2.0 token URL: {}", error))?;
+        Ok(Some(Self {
+            token_url,
+            client_id: client_id.clone(),
+            client_secret: client_secret.inner().to_owned(),
+            scopes: scopes.clone(),
+            client: tls_settings.http_client(proxy)?,
+            token: Mutex::new(None),
+        }))
+    }
+
+    /// Authenticates `request` with the current token, fetching a new one if it is due.
+    pub async fn apply(&self, request: &mut Request<Bytes>) -> crate::Result<()> {
+        let mut token = self.token.lock().await;
+        let now = Instant::now();
+        let current = token
+            .as_ref()
+            .filter(|token| token.refresh_at.map_or(true, |refresh_at| now < refresh_at));
+        let value = match current {
+            Some(current) => current.value.clone(),
+            None => {
+                let fetched = self
+                    .fetch()
+                    .await
+                    .map_err(|error| format!("Unable to obtain an OAuth 2.0 token: {}", error))?;
+                let value = HeaderValue::from_str(&format!("Bearer {}", fetched.access_token))?;
+                *token = Some(Token {
+                    value: value.clone(),
+                    refresh_at: fetched
+                        .expires_in
+                        .map(|secs| now + Duration::from_secs(secs).saturating_sub(REFRESH_BEFORE)),
+                });
+                value
+            }
+        };
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
+    /// Drops the current token, which the server rejected, so that a new one is fetched.
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+
+    async fn fetch(&self) -> crate::Result<TokenResponse> {
+        let credentials = format!(
+            "{}:{}",
+            form_urlencode(&self.client_id),
+            form_urlencode(&self.client_secret)
+        );
+        let request = Request::post(self.token_url.clone())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(ACCEPT, "application/json")
+            .header(
+                AUTHORIZATION,
+                format!("Basic {}", BASE64_STANDARD.encode(credentials)),
+            )
+            .body(Body::from(self.form()))?;
+        let response = self.client.send(request).await?;
+        if response.status() != StatusCode::OK {
+            return Err(format!("unexpected status {}", response.status()).into());
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Returns the form of token requests.
+    fn form(&self) -> String {
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "client_credentials");
+        if !self.scopes.is_empty() {
+            form.append_pair("scope", &self.scopes.join(" "));
+        }
+        form.finish()
+    }
+}
+
+/// Encodes client credentials for HTTP Basic authentication, as required by RFC 6749.
+fn form_urlencode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::StreamExt;
+    use http::Response;
+
+    use super::*;
+    use crate::{sinks::util::test::build_test_server_generic, test_util::next_addr};
+
+    fn oauth2(token_url: String, scopes: Vec<String>) -> OAuth2 {
+        let auth = InfinoAuthConfig::OAuth2 {
+            token_url,
+            client_id: "vector".into(),
+            client_secret: "s3cret&".to_string().into(),
+            scopes,
+        };
+        OAuth2::new(
+            Some(&auth),
+            &InfinoTlsSettings::default(),
+            &ProxyConfig::default(),
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn ignores_other_strategies() {
+        let auth = InfinoAuthConfig::Basic {
+            user: "user".into(),
+            password: "password".to_string().into(),
+        };
+        let (tls, proxy) = (InfinoTlsSettings::default(), ProxyConfig::default());
+        assert!(OAuth2::new(Some(&auth), &tls, &proxy).unwrap().is_none());
+        assert!(OAuth2::new(None, &tls, &proxy).unwrap().is_none());
+    }
+
+    #[test]
+    fn requests_scopes() {
+        let oauth2 = oauth2(
+            "https://auth.example.com/token".into(),
+            vec!["ingest".into(), "read".into()],
+        );
+        assert_eq!(
+            oauth2.form(),
+            "grant_type=client_credentials&scope=ingest+read"
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_tokens_until_invalidated() {
+        let addr = next_addr();
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&fetched);
+        let (mut rx, trigger, server) = build_test_server_generic(addr, move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            Response::new(Body::from(format!(
+                r#"{{"access_token":"token-{}","token_type":"Bearer","expires_in":3600}}"#,
+                n
+            )))
+        });
+        tokio::spawn(server);
+
+        let oauth2 = oauth2(format!("http://{}/token", addr), Vec::new());
+        let authorization = || async {
+            let mut request = Request::new(Bytes::new());
+            oauth2.apply(&mut request).await.unwrap();
+            request.headers()[AUTHORIZATION].clone()
+        };
+        assert_eq!(authorization().await, "Bearer token-1");
+        assert_eq!(authorization().await, "Bearer token-1");
+        oauth2.invalidate().await;
+        assert_eq!(authorization().await, "Bearer token-2");
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+
+        let (parts, body) = rx.next().await.unwrap();
+        let credentials = BASE64_STANDARD.encode("vector:s3cret%26");
+        assert_eq!(
+            parts.headers[AUTHORIZATION],
+            format!("Basic {}", credentials).as_str()
+        );
+        assert_eq!(body, "grant_type=client_credentials");
+        drop(trigger);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::PollSemaphore;
+use tower::{Layer, Service, ServiceExt};
+
+use super::{
+    service::{InfinoRequest, InfinoResponse},
+    sink::PartitionKey,
+};
+
+/// The maximum number of requests waiting for their turn, either on the previous request of their
+/// partition or on the inner service, beyond which no more requests are taken until one is sent.
+const MAX_QUEUED_REQUESTS: usize = 64;
+
+/// The last request issued for a partition, which the next request for it must wait on.
+struct Tail {
+    sequence: u64,
+    done: oneshot::Receiver<()>,
+}
+
+#[derive(Default)]
+struct Partitions {
+    next_sequence: u64,
+    tails: HashMap<PartitionKey, Tail>,
+}
+
+impl Partitions {
+    /// Registers a new request for `key`, returning its sequence number, the receiver of the
+    /// request it has to wait for, and the sender that releases the request after it.
+    fn enqueue(
+        &mut self,
+        key: PartitionKey,
+    ) -> (u64, Option<oneshot::Receiver<()>>, oneshot::Sender<()>) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let (tx, rx) = oneshot::channel();
+        let previous = self.tails.insert(key, Tail { sequence, done: rx });
+        (sequence, previous.map(|tail| tail.done), tx)
+    }
+
+    /// Forgets about the partition once its last request has completed.
+    fn complete(&mut self, key: &PartitionKey, sequence: u64) {
+        if self
+            .tails
+            .get(key)
+            .is_some_and(|tail| tail.sequence == sequence)
+        {
+            self.tails.remove(key);
+        }
+    }
+}
+
+/// Keeps at most one request per partition in flight when `ordered` is enabled.
+///
+/// Requests for the same partition are sent in the order they were issued, each one only after
+/// the previous one has completed, including all of its retries. Requests for different
+/// partitions are not held back.
+///
+/// At most `MAX_QUEUED_REQUESTS` requests wait for their turn at once, after which the service is
+/// not ready until one of them is handed to the inner service, so that the sink keeps its
+/// backpressure.
+#[derive(Clone)]
+pub struct OrderedLayer {
+    partitions: Arc<Mutex<Partitions>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Default for OrderedLayer {
+    fn default() -> Self {
+        Self {
+            partitions: Default::default(),
+            slots: Arc::new(Semaphore::new(MAX_QUEUED_REQUESTS)),
+        }
+    }
+}
+
+impl<S> Layer<S> for OrderedLayer {
+    type Service = OrderedService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OrderedService {
+            partitions: Arc::clone(&self.partitions),
+            slots: PollSemaphore::new(Arc::clone(&self.slots)),
+            slot: None,
+            inner: Arc::new(tokio::sync::Mutex::new(inner)),
+        }
+    }
+}
+
+pub struct OrderedService<S> {
+    partitions: Arc<Mutex<Partitions>>,
+    slots: PollSemaphore,
+    slot: Option<OwnedSemaphorePermit>,
+    // Shared so that a request can be handed to the inner service whenever its turn comes,
+    // rather than when the driver calls us.
+    inner: Arc<tokio::sync::Mutex<S>>,
+}
+
+/// Hands `req` to `inner` once it is ready, without holding the lock for the whole request, and
+/// frees its queue slot once it is handed over.
+async fn send<S>(
+    inner: &tokio::sync::Mutex<S>,
+    req: InfinoRequest,
+    slot: OwnedSemaphorePermit,
+) -> Result<InfinoResponse, crate::Error>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+{
+    let future = {
+        let mut inner = inner.lock().await;
+        inner.ready().await?.call(req)
+    };
+    drop(slot);
+    future.await
+}
+
+impl<S> Service<InfinoRequest> for OrderedService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness of the inner service is awaited once the request is allowed to go out, so
+        // that a partition waiting on its predecessor does not hold back other partitions. Only
+        // the number of requests waiting for their turn is bounded here.
+        if self.slot.is_none() {
+            match ready!(self.slots.poll_acquire(cx)) {
+                Some(slot) => self.slot = Some(slot),
+                None => {
+                    return Poll::Ready(Err("Ordered queue semaphore unexpectedly closed".into()))
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let slot = self
+            .slot
+            .take()
+            .expect("poll_ready not called before invoking call");
+        let inner = Arc::clone(&self.inner);
+        let Some(key) = req.partition_key.clone() else {
+            return Box::pin(async move { send(&inner, req, slot).await });
+        };
+
+        let partitions = Arc::clone(&self.partitions);
+        let (sequence, previous, done) = partitions
+            .lock()
+            .expect("ordered partitions lock poisoned")
+            .enqueue(key.clone());
+
+        Box::pin(async move {
+            if let Some(previous) = previous {
+                // An error only means the previous request was dropped, which releases us too.
+                _ = previous.await;
+            }
+
+            let response = send(&inner, req, slot).await;
+
+            partitions
+                .lock()
+                .expect("ordered partitions lock poisoned")
+                .complete(&key, sequence);
+            drop(done);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::FutureExt;
+    use http::Response;
+    use tower_test::mock;
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::*;
+    use crate::event::EventStatus;
+
+    fn key(index: &str) -> PartitionKey {
+        PartitionKey {
+            index: index.to_owned(),
+            bulk_action: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn chains_requests_per_partition() {
+        let mut partitions = Partitions::default();
+
+        let (first, previous, first_done) = partitions.enqueue(key("a"));
+        assert!(previous.is_none());
+        let (_, other, _) = partitions.enqueue(key("b"));
+        assert!(other.is_none());
+
+        let (second, previous, _) = partitions.enqueue(key("a"));
+        let mut previous = previous.expect("second request waits on the first");
+        assert!(previous.try_recv().is_err());
+
+        drop(first_done);
+        assert!(previous.await.is_err());
+
+        // The first request completing must not forget the partition while the second is queued.
+        partitions.complete(&key("a"), first);
+        assert!(partitions.tails.contains_key(&key("a")));
+        partitions.complete(&key("a"), second);
+        assert!(!partitions.tails.contains_key(&key("a")));
+    }
+
+    fn request(index: &str) -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::new(),
+            finalizers: Default::default(),
+            batch_size: 1,
+            events_byte_size: Default::default(),
+            partition_key: Some(key(index)),
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn bounds_queued_requests() {
+        let (inner, mut handle) = mock::pair::<InfinoRequest, InfinoResponse>();
+        handle.allow(0);
+        let mut service = OrderedLayer::default().layer(inner);
+
+        let mut responses = Vec::new();
+        for i in 0..MAX_QUEUED_REQUESTS {
+            service.ready().await.unwrap();
+            responses.push(tokio::spawn(service.call(request(&i.to_string()))));
+        }
+        // Every request waits on the inner service, so no more are taken.
+        assert!(service.ready().now_or_never().is_none());
+
+        handle.allow(1);
+        let (_, send) = handle.next_request().await.unwrap();
+        service.ready().await.unwrap();
+        send.send_response(InfinoResponse {
+            http_response: Response::new(Bytes::new()),
+            event_status: EventStatus::Delivered,
+            batch_size: 1,
+            events_byte_size: GroupedCountByteSize::new_untagged(),
+        });
+        assert!(responses.remove(0).await.unwrap().is_ok());
+    }
+}
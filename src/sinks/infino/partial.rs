@@ -0,0 +1,465 @@
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Response, StatusCode};
+use serde_json::Value as JsonValue;
+use tokio::time::Instant;
+use tower::{Layer, Service, ServiceExt};
+use vector_lib::request_metadata::RequestMetadata;
+
+use super::{
+    bisect::{compress, decompress, split_documents},
+    retry::is_retriable,
+    service::{BulkOutcome, InfinoRequest, InfinoResponse},
+};
+use crate::{
+    event::EventStatus,
+    sinks::util::{service::TowerRequestSettings, Compression},
+};
+
+/// Sends the documents of a bulk request that failed with a retriable status again, on their own.
+///
+/// Infino writes the documents of a bulk request independently, so a successful response can hold
+/// items rejected with `429 Too Many Requests` or a server error while the other documents were
+/// written. Only those documents are sent again, with the backoff of request retries, and their
+/// outcomes are merged back into the response of the request.
+///
+/// The documents are sent again within the timeout of the request, as the layer sits below it.
+/// Were the request to time out, it would be sent again as a whole, along with the documents that
+/// were written, so retries stop after `MAX_ATTEMPTS`, or once half of the timeout has passed, and
+/// the documents still failing are reported as such.
+#[derive(Clone)]
+pub struct PartialRetryLayer {
+    compression: Compression,
+    ignore_conflicts: bool,
+    attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    time_budget: Duration,
+}
+
+/// The most times the failed documents of a request are sent again.
+const MAX_ATTEMPTS: usize = 5;
+
+impl PartialRetryLayer {
+    pub fn new(
+        compression: Compression,
+        ignore_conflicts: bool,
+        settings: &TowerRequestSettings,
+    ) -> Self {
+        Self {
+            compression,
+            ignore_conflicts,
+            attempts: settings.retry_attempts.min(MAX_ATTEMPTS),
+            initial_backoff: settings.retry_initial_backoff,
+            max_backoff: settings.retry_max_duration,
+            time_budget: settings.timeout / 2,
+        }
+    }
+}
+
+impl<S> Layer<S> for PartialRetryLayer {
+    type Service = PartialRetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PartialRetryService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PartialRetryService<S> {
+    layer: PartialRetryLayer,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for PartialRetryService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        // Only the service that was driven to readiness may be called, so take it and leave the
+        // clone behind, as recommended by `tower`.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(send(inner, req, layer))
+    }
+}
+
+async fn send<S>(
+    mut inner: S,
+    req: InfinoRequest,
+    layer: PartialRetryLayer,
+) -> Result<InfinoResponse, crate::Error>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+{
+    // Kept aside, as the inner service consumes the request.
+    let template = InfinoRequest {
+        payload: Bytes::new(),
+        finalizers: Default::default(),
+        batch_size: 0,
+        events_byte_size: Default::default(),
+        partition_key: req.partition_key.clone(),
+        indexes: req.indexes.clone(),
+        lane: req.lane,
+        encryption_key: req.encryption_key.clone(),
+        headers: req.headers.clone(),
+        query: req.query.clone(),
+        metadata: RequestMetadata::default(),
+    };
+    let payload = req.payload.clone();
+    let deadline = Instant::now() + layer.time_budget;
+
+    let mut response = inner.call(req).await?;
+    let Some(mut body) = parse_body(&response.http_response) else {
+        return Ok(response);
+    };
+    let Some(items) = body.get_mut("items").and_then(JsonValue::as_array_mut) else {
+        return Ok(response);
+    };
+    let mut pending = retriable(items, 0..items.len());
+    if pending.is_empty() {
+        return Ok(response);
+    }
+    let decompressed = match decompress(&payload, layer.compression) {
+        Ok(decompressed) => decompressed,
+        Err(error) => {
+            warn!(
+                message = "Unable to read request to retry its failed documents.",
+                %error,
+                internal_log_rate_limit = true
+            );
+            return Ok(response);
+        }
+    };
+    let documents = split_documents(&decompressed);
+    if documents.len() != items.len() {
+        return Ok(response);
+    }
+
+    let mut backoff = layer.initial_backoff;
+    let mut attempt = 0;
+    while !pending.is_empty() && attempt < layer.attempts {
+        if Instant::now() + backoff >= deadline {
+            break;
+        }
+        attempt += 1;
+        debug!(
+            message = "Retrying failed documents.",
+            documents = pending.len(),
+            attempt,
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(layer.max_backoff);
+
+        let retried = pending.iter().map(|i| documents[*i]).collect::<Vec<_>>();
+        let sent = tokio::time::timeout_at(deadline, async {
+            let retry = InfinoRequest {
+                payload: compress(&retried, layer.compression)?,
+                batch_size: pending.len(),
+                ..template.clone()
+            };
+            inner.ready().await?.call(retry).await
+        });
+        let retry_response = match sent.await {
+            Ok(Ok(retry_response)) => retry_response,
+            // The other documents were written, so the request must not fail as a whole, which
+            // would send them again. The documents still pending are reported as failed instead.
+            Ok(Err(error)) => {
+                warn!(
+                    message = "Failed to retry failed documents.",
+                    %error,
+                    internal_log_rate_limit = true
+                );
+                break;
+            }
+            Err(_) => break,
+        };
+
+        let retried_items =
+            parse_body(&retry_response.http_response).and_then(|mut body| {
+                match body.get_mut("items").map(JsonValue::take) {
+                    Some(JsonValue::Array(items)) if items.len() == pending.len() => Some(items),
+                    _ => None,
+                }
+            });
+        match retried_items {
+            Some(retried_items) => {
+                for (i, item) in pending.iter().zip(retried_items) {
+                    items[*i] = item;
+                }
+                pending = retriable(items, pending);
+            }
+            // The whole request failed, so its documents are sent again if it can be retried.
+            None if is_retriable(retry_response.http_response.status()) => {}
+            None => break,
+        }
+    }
+
+    let errors = items
+        .iter()
+        .any(|item| !status(item).map_or(false, |s| s.is_success()));
+    body["errors"] = JsonValue::Bool(errors);
+    let (parts, _) = response.http_response.into_parts();
+    let merged = Response::from_parts(parts, Bytes::from(serde_json::to_vec(&body)?));
+    if BulkOutcome::of(&merged, layer.ignore_conflicts) == BulkOutcome::Success {
+        response.event_status = EventStatus::Delivered;
+    }
+    response.http_response = merged;
+    Ok(response)
+}
+
+/// Returns the body of a successful bulk response, if it can be parsed.
+fn parse_body(response: &Response<Bytes>) -> Option<JsonValue> {
+    if !response.status().is_success() {
+        return None;
+    }
+    serde_json::from_slice(response.body()).ok()
+}
+
+/// Returns the status of a bulk response item, which holds the result of its action.
+pub(super) fn status(item: &JsonValue) -> Option<StatusCode> {
+    item.as_object()?
+        .values()
+        .next()?
+        .get("status")?
+        .as_u64()
+        .and_then(|status| u16::try_from(status).ok())
+        .and_then(|status| StatusCode::from_u16(status).ok())
+}
+
+/// Returns those of `indexes` whose item failed with a retriable status.
+fn retriable(items: &[JsonValue], indexes: impl IntoIterator<Item = usize>) -> Vec<usize> {
+    indexes
+        .into_iter()
+        .filter(|i| status(&items[*i]).map_or(false, is_retriable))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+    use crate::sinks::util::TowerRequestConfig;
+
+    fn request(payload: &'static str, batch_size: usize) -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::from_static(payload.as_bytes()),
+            finalizers: Default::default(),
+            batch_size,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: Default::default(),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: RequestMetadata::default(),
+        }
+    }
+
+    fn response(body: &'static str, event_status: EventStatus) -> InfinoResponse {
+        InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::OK)
+                .body(Bytes::from_static(body.as_bytes()))
+                .unwrap(),
+            event_status,
+            batch_size: 1,
+            events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+        }
+    }
+
+    fn layer(attempts: usize) -> PartialRetryLayer {
+        PartialRetryLayer {
+            compression: Compression::None,
+            ignore_conflicts: false,
+            attempts,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+            time_budget: Duration::from_secs(30),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_only_failed_documents() {
+        let payload = "{\"index\":{}}\n{\"message\":\"one\"}\n{\"delete\":{\"_id\":\"2\"}}\n{\"index\":{}}\n{\"message\":\"three\"}\n";
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let service = {
+            let sent = Arc::clone(&sent);
+            tower::service_fn(move |req: InfinoRequest| {
+                let mut sent = sent.lock().unwrap();
+                sent.push(String::from_utf8(req.payload.to_vec()).unwrap());
+                let response = match sent.len() {
+                    1 => response(
+                        r#"{"errors":true,"items":[{"index":{"status":201}},{"delete":{"status":429}},{"index":{"status":400}}]}"#,
+                        EventStatus::Rejected,
+                    ),
+                    _ => response(
+                        r#"{"errors":false,"items":[{"delete":{"status":200}}]}"#,
+                        EventStatus::Delivered,
+                    ),
+                };
+                futures::future::ok::<_, crate::Error>(response)
+            })
+        };
+
+        let response = layer(3)
+            .layer(service)
+            .oneshot(request(payload, 3))
+            .await
+            .unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            [payload, "{\"delete\":{\"_id\":\"2\"}}\n"]
+        );
+
+        // The document rejected with a client error is not retried, nor delivered.
+        assert_eq!(response.event_status, EventStatus::Rejected);
+        let body = serde_json::from_slice::<JsonValue>(response.http_response.body()).unwrap();
+        assert_eq!(body["errors"], JsonValue::Bool(true));
+        assert_eq!(body["items"][1]["delete"]["status"], 200);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delivers_once_every_document_is_written() {
+        let attempts = Arc::new(Mutex::new(0));
+        let service = {
+            let attempts = Arc::clone(&attempts);
+            tower::service_fn(move |_: InfinoRequest| {
+                let mut attempts = attempts.lock().unwrap();
+                *attempts += 1;
+                let response = match *attempts {
+                    1 => response(
+                        r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":503}}]}"#,
+                        EventStatus::Rejected,
+                    ),
+                    2 => response(
+                        r#"{"errors":true,"items":[{"index":{"status":429}}]}"#,
+                        EventStatus::Rejected,
+                    ),
+                    _ => response(
+                        r#"{"errors":false,"items":[{"index":{"status":201}}]}"#,
+                        EventStatus::Delivered,
+                    ),
+                };
+                futures::future::ok::<_, crate::Error>(response)
+            })
+        };
+
+        let payload =
+            "{\"index\":{}}\n{\"message\":\"one\"}\n{\"index\":{}}\n{\"message\":\"two\"}\n";
+        let response = layer(3)
+            .layer(service)
+            .oneshot(request(payload, 2))
+            .await
+            .unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert_eq!(response.event_status, EventStatus::Delivered);
+        let body = serde_json::from_slice::<JsonValue>(response.http_response.body()).unwrap();
+        assert_eq!(body["errors"], JsonValue::Bool(false));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_within_the_request_timeout() {
+        let attempts = Arc::new(Mutex::new(0));
+        let service = {
+            let attempts = Arc::clone(&attempts);
+            tower::service_fn(move |_: InfinoRequest| {
+                *attempts.lock().unwrap() += 1;
+                futures::future::ok::<_, crate::Error>(response(
+                    r#"{"errors":true,"items":[{"index":{"status":503}}]}"#,
+                    EventStatus::Rejected,
+                ))
+            })
+        };
+
+        let started = Instant::now();
+        let payload = "{\"index\":{}}\n{\"message\":\"one\"}\n";
+        let response = layer(usize::MAX)
+            .layer(service)
+            .oneshot(request(payload, 1))
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(30));
+        // Backoffs of 1, 2, 4, 8, and 10 seconds fit within the 30 seconds, but not another one.
+        assert_eq!(*attempts.lock().unwrap(), 6);
+        assert_eq!(response.event_status, EventStatus::Rejected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_pending_documents_when_retries_fail() {
+        let attempts = Arc::new(Mutex::new(0));
+        let service = {
+            let attempts = Arc::clone(&attempts);
+            tower::service_fn(move |_: InfinoRequest| {
+                let mut attempts = attempts.lock().unwrap();
+                *attempts += 1;
+                match *attempts {
+                    1 => futures::future::ok(response(
+                        r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":503}}]}"#,
+                        EventStatus::Rejected,
+                    )),
+                    _ => futures::future::err::<InfinoResponse, crate::Error>(
+                        "connection reset".into(),
+                    ),
+                }
+            })
+        };
+
+        let payload =
+            "{\"index\":{}}\n{\"message\":\"one\"}\n{\"index\":{}}\n{\"message\":\"two\"}\n";
+        let response = layer(3)
+            .layer(service)
+            .oneshot(request(payload, 2))
+            .await
+            .unwrap();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert_eq!(response.event_status, EventStatus::Rejected);
+        let body = serde_json::from_slice::<JsonValue>(response.http_response.body()).unwrap();
+        assert_eq!(body["errors"], JsonValue::Bool(true));
+        assert_eq!(body["items"][0]["index"]["status"], 201);
+        assert_eq!(body["items"][1]["index"]["status"], 503);
+    }
+
+    #[test]
+    fn caps_attempts() {
+        let settings = TowerRequestConfig::default().into_settings();
+        let layer = PartialRetryLayer::new(Compression::None, false, &settings);
+        assert_eq!(layer.attempts, MAX_ATTEMPTS);
+        assert_eq!(layer.time_budget, settings.timeout / 2);
+    }
+
+    #[test]
+    fn selects_retriable_items() {
+        let items = serde_json::from_str::<Vec<JsonValue>>(
+            r#"[{"index":{"status":201}},{"create":{"status":429}},{"index":{"status":409}},{"index":{"status":502}},{"index":{}}]"#,
+        )
+        .unwrap();
+        assert_eq!(retriable(&items, 0..items.len()), [1, 3]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::{Request, StatusCode, Uri};
+use hyper::Body;
+use serde::Deserialize;
+use tokio::{sync::Mutex, time::Instant};
+use vector_lib::configurable::configurable_component;
+
+use crate::http::HttpClient;
+
+/// Presigned URL configuration.
+///
+/// Bulk requests are sent to short-lived signed URLs obtained from a provider, instead of the bulk
+/// API of the `endpoints`, as required by some managed ingest front doors. The provider returns a
+/// JSON object with the `url` to send requests to, and the number of seconds it remains valid for
+/// in `expires_in_secs`. A new URL is obtained before the current one expires.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PresignedUrlConfig {
+    #[configurable(derived)]
+    pub provider: PresignedUrlProvider,
+
+    /// How long before the URL expires to obtain a new one, in seconds.
+    #[serde(default = "default_refresh_before_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub refresh_before_secs: u64,
+}
+
+const fn default_refresh_before_secs() -> u64 {
+    60
+}
+
+/// Where presigned URLs are obtained from.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", tag = "type")]
+#[configurable(metadata(docs::enum_tag_description = "The type of the provider."))]
+pub enum PresignedUrlProvider {
+    /// Runs a command, which prints the URL to its standard output.
+    Command {
+        /// The command to run, followed by its arguments.
+        #[configurable(metadata(docs::examples = "/usr/local/bin/infino-presign"))]
+        command: Vec<String>,
+    },
+
+    /// Sends a `GET` request to an HTTP endpoint, which responds with the URL.
+    Http {
+        /// The URL of the endpoint.
+        #[configurable(metadata(docs::examples = "http://localhost:8080/presign"))]
+        url: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct PresignedUrl {
+    url: String,
+    expires_in_secs: u64,
+}
+
+struct Signed {
+    uri: Uri,
+    refresh_at: Instant,
+}
+
+/// Obtains presigned URLs from the provider, and caches them until they are due to be refreshed.
+pub struct PresignedUrls {
+    provider: PresignedUrlProvider,
+    refresh_before: Duration,
+    client: HttpClient,
+    signed: Mutex<Option<Signed>>,
+}
+
+impl PresignedUrls {
+    pub fn new(config: &PresignedUrlConfig, client: HttpClient) -> crate::Result<Self> {
+        match &config.provider {
+            PresignedUrlProvider::Command { command } if command.is_empty() => {
+                return Err("`presigned_url.provider.command` must not be empty".into())
+            }
+            PresignedUrlProvider::Http { url } => {
+                url.parse::<Uri>()
+                    .map_err(|error| format!("Invalid presigned URL provider: {}", error))?;
+            }
+            PresignedUrlProvider::Command { .. } => {}
+        }
+        Ok(Self {
+            provider: config.provider.clone(),
+            refresh_before: Duration::from_secs(config.refresh_before_secs),
+            client,
+            signed: Mutex::new(None),
+        })
+    }
+
+    /// Returns the URL to send the next request to, obtaining a new one if it is due.
+    pub async fn uri(&self) -> crate::Result<Uri> {
+        let mut signed = self.signed.lock().await;
+        let now = Instant::now();
+        if let Some(signed) = signed.as_ref().filter(|signed| now < signed.refresh_at) {
+            return Ok(signed.uri.clone());
+        }
+
+        let presigned = self
+            .fetch()
+            .await
+            .map_err(|error| format!("Unable to obtain a presigned URL: {}", error))?;
+        let uri = presigned
+            .url
+            .parse::<Uri>()
+            .map_err(|_| "Presigned URL provider returned an invalid URL")?;
+        let valid_for = Duration::from_secs(presigned.expires_in_secs);
+        *signed = Some(Signed {
+            uri: uri.clone(),
+            refresh_at: now + valid_for.saturating_sub(self.refresh_before),
+        });
+        Ok(uri)
+    }
+
+    async fn fetch(&self) -> crate::Result<PresignedUrl> {
+        let output = match &self.provider {
+            PresignedUrlProvider::Command { command } => {
+                let output = tokio::process::Command::new(&command[0])
+                    .args(&command[1..])
+                    .kill_on_drop(true)
+                    .output()
+                    .await?;
+                if !output.status.success() {
+                    return Err(format!("command exited with {}", output.status).into());
+                }
+                Bytes::from(output.stdout)
+            }
+            PresignedUrlProvider::Http { url } => {
+                let request = Request::get(url.as_str()).body(Body::empty())?;
+                let response = self.client.send(request).await?;
+                if response.status() != StatusCode::OK {
+                    return Err(format!("unexpected status {}", response.status()).into());
+                }
+                hyper::body::to_bytes(response.into_body()).await?
+            }
+        };
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::TlsSettings;
+
+    fn urls(script: &str, refresh_before_secs: u64) -> PresignedUrls {
+        let config = PresignedUrlConfig {
+            provider: PresignedUrlProvider::Command {
+                command: vec!["sh".into(), "-c".into(), script.into()],
+            },
+            refresh_before_secs,
+        };
+        let client = HttpClient::new(TlsSettings::default(), &Default::default()).unwrap();
+        PresignedUrls::new(&config, client).unwrap()
+    }
+
+    #[tokio::test]
+    async fn caches_url_until_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("count");
+        let script = format!(
+            r#"echo x >> {0}; echo "{{\"url\":\"https://ingest/bulk?n=$(wc -l < {0} | tr -d ' ')\",\"expires_in_secs\":3600}}""#,
+            counter.display()
+        );
+
+        let cached = urls(&script, 60);
+        assert_eq!(cached.uri().await.unwrap(), "https://ingest/bulk?n=1");
+        assert_eq!(cached.uri().await.unwrap(), "https://ingest/bulk?n=1");
+
+        // URLs expiring before they are due to be refreshed are obtained again every time.
+        let expiring = urls(&script, 7200);
+        assert_eq!(expiring.uri().await.unwrap(), "https://ingest/bulk?n=2");
+        assert_eq!(expiring.uri().await.unwrap(), "https://ingest/bulk?n=3");
+    }
+
+    #[tokio::test]
+    async fn fails_on_provider_errors() {
+        assert!(urls("exit 1", 60).uri().await.is_err());
+        assert!(urls("echo not json", 60).uri().await.is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use bytes::Bytes;
+use http::{Method, StatusCode};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use vector_lib::configurable::configurable_component;
+
+use super::InfinoCommon;
+use crate::{http::HttpClient, sinks::HealthcheckError};
+
+/// Write probe configuration.
+///
+/// The healthcheck also indexes a canary document in `index`, and deletes it, so that the sink is
+/// only healthy once it is authorized to write, rather than as soon as the cluster is reachable.
+/// The credentials need to be allowed to index and delete documents in `index`.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WriteProbeConfig {
+    /// The index the canary document is written to.
+    #[configurable(metadata(docs::examples = "vector-healthcheck"))]
+    pub index: String,
+}
+
+impl WriteProbeConfig {
+    /// Indexes a canary document through `common`, and deletes it.
+    pub async fn check(&self, common: &InfinoCommon, client: HttpClient) -> crate::Result<()> {
+        let path = format!(
+            "/{}/_doc/{}",
+            utf8_percent_encode(&self.index, NON_ALPHANUMERIC),
+            canary_id(),
+        );
+        let document = serde_json::json!({
+            "message": "Vector healthcheck canary document.",
+            "@timestamp": chrono::Utc::now(),
+        });
+
+        let status = common
+            .request(
+                client.clone(),
+                Method::PUT,
+                &path,
+                Bytes::from(serde_json::to_vec(&document)?),
+            )
+            .await?
+            .status();
+        check_status("index", status)?;
+
+        let status = common
+            .request(client, Method::DELETE, &path, Bytes::new())
+            .await?
+            .status();
+        // The document may already be gone, such as when the index has a short retention.
+        if status == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check_status("delete", status)
+    }
+}
+
+/// Returns the ID of a canary document, unique to this check.
+fn canary_id() -> String {
+    format!("vector-healthcheck-{}", uuid::Uuid::new_v4())
+}
+
+fn check_status(operation: &str, status: StatusCode) -> crate::Result<()> {
+    match status {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(format!(
+            "Not authorized to {} the healthcheck canary document: {}",
+            operation, status
+        )
+        .into()),
+        status => Err(HealthcheckError::UnexpectedStatus { status }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_permissions() {
+        assert!(check_status("index", StatusCode::CREATED).is_ok());
+        let error = check_status("index", StatusCode::FORBIDDEN).unwrap_err();
+        assert!(error.to_string().contains("Not authorized to index"));
+        assert!(check_status("delete", StatusCode::INTERNAL_SERVER_ERROR).is_err());
+    }
+
+    #[test]
+    fn generates_unique_canary_ids() {
+        assert_ne!(canary_id(), canary_id());
+        assert!(canary_id().starts_with("vector-healthcheck-"));
+    }
+}
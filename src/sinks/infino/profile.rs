@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    tls::InfinoTlsConfig, InfinoAuthConfig, InfinoConfig, InfinoRequestConfig, ParseError,
+};
+use crate::config::{format, Format};
+
+/// A connection profile, shared by the Infino sinks referring to it with their `profile` option.
+///
+/// Options set by a sink take precedence over the profile. The `request` options are merged one by
+/// one, so that a sink only takes those of the profile it leaves at their default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionProfile {
+    #[serde(skip)]
+    path: PathBuf,
+
+    #[serde(default)]
+    endpoints: Vec<String>,
+
+    #[serde(default)]
+    auth: Option<InfinoAuthConfig>,
+
+    #[serde(default)]
+    tls: Option<InfinoTlsConfig>,
+
+    /// Kept as written, so that the options the profile leaves out are told apart from those it
+    /// sets to their default.
+    #[serde(default)]
+    request: Option<Value>,
+}
+
+impl ConnectionProfile {
+    /// Loads the profile from a TOML, YAML, or JSON file, according to its extension.
+    pub fn load(path: &Path) -> Result<Self, ParseError> {
+        let invalid = |reason: String| ParseError::InvalidProfile {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let format = Format::from_path(path)
+            .map_err(|_| invalid("unknown format, expected a toml, yaml, or json file".into()))?;
+        let content = std::fs::read_to_string(path).map_err(|error| invalid(error.to_string()))?;
+        let mut profile = format::deserialize::<Self>(&content, format)
+            .map_err(|errors| invalid(errors.join(", ")))?;
+        if let Some(request) = &profile.request {
+            serde_json::from_value::<InfinoRequestConfig>(request.clone())
+                .map_err(|error| invalid(format!("request: {}", error)))?;
+        }
+        profile.path = path.to_path_buf();
+        Ok(profile)
+    }
+
+    /// Fills in the options of `config` from the profile.
+    pub fn apply(self, config: &mut InfinoConfig) -> Result<(), ParseError> {
+        if config.endpoint.is_none() && config.endpoints.is_empty() {
+            config.endpoints = self.endpoints;
+        }
+        if config.auth.is_none() {
+            config.auth = self.auth;
+        }
+        if config.tls.is_none() {
+            config.tls = self.tls;
+        }
+        if let Some(request) = self.request {
+            let invalid = |error: serde_json::Error| ParseError::InvalidProfile {
+                path: self.path.clone(),
+                reason: format!("request: {}", error),
+            };
+            let default = serde_json::to_value(InfinoRequestConfig::default()).map_err(invalid)?;
+            let mut merged = serde_json::to_value(&config.request).map_err(invalid)?;
+            merge_defaults(&mut merged, request, Some(&default));
+            config.request = serde_json::from_value(merged).map_err(invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets the options of `sink` that are left at their `default` to those of `profile`.
+///
+/// Tables are merged key by key, so that the headers of both are kept, with those of the sink
+/// taking precedence. An option of the sink set to its default value cannot be told apart from one
+/// left out, so it takes the value of the profile.
+fn merge_defaults(sink: &mut Value, profile: Value, default: Option<&Value>) {
+    match (sink, profile) {
+        (Value::Object(sink), Value::Object(profile)) => {
+            for (key, profile) in profile {
+                let default = default.and_then(|default| default.get(&key));
+                match sink.get_mut(&key) {
+                    Some(sink) => merge_defaults(sink, profile, default),
+                    None => {
+                        sink.insert(key, profile);
+                    }
+                }
+            }
+        }
+        (sink, profile) => {
+            if default.map_or(sink.is_null(), |default| default == sink) {
+                *sink = profile;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_profile() {
+        let profile = toml::from_str::<ConnectionProfile>(
+            r#"
+            endpoints = ["https://infino-0:9000", "https://infino-1:9000"]
+            auth.strategy = "basic"
+            auth.user = "vector"
+            auth.password = "secret"
+            request.timeout_secs = 30
+            request.headers.X-Team = "platform"
+            request.headers.X-Env = "prod"
+        "#,
+        )
+        .unwrap();
+        let mut config = toml::from_str::<InfinoConfig>(
+            r#"
+            request.headers.X-Env = "staging"
+        "#,
+        )
+        .unwrap();
+
+        profile.apply(&mut config).unwrap();
+        assert_eq!(config.endpoints.len(), 2);
+        assert!(matches!(config.auth, Some(InfinoAuthConfig::Basic { .. })));
+        assert_eq!(config.request.tower.timeout_secs, 30);
+        assert_eq!(config.request.headers["X-Team"], "platform");
+        assert_eq!(config.request.headers["X-Env"], "staging");
+    }
+
+    #[test]
+    fn keeps_sink_options() {
+        let profile = ConnectionProfile {
+            endpoints: vec!["https://infino-0:9000".to_owned()],
+            ..Default::default()
+        };
+        let mut config = InfinoConfig {
+            endpoint: Some("https://local:9000".to_owned()),
+            ..Default::default()
+        };
+
+        profile.apply(&mut config).unwrap();
+        assert!(config.endpoints.is_empty());
+    }
+
+    #[test]
+    fn keeps_sink_request_options() {
+        let profile = toml::from_str::<ConnectionProfile>(
+            r#"
+            request.timeout_secs = 30
+            request.retry_attempts = 5
+            request.rate_limit_bytes_per_sec = 1048576
+        "#,
+        )
+        .unwrap();
+        let mut config = toml::from_str::<InfinoConfig>(
+            r#"
+            request.timeout_secs = 10
+        "#,
+        )
+        .unwrap();
+
+        profile.apply(&mut config).unwrap();
+        assert_eq!(config.request.tower.timeout_secs, 10);
+        assert_eq!(config.request.tower.retry_attempts, 5);
+        assert_eq!(
+            config.request.rate_limit_bytes_per_sec,
+            std::num::NonZeroU64::new(1048576)
+        );
+    }
+}
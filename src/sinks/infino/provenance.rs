@@ -0,0 +1,109 @@
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vrl::path::PathPrefix;
+
+use crate::event::{LogEvent, ObjectMap, Value};
+
+/// Provenance configuration.
+///
+/// Adds the hostname of the Vector instance and the source of each event to its document, so that
+/// the origin of documents can be queried in Infino without remapping the events of every source.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ProvenanceConfig {
+    /// The name of the field the provenance of documents is written to.
+    ///
+    /// The field holds the `host` of the Vector instance, the `source_id` and `source_type` of the
+    /// source the event was received from, and the `pipeline_id` if set.
+    #[serde(default = "default_field")]
+    #[configurable(metadata(docs::examples = "vector"))]
+    #[configurable(metadata(docs::examples = "_provenance"))]
+    pub field: ConfigValuePath,
+
+    /// An identifier of the pipeline, such as its name or the version of its configuration.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "edge-eu-west-1"))]
+    pub pipeline_id: Option<String>,
+}
+
+impl Default for ProvenanceConfig {
+    fn default() -> Self {
+        Self {
+            field: default_field(),
+            pipeline_id: None,
+        }
+    }
+}
+
+fn default_field() -> ConfigValuePath {
+    ConfigValuePath::from("vector")
+}
+
+/// Writes the provenance of events to their document.
+#[derive(Clone, Debug)]
+pub struct Provenance {
+    field: ConfigValuePath,
+    host: Option<String>,
+    pipeline_id: Option<String>,
+}
+
+impl Provenance {
+    pub fn new(config: &ProvenanceConfig) -> Self {
+        Self {
+            field: config.field.clone(),
+            host: crate::get_hostname().ok(),
+            pipeline_id: config.pipeline_id.clone(),
+        }
+    }
+
+    pub fn apply(&self, log: &mut LogEvent) {
+        let metadata = log.metadata();
+        let mut provenance = ObjectMap::new();
+        if let Some(host) = &self.host {
+            provenance.insert("host".into(), Value::from(host.as_str()));
+        }
+        if let Some(source_id) = metadata.source_id() {
+            provenance.insert("source_id".into(), Value::from(source_id.to_string()));
+        }
+        if let Some(source_type) = metadata.source_type() {
+            provenance.insert("source_type".into(), Value::from(source_type));
+        }
+        if let Some(pipeline_id) = &self.pipeline_id {
+            provenance.insert("pipeline_id".into(), Value::from(pipeline_id.as_str()));
+        }
+        log.insert((PathPrefix::Event, &self.field), provenance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use vector_lib::config::ComponentKey;
+
+    use super::*;
+
+    #[test]
+    fn writes_provenance() {
+        let provenance = Provenance {
+            field: ConfigValuePath::from("vector"),
+            host: Some("vector-0".to_owned()),
+            pipeline_id: Some("edge".to_owned()),
+        };
+
+        let mut log = LogEvent::from("hello");
+        log.metadata_mut()
+            .set_source_id(Arc::new(ComponentKey::from("in")));
+        log.metadata_mut().set_source_type("demo_logs");
+        provenance.apply(&mut log);
+
+        assert_eq!(log.get("vector.host"), Some(&Value::from("vector-0")));
+        assert_eq!(log.get("vector.source_id"), Some(&Value::from("in")));
+        assert_eq!(
+            log.get("vector.source_type"),
+            Some(&Value::from("demo_logs"))
+        );
+        assert_eq!(log.get("vector.pipeline_id"), Some(&Value::from("edge")));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use http::Uri;
+
+use super::{config::dynamic_template, encoder::ProcessedEvent};
+use crate::{internal_events::TemplateRenderingError, template::Template};
+
+/// Renders the query parameters of requests from the events they hold.
+///
+/// Events are batched per rendered parameters, so that the parameters of a request hold for every
+/// one of its events.
+#[derive(Clone, Debug)]
+pub struct BatchQuery {
+    params: Vec<(String, Template)>,
+}
+
+impl BatchQuery {
+    /// Returns the parameters of `query` holding a template, if any.
+    pub fn new(query: Option<&HashMap<String, String>>) -> Option<Self> {
+        let mut params = query?
+            .iter()
+            .filter_map(|(param, value)| Some((param.clone(), dynamic_template(value)?)))
+            .collect::<Vec<_>>();
+        if params.is_empty() {
+            return None;
+        }
+        // Events rendering the same parameters have to share a batch, whatever the map order.
+        params.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Some(Self { params })
+    }
+
+    /// Renders the parameters of `event`, or returns `None` if the event has to be dropped.
+    pub fn render(&self, event: &ProcessedEvent) -> Option<Vec<(String, String)>> {
+        self.params
+            .iter()
+            .map(|(param, template)| {
+                let value = template
+                    .render_string(&event.log)
+                    .map_err(|error| {
+                        emit!(TemplateRenderingError {
+                            error,
+                            field: Some("query"),
+                            drop_event: true,
+                        });
+                    })
+                    .ok()?;
+                Some((param.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Returns `uri` with `query` appended to its query string.
+pub fn with_query(uri: &Uri, query: &[(String, String)]) -> crate::Result<Uri> {
+    let existing = uri.query().unwrap_or_default().to_owned();
+    let query = url::form_urlencoded::Serializer::for_suffix(existing, 0)
+        .extend_pairs(query)
+        .finish();
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(format!("{}?{}", uri.path(), query).parse()?);
+    Ok(Uri::from_parts(parts)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::LogEvent,
+        sinks::infino::{encoder::DocumentMetadata, BulkAction},
+    };
+
+    fn event(tenant: &str) -> ProcessedEvent {
+        let mut log = LogEvent::from("message");
+        log.insert("tenant", tenant);
+        ProcessedEvent {
+            index: "logs".to_owned(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn renders_templated_params() {
+        let query = HashMap::from([
+            ("refresh".to_owned(), "false".to_owned()),
+            ("routing".to_owned(), "{{ tenant }}".to_owned()),
+        ]);
+        let query = BatchQuery::new(Some(&query)).unwrap();
+        assert_eq!(
+            query.render(&event("tenant-a")),
+            Some(vec![("routing".to_owned(), "tenant-a".to_owned())])
+        );
+
+        let missing = HashMap::from([("routing".to_owned(), "{{ missing }}".to_owned())]);
+        let missing = BatchQuery::new(Some(&missing)).unwrap();
+        assert_eq!(missing.render(&event("tenant-a")), None);
+    }
+
+    #[test]
+    fn skips_static_params() {
+        let query = HashMap::from([("timeout".to_owned(), "30s".to_owned())]);
+        assert!(BatchQuery::new(Some(&query)).is_none());
+        assert!(BatchQuery::new(None).is_none());
+    }
+
+    #[test]
+    fn appends_to_the_query_string() {
+        let uri = "http://infino:3000/_bulk?timeout=60s"
+            .parse::<Uri>()
+            .unwrap();
+        let query = [("routing".to_owned(), "a b".to_owned())];
+        assert_eq!(
+            with_query(&uri, &query).unwrap(),
+            "http://infino:3000/_bulk?timeout=60s&routing=a+b"
+        );
+    }
+}
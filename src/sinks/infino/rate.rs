@@ -0,0 +1,159 @@
+use std::num::NonZeroUsize;
+
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+
+use crate::{
+    event::{
+        metric::{MetricKind, MetricSeries},
+        EventStatus, Finalizable, Metric, MetricValue,
+    },
+    internal_events::InfinoMetricsDropped,
+};
+
+/// The number of counter series whose previous point is remembered.
+const MAX_TRACKED_SERIES: NonZeroUsize = match NonZeroUsize::new(100_000) {
+    Some(max) => max,
+    None => unreachable!(),
+};
+
+/// Converts counters into gauges of their rate per second over the interval since the previous
+/// point of their series.
+///
+/// Incremental counters that carry their interval are converted on their own. Other counters
+/// need the previous point of their series, so the first point of every series is dropped. A
+/// monotonic counter going down is taken as a reset, and its new value as the delta.
+pub struct CounterRates {
+    previous: LruCache<MetricSeries, (f64, DateTime<Utc>)>,
+}
+
+impl Default for CounterRates {
+    fn default() -> Self {
+        Self {
+            previous: LruCache::new(MAX_TRACKED_SERIES),
+        }
+    }
+}
+
+impl CounterRates {
+    /// Returns the rate of `metric` if it is a counter, `metric` itself if it is not, or `None`
+    /// if the rate cannot be computed yet.
+    pub fn convert(&mut self, mut metric: Metric) -> Option<Metric> {
+        let MetricValue::Counter { value } = *metric.value() else {
+            return Some(metric);
+        };
+        let timestamp = metric.timestamp().unwrap_or_else(Utc::now);
+
+        let rate = match (metric.kind(), metric.interval_ms()) {
+            (MetricKind::Incremental, Some(interval_ms)) => {
+                Some(value * 1000.0 / f64::from(interval_ms.get()))
+            }
+            (MetricKind::Incremental, None) => self
+                .previous
+                .put(metric.series().clone(), (0.0, timestamp))
+                .and_then(|(_, previous_at)| per_second(value, previous_at, timestamp)),
+            (MetricKind::Absolute, _) => self
+                .previous
+                .put(metric.series().clone(), (value, timestamp))
+                .and_then(|(previous, previous_at)| {
+                    let delta = if value >= previous {
+                        value - previous
+                    } else {
+                        value
+                    };
+                    per_second(delta, previous_at, timestamp)
+                }),
+        };
+
+        match rate {
+            Some(rate) => {
+                let data = metric.data_mut();
+                data.kind = MetricKind::Absolute;
+                data.value = MetricValue::Gauge { value: rate };
+                Some(metric)
+            }
+            None => {
+                metric
+                    .take_finalizers()
+                    .update_status(EventStatus::Delivered);
+                emit!(InfinoMetricsDropped {
+                    count: 1,
+                    reason: "Counter has no previous point to compute a rate from.",
+                });
+                None
+            }
+        }
+    }
+}
+
+fn per_second(delta: f64, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<f64> {
+    let elapsed = (to - from).num_milliseconds();
+    (elapsed > 0).then(|| delta * 1000.0 / elapsed as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn counter(kind: MetricKind, value: f64, second: u32) -> Metric {
+        Metric::new("requests", kind, MetricValue::Counter { value }).with_timestamp(Some(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap(),
+        ))
+    }
+
+    fn rate(metric: Option<Metric>) -> Option<f64> {
+        metric.map(|metric| match metric.value() {
+            MetricValue::Gauge { value } => *value,
+            value => panic!("unexpected value {value:?}"),
+        })
+    }
+
+    #[test]
+    fn converts_absolute_counters() {
+        let mut rates = CounterRates::default();
+        assert_eq!(rates.convert(counter(MetricKind::Absolute, 100.0, 0)), None);
+        assert_eq!(
+            rate(rates.convert(counter(MetricKind::Absolute, 120.0, 10))),
+            Some(2.0)
+        );
+        // A reset starts counting from zero again.
+        assert_eq!(
+            rate(rates.convert(counter(MetricKind::Absolute, 5.0, 15))),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn converts_incremental_counters() {
+        let mut rates = CounterRates::default();
+        assert_eq!(
+            rates.convert(counter(MetricKind::Incremental, 10.0, 0)),
+            None
+        );
+        assert_eq!(
+            rate(rates.convert(counter(MetricKind::Incremental, 10.0, 5))),
+            Some(2.0)
+        );
+
+        let with_interval =
+            counter(MetricKind::Incremental, 30.0, 0).with_interval_ms(NonZeroU32::new(10_000));
+        assert_eq!(
+            rate(CounterRates::default().convert(with_interval)),
+            Some(3.0)
+        );
+    }
+
+    #[test]
+    fn passes_other_metrics_through() {
+        let gauge = Metric::new(
+            "memory",
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 1.0 },
+        );
+        assert_eq!(CounterRates::default().convert(gauge.clone()), Some(gauge));
+    }
+}
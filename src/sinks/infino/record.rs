@@ -0,0 +1,270 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::Uri;
+use serde::Serialize;
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    query::with_query,
+    redact::redact,
+    service::{InfinoRequest, InfinoResponse},
+};
+use crate::sinks::util::Compression;
+
+/// Request recording configuration.
+///
+/// Every request sent to Infino is written to the configured directory as a pair of files: the
+/// payload exactly as it was sent (`<name>.bulk`), and its metadata (`<name>.json`), including the
+/// path, query string and `Content-Type` it was sent with. Recorded
+/// requests can be re-sent with `scripts/infino-replay.sh` to reproduce ingest problems against a
+/// test cluster. Credentials are never recorded.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RecordConfig {
+    /// The directory that requests are recorded to.
+    ///
+    /// It is created if it does not exist.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/infino-recording"))]
+    pub path: PathBuf,
+
+    /// The maximum number of requests to record, after which recording stops.
+    ///
+    /// By default, all requests are recorded.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = 1000))]
+    pub max_requests: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct RecordMetadata<'a> {
+    timestamp: String,
+    endpoint: &'a str,
+    path: &'a str,
+    content_type: &'a str,
+    content_encoding: Option<&'static str>,
+    batch_size: usize,
+    payload_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct RequestRecorder {
+    path: PathBuf,
+    max_requests: Option<u64>,
+    compression: Compression,
+    content_type: String,
+    recorded: AtomicU64,
+}
+
+impl RequestRecorder {
+    pub fn new(
+        config: &RecordConfig,
+        compression: Compression,
+        content_type: String,
+    ) -> crate::Result<Self> {
+        std::fs::create_dir_all(&config.path).map_err(|error| {
+            format!(
+                "Unable to create request recording directory {:?}: {}",
+                config.path, error
+            )
+        })?;
+
+        Ok(Self {
+            path: config.path.clone(),
+            max_requests: config.max_requests,
+            compression,
+            content_type,
+            recorded: AtomicU64::new(0),
+        })
+    }
+
+    /// Reserves the next sequence number, or `None` once `max_requests` is reached.
+    fn next_sequence(&self) -> Option<u64> {
+        let sequence = self.recorded.fetch_add(1, Ordering::Relaxed);
+        match self.max_requests {
+            Some(max) if sequence >= max => None,
+            _ => Some(sequence),
+        }
+    }
+
+    async fn record(
+        &self,
+        sequence: u64,
+        endpoint: &str,
+        path: &str,
+        batch_size: usize,
+        payload: &Bytes,
+        response: &Result<InfinoResponse, crate::Error>,
+    ) -> std::io::Result<()> {
+        let now = chrono::Utc::now();
+        // The timestamp prefix keeps recordings from several runs in send order.
+        let name = format!("{}-{:08}", now.format("%Y%m%dT%H%M%S%.3fZ"), sequence);
+
+        let metadata = RecordMetadata {
+            timestamp: now.to_rfc3339(),
+            endpoint,
+            path,
+            content_type: &self.content_type,
+            content_encoding: self.compression.content_encoding(),
+            batch_size,
+            payload_bytes: payload.len(),
+            status: response
+                .as_ref()
+                .ok()
+                .map(|response| response.http_response.status().as_u16()),
+            error: response
+                .as_ref()
+                .err()
+                .map(|error| redact(&error.to_string()).into_owned()),
+        };
+
+        tokio::fs::write(self.path.join(format!("{}.bulk", name)), payload).await?;
+        tokio::fs::write(
+            self.path.join(format!("{}.json", name)),
+            serde_json::to_vec_pretty(&metadata)?,
+        )
+        .await
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordLayer {
+    recorder: Arc<RequestRecorder>,
+    endpoint: String,
+    bulk_uri: Uri,
+}
+
+impl RecordLayer {
+    pub const fn new(recorder: Arc<RequestRecorder>, endpoint: String, bulk_uri: Uri) -> Self {
+        Self {
+            recorder,
+            endpoint,
+            bulk_uri,
+        }
+    }
+}
+
+impl<S> Layer<S> for RecordLayer {
+    type Service = RecordService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordService {
+            recorder: Arc::clone(&self.recorder),
+            endpoint: self.endpoint.clone(),
+            bulk_uri: self.bulk_uri.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordService<S> {
+    recorder: Arc<RequestRecorder>,
+    endpoint: String,
+    bulk_uri: Uri,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for RecordService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let Some(sequence) = self.recorder.next_sequence() else {
+            return Box::pin(self.inner.call(req));
+        };
+
+        let recorder = Arc::clone(&self.recorder);
+        let endpoint = self.endpoint.clone();
+        let path = request_path(&self.bulk_uri, &req.query);
+        let batch_size = req.batch_size;
+        let payload = req.payload.clone();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let response = future.await;
+            if let Err(error) = recorder
+                .record(sequence, &endpoint, &path, batch_size, &payload, &response)
+                .await
+            {
+                warn!(
+                    message = "Failed to record request.",
+                    %error,
+                    internal_log_rate_limit = true
+                );
+            }
+            response
+        })
+    }
+}
+
+/// The path and query string a request is sent to, relative to the endpoint.
+fn request_path(bulk_uri: &Uri, query: &[(String, String)]) -> String {
+    let uri = if query.is_empty() {
+        None
+    } else {
+        with_query(bulk_uri, query).ok()
+    };
+    uri.as_ref()
+        .unwrap_or(bulk_uri)
+        .path_and_query()
+        .map_or_else(|| bulk_uri.path().to_owned(), ToString::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_after_max_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = RequestRecorder::new(
+            &RecordConfig {
+                path: dir.path().join("recording"),
+                max_requests: Some(2),
+            },
+            Compression::None,
+            "application/x-ndjson".to_owned(),
+        )
+        .unwrap();
+
+        assert_eq!(recorder.next_sequence(), Some(0));
+        assert_eq!(recorder.next_sequence(), Some(1));
+        assert_eq!(recorder.next_sequence(), None);
+        assert!(dir.path().join("recording").is_dir());
+    }
+
+    #[test]
+    fn records_request_path() {
+        let bulk_uri = "https://example.com/prefix/_bulk?pipeline=logs"
+            .parse::<Uri>()
+            .unwrap();
+        assert_eq!(request_path(&bulk_uri, &[]), "/prefix/_bulk?pipeline=logs");
+        assert_eq!(
+            request_path(&bulk_uri, &[("routing".to_owned(), "tenant-a".to_owned())]),
+            "/prefix/_bulk?pipeline=logs&routing=tenant-a"
+        );
+    }
+}
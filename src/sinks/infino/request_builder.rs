@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use vector_lib::EstimatedJsonEncodedSizeOf;
+use vector_lib::{json_size::JsonSize, request_metadata::RequestMetadata};
+
+use crate::{
+    event::{EventFinalizers, Finalizable},
+    sinks::{
+        infino::{
+            dispatch::Lane,
+            encoder::{InfinoEncoder, ProcessedEvent},
+            service::InfinoRequest,
+            sink::PartitionKey,
+        },
+        util::{
+            metadata::RequestMetadataBuilder, request_builder::EncodeResult, Compression,
+            RequestBuilder,
+        },
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct InfinoRequestBuilder {
+    pub compression: Compression,
+    pub encoder: InfinoEncoder,
+    pub ordered: bool,
+}
+
+pub struct Metadata {
+    finalizers: Vec<EventFinalizers>,
+    batch_size: usize,
+    events_byte_size: JsonSize,
+    partition_key: Option<PartitionKey>,
+    indexes: BTreeMap<String, usize>,
+    lane: Lane,
+    encryption_key: Option<String>,
+    headers: Vec<(String, String)>,
+    query: Vec<(String, String)>,
+}
+
+impl RequestBuilder<Vec<ProcessedEvent>> for InfinoRequestBuilder {
+    type Metadata = Metadata;
+    type Events = Vec<ProcessedEvent>;
+    type Encoder = InfinoEncoder;
+    type Payload = Bytes;
+    type Request = InfinoRequest;
+    type Error = std::io::Error;
+
+    fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    fn encoder(&self) -> &Self::Encoder {
+        &self.encoder
+    }
+
+    fn split_input(
+        &self,
+        mut events: Vec<ProcessedEvent>,
+    ) -> (Self::Metadata, RequestMetadataBuilder, Self::Events) {
+        let events_byte_size = events
+            .iter()
+            .map(|x| x.log.estimated_json_encoded_size_of())
+            .reduce(|a, b| a + b)
+            .unwrap_or(JsonSize::zero());
+
+        // A batch only belongs to a partition when all of its events share the same key, which
+        // is always the case when the sink runs in ordered mode.
+        let partition_key = events
+            .first()
+            .map(|event| PartitionKey::of(event, self.ordered))
+            .filter(|key| {
+                events
+                    .iter()
+                    .all(|event| PartitionKey::of(event, self.ordered) == *key)
+            });
+
+        let mut indexes = BTreeMap::new();
+        for event in &events {
+            *indexes.entry(event.index.clone()).or_default() += 1;
+        }
+
+        // Batches never mix lanes, encryption keys, headers, nor query parameters.
+        let lane = events.first().map(|event| event.lane).unwrap_or_default();
+        let encryption_key = events
+            .first()
+            .and_then(|event| event.encryption_key.clone());
+        let headers = events
+            .first()
+            .map(|event| event.headers.clone())
+            .unwrap_or_default();
+        let query = events
+            .first()
+            .map(|event| event.query.clone())
+            .unwrap_or_default();
+
+        let metadata_builder = RequestMetadataBuilder::from_events(&events);
+
+        let infino_metadata = Metadata {
+            finalizers: events
+                .iter_mut()
+                .map(Finalizable::take_finalizers)
+                .collect(),
+            batch_size: events.len(),
+            events_byte_size,
+            partition_key,
+            indexes,
+            lane,
+            encryption_key,
+            headers,
+            query,
+        };
+        (infino_metadata, metadata_builder, events)
+    }
+
+    fn build_request(
+        &self,
+        infino_metadata: Self::Metadata,
+        metadata: RequestMetadata,
+        payload: EncodeResult<Self::Payload>,
+    ) -> Self::Request {
+        InfinoRequest {
+            payload: payload.into_payload(),
+            finalizers: infino_metadata.finalizers,
+            batch_size: infino_metadata.batch_size,
+            events_byte_size: infino_metadata.events_byte_size,
+            partition_key: infino_metadata.partition_key,
+            indexes: infino_metadata.indexes,
+            lane: infino_metadata.lane,
+            encryption_key: infino_metadata.encryption_key,
+            headers: infino_metadata.headers,
+            query: infino_metadata.query,
+            metadata,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Periodic re-resolution of endpoint hostnames.
+//!
+//! Connections are kept open in the pool of a client for as long as they are used, so an endpoint
+//! whose hostname moves to new addresses, as a Kubernetes service does when its pods are replaced,
+//! keeps being sent requests on the addresses it used to have. The hostname of each endpoint is
+//! resolved again on an interval, and the endpoint is given a new client, with connections of its
+//! own, whenever its addresses change.
+
+use std::{net::IpAddr, time::Duration};
+
+use http::Uri;
+use tokio::sync::watch;
+use vector_lib::config::proxy::ProxyConfig;
+
+use super::tls::InfinoTlsSettings;
+use crate::{dns::Resolver, http::HttpClient, internal_events::InfinoEndpointAddressesChanged};
+
+/// Tells an endpoint when the addresses of its hostname change, and builds it a new client then.
+///
+/// Each clone is told of the changes on its own.
+#[derive(Clone)]
+pub struct DnsRefresh {
+    changes: watch::Receiver<Vec<IpAddr>>,
+    tls_settings: InfinoTlsSettings,
+    proxy: ProxyConfig,
+}
+
+impl DnsRefresh {
+    /// Resolves the hostname of `endpoint` every `interval`, until the returned value is dropped.
+    ///
+    /// Returns `None` for endpoints with an IP address rather than a hostname, which never move.
+    pub fn new(
+        endpoint: &str,
+        interval: Duration,
+        tls_settings: InfinoTlsSettings,
+        proxy: ProxyConfig,
+    ) -> Option<Self> {
+        let host = hostname(endpoint)?;
+        let (sender, changes) = watch::channel(Vec::new());
+        tokio::spawn(resolve(endpoint.to_owned(), host, interval, sender));
+        Some(Self {
+            changes,
+            tls_settings,
+            proxy,
+        })
+    }
+
+    /// Returns a new client if the addresses of the endpoint changed since it was last called.
+    pub fn refreshed_client(&mut self) -> Option<crate::Result<HttpClient>> {
+        if !self.changes.has_changed().unwrap_or(false) {
+            return None;
+        }
+        self.changes.borrow_and_update();
+        Some(
+            self.tls_settings
+                .http_client(&self.proxy)
+                .map_err(Into::into),
+        )
+    }
+
+    /// Builds the clients of later changes with `tls_settings`, such as a reloaded certificate.
+    pub fn set_tls_settings(&mut self, tls_settings: InfinoTlsSettings) {
+        self.tls_settings = tls_settings;
+    }
+}
+
+/// Returns the hostname of `endpoint`, unless it is an IP address.
+fn hostname(endpoint: &str) -> Option<String> {
+    let uri = endpoint.parse::<Uri>().ok()?;
+    let host = uri.host()?;
+    let address = host.trim_start_matches('[').trim_end_matches(']');
+    address.parse::<IpAddr>().is_err().then(|| host.to_owned())
+}
+
+/// Sends the addresses of `host` to `sender` whenever they change from those it last had.
+async fn resolve(
+    endpoint: String,
+    host: String,
+    interval: Duration,
+    sender: watch::Sender<Vec<IpAddr>>,
+) {
+    let mut known: Option<Vec<IpAddr>> = None;
+    loop {
+        match Resolver.lookup_ip(host.clone()).await {
+            Ok(addresses) => {
+                let mut addresses = addresses.collect::<Vec<_>>();
+                addresses.sort_unstable();
+                addresses.dedup();
+                if known.as_ref().is_some_and(|known| *known != addresses) {
+                    emit!(InfinoEndpointAddressesChanged {
+                        endpoint: &endpoint,
+                        addresses: addresses.len(),
+                    });
+                    if sender.send(addresses.clone()).is_err() {
+                        return;
+                    }
+                }
+                known = Some(addresses);
+            }
+            // Failed lookups keep the connections there are, rather than dropping them.
+            Err(error) => warn!(
+                message = "Unable to resolve the hostname of the endpoint.",
+                %endpoint,
+                %error,
+                internal_log_rate_limit = true,
+            ),
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = sender.closed() => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_ip_addresses() {
+        assert_eq!(
+            hostname("http://infino.default.svc:3000"),
+            Some("infino.default.svc".to_owned())
+        );
+        assert_eq!(hostname("https://localhost"), Some("localhost".to_owned()));
+        assert_eq!(hostname("http://10.0.0.1:3000"), None);
+        assert_eq!(hostname("http://[::1]:3000"), None);
+    }
+
+    #[tokio::test]
+    async fn keeps_clients_until_addresses_change() {
+        let (sender, changes) = watch::channel(Vec::new());
+        let mut refresh = DnsRefresh {
+            changes,
+            tls_settings: InfinoTlsSettings::default(),
+            proxy: ProxyConfig::default(),
+        };
+        assert!(refresh.refreshed_client().is_none());
+
+        sender.send(vec![IpAddr::from([10, 0, 0, 2])]).unwrap();
+        assert!(refresh.refreshed_client().unwrap().is_ok());
+        assert!(refresh.refreshed_client().is_none());
+    }
+
+    #[tokio::test]
+    async fn stops_once_dropped() {
+        let (sender, changes) = watch::channel(Vec::new());
+        let task = tokio::spawn(resolve(
+            "http://localhost:3000".to_owned(),
+            "localhost".to_owned(),
+            Duration::from_secs(3600),
+            sender,
+        ));
+        drop(changes);
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,442 @@
+use std::borrow::Cow;
+
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    http::HttpError,
+    sinks::{
+        infino::{
+            redact::{redact, ResponseDiagnostics},
+            service::InfinoResponse,
+        },
+        util::retries::{RetryAction, RetryLogic},
+    },
+};
+
+/// Backpressure and server errors may succeed when retried.
+pub(super) fn is_retriable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[derive(Deserialize, Debug)]
+pub(super) struct InfinoResultResponse {
+    items: Vec<InfinoResultItem>,
+}
+
+impl InfinoResultResponse {
+    pub(super) fn parse(body: &str) -> Result<Self, String> {
+        serde_json::from_str::<InfinoResultResponse>(body).map_err(|json_error| {
+            format!(
+                "some messages failed, could not parse response, error: {}",
+                json_error
+            )
+        })
+    }
+
+    /// Returns iterator over status codes for items and optional error details.
+    fn iter_status(&self) -> impl Iterator<Item = (StatusCode, Option<&InfinoErrorDetails>)> {
+        self.items.iter().filter_map(|item| {
+            item.result()
+                .status
+                .and_then(|status| StatusCode::from_u16(status).ok())
+                .map(|status| (status, item.result().error.as_ref()))
+        })
+    }
+
+    /// Returns the status of every item, in the order of the documents of the request.
+    pub(super) fn item_statuses(&self) -> impl Iterator<Item = Option<StatusCode>> + '_ {
+        self.items.iter().map(|item| {
+            item.result()
+                .status
+                .and_then(|status| StatusCode::from_u16(status).ok())
+        })
+    }
+
+    /// Returns `true` if every failed item was rejected with a version conflict, which means the
+    /// document already exists when using the `create` action.
+    pub(super) fn only_conflicts(&self) -> bool {
+        self.iter_status()
+            .filter(|(status, _)| !status.is_success())
+            .all(|(status, _)| status == StatusCode::CONFLICT)
+    }
+
+    /// Returns `true` if every item was rejected with `429 Too Many Requests`, which means the whole
+    /// request was pushed back and none of its documents were written.
+    pub(super) fn only_backpressure(&self) -> bool {
+        !self.items.is_empty()
+            && self
+                .item_statuses()
+                .all(|status| status == Some(StatusCode::TOO_MANY_REQUESTS))
+    }
+
+    /// Returns the number of items, and the number of those that failed. Items rejected with a
+    /// version conflict do not count as failed when `ignore_conflicts` is set.
+    pub(super) fn count_failed(&self, ignore_conflicts: bool) -> (usize, usize) {
+        let failed = self
+            .iter_status()
+            .filter(|(status, _)| {
+                !status.is_success() && !(ignore_conflicts && *status == StatusCode::CONFLICT)
+            })
+            .count();
+        (self.items.len(), failed)
+    }
+
+    /// Selects the first error since logging all errors would be quite verbose and many are duplicates.
+    fn get_error_reason(&self, body: &str) -> String {
+        match self
+            .items
+            .iter()
+            .find_map(|item| item.result().error.as_ref())
+        {
+            Some(error) => format!("error type: {}, reason: {}", error.err_type, error.reason),
+            None => format!("error response: {}", body),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+enum InfinoResultItem {
+    #[serde(rename = "index")]
+    Index(InfinoIndexResult),
+    #[serde(rename = "create")]
+    Create(InfinoIndexResult),
+    #[serde(rename = "update")]
+    Update(InfinoIndexResult),
+    #[serde(rename = "delete")]
+    Delete(InfinoIndexResult),
+}
+
+impl InfinoResultItem {
+    #[allow(clippy::missing_const_for_fn)] // const cannot run destructor
+    fn result(&self) -> &InfinoIndexResult {
+        match self {
+            InfinoResultItem::Index(r) => r,
+            InfinoResultItem::Create(r) => r,
+            InfinoResultItem::Update(r) => r,
+            InfinoResultItem::Delete(r) => r,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct InfinoIndexResult {
+    status: Option<u16>,
+    error: Option<InfinoErrorDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct InfinoErrorDetails {
+    reason: String,
+    #[serde(rename = "type")]
+    err_type: String,
+}
+
+#[derive(Clone)]
+pub struct InfinoRetryLogic {
+    /// Whether items rejected with a version conflict count as delivered.
+    pub ignore_conflicts: bool,
+    /// What is kept of failed responses in errors.
+    pub diagnostics: ResponseDiagnostics,
+    /// Whether bulk responses whose items were all pushed back are retried as a whole, so that
+    /// adaptive concurrency backs off.
+    pub backpressure: bool,
+}
+
+impl InfinoRetryLogic {
+    fn capture_body(&self, response: &InfinoResponse) -> String {
+        self.diagnostics.body(&response.http_response)
+    }
+
+    fn retry_action(&self, response: &InfinoResponse) -> RetryAction {
+        let status = response.http_response.status();
+
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => RetryAction::Retry("too many requests".into()),
+            StatusCode::NOT_IMPLEMENTED => {
+                RetryAction::DontRetry("endpoint not implemented".into())
+            }
+            _ if status.is_server_error() => {
+                RetryAction::Retry(format!("{}: {}", status, self.capture_body(response)).into())
+            }
+            _ if status.is_client_error() => {
+                let body = self.capture_body(response);
+                RetryAction::DontRetry(format!("client-side error, {}: {}", status, body).into())
+            }
+            _ if status.is_success() => {
+                let body = String::from_utf8_lossy(response.http_response.body());
+
+                if body.contains("\"errors\":true") {
+                    match InfinoResultResponse::parse(&body) {
+                        Ok(resp) if self.ignore_conflicts && resp.only_conflicts() => {
+                            RetryAction::Successful
+                        }
+                        // Nothing was written, so the request is sent again as a whole, and the
+                        // adaptive concurrency controller backs off as it does for a `429`.
+                        Ok(resp) if self.backpressure && resp.only_backpressure() => {
+                            RetryAction::Retry("too many requests".into())
+                        }
+                        // Some documents were written, so the request is not sent again as a whole,
+                        // which would duplicate them. `request_retry_partial` sends the failed
+                        // documents again on their own.
+                        Ok(resp) => {
+                            let reason = resp.get_error_reason(&self.capture_body(response));
+                            RetryAction::DontRetry(redact(&reason).into_owned().into())
+                        }
+                        Err(msg) => RetryAction::DontRetry(msg.into()),
+                    }
+                } else {
+                    RetryAction::Successful
+                }
+            }
+            _ => RetryAction::DontRetry(format!("response status: {}", status).into()),
+        }
+    }
+}
+
+impl RetryLogic for InfinoRetryLogic {
+    type Error = HttpError;
+    type Response = InfinoResponse;
+
+    fn is_retriable_error(&self, _error: &Self::Error) -> bool {
+        true
+    }
+
+    fn should_retry_response(&self, response: &InfinoResponse) -> RetryAction {
+        let describe = |reason: Cow<'static, str>| {
+            let reason = self
+                .diagnostics
+                .describe(&response.http_response, reason.into_owned());
+            Cow::Owned(reason)
+        };
+        match self.retry_action(response) {
+            RetryAction::Retry(reason) => RetryAction::Retry(describe(reason)),
+            RetryAction::DontRetry(reason) => RetryAction::DontRetry(describe(reason)),
+            RetryAction::Successful => RetryAction::Successful,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::Response;
+    use similar_asserts::assert_eq;
+    use vector_lib::{internal_event::CountByteSize, json_size::JsonSize};
+
+    use super::*;
+    use crate::event::EventStatus;
+
+    #[test]
+    fn handles_error_response() {
+        let json = "{\"took\":185,\"errors\":true,\"items\":[{\"index\":{\"_index\":\"test-hgw28jv10u\",\"_type\":\"log_lines\",\"_id\":\"3GhQLXEBE62DvOOUKdFH\",\"status\":400,\"error\":{\"type\":\"illegal_argument_exception\",\"reason\":\"mapper [message] of different type, current_type [long], merged_type [text]\"}}}]}";
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from(json))
+            .unwrap();
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        assert!(matches!(
+            logic.should_retry_response(&InfinoResponse {
+                http_response: response,
+                event_status: EventStatus::Rejected,
+                batch_size: 1,
+                events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+            }),
+            RetryAction::DontRetry(_)
+        ));
+    }
+
+    #[test]
+    fn handles_partial_error_response() {
+        let json = "{\"took\":34,\"errors\":true,\"items\":[{\"index\":{\"_index\":\"test-asjkf1234\",\"_type\":\"log_lines\",\"_id\":\"4Z3QLYEBT52RtoOEKz2H\",\"status\":429}}]}";
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from(json))
+            .unwrap();
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        assert!(matches!(
+            logic.should_retry_response(&InfinoResponse {
+                http_response: response,
+                event_status: EventStatus::Errored,
+                batch_size: 1,
+                events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+            }),
+            RetryAction::DontRetry(_)
+        ));
+    }
+
+    #[test]
+    fn retries_backpressure_with_adaptive_concurrency() {
+        let response = |json: &'static str| InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::OK)
+                .body(Bytes::from(json))
+                .unwrap(),
+            event_status: EventStatus::Errored,
+            batch_size: 2,
+            events_byte_size: CountByteSize(2, JsonSize::new(2)).into(),
+        };
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: true,
+        };
+
+        // Some documents were written, so the request is not sent again as a whole.
+        let json = "{\"took\":34,\"errors\":true,\"items\":[{\"index\":{\"_index\":\"test-asjkf1234\",\"_id\":\"4Z3QLYEBT52RtoOEKz2G\",\"status\":201}},{\"index\":{\"_index\":\"test-asjkf1234\",\"_type\":\"log_lines\",\"_id\":\"4Z3QLYEBT52RtoOEKz2H\",\"status\":429}}]}";
+        assert!(matches!(
+            logic.should_retry_response(&response(json)),
+            RetryAction::DontRetry(_)
+        ));
+
+        // The whole request was pushed back, which is back pressure.
+        let json = "{\"took\":34,\"errors\":true,\"items\":[{\"index\":{\"_index\":\"test-asjkf1234\",\"_type\":\"log_lines\",\"_id\":\"4Z3QLYEBT52RtoOEKz2H\",\"status\":429}}]}";
+        assert!(matches!(
+            logic.should_retry_response(&response(json)),
+            RetryAction::Retry(_)
+        ));
+    }
+
+    #[test]
+    fn redacts_error_response() {
+        let json = r#"{"error":"security_exception","request":{"headers":{"authorization":"Basic dXNlcjpodW50ZXIy"}}}"#;
+        let response = Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Bytes::from(json))
+            .unwrap();
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        match logic.should_retry_response(&InfinoResponse {
+            http_response: response,
+            event_status: EventStatus::Rejected,
+            batch_size: 1,
+            events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+        }) {
+            RetryAction::DontRetry(reason) => {
+                assert!(reason.contains("security_exception"));
+                assert!(!reason.contains("dXNlcjpodW50ZXIy"));
+            }
+            _ => panic!("expected the request not to be retried"),
+        }
+    }
+
+    #[test]
+    fn bounds_error_bodies() {
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: ResponseDiagnostics {
+                body_bytes: 16,
+                ..Default::default()
+            },
+            backpressure: false,
+        };
+        match logic.should_retry_response(&InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Bytes::from("x".repeat(1024)))
+                .unwrap(),
+            event_status: EventStatus::Errored,
+            batch_size: 1,
+            events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+        }) {
+            RetryAction::Retry(reason) => assert_eq!(
+                reason,
+                format!(
+                    "503 Service Unavailable: {}... (1008 bytes omitted)",
+                    "x".repeat(16)
+                )
+            ),
+            _ => panic!("expected the request to be retried"),
+        }
+    }
+
+    #[test]
+    fn correlates_request_ids() {
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        match logic.should_retry_response(&InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("X-Request-Id", "4bf92f3577b34da6")
+                .body(Bytes::from("invalid"))
+                .unwrap(),
+            event_status: EventStatus::Rejected,
+            batch_size: 1,
+            events_byte_size: CountByteSize(1, JsonSize::new(1)).into(),
+        }) {
+            RetryAction::DontRetry(reason) => assert_eq!(
+                reason,
+                "client-side error, 400 Bad Request: invalid (request ID: 4bf92f3577b34da6)"
+            ),
+            _ => panic!("expected the request not to be retried"),
+        }
+    }
+
+    #[test]
+    fn handles_conflicts_when_ignored() {
+        let json = "{\"took\":3,\"errors\":true,\"items\":[{\"create\":{\"_index\":\"test\",\"_id\":\"a1\",\"status\":201}},{\"create\":{\"_index\":\"test\",\"_id\":\"b2\",\"status\":409,\"error\":{\"type\":\"version_conflict_engine_exception\",\"reason\":\"[b2]: version conflict, document already exists\"}}}]}";
+        let response = || InfinoResponse {
+            http_response: Response::builder()
+                .status(StatusCode::OK)
+                .body(Bytes::from(json))
+                .unwrap(),
+            event_status: EventStatus::Delivered,
+            batch_size: 2,
+            events_byte_size: CountByteSize(2, JsonSize::new(2)).into(),
+        };
+
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: true,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        assert!(matches!(
+            logic.should_retry_response(&response()),
+            RetryAction::Successful
+        ));
+
+        let logic = InfinoRetryLogic {
+            ignore_conflicts: false,
+            diagnostics: Default::default(),
+            backpressure: false,
+        };
+        assert!(matches!(
+            logic.should_retry_response(&response()),
+            RetryAction::DontRetry(_)
+        ));
+    }
+
+    #[test]
+    fn get_index_error_reason() {
+        let json = "{\"took\":185,\"errors\":true,\"items\":[{\"index\":{\"_index\":\"test-hgw28jv10u\",\"_type\":\"log_lines\",\"_id\":\"3GhQLXEBE62DvOOUKdFH\",\"status\":400,\"error\":{\"type\":\"illegal_argument_exception\",\"reason\":\"mapper [message] of different type, current_type [long], merged_type [text]\"}}}]}";
+        let reason = match InfinoResultResponse::parse(json) {
+            Ok(resp) => resp.get_error_reason(json),
+            Err(msg) => msg,
+        };
+        assert_eq!(reason, "error type: illegal_argument_exception, reason: mapper [message] of different type, current_type [long], merged_type [text]");
+    }
+
+    #[test]
+    fn get_create_error_reason() {
+        let json = "{\"took\":3,\"errors\":true,\"items\":[{\"create\":{\"_index\":\"test-hgw28jv10u\",\"_type\":\"_doc\",\"_id\":\"aBLq1HcBWD7eBWkW2nj4\",\"status\":400,\"error\":{\"type\":\"mapper_parsing_exception\",\"reason\":\"object mapping for [host] tried to parse field [host] as object, but found a concrete value\"}}}]}";
+        let reason = match InfinoResultResponse::parse(json) {
+            Ok(resp) => resp.get_error_reason(json),
+            Err(msg) => msg,
+        };
+        assert_eq!(reason, "error type: mapper_parsing_exception, reason: object mapping for [host] tried to parse field [host] as object, but found a concrete value");
+    }
+}
@@ -0,0 +1,604 @@
+//! Disk-backed retry queue.
+//!
+//! Requests that are still failing once their retries are exhausted are written to disk, and their
+//! events are acknowledged, so that the backlog of an outage survives restarts of Vector. The
+//! queued requests are sent again in the order they were queued, until they are delivered or
+//! expire, including those queued before Vector restarted.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use futures::{future::BoxFuture, FutureExt};
+use http::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tower::{buffer::Buffer, Layer, Service, ServiceExt};
+use vector_lib::{configurable::configurable_component, request_metadata::GroupedCountByteSize};
+
+use super::service::{InfinoRequest, InfinoResponse};
+use crate::{
+    event::EventStatus,
+    internal_events::{
+        InfinoRetryQueueExpired, InfinoRetryQueueFull, InfinoRetryQueueReadError,
+        InfinoRetryQueueRejected, InfinoRetryQueued,
+    },
+};
+
+/// The number of requests waiting to be sent to the inner service.
+const BUFFER_BOUND: usize = 64;
+
+/// Disk-backed retry queue configuration.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RetryQueueConfig {
+    /// The directory queued requests are written to.
+    ///
+    /// It must not be shared with another sink.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/infino-retry-queue"))]
+    pub path: PathBuf,
+
+    /// The maximum size of the queued requests, in bytes.
+    ///
+    /// Requests failing while the queue is full are not queued, and their events are not
+    /// acknowledged.
+    #[serde(default = "default_max_bytes")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub max_bytes: u64,
+
+    /// How long requests are kept in the queue, in seconds.
+    ///
+    /// Requests that are not delivered by then are dropped.
+    #[serde(default = "default_ttl_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub ttl_secs: u64,
+
+    /// The interval between attempts to send the queued requests again, in seconds.
+    #[serde(default = "default_replay_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub replay_interval_secs: u64,
+}
+
+const fn default_max_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+const fn default_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+const fn default_replay_interval_secs() -> u64 {
+    30
+}
+
+/// The part of a request that is written along with its payload.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct QueuedRequest {
+    batch_size: usize,
+    indexes: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    query: Vec<(String, String)>,
+}
+
+/// The queued requests, one file each, named after the time they were queued and the number of
+/// their events, so that the events are accounted for even if the file cannot be read.
+struct Queue {
+    path: PathBuf,
+    max_bytes: u64,
+    ttl: Duration,
+    bytes: AtomicU64,
+    sequence: AtomicU64,
+}
+
+impl Queue {
+    fn open(config: &RetryQueueConfig) -> crate::Result<Self> {
+        std::fs::create_dir_all(&config.path).map_err(|error| {
+            format!(
+                "Unable to create the retry queue directory {:?}: {}",
+                config.path, error
+            )
+        })?;
+        let queue = Self {
+            path: config.path.clone(),
+            max_bytes: config.max_bytes,
+            ttl: Duration::from_secs(config.ttl_secs),
+            bytes: AtomicU64::new(0),
+            sequence: AtomicU64::new(0),
+        };
+        // Requests that were being written when Vector stopped were never acknowledged.
+        for entry in std::fs::read_dir(&queue.path)?.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .map_or(false, |extension| extension == "partial")
+            {
+                _ = std::fs::remove_file(path);
+            }
+        }
+        let bytes = queue
+            .files()?
+            .iter()
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        queue.bytes.store(bytes, Ordering::Relaxed);
+        Ok(queue)
+    }
+
+    /// Returns the queued files, oldest first.
+    fn files(&self) -> std::io::Result<Vec<PathBuf>> {
+        let mut files = std::fs::read_dir(&self.path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |extension| extension == "batch")
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Writes `payload` to the queue, unless it is full.
+    async fn push(&self, request: &QueuedRequest, payload: &Bytes) -> crate::Result<bool> {
+        let mut contents = serde_json::to_vec(request)?;
+        contents.push(b'\n');
+        contents.extend_from_slice(payload);
+
+        // Reserved before writing, so that concurrent requests cannot overfill the queue.
+        let size = contents.len() as u64;
+        let reserved = self
+            .bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bytes| {
+                bytes
+                    .checked_add(size)
+                    .filter(|bytes| *bytes <= self.max_bytes)
+            })
+            .is_ok();
+        if !reserved {
+            return Ok(false);
+        }
+
+        let result = self.write(request.batch_size, &contents).await;
+        if result.is_err() {
+            self.bytes.fetch_sub(size, Ordering::Relaxed);
+        }
+        result.map(|()| true)
+    }
+
+    async fn write(&self, count: usize, contents: &[u8]) -> crate::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{:020}-{:010}-{}", now, sequence, count);
+        // Written aside first, so that a crash never leaves a partial request in the queue.
+        let partial = self.path.join(format!("{}.partial", name));
+        tokio::fs::write(&partial, contents).await?;
+        if let Err(error) =
+            tokio::fs::rename(&partial, self.path.join(format!("{}.batch", name))).await
+        {
+            _ = tokio::fs::remove_file(&partial).await;
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, file: &Path) {
+        if let Ok(metadata) = tokio::fs::metadata(file).await {
+            if tokio::fs::remove_file(file).await.is_ok() {
+                self.bytes.fetch_sub(metadata.len(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns whether the request in `file` was queued longer than the TTL ago.
+    fn is_expired(&self, file: &Path) -> bool {
+        let queued_at = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split('-').next())
+            .and_then(|millis| millis.parse::<u64>().ok())
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis));
+        match queued_at {
+            Some(queued_at) => SystemTime::now()
+                .duration_since(queued_at)
+                .map_or(false, |age| age > self.ttl),
+            None => true,
+        }
+    }
+}
+
+/// Returns the number of events of the request in `file`, or zero if its name does not tell.
+fn queued_count(file: &Path) -> usize {
+    file.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.split('-').nth(2))
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+async fn read(file: &Path) -> crate::Result<(QueuedRequest, Bytes)> {
+    let contents = Bytes::from(tokio::fs::read(file).await?);
+    let newline = contents
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or("queued request has no header")?;
+    let request = serde_json::from_slice(&contents[..newline])?;
+    Ok((request, contents.slice(newline + 1..)))
+}
+
+/// Sends the queued requests again, until the first one that fails.
+async fn replay<S>(queue: &Queue, service: &mut S)
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+{
+    let Ok(files) = queue.files() else {
+        return;
+    };
+    for file in files {
+        if queue.is_expired(&file) {
+            queue.remove(&file).await;
+            emit!(InfinoRetryQueueExpired {
+                count: queued_count(&file),
+            });
+            continue;
+        }
+        let (queued, payload) = match read(&file).await {
+            Ok(request) => request,
+            Err(error) => {
+                queue.remove(&file).await;
+                emit!(InfinoRetryQueueReadError {
+                    count: queued_count(&file),
+                    error,
+                });
+                continue;
+            }
+        };
+        let batch_size = queued.batch_size;
+        let request = InfinoRequest {
+            payload,
+            finalizers: Default::default(),
+            batch_size,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: queued.indexes,
+            lane: Default::default(),
+            encryption_key: queued.encryption_key,
+            headers: queued.headers,
+            query: queued.query,
+            metadata: Default::default(),
+        };
+        let result = match service.ready().await {
+            Ok(service) => service.call(request).await,
+            Err(error) => Err(error),
+        };
+        match result.map(|response| response.event_status) {
+            Ok(EventStatus::Errored) | Err(_) => return,
+            // Rejected requests would be rejected again.
+            Ok(EventStatus::Rejected) => {
+                queue.remove(&file).await;
+                emit!(InfinoRetryQueueRejected { count: batch_size });
+            }
+            Ok(_) => queue.remove(&file).await,
+        }
+    }
+}
+
+pub struct RetryQueueLayer {
+    queue: Arc<Queue>,
+    replay_interval: Duration,
+}
+
+impl RetryQueueLayer {
+    pub fn new(config: &RetryQueueConfig) -> crate::Result<Self> {
+        if config.replay_interval_secs == 0 {
+            return Err("`retry_queue.replay_interval_secs` must be greater than zero".into());
+        }
+        Ok(Self {
+            queue: Arc::new(Queue::open(config)?),
+            replay_interval: Duration::from_secs(config.replay_interval_secs),
+        })
+    }
+}
+
+impl<S> Layer<S> for RetryQueueLayer
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Service = RetryQueueService<S>;
+
+    /// Wraps `inner`, and starts sending the queued requests again in the background.
+    fn layer(&self, inner: S) -> Self::Service {
+        let inner = Buffer::new(inner, BUFFER_BOUND);
+        let (stop, mut stopped) = oneshot::channel::<()>();
+
+        let mut replayed = inner.clone();
+        let queue = Arc::clone(&self.queue);
+        let mut interval = tokio::time::interval(self.replay_interval);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => break,
+                    _ = interval.tick() => replay(&queue, &mut replayed).await,
+                }
+            }
+        });
+
+        RetryQueueService {
+            inner,
+            queue: Arc::clone(&self.queue),
+            _stop: stop,
+        }
+    }
+}
+
+pub struct RetryQueueService<S>
+where
+    S: Service<InfinoRequest>,
+{
+    inner: Buffer<S, InfinoRequest>,
+    queue: Arc<Queue>,
+    /// Stops the replay of the queued requests once the service is dropped.
+    _stop: oneshot::Sender<()>,
+}
+
+impl<S> Service<InfinoRequest> for RetryQueueService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        let queue = Arc::clone(&self.queue);
+        let payload = req.payload.clone();
+        let queued = QueuedRequest {
+            batch_size: req.batch_size,
+            indexes: req.indexes.clone(),
+            encryption_key: req.encryption_key.clone(),
+            headers: req.headers.clone(),
+            query: req.query.clone(),
+        };
+        let future = self.inner.call(req);
+        async move {
+            let result = future.await;
+            let failed = match &result {
+                Ok(response) => response.event_status == EventStatus::Errored,
+                Err(_) => true,
+            };
+            if !failed {
+                return result;
+            }
+            match queue.push(&queued, &payload).await {
+                Ok(true) => {
+                    emit!(InfinoRetryQueued {
+                        count: queued.batch_size,
+                        byte_size: payload.len(),
+                    });
+                    Ok(InfinoResponse {
+                        http_response: Response::builder()
+                            .status(StatusCode::ACCEPTED)
+                            .body(Bytes::new())
+                            .expect("valid response"),
+                        event_status: EventStatus::Delivered,
+                        batch_size: queued.batch_size,
+                        events_byte_size: GroupedCountByteSize::new_untagged(),
+                    })
+                }
+                Ok(false) => {
+                    emit!(InfinoRetryQueueFull {
+                        count: queued.batch_size,
+                    });
+                    result
+                }
+                Err(error) => {
+                    warn!(
+                        message = "Unable to write request to the retry queue.",
+                        %error,
+                        internal_log_rate_limit = true,
+                    );
+                    result
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use futures::future;
+    use tower::service_fn;
+
+    use super::*;
+
+    fn config(path: &Path) -> RetryQueueConfig {
+        RetryQueueConfig {
+            path: path.to_owned(),
+            max_bytes: default_max_bytes(),
+            ttl_secs: default_ttl_secs(),
+            replay_interval_secs: 3600,
+        }
+    }
+
+    fn request(payload: &'static str) -> InfinoRequest {
+        InfinoRequest {
+            payload: Bytes::from_static(payload.as_bytes()),
+            finalizers: Default::default(),
+            batch_size: 2,
+            events_byte_size: Default::default(),
+            partition_key: None,
+            indexes: BTreeMap::from([("logs".to_owned(), 2)]),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn response(status: EventStatus) -> InfinoResponse {
+        InfinoResponse {
+            http_response: Response::new(Bytes::new()),
+            event_status: status,
+            batch_size: 2,
+            events_byte_size: GroupedCountByteSize::new_untagged(),
+        }
+    }
+
+    #[tokio::test]
+    async fn queues_failed_requests_and_replays_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let up = Arc::new(AtomicBool::new(false));
+        let inner = {
+            let up = Arc::clone(&up);
+            service_fn(move |_: InfinoRequest| {
+                let status = if up.load(Ordering::Relaxed) {
+                    EventStatus::Delivered
+                } else {
+                    EventStatus::Errored
+                };
+                future::ok::<_, crate::Error>(response(status))
+            })
+        };
+
+        let layer = RetryQueueLayer::new(&config(dir.path())).unwrap();
+        let mut service = layer.layer(inner);
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request("{\"index\":{}}\n{}\n"))
+            .await
+            .unwrap();
+        assert_eq!(response.event_status, EventStatus::Delivered);
+
+        let queue = &layer.queue;
+        let files = queue.files().unwrap();
+        assert_eq!(files.len(), 1);
+        let (queued, payload) = read(&files[0]).await.unwrap();
+        assert_eq!(queued.batch_size, 2);
+        assert_eq!(payload, "{\"index\":{}}\n{}\n");
+
+        // Requests stay queued while the endpoints are down.
+        replay(queue, &mut service.inner.clone()).await;
+        assert_eq!(queue.files().unwrap().len(), 1);
+
+        up.store(true, Ordering::Relaxed);
+        replay(queue, &mut service.inner.clone()).await;
+        assert!(queue.files().unwrap().is_empty());
+        assert_eq!(queue.bytes.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn bounds_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Queue::open(&RetryQueueConfig {
+            max_bytes: 64,
+            ..config(dir.path())
+        })
+        .unwrap();
+        let request = QueuedRequest {
+            batch_size: 1,
+            indexes: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        };
+        assert!(queue
+            .push(&request, &Bytes::from(vec![b'x'; 16]))
+            .await
+            .unwrap());
+        assert!(!queue
+            .push(&request, &Bytes::from(vec![b'x'; 32]))
+            .await
+            .unwrap());
+
+        // The size of the queue is restored when it is opened again.
+        let reopened = Queue::open(&config(dir.path())).unwrap();
+        assert_eq!(
+            reopened.bytes.load(Ordering::Relaxed),
+            queue.bytes.load(Ordering::Relaxed)
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_rejected_and_unreadable_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Queue::open(&config(dir.path())).unwrap();
+        let request = QueuedRequest {
+            batch_size: 3,
+            indexes: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        };
+        assert!(queue.push(&request, &Bytes::from("{}\n")).await.unwrap());
+        let files = queue.files().unwrap();
+        assert_eq!(queued_count(&files[0]), 3);
+
+        // The number of events is kept in the name, in case the contents cannot be read.
+        std::fs::write(&files[0], "not a request").unwrap();
+        assert_eq!(queued_count(&files[0]), 3);
+        let mut inner = service_fn(|_: InfinoRequest| {
+            future::ok::<_, crate::Error>(response(EventStatus::Rejected))
+        });
+        replay(&queue, &mut inner).await;
+        assert!(queue.files().unwrap().is_empty());
+
+        assert!(queue.push(&request, &Bytes::from("{}\n")).await.unwrap());
+        replay(&queue, &mut inner).await;
+        assert!(queue.files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn removes_partial_requests_when_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let partial = dir.path().join("00000000000000000000-0000000000-1.partial");
+        std::fs::write(&partial, "{}").unwrap();
+
+        let queue = Queue::open(&config(dir.path())).unwrap();
+        assert!(!partial.exists());
+        assert_eq!(queue.bytes.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn expires_old_requests() {
+        let queue = Queue {
+            path: PathBuf::new(),
+            max_bytes: 0,
+            ttl: Duration::from_secs(60),
+            bytes: AtomicU64::new(0),
+            sequence: AtomicU64::new(0),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        assert!(!queue.is_expired(Path::new(&format!("{:020}-0000000000.batch", now))));
+        assert!(queue.is_expired(Path::new(&format!(
+            "{:020}-0000000000.batch",
+            now - 120_000
+        ))));
+    }
+}
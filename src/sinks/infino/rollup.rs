@@ -0,0 +1,181 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    num::NonZeroU64,
+    time::Duration,
+};
+
+use async_stream::stream;
+use futures::{stream::BoxStream, StreamExt};
+use vector_lib::configurable::configurable_component;
+
+use crate::event::{
+    metric::{MetricData, MetricKind, MetricSeries},
+    Event, EventMetadata, Metric,
+};
+
+/// Metric rollup configuration.
+///
+/// Merges the points of each metric series received within a window into a single point before
+/// sending it: incremental metrics are added together, and only the latest value of absolute
+/// metrics is kept. This greatly reduces the number of points written for high-frequency
+/// emitters. Logs are not delayed.
+#[configurable_component]
+#[derive(Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RollupConfig {
+    /// The duration of the window points are merged in.
+    #[serde(default = "default_window_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub window_secs: NonZeroU64,
+}
+
+const fn default_window_secs() -> NonZeroU64 {
+    match NonZeroU64::new(10) {
+        Some(window) => window,
+        None => unreachable!(),
+    }
+}
+
+#[derive(Default)]
+struct Rollup {
+    series: HashMap<MetricSeries, (MetricData, EventMetadata)>,
+}
+
+impl Rollup {
+    /// Merges `metric` into its series, returning the previous point of the series if it could
+    /// not be merged with it.
+    fn record(&mut self, metric: Metric) -> Option<Metric> {
+        let (series, data, metadata) = metric.into_parts();
+        match self.series.entry(series) {
+            Entry::Occupied(mut entry) => {
+                let existing = entry.get_mut();
+                let incremental = data.kind == MetricKind::Incremental;
+                if existing.0.kind == data.kind && (!incremental || existing.0.update(&data)) {
+                    if !incremental {
+                        existing.0 = data;
+                    }
+                    // Keep the finalizers of every point until the merged point is delivered.
+                    existing.1.merge(metadata);
+                    None
+                } else {
+                    let (data, metadata) = std::mem::replace(existing, (data, metadata));
+                    Some(Metric::from_parts(entry.key().clone(), data, metadata))
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((data, metadata));
+                None
+            }
+        }
+    }
+
+    fn flush_into(&mut self, output: &mut Vec<Event>) {
+        output.extend(
+            self.series.drain().map(|(series, (data, metadata))| {
+                Metric::from_parts(series, data, metadata).into()
+            }),
+        );
+    }
+}
+
+/// Rolls up the metrics of `input` over windows of `window`, passing other events through.
+pub fn rollup(mut input: BoxStream<'_, Event>, window: Duration) -> BoxStream<'_, Event> {
+    Box::pin(stream! {
+        let mut rollup = Rollup::default();
+        let mut ticks = tokio::time::interval(window);
+        // The first tick completes immediately.
+        ticks.tick().await;
+
+        let mut output = Vec::new();
+        let mut done = false;
+        while !done {
+            tokio::select! {
+                _ = ticks.tick() => rollup.flush_into(&mut output),
+                event = input.next() => match event {
+                    Some(Event::Metric(metric)) => {
+                        output.extend(rollup.record(metric).map(Event::from));
+                    }
+                    Some(event) => output.push(event),
+                    None => {
+                        rollup.flush_into(&mut output);
+                        done = true;
+                    }
+                },
+            }
+            for event in output.drain(..) {
+                yield event;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::event::{LogEvent, MetricValue};
+
+    fn counter(name: &str, value: f64) -> Event {
+        Metric::new(
+            name,
+            MetricKind::Incremental,
+            MetricValue::Counter { value },
+        )
+        .into()
+    }
+
+    fn gauge(value: f64) -> Event {
+        Metric::new("memory", MetricKind::Absolute, MetricValue::Gauge { value }).into()
+    }
+
+    #[tokio::test]
+    async fn merges_points_per_series() {
+        let input = stream::iter(vec![
+            counter("requests", 1.0),
+            gauge(10.0),
+            Event::from(LogEvent::from("passes through")),
+            counter("requests", 2.0),
+            counter("errors", 1.0),
+            gauge(20.0),
+        ])
+        .boxed();
+
+        let output = rollup(input, Duration::from_secs(60))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(output.len(), 4);
+        assert!(output[0].as_log().get_message().is_some());
+
+        let value = |name: &str| {
+            output
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Metric(metric) => Some(metric),
+                    _ => None,
+                })
+                .find(|metric| metric.name() == name)
+                .map(|metric| metric.value().clone())
+                .unwrap()
+        };
+        assert_eq!(value("requests"), MetricValue::Counter { value: 3.0 });
+        assert_eq!(value("errors"), MetricValue::Counter { value: 1.0 });
+        assert_eq!(value("memory"), MetricValue::Gauge { value: 20.0 });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_every_window() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut output = rollup(rx.boxed(), Duration::from_secs(10));
+
+        tx.unbounded_send(counter("requests", 1.0)).unwrap();
+        tx.unbounded_send(counter("requests", 1.0)).unwrap();
+        let started = tokio::time::Instant::now();
+        let event = output.next().await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(10));
+        assert_eq!(
+            event.as_metric().value(),
+            &MetricValue::Counter { value: 2.0 }
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs::OpenOptions,
+    num::NonZeroU64,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use tower::{Layer, Service};
+use vector_lib::configurable::configurable_component;
+
+use super::{
+    redact::redact,
+    service::{InfinoRequest, InfinoResponse},
+};
+
+/// Request sampling configuration.
+///
+/// Captures a sample of the request bodies sent to Infino, along with a summary of the response,
+/// and appends them as newline-delimited JSON to a local file. This is intended for diagnosing
+/// encoding issues in production and should not be left enabled permanently.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RequestSampleConfig {
+    /// The file that sampled requests are appended to.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/infino-requests.ndjson"))]
+    pub path: PathBuf,
+
+    /// Sample one out of every `rate` requests.
+    #[serde(default = "default_rate")]
+    #[configurable(metadata(docs::examples = 100))]
+    pub rate: NonZeroU64,
+
+    /// The maximum number of bytes of each request and response body to record.
+    ///
+    /// Bodies larger than this are truncated.
+    #[serde(default = "default_max_body_bytes")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub max_body_bytes: usize,
+}
+
+const fn default_rate() -> NonZeroU64 {
+    match NonZeroU64::new(100) {
+        Some(rate) => rate,
+        None => unreachable!(),
+    }
+}
+
+const fn default_max_body_bytes() -> usize {
+    16 * 1024
+}
+
+#[derive(Serialize)]
+struct SampleRecord<'a> {
+    timestamp: String,
+    batch_size: usize,
+    request_bytes: usize,
+    request_body: String,
+    /// `utf8` when the body was recorded as text, `base64` when it was compressed.
+    request_body_encoding: &'static str,
+    request_truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Decides which requests are sampled and writes them out.
+#[derive(Debug)]
+pub struct RequestSampler {
+    rate: u64,
+    max_body_bytes: usize,
+    compressed: bool,
+    seen: AtomicU64,
+    file: Mutex<File>,
+}
+
+impl RequestSampler {
+    pub fn new(config: &RequestSampleConfig, compressed: bool) -> crate::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)
+            .map_err(|error| {
+                format!(
+                    "Unable to open request sample file {:?}: {}",
+                    config.path, error
+                )
+            })?;
+
+        Ok(Self {
+            rate: config.rate.get(),
+            max_body_bytes: config.max_body_bytes,
+            compressed,
+            seen: AtomicU64::new(0),
+            file: Mutex::new(File::from_std(file)),
+        })
+    }
+
+    fn should_sample(&self) -> bool {
+        self.seen.fetch_add(1, Ordering::Relaxed) % self.rate == 0
+    }
+
+    fn truncate<'a>(&self, body: &'a [u8]) -> (&'a [u8], bool) {
+        if body.len() > self.max_body_bytes {
+            (&body[..self.max_body_bytes], true)
+        } else {
+            (body, false)
+        }
+    }
+
+    async fn record(
+        &self,
+        batch_size: usize,
+        payload: &Bytes,
+        response: &Result<InfinoResponse, crate::Error>,
+    ) {
+        let (body, request_truncated) = self.truncate(payload);
+        let (request_body, request_body_encoding) = if self.compressed {
+            (BASE64_STANDARD.encode(body), "base64")
+        } else {
+            (String::from_utf8_lossy(body).into_owned(), "utf8")
+        };
+
+        let error = response
+            .as_ref()
+            .err()
+            .map(|error| redact(&error.to_string()).into_owned());
+        let (status, response_body) = match response {
+            Ok(response) => {
+                let (body, _) = self.truncate(response.http_response.body());
+                (
+                    Some(response.http_response.status().as_u16()),
+                    Some(redact(&String::from_utf8_lossy(body)).into_owned()),
+                )
+            }
+            Err(_) => (None, None),
+        };
+
+        let record = SampleRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            batch_size,
+            request_bytes: payload.len(),
+            request_body,
+            request_body_encoding,
+            request_truncated,
+            status,
+            response_body,
+            error: error.as_deref(),
+        };
+
+        let mut line = match serde_json::to_vec(&record) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!(
+                    message = "Failed to encode request sample.",
+                    %error,
+                    internal_log_rate_limit = true
+                );
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        let result = async {
+            file.write_all(&line).await?;
+            file.flush().await
+        }
+        .await;
+        if let Err(error) = result {
+            warn!(
+                message = "Failed to write request sample.",
+                %error,
+                internal_log_rate_limit = true
+            );
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestSampleLayer {
+    sampler: Arc<RequestSampler>,
+}
+
+impl RequestSampleLayer {
+    pub const fn new(sampler: Arc<RequestSampler>) -> Self {
+        Self { sampler }
+    }
+}
+
+impl<S> Layer<S> for RequestSampleLayer {
+    type Service = RequestSampleService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestSampleService {
+            sampler: Arc::clone(&self.sampler),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestSampleService<S> {
+    sampler: Arc<RequestSampler>,
+    inner: S,
+}
+
+impl<S> Service<InfinoRequest> for RequestSampleService<S>
+where
+    S: Service<InfinoRequest, Response = InfinoResponse, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = InfinoResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: InfinoRequest) -> Self::Future {
+        if !self.sampler.should_sample() {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let sampler = Arc::clone(&self.sampler);
+        let batch_size = req.batch_size;
+        let payload = req.payload.clone();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let response = future.await;
+            sampler.record(batch_size, &payload, &response).await;
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(rate: u64, max_body_bytes: usize) -> (RequestSampler, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RequestSampleConfig {
+            path: dir.path().join("samples.ndjson"),
+            rate: NonZeroU64::new(rate).unwrap(),
+            max_body_bytes,
+        };
+        (RequestSampler::new(&config, false).unwrap(), dir)
+    }
+
+    #[test]
+    fn samples_one_in_rate() {
+        let (sampler, _dir) = sampler(3, 16);
+        let sampled = (0..9).filter(|_| sampler.should_sample()).count();
+        assert_eq!(sampled, 3);
+    }
+
+    #[tokio::test]
+    async fn records_truncated_body() {
+        let (sampler, dir) = sampler(1, 4);
+        sampler
+            .record(
+                2,
+                &Bytes::from_static(b"abcdefgh"),
+                &Err("connection reset".into()),
+            )
+            .await;
+
+        let contents = std::fs::read_to_string(dir.path().join("samples.ndjson")).unwrap();
+        let record: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(record["request_body"], "abcd");
+        assert_eq!(record["request_truncated"], true);
+        assert_eq!(record["request_bytes"], 8);
+        assert_eq!(record["error"], "connection reset");
+        assert!(record.get("status").is_none());
+    }
+}
@@ -0,0 +1,227 @@
+//! Self-monitoring.
+//!
+//! The internal metrics and logs of the sink are written to an index of Infino, along with the
+//! events it receives, so that the health of the sink can be monitored without an
+//! `internal_metrics` and `internal_logs` pipeline. Only the metrics and logs of this sink are
+//! written, which are found by the `component_id` of the span the sink is built in.
+
+use std::{num::NonZeroU64, time::Duration};
+
+use chrono::Utc;
+use futures::{future, stream, Stream, StreamExt};
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use vector_lib::{configurable::configurable_component, lookup::event_path};
+use vrl::value::Value;
+
+use super::metric::{split_aggregates, MetricEncoder};
+use crate::{
+    event::{LogEvent, Metric},
+    metrics::Controller,
+    trace::{current_component_field, TraceSubscription},
+};
+
+/// Self-monitoring configuration.
+///
+/// As the logs of the sink are written by the sink, errors writing them are logged, and written
+/// again. Logs are rate limited, which bounds this loop, but the index should be allowed by
+/// `allowed_indexes`, and be writable, to avoid it.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SelfMonitoringConfig {
+    /// The index the internal metrics and logs of the sink are written to.
+    #[configurable(metadata(docs::examples = "vector-self-monitoring"))]
+    pub index: String,
+
+    /// The interval between captures of the internal metrics of the sink, in seconds.
+    #[serde(default = "default_scrape_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub scrape_interval_secs: NonZeroU64,
+
+    /// Whether the internal metrics of the sink are written.
+    #[serde(default = "crate::serde::default_true")]
+    pub metrics: bool,
+
+    /// Whether the internal logs of the sink are written.
+    #[serde(default = "crate::serde::default_true")]
+    pub logs: bool,
+}
+
+const fn default_scrape_interval_secs() -> NonZeroU64 {
+    match NonZeroU64::new(15) {
+        Some(secs) => secs,
+        None => unreachable!(),
+    }
+}
+
+/// Which components the internal metrics and logs are written for.
+#[derive(Clone, Debug, PartialEq)]
+enum Component {
+    /// The sink, known by its ID.
+    Id(String),
+    /// The sinks of this type, when the ID of the sink is not known.
+    Type(&'static str),
+}
+
+impl Component {
+    fn matches_metric(&self, metric: &Metric) -> bool {
+        match self {
+            Self::Id(id) => metric.tag_value("component_id").as_deref() == Some(id.as_str()),
+            Self::Type(component_type) => {
+                metric.tag_value("component_kind").as_deref() == Some("sink")
+                    && metric.tag_value("component_type").as_deref() == Some(*component_type)
+            }
+        }
+    }
+
+    fn matches_log(&self, log: &LogEvent) -> bool {
+        let field = |name| match log.get(event_path!("vector", name)) {
+            Some(Value::Bytes(value)) => Some(String::from_utf8_lossy(value).into_owned()),
+            _ => None,
+        };
+        match self {
+            Self::Id(id) => field("component_id").as_ref() == Some(id),
+            Self::Type(component_type) => {
+                field("component_kind").as_deref() == Some("sink")
+                    && field("component_type").as_deref() == Some(*component_type)
+            }
+        }
+    }
+}
+
+/// Produces the internal metrics and logs of the sink, as documents of the self-monitoring index.
+pub struct SelfMonitoring {
+    config: SelfMonitoringConfig,
+    component: Component,
+    controller: Option<&'static Controller>,
+    metric_encoder: MetricEncoder,
+}
+
+impl SelfMonitoring {
+    /// Returns the self-monitoring of the sink being built.
+    pub fn new(
+        config: &SelfMonitoringConfig,
+        metric_encoder: MetricEncoder,
+    ) -> crate::Result<Self> {
+        let component = match current_component_field("component_id") {
+            Some(Value::Bytes(id)) => Component::Id(String::from_utf8_lossy(&id).into_owned()),
+            _ => Component::Type("Infino"),
+        };
+        let controller =
+            if config.metrics {
+                Some(Controller::get().map_err(|error| {
+                    format!("Self-monitoring requires internal metrics: {}", error)
+                })?)
+            } else {
+                None
+            };
+        Ok(Self {
+            config: config.clone(),
+            component,
+            controller,
+            metric_encoder,
+        })
+    }
+
+    /// Returns the documents of the self-monitoring index, with the index they are written to.
+    pub fn events(self) -> impl Stream<Item = (LogEvent, Option<String>)> + Send + 'static {
+        let Self {
+            config,
+            component,
+            controller,
+            metric_encoder,
+        } = self;
+        let index = config.index;
+        let interval = Duration::from_secs(config.scrape_interval_secs.get());
+
+        let metrics = match controller {
+            Some(controller) => {
+                let component = component.clone();
+                IntervalStream::new(time::interval(interval))
+                    .flat_map(move |_| {
+                        let logs = controller
+                            .capture_metrics()
+                            .into_iter()
+                            .filter(|metric| component.matches_metric(metric))
+                            .flat_map(split_aggregates)
+                            .filter_map(|metric| metric_encoder.encode(metric))
+                            .collect::<Vec<_>>();
+                        stream::iter(logs)
+                    })
+                    .boxed()
+            }
+            None => stream::empty().boxed(),
+        };
+        let logs = if config.logs {
+            TraceSubscription::subscribe()
+                .into_stream()
+                .filter(move |log| future::ready(component.matches_log(log)))
+                .map(|mut log| {
+                    if !log.contains(event_path!("timestamp")) {
+                        log.insert(event_path!("timestamp"), Utc::now());
+                    }
+                    log
+                })
+                .boxed()
+        } else {
+            stream::empty().boxed()
+        };
+
+        stream::select(metrics, logs).map(move |log| (log, Some(index.clone())))
+    }
+}
+
+/// Merges the self-monitoring documents into `input`, until `input` ends.
+pub fn merge<'a, T: Send + 'a>(
+    input: impl Stream<Item = T> + Send + 'a,
+    monitoring: impl Stream<Item = T> + Send + 'a,
+) -> impl Stream<Item = T> + Send + 'a {
+    stream::select(
+        input.map(Some).chain(stream::once(future::ready(None))),
+        monitoring.map(Some),
+    )
+    .take_while(|item| future::ready(item.is_some()))
+    .filter_map(future::ready)
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_lib::metric_tags;
+
+    use super::*;
+    use crate::event::{MetricKind, MetricValue};
+
+    #[tokio::test]
+    async fn merges_until_input_ends() {
+        let merged = merge(stream::iter([1, 2, 3]), stream::repeat(0))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(merged.iter().filter(|item| **item != 0).count(), 3);
+    }
+
+    #[test]
+    fn matches_the_sink() {
+        let metric = |id: &str| {
+            Metric::new(
+                "component_sent_events_total",
+                MetricKind::Absolute,
+                MetricValue::Counter { value: 1.0 },
+            )
+            .with_tags(Some(metric_tags!(
+                "component_id" => id,
+                "component_kind" => "sink",
+                "component_type" => "Infino",
+            )))
+        };
+        let component = Component::Id("infino".to_owned());
+        assert!(component.matches_metric(&metric("infino")));
+        assert!(!component.matches_metric(&metric("other")));
+        assert!(Component::Type("Infino").matches_metric(&metric("other")));
+
+        let mut log = LogEvent::from("Request failed.");
+        log.insert(event_path!("vector", "component_id"), "infino");
+        assert!(component.matches_log(&log));
+        assert!(!Component::Id("other".to_owned()).matches_log(&log));
+    }
+}
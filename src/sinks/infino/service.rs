@@ -1,12 +1,12 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::Arc,
     task::{Context, Poll},
 };
 
 use bytes::Bytes;
 use futures::future::BoxFuture;
-use http::{Response, Uri};
+use http::{HeaderName, Response, StatusCode, Uri};
 use hyper::{service::Service, Body, Request};
 use tower::ServiceExt;
 use vector_lib::stream::DriverResponse;
@@ -16,10 +16,26 @@ use vector_lib::{
     request_metadata::{GroupedCountByteSize, MetaDescriptive, RequestMetadata},
 };
 
-use super::{InfinoCommon, InfinoConfig};
+use super::{
+    api_key::ApiKey,
+    cert_reload::CertReload,
+    checksum::checksum,
+    dispatch::Lane,
+    endpoint::host_header,
+    kerberos::Spnego,
+    oauth2::OAuth2,
+    presign::PresignedUrls,
+    query::with_query,
+    redact::{RedactedResponse, ResponseDiagnostics},
+    resolve::DnsRefresh,
+    retry::InfinoResultResponse,
+    sink::PartitionKey,
+    InfinoCommon, InfinoConfig,
+};
 use crate::{
     event::{EventFinalizers, EventStatus, Finalizable},
     http::HttpClient,
+    internal_events::InfinoBulkResponse,
     sinks::util::{
         auth::Auth,
         http::{HttpBatchService, RequestConfig},
@@ -30,9 +46,23 @@ use crate::{
 #[derive(Clone, Debug)]
 pub struct InfinoRequest {
     pub payload: Bytes,
-    pub finalizers: EventFinalizers,
+    /// The finalizers of each document of the payload, in order, which are acknowledged on their
+    /// own according to the outcome of their document.
+    pub finalizers: Vec<EventFinalizers>,
     pub batch_size: usize,
     pub events_byte_size: JsonSize,
+    /// The partition shared by every event of the request, if any.
+    pub partition_key: Option<PartitionKey>,
+    /// The distinct indexes the events of the request are written to, with their number of events.
+    pub indexes: BTreeMap<String, usize>,
+    /// The priority lane the request is dispatched in.
+    pub lane: Lane,
+    /// The key Infino encrypts the documents of the request with, if any.
+    pub encryption_key: Option<String>,
+    /// The headers rendered from the events of the request.
+    pub headers: Vec<(String, String)>,
+    /// The query parameters rendered from the events of the request.
+    pub query: Vec<(String, String)>,
     pub metadata: RequestMetadata,
 }
 
@@ -49,8 +79,12 @@ impl ElementCount for InfinoRequest {
 }
 
 impl Finalizable for InfinoRequest {
+    /// Documents are acknowledged on their own by [`AckLayer`], so there is nothing left to
+    /// acknowledge with the status of the whole request.
+    ///
+    /// [`AckLayer`]: super::ack::AckLayer
     fn take_finalizers(&mut self) -> EventFinalizers {
-        std::mem::take(&mut self.finalizers)
+        EventFinalizers::default()
     }
 }
 
@@ -64,49 +98,133 @@ impl MetaDescriptive for InfinoRequest {
     }
 }
 
+type BatchService =
+    HttpBatchService<BoxFuture<'static, Result<http::Request<Bytes>, crate::Error>>, InfinoRequest>;
+
 #[derive(Clone)]
 pub struct InfinoService {
     // TODO: `HttpBatchService` has been deprecated for direct use in sinks.
     //       This sink should undergo a refactor to utilize the `HttpService`
     //       instead, which extracts much of the boilerplate code for `Service`.
-    batch_service: HttpBatchService<
-        BoxFuture<'static, Result<http::Request<Bytes>, crate::Error>>,
-        InfinoRequest,
-    >,
+    batch_service: BatchService,
+    http_request_builder: Arc<HttpRequestBuilder>,
+    dns_refresh: Option<DnsRefresh>,
+    cert_reload: Option<CertReload>,
+    ignore_conflicts: bool,
+    diagnostics: ResponseDiagnostics,
 }
 
 impl InfinoService {
     pub fn new(
         http_client: HttpClient<Body>,
         http_request_builder: HttpRequestBuilder,
+        ignore_conflicts: bool,
+        diagnostics: ResponseDiagnostics,
     ) -> InfinoService {
         let http_request_builder = Arc::new(http_request_builder);
-        let batch_service = HttpBatchService::new(http_client, move |req| {
-            let request_builder = Arc::clone(&http_request_builder);
-            let future: BoxFuture<'static, Result<http::Request<Bytes>, crate::Error>> =
-                Box::pin(async move { request_builder.build_request(req).await });
-            future
+        InfinoService {
+            batch_service: batch_service(http_client, Arc::clone(&http_request_builder)),
+            http_request_builder,
+            dns_refresh: None,
+            cert_reload: None,
+            ignore_conflicts,
+            diagnostics,
+        }
+    }
+
+    /// Sends requests with a new client, and so on new connections, whenever `dns_refresh` tells
+    /// that the addresses of the endpoint changed.
+    pub fn with_dns_refresh(mut self, dns_refresh: Option<DnsRefresh>) -> Self {
+        self.dns_refresh = dns_refresh;
+        self
+    }
+
+    /// Sends requests with a new client whenever `cert_reload` tells that the client certificate
+    /// changed.
+    pub fn with_cert_reload(mut self, cert_reload: Option<CertReload>) -> Self {
+        self.cert_reload = cert_reload;
+        self
+    }
+
+    fn refresh_client(&mut self) {
+        let reloaded = self
+            .cert_reload
+            .as_mut()
+            .and_then(CertReload::reloaded_client)
+            .map(|(tls_settings, client)| {
+                if let Some(dns_refresh) = self.dns_refresh.as_mut() {
+                    dns_refresh.set_tls_settings(tls_settings);
+                }
+                client
+            });
+        let client = reloaded.or_else(|| {
+            self.dns_refresh
+                .as_mut()
+                .and_then(DnsRefresh::refreshed_client)
         });
-        InfinoService { batch_service }
+        match client {
+            Some(Ok(client)) => {
+                self.batch_service = batch_service(client, Arc::clone(&self.http_request_builder));
+            }
+            Some(Err(error)) => warn!(
+                message = "Unable to build a new client for the endpoint, keeping the current one.",
+                %error,
+                internal_log_rate_limit = true,
+            ),
+            None => {}
+        }
     }
 }
 
+fn batch_service(
+    http_client: HttpClient<Body>,
+    http_request_builder: Arc<HttpRequestBuilder>,
+) -> BatchService {
+    HttpBatchService::new(http_client, move |req| {
+        let request_builder = Arc::clone(&http_request_builder);
+        let future: BoxFuture<'static, Result<http::Request<Bytes>, crate::Error>> =
+            Box::pin(async move { request_builder.build_request(req).await });
+        future
+    })
+}
+
 pub struct HttpRequestBuilder {
     pub bulk_uri: Uri,
     pub query_params: HashMap<String, String>,
     pub auth: Option<Auth>,
     pub compression: Compression,
     pub http_request_config: RequestConfig,
+    /// The value of the `Content-Type` header of requests.
+    pub content_type: String,
+    /// The `Host` header of requests, when it differs from the authority of `bulk_uri`.
+    pub host_header: Option<String>,
+    /// The header the checksum of the payload is sent in, if any.
+    pub checksum_header: Option<HeaderName>,
+    /// The header the encryption key of the request is sent in, if any.
+    pub encryption_header: Option<HeaderName>,
+    pub kerberos: Option<Spnego>,
+    pub oauth2: Option<Arc<OAuth2>>,
+    pub api_key: Option<ApiKey>,
+    /// The presigned URLs requests are sent to instead of `bulk_uri`, if any.
+    pub presigned_urls: Option<Arc<PresignedUrls>>,
 }
 
 impl HttpRequestBuilder {
     pub fn new(common: &InfinoCommon, config: &InfinoConfig) -> HttpRequestBuilder {
         HttpRequestBuilder {
             bulk_uri: common.bulk_uri.clone(),
-            http_request_config: config.request.clone(),
+            http_request_config: config.request.http(),
+            content_type: config.request.content_type.header_value(),
+            host_header: host_header(&common.bulk_uri),
             auth: common.auth.clone(),
             query_params: common.query_params.clone(),
             compression: config.compression,
+            checksum_header: None,
+            encryption_header: None,
+            kerberos: common.kerberos.clone(),
+            oauth2: common.oauth2.clone(),
+            api_key: common.api_key.clone(),
+            presigned_urls: None,
         }
     }
 
@@ -114,15 +232,25 @@ impl HttpRequestBuilder {
         &self,
         es_req: InfinoRequest,
     ) -> Result<Request<Bytes>, crate::Error> {
-        let mut builder = Request::post(&self.bulk_uri);
+        // Presigned URLs are signed along with their query string, which is left as is.
+        let mut builder = match &self.presigned_urls {
+            Some(urls) => Request::post(urls.uri().await?),
+            None if es_req.query.is_empty() => Request::post(&self.bulk_uri),
+            None => Request::post(with_query(&self.bulk_uri, &es_req.query)?),
+        };
+
+        builder = builder.header("Content-Type", &self.content_type);
 
-        builder = builder.header("Content-Type", "application/x-ndjson");
+        // Presigned URLs have their own host.
+        if let (None, Some(host)) = (&self.presigned_urls, &self.host_header) {
+            builder = builder.header("Host", host);
+        }
 
         if let Some(ce) = self.compression.content_encoding() {
             builder = builder.header("Content-Encoding", ce);
         }
 
-        if let Some(ae) = self.compression.accept_encoding() {
+        if let Some(ae) = accept_encoding(self.compression) {
             builder = builder.header("Accept-Encoding", ae);
         }
 
@@ -130,6 +258,18 @@ impl HttpRequestBuilder {
             builder = builder.header(&header[..], &value[..]);
         }
 
+        for (header, value) in &es_req.headers {
+            builder = builder.header(&header[..], &value[..]);
+        }
+
+        if let (Some(header), Some(key)) = (&self.encryption_header, &es_req.encryption_key) {
+            builder = builder.header(header, key);
+        }
+
+        if let Some(header) = &self.checksum_header {
+            builder = builder.header(header, checksum(&es_req.payload));
+        }
+
         let mut request = builder
             .body(es_req.payload)
             .expect("Invalid http request value used");
@@ -144,16 +284,23 @@ impl HttpRequestBuilder {
                     credentials_provider: provider,
                     region,
                 } => {
-                    crate::sinks::Infino::sign_request(
-                        &mut request,
-                        provider,
-                        &Some(region.clone()),
-                    )
-                    .await?;
+                    super::sign_request(&mut request, provider, &Some(region.clone())).await?;
                 }
             }
         }
 
+        if let Some(kerberos) = &self.kerberos {
+            kerberos.apply(&mut request).await?;
+        }
+
+        if let Some(oauth2) = &self.oauth2 {
+            oauth2.apply(&mut request).await?;
+        }
+
+        if let Some(api_key) = &self.api_key {
+            api_key.apply(&mut request);
+        }
+
         Ok(request)
     }
 }
@@ -187,15 +334,28 @@ impl Service<InfinoRequest> for InfinoService {
 
     // Emission of internal events for errors and dropped events is handled upstream by the caller.
     fn call(&mut self, mut req: InfinoRequest) -> Self::Future {
+        self.refresh_client();
         let mut http_service = self.batch_service.clone();
+        let ignore_conflicts = self.ignore_conflicts;
+        let diagnostics = self.diagnostics.clone();
+        let oauth2 = self.http_request_builder.oauth2.clone();
         Box::pin(async move {
             http_service.ready().await?;
             let batch_size = req.batch_size;
             let events_byte_size =
                 std::mem::take(req.metadata_mut()).into_events_estimated_json_encoded_byte_size();
-            let http_response = http_service.call(req).await?;
+            // Requests rejected with an expired or revoked token are sent once more with a new one.
+            let retry = oauth2.as_ref().map(|_| req.clone());
+            let mut http_response = http_service.call(req).await?;
+            if let (Some(oauth2), Some(req)) = (oauth2, retry) {
+                if http_response.status() == StatusCode::UNAUTHORIZED {
+                    oauth2.invalidate().await;
+                    http_service.ready().await?;
+                    http_response = http_service.call(req).await?;
+                }
+            }
 
-            let event_status = get_event_status(&http_response);
+            let event_status = get_event_status(&http_response, ignore_conflicts, &diagnostics);
             Ok(InfinoResponse {
                 event_status,
                 http_response,
@@ -206,34 +366,160 @@ impl Service<InfinoRequest> for InfinoService {
     }
 }
 
+/// Returns the encoding responses are asked for. Responses are only decoded from gzip, so requests
+/// compressed with zstd or snappy do not ask for responses in their own encoding, which could not be
+/// read.
+const fn accept_encoding(compression: Compression) -> Option<&'static str> {
+    match compression {
+        Compression::Zstd(_) | Compression::Snappy => None,
+        _ => compression.accept_encoding(),
+    }
+}
+
 // This event is not part of the event framework but is kept because some users were depending on it
 // to identify the number of errors returned by Infino. It can be dropped when we have better
 // telemetry. Ref: #15886
-fn emit_bad_response_error(response: &Response<Bytes>) {
+fn emit_bad_response_error(response: &Response<Bytes>, diagnostics: &ResponseDiagnostics) {
     let error_code = format!("http_response_{}", response.status().as_u16());
 
     error!(
         message =  "Response contained errors.",
         error_code = error_code,
-        response = ?response,
+        request_id = diagnostics.request_id(response),
+        response = ?RedactedResponse(response, diagnostics.body_bytes),
     );
 }
 
-fn get_event_status(response: &Response<Bytes>) -> EventStatus {
+/// The outcome of a bulk request, which tells apart the successful responses holding failed
+/// items from full successes and full failures.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BulkOutcome {
+    /// Every item was written.
+    Success,
+    /// The request succeeded, but some of its items failed.
+    PartialFailure { items: usize, failed: usize },
+    /// The request failed, or all of its items did.
+    Failure,
+}
+
+impl BulkOutcome {
+    pub(super) fn of(response: &Response<Bytes>, ignore_conflicts: bool) -> Self {
+        if !response.status().is_success() {
+            return Self::Failure;
+        }
+        let body = String::from_utf8_lossy(response.body());
+        if !body.contains("\"errors\":true") {
+            return Self::Success;
+        }
+        match InfinoResultResponse::parse(&body) {
+            Ok(result) => match result.count_failed(ignore_conflicts) {
+                (_, 0) => Self::Success,
+                (items, failed) if failed < items => Self::PartialFailure { items, failed },
+                _ => Self::Failure,
+            },
+            Err(_) => Self::Failure,
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::PartialFailure { .. } => "partial_failure",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+pub(super) fn get_event_status(
+    response: &Response<Bytes>,
+    ignore_conflicts: bool,
+    diagnostics: &ResponseDiagnostics,
+) -> EventStatus {
+    let outcome = BulkOutcome::of(response, ignore_conflicts);
+    emit!(InfinoBulkResponse {
+        outcome: outcome.as_str(),
+        failed_items: match outcome {
+            BulkOutcome::PartialFailure { failed, .. } => failed,
+            _ => 0,
+        },
+    });
+
     let status = response.status();
     if status.is_success() {
-        let body = String::from_utf8_lossy(response.body());
-        if body.contains("\"errors\":true") {
-            emit_bad_response_error(response);
-            EventStatus::Rejected
-        } else {
+        // A batch with failed items is rejected as a whole here, and `AckLayer` acknowledges its
+        // events one by one from the items. Its outcome still tells it apart from a full failure.
+        if outcome == BulkOutcome::Success {
             EventStatus::Delivered
+        } else {
+            emit_bad_response_error(response, diagnostics);
+            EventStatus::Rejected
         }
     } else if status.is_server_error() {
-        emit_bad_response_error(response);
+        emit_bad_response_error(response, diagnostics);
         EventStatus::Errored
     } else {
-        emit_bad_response_error(response);
+        emit_bad_response_error(response, diagnostics);
         EventStatus::Rejected
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &'static str) -> Response<Bytes> {
+        Response::builder()
+            .status(status)
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap()
+    }
+
+    #[test]
+    fn tells_partial_failures_apart() {
+        let partial = r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed"}}}]}"#;
+        let failed = r#"{"errors":true,"items":[{"index":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed"}}}]}"#;
+        let conflict = r#"{"errors":true,"items":[{"create":{"status":201}},{"create":{"status":409,"error":{"type":"version_conflict_engine_exception","reason":"exists"}}}]}"#;
+
+        let outcome = |status, body, ignore_conflicts| {
+            BulkOutcome::of(&response(status, body), ignore_conflicts)
+        };
+        assert_eq!(
+            outcome(200, r#"{"errors":false}"#, false),
+            BulkOutcome::Success
+        );
+        assert_eq!(
+            outcome(200, partial, false),
+            BulkOutcome::PartialFailure {
+                items: 2,
+                failed: 1
+            }
+        );
+        assert_eq!(outcome(200, failed, false), BulkOutcome::Failure);
+        assert_eq!(outcome(200, conflict, true), BulkOutcome::Success);
+        assert_eq!(outcome(500, "", false), BulkOutcome::Failure);
+
+        assert_eq!(
+            get_event_status(&response(200, partial), false, &Default::default()),
+            EventStatus::Rejected
+        );
+    }
+
+    #[test]
+    fn asks_for_readable_responses() {
+        use crate::sinks::util::buffer::compression::CompressionLevel;
+
+        assert_eq!(accept_encoding(Compression::gzip_default()), Some("gzip"));
+        assert_eq!(
+            accept_encoding(Compression::Zstd(CompressionLevel::Val(9))),
+            None
+        );
+        assert_eq!(accept_encoding(Compression::Snappy), None);
+        assert_eq!(accept_encoding(Compression::None), None);
+        // Requests are still sent in their own encoding.
+        assert_eq!(
+            Compression::Zstd(CompressionLevel::Val(9)).content_encoding(),
+            Some("zstd")
+        );
+        assert_eq!(Compression::Snappy.content_encoding(), Some("snappy"));
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vrl::path::PathPrefix;
+
+use crate::event::{LogEvent, Value};
+
+/// Severity normalization configuration.
+///
+/// Reads the level of logs, in whatever form their source wrote it, and writes it as one of a
+/// fixed set of severities, so that logs of all sources can be filtered by severity in Infino.
+///
+/// Level names such as `WARN`, `warning`, or `err` are recognized regardless of case, as are the
+/// numeric syslog severities, from `0` for `emergency` to `7` for `debug`, and the numeric levels
+/// of Bunyan and Pino, from `10` for `trace` to `60` for `critical`.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SeverityConfig {
+    /// The fields the level of logs is read from.
+    ///
+    /// The first of these fields present in a log is used.
+    #[serde(default = "default_fields")]
+    #[configurable(metadata(docs::examples = "level"))]
+    #[configurable(metadata(docs::examples = "log.level"))]
+    pub fields: Vec<ConfigValuePath>,
+
+    /// The name of the field the severity is written to.
+    ///
+    /// Logs whose level is not recognized are written without a severity.
+    #[serde(default = "default_target")]
+    pub target: ConfigValuePath,
+
+    /// Additional level representations, and the severity they map to.
+    ///
+    /// These take precedence over the built-in ones, and are matched regardless of case.
+    #[serde(default)]
+    #[configurable(metadata(
+        docs::additional_props_description = "The severity the level maps to."
+    ))]
+    #[configurable(metadata(docs::examples = "example_mapping()"))]
+    pub mapping: HashMap<String, Severity>,
+}
+
+impl Default for SeverityConfig {
+    fn default() -> Self {
+        Self {
+            fields: default_fields(),
+            target: default_target(),
+            mapping: HashMap::new(),
+        }
+    }
+}
+
+fn default_fields() -> Vec<ConfigValuePath> {
+    vec![
+        ConfigValuePath::from("severity"),
+        ConfigValuePath::from("level"),
+    ]
+}
+
+fn default_target() -> ConfigValuePath {
+    ConfigValuePath::from("severity")
+}
+
+fn example_mapping() -> HashMap<String, Severity> {
+    HashMap::from([
+        ("W".to_owned(), Severity::Warning),
+        ("E".to_owned(), Severity::Error),
+    ])
+}
+
+/// A normalized severity.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Trace.
+    Trace,
+
+    /// Debug.
+    Debug,
+
+    /// Informational.
+    Info,
+
+    /// Normal but significant.
+    Notice,
+
+    /// Warning.
+    Warning,
+
+    /// Error.
+    Error,
+
+    /// Critical.
+    Critical,
+
+    /// Action must be taken immediately.
+    Alert,
+
+    /// The system is unusable.
+    Emergency,
+}
+
+impl Severity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Notice => "notice",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+            Self::Alert => "alert",
+            Self::Emergency => "emergency",
+        }
+    }
+
+    /// Returns the severity of a built-in level representation, which must be lowercase.
+    fn parse(level: &str) -> Option<Self> {
+        let severity = match level {
+            "trace" | "10" => Self::Trace,
+            "debug" | "dbg" | "7" | "20" => Self::Debug,
+            "info" | "information" | "informational" | "6" | "30" => Self::Info,
+            "notice" | "5" => Self::Notice,
+            "warn" | "warning" | "4" | "40" => Self::Warning,
+            "error" | "err" | "3" | "50" => Self::Error,
+            "critical" | "crit" | "fatal" | "2" | "60" => Self::Critical,
+            "alert" | "1" => Self::Alert,
+            "emergency" | "emerg" | "panic" | "0" => Self::Emergency,
+            _ => return None,
+        };
+        Some(severity)
+    }
+}
+
+/// Writes the normalized severity of logs.
+#[derive(Clone, Debug)]
+pub struct SeverityNormalizer {
+    fields: Vec<ConfigValuePath>,
+    target: ConfigValuePath,
+    mapping: HashMap<String, Severity>,
+}
+
+impl SeverityNormalizer {
+    pub fn new(config: &SeverityConfig) -> Self {
+        Self {
+            fields: config.fields.clone(),
+            target: config.target.clone(),
+            mapping: config
+                .mapping
+                .iter()
+                .map(|(level, severity)| (level.trim().to_lowercase(), *severity))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, log: &mut LogEvent) {
+        let Some(level) = self
+            .fields
+            .iter()
+            .find_map(|field| log.get((PathPrefix::Event, field)))
+        else {
+            return;
+        };
+        let level = match level {
+            Value::Bytes(bytes) => String::from_utf8_lossy(bytes).trim().to_lowercase(),
+            Value::Integer(level) => level.to_string(),
+            _ => return,
+        };
+        let severity = self
+            .mapping
+            .get(&level)
+            .copied()
+            .or_else(|| Severity::parse(&level));
+        if let Some(severity) = severity {
+            log.insert((PathPrefix::Event, &self.target), severity.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn severity(normalizer: &SeverityNormalizer, field: &str, level: Value) -> Option<Value> {
+        let mut log = LogEvent::default();
+        log.insert(field, level);
+        normalizer.apply(&mut log);
+        log.get("severity").cloned()
+    }
+
+    #[test]
+    fn normalizes_levels() {
+        let normalizer = SeverityNormalizer::new(&SeverityConfig::default());
+        for (level, expected) in [
+            (Value::from("WARN"), "warning"),
+            (Value::from(" warning "), "warning"),
+            (Value::from("Err"), "error"),
+            (Value::from(30), "info"),
+            (Value::from("60"), "critical"),
+            (Value::from(0), "emergency"),
+            (Value::from(7), "debug"),
+        ] {
+            assert_eq!(
+                severity(&normalizer, "level", level.clone()),
+                Some(Value::from(expected)),
+                "{level:?}"
+            );
+        }
+        assert_eq!(severity(&normalizer, "level", Value::from("loud")), None);
+        assert_eq!(severity(&normalizer, "lvl", Value::from("info")), None);
+    }
+
+    #[test]
+    fn applies_custom_mapping() {
+        let normalizer = SeverityNormalizer::new(&SeverityConfig {
+            mapping: HashMap::from([
+                ("W".to_owned(), Severity::Warning),
+                ("info".to_owned(), Severity::Notice),
+            ]),
+            ..Default::default()
+        });
+        assert_eq!(
+            severity(&normalizer, "level", Value::from("w")),
+            Some(Value::from("warning"))
+        );
+        assert_eq!(
+            severity(&normalizer, "level", Value::from("INFO")),
+            Some(Value::from("notice"))
+        );
+    }
+}
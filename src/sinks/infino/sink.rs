@@ -1,9 +1,11 @@
-use std::fmt;
+use std::{fmt, sync::Arc, time::Duration};
 
-use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use sha2::{Digest, Sha256};
+use vector_lib::lookup::{event_path, lookup_v2::ConfigValuePath};
 use vrl::path::PathPrefix;
 
 use crate::{
+    internal_events::InfinoBulkActionInvalid,
     sinks::{
         infino::{
             encoder::ProcessedEvent, request_builder::InfinoRequestBuilder, service::InfinoRequest,
@@ -11,43 +13,166 @@ use crate::{
         },
         prelude::*,
     },
-    transforms::metric_to_log::MetricToLog,
 };
 
 use super::{
+    batch::{BatchCostConfig, BatchTarget, InfinoBatcher},
+    bundle::{bundle, BundleConfig},
+    dedup::Dedup,
+    dispatch::PriorityConfig,
+    distribution::DistributionStrategy,
     encoder::{DocumentMetadata, DocumentVersion, DocumentVersionType},
-    InfinoCommon, InfinoConfig, VersionType,
+    encryption::EncryptionConfig,
+    geo::normalize_geo_points,
+    headers::BatchHeaders,
+    index_policy::IndexPolicy,
+    metric::{split_aggregates, MetricEncoder},
+    provenance::Provenance,
+    query::BatchQuery,
+    rate::CounterRates,
+    rollup::rollup,
+    self_monitoring::{self, SelfMonitoring},
+    severity::SeverityNormalizer,
+    timestamp::{resolve_timestamp, TimestampField},
+    trace::{convert_trace_to_ingest_trace, TraceConfig},
+    DedupStrategy, InfinoCommon, InfinoConfig, ParseError, VersionType, DATA_STREAM_TIMESTAMP_KEY,
+    MESSAGE_KEY,
 };
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct PartitionKey {
     pub index: String,
-    pub bulk_action: BulkAction,
+    pub bulk_action: Option<BulkAction>,
+}
+
+impl PartitionKey {
+    /// In ordered mode, events are only partitioned by index, so that the index, update and
+    /// delete actions sent to the same index are delivered in the order they were received.
+    pub fn of(event: &ProcessedEvent, ordered: bool) -> Self {
+        Self {
+            index: event.index.clone(),
+            bulk_action: (!ordered).then_some(event.bulk_action),
+        }
+    }
 }
 
 pub struct InfinoSink<S> {
     pub batch_settings: BatcherSettings,
+    pub batch_target: Arc<BatchTarget>,
+    pub idle_timeout: Option<Duration>,
+    pub max_delay: Option<Duration>,
+    pub batch_cost: Option<BatchCostConfig>,
     pub request_builder: InfinoRequestBuilder,
     pub transformer: Transformer,
     pub service: S,
     pub mode: InfinoCommonMode,
     pub id_key_field: Option<ConfigValuePath>,
+    pub message_key: Option<ConfigValuePath>,
+    pub raw_key: Option<ConfigValuePath>,
+    pub provenance: Option<Provenance>,
+    pub severity: Option<SeverityNormalizer>,
+    pub parse_json_fields: Vec<ConfigValuePath>,
+    pub geo_points: Vec<ConfigValuePath>,
+    pub timestamp_fields: Vec<TimestampField>,
+    pub index_policy: IndexPolicy,
+    pub encryption: Option<EncryptionConfig>,
+    pub batch_headers: Option<BatchHeaders>,
+    pub batch_query: Option<BatchQuery>,
+    pub partitioned: bool,
+    pub ordered: bool,
+    pub reorder_window: Option<usize>,
+    pub dedup: Option<Dedup>,
+    pub dedup_strategy: Option<DedupStrategy>,
+    pub priority: Option<PriorityConfig>,
+    pub metric_encoder: MetricEncoder,
+    pub metric_rollup: Option<Duration>,
+    pub bundle: Option<BundleConfig>,
+    pub traces: Option<TraceConfig>,
+    pub counter_rates: Option<CounterRates>,
+    pub self_monitoring: Option<SelfMonitoring>,
 }
 
 impl<S> InfinoSink<S> {
-    pub fn new(common: &InfinoCommon, config: &InfinoConfig, service: S) -> crate::Result<Self> {
+    pub fn new(
+        common: &InfinoCommon,
+        config: &InfinoConfig,
+        service: S,
+        batch_target: Arc<BatchTarget>,
+    ) -> crate::Result<Self> {
         let batch_settings = config.batch.into_batcher_settings()?;
+        let idle_timeout = config
+            .idle_flush_secs
+            .map(|secs| interval("idle_flush_secs", secs))
+            .transpose()?;
+        let max_delay = config
+            .max_event_delay_secs
+            .map(|secs| interval("max_event_delay_secs", secs))
+            .transpose()?;
+        if let Some(cost) = &config.batch_cost {
+            cost.validate()?;
+        }
 
         Ok(InfinoSink {
             batch_settings,
+            batch_target,
+            idle_timeout,
+            max_delay,
+            batch_cost: config.batch_cost.clone(),
             request_builder: common.request_builder.clone(),
             transformer: config.encoding.clone(),
             service,
             mode: common.mode.clone(),
+            id_key_field: config.id_key.clone(),
+            message_key: config.message_key.clone(),
+            raw_key: config.store_raw.clone(),
+            provenance: config.provenance.as_ref().map(Provenance::new),
+            severity: config.severity.as_ref().map(SeverityNormalizer::new),
+            parse_json_fields: config.parse_json_fields.clone(),
+            geo_points: config.geo_points.clone(),
+            timestamp_fields: config.timestamp_fields.clone(),
+            index_policy: IndexPolicy::new(&config.allowed_indexes, &config.denied_indexes)?,
+            encryption: config.encryption.clone(),
+            batch_headers: BatchHeaders::new(&config.request.rendered_headers())?,
+            batch_query: BatchQuery::new(config.query.as_ref()),
+            partitioned: config.ordered
+                || config.fairness.is_some()
+                || config.distribution_strategy() == DistributionStrategy::Hash,
+            ordered: config.ordered,
+            reorder_window: config.reorder.map(|reorder| reorder.max_events),
+            dedup: config.dedup.as_ref().map(Dedup::new),
+            dedup_strategy: config.dedup_strategy,
+            priority: config.priority.clone(),
+            metric_encoder: MetricEncoder::new(config.metric_encoding.clone())?
+                .with_non_finite_floats(config.non_finite_floats),
+            metric_rollup: config
+                .metric_rollup
+                .map(|rollup| Duration::from_secs(rollup.window_secs.get())),
+            bundle: config.bundle.clone(),
+            traces: config.traces.clone(),
+            counter_rates: config
+                .metric_encoding
+                .counter_rates
+                .then(CounterRates::default),
+            self_monitoring: config
+                .self_monitoring
+                .as_ref()
+                .map(|self_monitoring| {
+                    let encoder = MetricEncoder::new(config.metric_encoding.clone())?
+                        .with_non_finite_floats(config.non_finite_floats);
+                    SelfMonitoring::new(self_monitoring, encoder)
+                })
+                .transpose()?,
         })
     }
 }
 
+fn interval(option: &'static str, secs: f64) -> Result<Duration, ParseError> {
+    Duration::try_from_secs_f64(secs)
+        .ok()
+        .filter(|interval| !interval.is_zero())
+        .ok_or(ParseError::InvalidInterval { option })
+}
+
 impl<S> InfinoSink<S>
 where
     S: Service<InfinoRequest> + Send + 'static,
@@ -58,98 +183,332 @@ where
     pub async fn run_inner(self: Box<Self>, input: BoxStream<'_, Event>) -> Result<(), ()> {
         let mode = self.mode;
         let id_key_field = self.id_key_field.as_ref();
+        let message_key = self.message_key;
+        let raw_key = self.raw_key;
+        let provenance = self.provenance;
+        let severity = self.severity;
+        let json_fields = self.parse_json_fields;
+        let geo_points = self.geo_points;
+        let timestamp_fields = self.timestamp_fields;
+        let index_policy = self.index_policy;
+        let encryption = self.encryption;
+        let batch_headers = self.batch_headers;
+        let batch_query = self.batch_query;
         let transformer = self.transformer.clone();
+        let batch_settings = self.batch_settings;
+        let batch_target = self.batch_target;
+        let reorder_window = self.reorder_window;
+        let mut dedup = self.dedup;
+        let dedup_strategy = self.dedup_strategy;
+        let priority = self.priority;
+        let metric_encoder = self.metric_encoder;
+        let mut counter_rates = self.counter_rates;
+        let traces = self.traces;
+        let input = match self.metric_rollup {
+            Some(window) => rollup(input, window),
+            None => input,
+        };
+        let input = match self.bundle {
+            Some(config) => bundle(input, config),
+            None => input,
+        };
+        let input = input.flat_map(|event| match event {
+            Event::Metric(metric) => {
+                let points = split_aggregates(metric).into_iter().map(Event::Metric);
+                futures::stream::iter(points).left_stream()
+            }
+            event => futures::stream::once(future::ready(event)).right_stream(),
+        });
 
-        input
-            .filter_map(move |event| {
-                future::ready(match event {
-                    Event::Metric(metric) => {
-                        let ingest_metric = convert_metric_to_ingest_metric(
-                            metric,
-                            &mode,
-                            id_key_field,
-                            &transformer,
-                        );
-                        let result = state
-                            .coredb
-                            .append_metric_point("default", ingest_metric)
-                            .await;
-                        if let Err(error) = result {
-                            match error {
-                                CoreDBError::TooManyAppendsError() => {
-                                    return Err((StatusCode::TOO_MANY_REQUESTS, error.to_string()));
-                                }
-                                CoreDBError::UnsupportedIngest(_)
-                                | CoreDBError::IndexNotFound(_) => {
-                                    return Err((StatusCode::BAD_REQUEST, error.to_string()));
-                                }
-                                _ => {
-                                    error!("An unexpected error occurred.");
-                                }
-                            }
-                        }
+        let events = input.filter_map(|event| {
+            future::ready(match event {
+                Event::Metric(metric) => metric_encoder
+                    .filter(metric)
+                    .and_then(|metric| match counter_rates.as_mut() {
+                        Some(rates) => rates.convert(metric),
+                        None => Some(metric),
+                    })
+                    .and_then(|metric| match metric_encoder.route(&metric) {
+                        Ok(index) => metric_encoder.encode(metric).map(|log| (log, index)),
+                        Err(()) => None,
+                    }),
+                Event::Log(mut log) => {
+                    if let Some(key) = raw_key.as_ref() {
+                        store_raw(&mut log, key);
                     }
-                    Event::Log(log) => {
-                        let ingest_log =
-                            convert_log_to_ingest_log(log, &mode, id_key_field, &transformer);
-                        let result = state.coredb.append_event(ingest_log).await;
+                    if let Some(key) = message_key.as_ref() {
+                        move_message(&mut log, key);
                     }
-                    Event::Trace(_) => {
-                        // Although technically this will cause the event to be dropped, due to the sink
-                        // config it is not possible to send traces to this sink - so this situation can
-                        // never occur. We don't need to emit an `EventsDropped` event.
-                        None
+                    if let Some(severity) = severity.as_ref() {
+                        severity.apply(&mut log);
                     }
+                    parse_json_fields(&mut log, &json_fields);
+                    normalize_geo_points(&mut log, &geo_points);
+                    if !timestamp_fields.is_empty() {
+                        resolve_timestamp(&mut log, &timestamp_fields);
+                    }
+                    Some((log, None))
+                }
+                // The sink only accepts traces when `traces` is set.
+                Event::Trace(trace) => traces.as_ref().and_then(|traces| {
+                    let log = convert_trace_to_ingest_trace(trace);
+                    traces.index(&log).map(|index| (log, Some(index)))
+                }),
+            })
+        });
+        let events = match self.self_monitoring {
+            Some(monitoring) => self_monitoring::merge(events, monitoring.events()).boxed(),
+            None => events.boxed(),
+        };
+
+        let events = events
+            .filter_map(move |(mut log, index)| {
+                if let Some(provenance) = provenance.as_ref() {
+                    provenance.apply(&mut log);
+                }
+                let event = process_log(log, &mode, id_key_field, &transformer)
+                    .map(|mut event| {
+                        if let Some(index) = index {
+                            event.index = index;
+                        }
+                        event
+                    })
+                    .and_then(|event| index_policy.check(event))
+                    .and_then(|event| {
+                        render_templates(
+                            event,
+                            encryption.as_ref(),
+                            batch_headers.as_ref(),
+                            batch_query.as_ref(),
+                        )
+                    })
+                    .map(|mut event| {
+                        if let Some(DedupStrategy::CreateWithId) = dedup_strategy {
+                            create_with_content_id(&mut event);
+                        }
+                        if let Some(priority) = priority.as_ref() {
+                            event.lane = priority.lane(&event);
+                        }
+                        event
+                    });
+                future::ready(event)
+            })
+            .filter_map(move |event| {
+                future::ready(match dedup.as_mut() {
+                    Some(dedup) => dedup.check(event),
+                    None => Some(event),
                 })
+            });
+
+        // In ordered mode, or when dispatch is shared fairly between partitions, every batch holds
+        // a single partition, so that the service can tell the partition of each request.
+        let batches = InfinoBatcher::new(
+            events.boxed(),
+            &batch_settings,
+            batch_target,
+            self.partitioned,
+        )
+        .with_ordered(self.ordered)
+        .with_idle_timeout(self.idle_timeout)
+        .with_max_delay(self.max_delay)
+        .with_cost(self.batch_cost);
+
+        batches
+            .map(move |mut batch| {
+                if let Some(window) = reorder_window {
+                    reorder_by_timestamp(&mut batch, window);
+                }
+                batch
+            })
+            .request_builder(
+                default_request_builder_concurrency_limit(),
+                self.request_builder,
+            )
+            .filter_map(|request| async move {
+                match request {
+                    Err(error) => {
+                        emit!(SinkRequestBuildError { error });
+                        None
+                    }
+                    Ok(req) => Some(req),
+                }
             })
-            .for_each(|_| future::ready(()))
-            .await;
+            .into_driver(self.service)
+            .run()
+            .await
+    }
+}
 
-        Ok(())
+/// Stores a copy of `log`, serialized as JSON, in the field at `key`.
+pub(super) fn store_raw(log: &mut LogEvent, key: &ConfigValuePath) {
+    if let Ok(raw) = serde_json::to_string(log.value()) {
+        log.insert((PathPrefix::Event, key), raw);
     }
 }
-fn convert_metric_to_ingest_metric(metric: Metric) -> IngestMetric<'_> {
-    let metric_name = metric.name();
-    let metric_point = MetricPoint {
-        time: metric.timestamp().as_secs(),
-        value: match metric.value() {
-            MetricValue::Counter { value } => *value as f64,
-            MetricValue::Gauge { value } => *value as f64,
-            MetricValue::Histogram { samples, .. } => samples.sum(),
-            MetricValue::Set { values } => values.len() as f64,
-            MetricValue::Distribution { samples, .. } => samples.sum(),
-        },
+
+/// Replaces the fields at `keys` holding a JSON object or array with its parsed value.
+///
+/// Fields that do not hold valid JSON, or hold a JSON scalar, are left as they are.
+pub(super) fn parse_json_fields(log: &mut LogEvent, keys: &[ConfigValuePath]) {
+    for key in keys {
+        let parsed = match log.get((PathPrefix::Event, key)) {
+            Some(Value::Bytes(bytes))
+                if matches!(
+                    bytes.iter().find(|byte| !byte.is_ascii_whitespace()),
+                    Some(b'{' | b'[')
+                ) =>
+            {
+                serde_json::from_slice::<Value>(bytes).ok()
+            }
+            _ => None,
+        };
+        if let Some(parsed) = parsed {
+            log.insert((PathPrefix::Event, key), parsed);
+        }
+    }
+}
+
+/// Moves the field at `key` to the `message` field of the document, which Infino indexes for
+/// full-text search. Documents that already have a `message` field are left as they are.
+pub(super) fn move_message(log: &mut LogEvent, key: &ConfigValuePath) {
+    if log.contains(event_path!(MESSAGE_KEY)) {
+        return;
+    }
+    if let Some(message) = log.remove((PathPrefix::Event, key)) {
+        log.insert(event_path!(MESSAGE_KEY), message);
+    }
+}
+
+/// Sorts `events` by timestamp, in consecutive windows of at most `window` events.
+///
+/// The sort is stable, so events with equal or missing timestamps keep their relative order.
+pub(super) fn reorder_by_timestamp(events: &mut [ProcessedEvent], window: usize) {
+    for chunk in events.chunks_mut(window.max(1)) {
+        chunk.sort_by_cached_key(|event| {
+            event
+                .log
+                .get_timestamp()
+                .or_else(|| event.log.get(event_path!(DATA_STREAM_TIMESTAMP_KEY)))
+                .and_then(Value::as_timestamp)
+                .copied()
+        });
+    }
+}
+
+/// Switches `event` to the `create` action and, unless it already has one, gives it an `_id`
+/// derived from the hash of its document.
+///
+/// `update` and `delete` events are left alone, as they act on an existing document.
+pub(super) fn create_with_content_id(event: &mut ProcessedEvent) {
+    match event.bulk_action {
+        BulkAction::Index | BulkAction::Create => event.bulk_action = BulkAction::Create,
+        BulkAction::Update | BulkAction::Delete => return,
+    }
+    if matches!(event.document_metadata, DocumentMetadata::WithoutId) {
+        if let Ok(document) = serde_json::to_vec(event.log.value()) {
+            event.document_metadata = DocumentMetadata::Id(hex::encode(Sha256::digest(document)));
+        }
+    }
+}
+
+/// Renders the templates sent along with `event`, which is rejected if one of them fails to render.
+///
+/// The templates emit the failure, along with the event being dropped.
+pub(super) fn render_templates(
+    mut event: ProcessedEvent,
+    encryption: Option<&EncryptionConfig>,
+    batch_headers: Option<&BatchHeaders>,
+    batch_query: Option<&BatchQuery>,
+) -> Option<ProcessedEvent> {
+    let mut render = || -> Option<()> {
+        if let Some(encryption) = encryption {
+            event.encryption_key = Some(encryption.key(&event)?);
+        }
+        if let Some(batch_headers) = batch_headers {
+            event.headers = batch_headers.render(&event)?;
+        }
+        if let Some(batch_query) = batch_query {
+            event.query = batch_query.render(&event)?;
+        }
+        Some(())
     };
-    let labels = EventReference::new_with_params(
-        metric
-            .tags()
-            .iter()
-            .map(|(k, v)| FieldReference::new(k, v, None))
-            .collect(),
-    );
-    IngestMetric {
-        metric_name,
-        metric_point,
-        labels,
+    if render().is_none() {
+        event.take_finalizers().update_status(EventStatus::Rejected);
+        return None;
     }
+    Some(event)
 }
 
-fn convert_log_to_ingest_log(log: LogEvent) -> IngestLog<'_> {
-    let index_name = "logs";
-    let operation = IndexOperation::Index;
-    let time = log.timestamp().as_secs();
-    let message = EventReference::new_with_params(
-        log.fields()
-            .iter()
-            .map(|(k, v)| FieldReference::new(k, v.as_str().unwrap_or(""), None))
-            .collect(),
-    );
-    IngestLog {
-        index_name,
-        operation,
-        time,
-        message,
+/// Any `None` values returned from this function will already result in a `TemplateRenderingError`
+/// being emitted, so no further `EventsDropped` event needs emitting.
+pub(super) fn process_log(
+    mut log: LogEvent,
+    mode: &InfinoCommonMode,
+    id_key_field: Option<&ConfigValuePath>,
+    transformer: &Transformer,
+) -> Option<ProcessedEvent> {
+    let index = mode.index(&log)?;
+    let bulk_action = match mode.data_stream_action(&index) {
+        Some(bulk_action) => bulk_action,
+        None => mode.bulk_action(&log)?,
+    };
+
+    if let Some(cfg) = mode.as_data_stream_config() {
+        cfg.sync_fields(&mut log);
+        cfg.remap_timestamp(&mut log);
+    };
+    let id = if let Some(Value::Bytes(key)) =
+        id_key_field.and_then(|key| log.remove((PathPrefix::Event, key)))
+    {
+        Some(String::from_utf8_lossy(&key).into_owned())
+    } else {
+        None
+    };
+    if id.is_none() && bulk_action.needs_id() {
+        emit!(InfinoBulkActionInvalid {
+            action: bulk_action.as_str(),
+            reason: "Bulk action needs a document ID.",
+        });
+        return None;
     }
+    let document_metadata = match (id.clone(), mode.version_type(), mode.version(&log)) {
+        (None, _, _) => DocumentMetadata::WithoutId,
+        (Some(id), None, None) | (Some(id), None, Some(_)) | (Some(id), Some(_), None) => {
+            DocumentMetadata::Id(id)
+        }
+        (Some(id), Some(version_type), Some(version)) => match version_type {
+            VersionType::Internal => DocumentMetadata::Id(id),
+            VersionType::External => DocumentMetadata::IdAndVersion(
+                id,
+                DocumentVersion {
+                    kind: DocumentVersionType::External,
+                    value: version,
+                },
+            ),
+            VersionType::ExternalGte => DocumentMetadata::IdAndVersion(
+                id,
+                DocumentVersion {
+                    kind: DocumentVersionType::ExternalGte,
+                    value: version,
+                },
+            ),
+        },
+    };
+    let log = {
+        let mut event = Event::from(log);
+        transformer.transform(&mut event);
+        event.into_log()
+    };
+    Some(ProcessedEvent {
+        index,
+        bulk_action,
+        log,
+        document_metadata,
+        lane: Default::default(),
+        encryption_key: None,
+        headers: Vec::new(),
+        query: Vec::new(),
+    })
 }
 
 #[async_trait]
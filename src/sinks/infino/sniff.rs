@@ -0,0 +1,245 @@
+//! Discovery of the data nodes of the cluster.
+//!
+//! Like the sniffing of Elasticsearch clients, the nodes of the cluster are listed from the
+//! `_nodes/http` API of the endpoints on an interval, and those holding data are sent requests
+//! along with the endpoints, so that nodes added to the cluster share the load without the
+//! configuration being changed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU64,
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes};
+use futures::{future::BoxFuture, stream::BoxStream};
+use hyper::body;
+use serde::Deserialize;
+use tower::discover::Change;
+use vector_lib::configurable::configurable_component;
+
+use super::{redact::redact, InfinoCommon};
+use crate::{
+    http::HttpClient,
+    internal_events::{InfinoNodeDiscovered, InfinoNodeRemoved},
+};
+
+/// Sniffing configuration.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SniffingConfig {
+    /// Whether or not to send requests to the data nodes of the cluster along with the endpoints.
+    ///
+    /// The nodes are listed from the `_nodes/http` API of the endpoints, and are reached on the
+    /// address they publish, with the scheme, authentication, and TLS settings of the endpoints.
+    /// Nodes that leave the cluster stop being sent requests, while the endpoints always are.
+    /// Only applies to requests distributed with the `balance` strategy, to endpoints without a
+    /// `weight`.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The interval at which the nodes of the cluster are listed, in seconds.
+    #[serde(default = "default_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub interval_secs: NonZeroU64,
+}
+
+const fn default_interval_secs() -> NonZeroU64 {
+    match NonZeroU64::new(60) {
+        Some(secs) => secs,
+        None => unreachable!(),
+    }
+}
+
+impl Default for SniffingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NodesResponse {
+    #[serde(default)]
+    nodes: HashMap<String, Node>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    roles: Option<Vec<String>>,
+    http: Option<NodeHttp>,
+}
+
+#[derive(Deserialize)]
+struct NodeHttp {
+    publish_address: String,
+}
+
+impl Node {
+    /// Nodes that do not tell their roles hold every role.
+    fn holds_data(&self) -> bool {
+        self.roles.as_ref().map_or(true, |roles| {
+            roles.iter().any(|role| role.starts_with("data"))
+        })
+    }
+}
+
+/// Returns the endpoints of the data nodes listed in `body`, reached with `scheme`.
+fn data_nodes(body: &[u8], scheme: &str) -> crate::Result<Vec<String>> {
+    let response = serde_json::from_slice::<NodesResponse>(body)?;
+    let mut nodes = response
+        .nodes
+        .into_values()
+        .filter(Node::holds_data)
+        .filter_map(|node| node.http)
+        // Addresses are published as `hostname/ip:port` when the node is bound to a hostname.
+        .filter_map(|http| {
+            let address = http.publish_address.rsplit('/').next()?;
+            Some(format!("{}://{}", scheme, address))
+        })
+        .collect::<Vec<_>>();
+    nodes.sort_unstable();
+    nodes.dedup();
+    Ok(nodes)
+}
+
+/// Lists the data nodes of the cluster from the first endpoint that answers.
+async fn sniff(seeds: &[(InfinoCommon, HttpClient)]) -> crate::Result<Vec<String>> {
+    let mut last_error: Option<crate::Error> = None;
+    for (common, client) in seeds {
+        let result: crate::Result<_> = async {
+            let response = common
+                .request(
+                    client.clone(),
+                    http::Method::GET,
+                    "/_nodes/http",
+                    Bytes::new(),
+                )
+                .await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(format!("unexpected status {}", status).into());
+            }
+            let mut body = body::aggregate(response.into_body()).await?;
+            let body = body.copy_to_bytes(body.remaining());
+            let scheme = common.base_url.split("://").next().unwrap_or("http");
+            data_nodes(&body, scheme)
+        }
+        .await;
+        match result {
+            Ok(nodes) => return Ok(nodes),
+            Err(error) => {
+                last_error = Some(format!("{}: {}", redact(&common.base_url), error).into())
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| "no endpoint to list the nodes from".into()))
+}
+
+/// Returns the services of the endpoints, followed by those of the data nodes found every
+/// `interval`, as they join and leave the cluster.
+///
+/// The services of the nodes are built by `build` from their endpoint.
+pub fn discover<S, F>(
+    services: Vec<(String, S)>,
+    seeds: Vec<(InfinoCommon, HttpClient)>,
+    interval: Duration,
+    build: F,
+) -> BoxStream<'static, Change<usize, (String, S)>>
+where
+    S: Send + 'static,
+    F: Fn(String) -> BoxFuture<'static, crate::Result<(String, S)>> + Send + 'static,
+{
+    Box::pin(async_stream::stream! {
+        let endpoints = services
+            .iter()
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect::<HashSet<_>>();
+        let mut next_key = services.len();
+        for (key, service) in services.into_iter().enumerate() {
+            yield Change::Insert(key, service);
+        }
+
+        let mut discovered = HashMap::<String, usize>::new();
+        loop {
+            match sniff(&seeds).await {
+                Ok(nodes) => {
+                    let nodes = nodes
+                        .into_iter()
+                        .filter(|node| !endpoints.contains(node))
+                        .collect::<HashSet<_>>();
+                    let gone = discovered
+                        .keys()
+                        .filter(|node| !nodes.contains(*node))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for node in gone {
+                        if let Some(key) = discovered.remove(&node) {
+                            emit!(InfinoNodeRemoved { endpoint: &node });
+                            yield Change::Remove(key);
+                        }
+                    }
+                    for node in nodes {
+                        if discovered.contains_key(&node) {
+                            continue;
+                        }
+                        match build(node.clone()).await {
+                            Ok(service) => {
+                                emit!(InfinoNodeDiscovered { endpoint: &node });
+                                discovered.insert(node, next_key);
+                                yield Change::Insert(next_key, service);
+                                next_key += 1;
+                            }
+                            Err(error) => warn!(
+                                message = "Unable to send requests to a discovered node.",
+                                endpoint = %redact(&node),
+                                %error,
+                                internal_log_rate_limit = true,
+                            ),
+                        }
+                    }
+                }
+                // The nodes found so far are kept until the cluster can be listed again.
+                Err(error) => warn!(
+                    message = "Unable to list the nodes of the cluster.",
+                    %error,
+                    internal_log_rate_limit = true,
+                ),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODES: &str = r#"{
+        "nodes": {
+            "a": {"roles": ["master", "data_hot"], "http": {"publish_address": "10.0.0.1:3000"}},
+            "b": {"roles": ["master"], "http": {"publish_address": "10.0.0.2:3000"}},
+            "c": {"http": {"publish_address": "infino-2.infino/10.0.0.3:3000"}},
+            "d": {"roles": ["data"]}
+        }
+    }"#;
+
+    #[test]
+    fn lists_data_nodes() {
+        assert_eq!(
+            data_nodes(NODES.as_bytes(), "https").unwrap(),
+            ["https://10.0.0.1:3000", "https://10.0.0.3:3000"]
+        );
+        assert!(data_nodes(b"not json", "http").is_err());
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let config = toml::from_str::<SniffingConfig>("enabled = true").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.interval_secs.get(), 60);
+    }
+}
@@ -1,16 +1,31 @@
-use std::convert::TryFrom;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    sync::Arc,
+};
 
+use bytes::Bytes;
+use http::header::AUTHORIZATION;
 use vector_lib::lookup::PathPrefix;
 
 use crate::{
     codecs::Transformer,
+    config::interpolate_secrets,
     event::{LogEvent, Metric, MetricKind, MetricValue, ObjectMap, Value},
     sinks::{
-        util::encoding::Encoder,
-        Infino::{
-            sink::process_log, BulkAction, BulkConfig, DataStreamConfig, InfinoApiVersion,
-            InfinoCommon, InfinoConfig, InfinoMode, VersionType,
+        infino::{
+            check_version_compatibility,
+            encryption::EncryptionConfig,
+            service::{HttpRequestBuilder, InfinoRequest},
+            sink::{
+                create_with_content_id, move_message, parse_json_fields, process_log,
+                render_templates, reorder_by_timestamp, store_raw, PartitionKey,
+            },
+            BulkAction, BulkConfig, DataStreamConfig, DataStreamTimestampMode, DocumentMetadata,
+            InfinoApiVersion, InfinoCommon, InfinoCommonMode, InfinoConfig, InfinoMode, ParseError,
+            ProcessedEvent, VersionType,
         },
+        util::encoding::Encoder,
     },
     template::Template,
 };
@@ -29,6 +44,7 @@ async fn sets_create_action_when_configured() {
     let config = InfinoConfig {
         bulk: BulkConfig {
             action: parse_template("{{ action }}te"),
+            default_action: None,
             index: parse_template("vector"),
             version: None,
             version_type: VersionType::Internal,
@@ -70,6 +86,7 @@ async fn encoding_with_external_versioning_without_version_set_does_not_include_
     let config = InfinoConfig {
         bulk: BulkConfig {
             action: parse_template("create"),
+            default_action: None,
             index: parse_template("vector"),
             version: None,
             version_type: VersionType::External,
@@ -91,6 +108,7 @@ async fn encoding_with_external_versioning_with_version_set_includes_version() {
     let config = InfinoConfig {
         bulk: BulkConfig {
             action: parse_template("create"),
+            default_action: None,
             index: parse_template("vector"),
             version: Some(parse_template("{{ my_field }}")),
             version_type: VersionType::External,
@@ -139,6 +157,7 @@ async fn encoding_with_external_gte_versioning_with_version_set_includes_version
     let config = InfinoConfig {
         bulk: BulkConfig {
             action: parse_template("create"),
+            default_action: None,
             index: parse_template("vector"),
             version: Some(parse_template("{{ my_field }}")),
             version_type: VersionType::ExternalGte,
@@ -252,6 +271,37 @@ async fn encode_datastream_mode() {
     assert_eq!(encoded.len(), encoded_size);
 }
 
+#[tokio::test]
+async fn datastream_mode_allows_index_op() {
+    let config = InfinoConfig {
+        endpoints: vec![String::from("https://example.com")],
+        mode: InfinoMode::DataStream,
+        data_stream: Some(DataStreamConfig {
+            allow_index_op: true,
+            ..Default::default()
+        }),
+        api_version: InfinoApiVersion::V8,
+        ..Default::default()
+    };
+    let es = InfinoCommon::parse_single(&config).await.unwrap();
+
+    let log = LogEvent::from("hello there");
+    let processed = process_log(log, &es.mode, None, &config.encoding).unwrap();
+    assert_eq!(processed.bulk_action, BulkAction::Index);
+
+    let config = InfinoConfig {
+        api_version: InfinoApiVersion::V7,
+        ..config
+    };
+    assert!(matches!(
+        InfinoCommon::parse_single(&config)
+            .await
+            .unwrap_err()
+            .downcast_ref::<ParseError>(),
+        Some(ParseError::DataStreamIndexOpUnsupported { configured: 7 })
+    ));
+}
+
 #[tokio::test]
 async fn encode_datastream_mode_no_routing() {
     use chrono::{TimeZone, Utc};
@@ -383,6 +433,129 @@ async fn default_bulk_settings() {
     assert!(InfinoCommon::parse_single(&config).await.is_ok());
 }
 
+#[tokio::test]
+async fn single_endpoint() {
+    let config = InfinoConfig {
+        endpoint: Some(String::from("https://example.com")),
+        api_version: InfinoApiVersion::V7,
+        ..Default::default()
+    };
+    let common = InfinoCommon::parse_single(&config).await.unwrap();
+    assert_eq!(common.base_url, "https://example.com");
+
+    let config = InfinoConfig {
+        endpoints: vec![String::from("https://other.example.com")],
+        ..config
+    };
+    let error = InfinoCommon::parse_single(&config).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        ParseError::EndpointsExclusive.to_string()
+    );
+}
+
+#[tokio::test]
+async fn resolves_principal_per_endpoint() {
+    let config = toml::from_str::<InfinoConfig>(
+        r#"
+        endpoints = [
+            "https://a.example.com",
+            { url = "https://b.example.com", auth = { strategy = "basic", user = "writer", password = "password" } },
+        ]
+        api_version = "v7"
+        auth = { strategy = "basic", user = "reader", password = "password" }
+    "#,
+    )
+    .unwrap();
+    let commons = InfinoCommon::parse_many(&config, &Default::default())
+        .await
+        .unwrap();
+    let principals = commons
+        .iter()
+        .map(|common| common.principal.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(principals, [Some("reader"), Some("writer")]);
+}
+
+#[tokio::test]
+async fn resolves_rotated_secret_credentials() {
+    // Reloads resolve the secrets of the configuration again, and rebuild the sink from it.
+    let template = r#"
+        endpoints = ["https://example.com"]
+        api_version = "v7"
+        auth = { strategy = "basic", user = "vector", password = "SECRET[vault.infino_password]" }
+    "#;
+    for (password, expected) in [
+        ("first", "Basic dmVjdG9yOmZpcnN0"),
+        ("second", "Basic dmVjdG9yOnNlY29uZA=="),
+    ] {
+        let secrets = HashMap::from([("vault.infino_password".to_owned(), password.to_owned())]);
+        let config = interpolate_secrets(template, &secrets).unwrap();
+        let config = toml::from_str::<InfinoConfig>(&config).unwrap();
+        let common = InfinoCommon::parse_single(&config).await.unwrap();
+        let request = HttpRequestBuilder::new(&common, &config)
+            .build_request(InfinoRequest {
+                payload: Bytes::new(),
+                finalizers: Default::default(),
+                batch_size: 0,
+                events_byte_size: Default::default(),
+                partition_key: None,
+                indexes: Default::default(),
+                lane: Default::default(),
+                encryption_key: None,
+                headers: Vec::new(),
+                query: Vec::new(),
+                metadata: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(request.headers()[AUTHORIZATION], expected);
+    }
+}
+
+#[tokio::test]
+async fn interpolates_endpoints() {
+    std::env::set_var("INFINO_SINK_TEST_ENDPOINT", "https://late.example.com");
+    let config = InfinoConfig {
+        endpoints: vec![String::from("${INFINO_SINK_TEST_ENDPOINT}")],
+        interpolate_endpoints: true,
+        api_version: InfinoApiVersion::V7,
+        ..Default::default()
+    };
+    let common = InfinoCommon::parse_single(&config).await.unwrap();
+    assert_eq!(common.base_url, "https://late.example.com");
+
+    let config = InfinoConfig {
+        endpoints: vec![String::from("${INFINO_SINK_TEST_MISSING_ENDPOINT}")],
+        ..config
+    };
+    assert!(InfinoCommon::parse_single(&config).await.is_err());
+}
+
+#[tokio::test]
+async fn appends_static_query_params() {
+    let config = InfinoConfig {
+        endpoints: vec![String::from("https://example.com")],
+        api_version: InfinoApiVersion::V7,
+        query: Some(
+            [
+                ("timeout", "30s"),
+                ("refresh", "false"),
+                ("routing", "{{ tenant }}"),
+            ]
+            .into_iter()
+            .map(|(param, value)| (param.to_owned(), value.to_owned()))
+            .collect(),
+        ),
+        ..Default::default()
+    };
+    let common = InfinoCommon::parse_single(&config).await.unwrap();
+    assert_eq!(common.query_params["timeout"], "30s");
+    assert_eq!(common.query_params["refresh"], "false");
+    assert!(!common.query_params.contains_key("routing"));
+    assert!(common.bulk_uri.query().unwrap().contains("timeout=30s"));
+}
+
 #[tokio::test]
 async fn decode_bulk_action() {
     let config = InfinoConfig {
@@ -402,6 +575,160 @@ async fn decode_bulk_action() {
     assert!(matches!(action, BulkAction::Create));
 }
 
+#[tokio::test]
+async fn falls_back_to_default_bulk_action() {
+    let config = InfinoConfig {
+        bulk: BulkConfig {
+            action: parse_template("{{ _action }}"),
+            default_action: Some(BulkAction::Index),
+            index: parse_template("vector"),
+            ..Default::default()
+        },
+        endpoints: vec![String::from("https://example.com")],
+        api_version: InfinoApiVersion::V7,
+        ..Default::default()
+    };
+    let es = InfinoCommon::parse_single(&config).await.unwrap();
+
+    let mut log = LogEvent::from("hello there");
+    log.insert("_action", "delete");
+    assert_eq!(es.mode.bulk_action(&log), Some(BulkAction::Delete));
+    log.insert("_action", "upsert");
+    assert_eq!(es.mode.bulk_action(&log), Some(BulkAction::Index));
+    log.remove("_action");
+    assert_eq!(es.mode.bulk_action(&log), Some(BulkAction::Index));
+}
+
+#[tokio::test]
+async fn encodes_update_and_delete_actions() {
+    let config = InfinoConfig {
+        bulk: BulkConfig {
+            action: parse_template("{{ _action }}"),
+            index: parse_template("vector"),
+            ..Default::default()
+        },
+        id_key: Some("id".into()),
+        encoding: Transformer::new(None, Some(vec!["_action".into()]), None).unwrap(),
+        endpoints: vec![String::from("https://example.com")],
+        api_version: InfinoApiVersion::V8,
+        ..Default::default()
+    };
+    let es = InfinoCommon::parse_single(&config).await.unwrap();
+    let process = |action: &str, id: Option<&str>| {
+        let mut log = LogEvent::from("hello there");
+        log.insert("_action", action);
+        if let Some(id) = id {
+            log.insert("id", id);
+        }
+        process_log(log, &es.mode, config.id_key.as_ref(), &config.encoding)
+    };
+
+    let mut encoded = vec![];
+    let (encoded_size, _json_size) = es
+        .request_builder
+        .encoder
+        .encode_input(
+            vec![
+                process("update", Some("1")).unwrap(),
+                process("delete", Some("2")).unwrap(),
+            ],
+            &mut encoded,
+        )
+        .unwrap();
+
+    let expected = r#"{"update":{"_index":"vector","_id":"1"}}
+{"doc":{"message":"hello there"}}
+{"delete":{"_index":"vector","_id":"2"}}
+"#;
+    assert_eq!(std::str::from_utf8(&encoded).unwrap(), expected);
+    assert_eq!(encoded.len(), encoded_size);
+
+    // Without an ID, there is no document to update or delete.
+    assert!(process("update", None).is_none());
+    assert!(process("delete", None).is_none());
+}
+
+#[test]
+fn auto_mode_creates_in_data_streams() {
+    let mode = InfinoCommonMode::Bulk {
+        index: parse_template("{{ idx }}"),
+        action: parse_template("index"),
+        default_action: None,
+        version: None,
+        version_type: VersionType::Internal,
+        data_streams: Arc::new(HashSet::from([String::from("logs-nginx-default")])),
+    };
+    let transformer = Transformer::default();
+
+    let mut log = LogEvent::from("hello there");
+    log.insert("idx", "logs-nginx-default");
+    let event = process_log(log, &mode, None, &transformer).unwrap();
+    assert_eq!(event.bulk_action, BulkAction::Create);
+
+    let mut log = LogEvent::from("hello there");
+    log.insert("idx", "vector");
+    let event = process_log(log, &mode, None, &transformer).unwrap();
+    assert_eq!(event.bulk_action, BulkAction::Index);
+}
+
+#[test]
+fn partitions_by_rendered_index() {
+    use chrono::{TimeZone, Utc};
+
+    use crate::config::log_schema;
+
+    let mode = InfinoCommonMode::Bulk {
+        index: parse_template("logs-{{ service }}-%Y.%m.%d"),
+        action: parse_template("index"),
+        default_action: None,
+        version: None,
+        version_type: VersionType::Internal,
+        data_streams: Default::default(),
+    };
+    let transformer = Transformer::default();
+    let process = |service: &str| {
+        let mut log = LogEvent::from("hello there");
+        log.insert("service", service);
+        log.insert(
+            (PathPrefix::Event, log_schema().timestamp_key().unwrap()),
+            Utc.with_ymd_and_hms(2020, 12, 1, 1, 2, 3)
+                .single()
+                .expect("invalid timestamp"),
+        );
+        process_log(log, &mode, None, &transformer).unwrap()
+    };
+
+    let checkout = process("checkout");
+    let payments = process("payments");
+    assert_eq!(checkout.index, "logs-checkout-2020.12.01");
+    assert_eq!(payments.index, "logs-payments-2020.12.01");
+    assert_eq!(
+        PartitionKey::of(&checkout, false),
+        PartitionKey::of(&process("checkout"), false)
+    );
+    assert_ne!(
+        PartitionKey::of(&checkout, false),
+        PartitionKey::of(&payments, false)
+    );
+
+    // In ordered mode, the bulk action of an event does not change its partition.
+    let delete = ProcessedEvent {
+        bulk_action: BulkAction::Delete,
+        ..process("checkout")
+    };
+    assert_ne!(
+        PartitionKey::of(&checkout, false),
+        PartitionKey::of(&delete, false)
+    );
+    assert_eq!(
+        PartitionKey::of(&checkout, true),
+        PartitionKey::of(&delete, true)
+    );
+
+    // Events the index can not be rendered for are dropped.
+    assert!(process_log(LogEvent::from("hello there"), &mode, None, &transformer).is_none());
+}
+
 #[tokio::test]
 async fn encode_datastream_mode_no_sync() {
     use chrono::{TimeZone, Utc};
@@ -648,3 +975,294 @@ async fn datastream_index_name() {
         assert_eq!(processed_event.index, test_case.want, "{test_case:?}");
     }
 }
+
+#[test]
+fn version_compatibility() {
+    let config = InfinoConfig {
+        api_version: InfinoApiVersion::V7,
+        ..Default::default()
+    };
+    assert!(check_version_compatibility(&config, 7).is_ok());
+    assert!(matches!(
+        check_version_compatibility(&config, 8),
+        Err(ParseError::ApiVersionMismatch {
+            configured: 7,
+            server: 8
+        })
+    ));
+
+    let config = InfinoConfig {
+        mode: InfinoMode::DataStream,
+        ..Default::default()
+    };
+    assert!(check_version_compatibility(&config, 8).is_ok());
+    assert!(matches!(
+        check_version_compatibility(&config, 6),
+        Err(ParseError::UnsupportedFeature { required: 7, .. })
+    ));
+
+    let config = InfinoConfig {
+        data_stream: Some(DataStreamConfig {
+            allow_index_op: true,
+            ..Default::default()
+        }),
+        ..config
+    };
+    assert!(check_version_compatibility(&config, 8).is_ok());
+    assert!(matches!(
+        check_version_compatibility(&config, 7),
+        Err(ParseError::UnsupportedFeature { required: 8, .. })
+    ));
+}
+
+#[test]
+fn reorders_batch_by_timestamp() {
+    use chrono::{TimeZone, Utc};
+
+    use crate::config::log_schema;
+
+    let event = |message: &str, second: Option<u32>| {
+        let mut log = LogEvent::from(message);
+        if let Some(second) = second {
+            log.insert(
+                (PathPrefix::Event, log_schema().timestamp_key().unwrap()),
+                Utc.with_ymd_and_hms(2020, 12, 1, 1, 2, second).unwrap(),
+            );
+        }
+        ProcessedEvent {
+            index: "vector".to_owned(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    };
+
+    let mut events = vec![
+        event("c", Some(3)),
+        event("a", Some(1)),
+        event("none", None),
+        event("d", Some(4)),
+        event("b", Some(2)),
+    ];
+    reorder_by_timestamp(&mut events, 3);
+
+    let messages = events
+        .iter()
+        .map(|event| {
+            event
+                .log
+                .get_message()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(messages, vec!["none", "a", "c", "b", "d"]);
+}
+
+#[test]
+fn create_with_content_id_is_stable() {
+    let event = |message: &str| {
+        let mut log = LogEvent::from(message);
+        log.insert("status", 200);
+        ProcessedEvent {
+            index: "vector".to_owned(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        }
+    };
+
+    let mut first = event("hello");
+    let mut second = event("hello");
+    let mut other = event("goodbye");
+    for event in [&mut first, &mut second, &mut other] {
+        create_with_content_id(event);
+        assert_eq!(event.bulk_action, BulkAction::Create);
+    }
+    assert!(matches!(&first.document_metadata, DocumentMetadata::Id(id) if id.len() == 64));
+    assert_eq!(first.document_metadata, second.document_metadata);
+    assert_ne!(first.document_metadata, other.document_metadata);
+
+    let mut with_id = event("hello");
+    with_id.document_metadata = DocumentMetadata::Id("42".to_owned());
+    create_with_content_id(&mut with_id);
+    assert_eq!(
+        with_id.document_metadata,
+        DocumentMetadata::Id("42".to_owned())
+    );
+}
+
+#[test]
+fn create_with_content_id_keeps_updates_and_deletes() {
+    for bulk_action in [BulkAction::Update, BulkAction::Delete] {
+        let mut event = ProcessedEvent {
+            index: "vector".to_owned(),
+            bulk_action,
+            log: LogEvent::from("hello"),
+            document_metadata: DocumentMetadata::Id("42".to_owned()),
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        };
+        create_with_content_id(&mut event);
+        assert_eq!(event.bulk_action, bulk_action);
+        assert_eq!(
+            event.document_metadata,
+            DocumentMetadata::Id("42".to_owned())
+        );
+    }
+}
+
+#[test]
+fn moves_message() {
+    let mut log = LogEvent::default();
+    log.insert("msg", "hello");
+    log.insert("status", 200);
+
+    move_message(&mut log, &"msg".into());
+    assert_eq!(log.get("message"), Some(&Value::from("hello")));
+    assert!(!log.contains("msg"));
+    assert_eq!(log.get("status"), Some(&Value::from(200)));
+
+    let mut log = LogEvent::from("kept");
+    move_message(&mut log, &"msg".into());
+    assert_eq!(log.get("message"), Some(&Value::from("kept")));
+}
+
+#[test]
+fn keeps_existing_message() {
+    let mut log = LogEvent::from("kept");
+    log.insert("msg", "hello");
+
+    move_message(&mut log, &"msg".into());
+    assert_eq!(log.get("message"), Some(&Value::from("kept")));
+    assert_eq!(log.get("msg"), Some(&Value::from("hello")));
+}
+
+#[test]
+fn stores_raw_copy() {
+    let mut log = LogEvent::default();
+    log.insert("message", "hello");
+    log.insert("status", 200);
+
+    store_raw(&mut log, &"_raw".into());
+    assert_eq!(
+        log.get("_raw"),
+        Some(&Value::from(r#"{"message":"hello","status":200}"#))
+    );
+}
+
+#[test]
+fn parses_json_fields() {
+    let mut log = LogEvent::default();
+    log.insert("object", r#"{"user":{"id":42},"tags":["a"]}"#);
+    log.insert("array", "[1, 2]");
+    log.insert("invalid", "{not json");
+    log.insert("scalar", "42");
+    log.insert("unlisted", "{}");
+
+    let fields = ["object", "array", "invalid", "scalar", "missing"].map(Into::into);
+    parse_json_fields(&mut log, &fields);
+    assert_eq!(log.get("object.user.id"), Some(&Value::from(42)));
+    assert_eq!(log.get("object.tags[0]"), Some(&Value::from("a")));
+    assert_eq!(
+        log.get("array"),
+        Some(&Value::Array(vec![Value::from(1), Value::from(2)]))
+    );
+    assert_eq!(log.get("invalid"), Some(&Value::from("{not json")));
+    assert_eq!(log.get("scalar"), Some(&Value::from("42")));
+    assert_eq!(log.get("unlisted"), Some(&Value::from("{}")));
+}
+
+#[test]
+fn remaps_data_stream_timestamp() {
+    use chrono::{TimeZone, Utc};
+
+    use crate::config::log_schema;
+
+    let timestamp = Value::from(
+        Utc.with_ymd_and_hms(2020, 12, 1, 1, 2, 3)
+            .single()
+            .expect("invalid timestamp"),
+    );
+    let event = || {
+        let mut log = LogEvent::from("hello there");
+        log.insert(
+            (PathPrefix::Event, log_schema().timestamp_key().unwrap()),
+            timestamp.clone(),
+        );
+        log.insert("@timestamp", "existing");
+        log.insert("event.created", "created");
+        log
+    };
+
+    let mut log = event();
+    DataStreamConfig::default().remap_timestamp(&mut log);
+    assert_eq!(log.get("@timestamp"), Some(&timestamp));
+    assert!(log.get("timestamp").is_none());
+
+    let mut log = event();
+    let config = DataStreamConfig {
+        timestamp_mode: DataStreamTimestampMode::IfMissing,
+        ..Default::default()
+    };
+    config.remap_timestamp(&mut log);
+    assert_eq!(log.get("@timestamp"), Some(&Value::from("existing")));
+    assert_eq!(log.get("timestamp"), Some(&timestamp));
+
+    let mut log = event();
+    let config = DataStreamConfig {
+        timestamp_source: Some("event.created".into()),
+        ..Default::default()
+    };
+    config.remap_timestamp(&mut log);
+    assert_eq!(log.get("@timestamp"), Some(&Value::from("created")));
+    assert!(log.get("event.created").is_none());
+}
+
+#[tokio::test]
+async fn rejects_events_with_unrendered_templates() {
+    use crate::event::{BatchNotifier, BatchStatus, EventFinalizer};
+
+    let encryption = EncryptionConfig {
+        key: parse_template("{{ kms_key }}"),
+        header: "X-Infino-Encryption-Key".to_owned(),
+    };
+    let event = |key: Option<&str>| {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let mut log = LogEvent::from("hello");
+        if let Some(key) = key {
+            log.insert("kms_key", key);
+        }
+        log.add_finalizer(EventFinalizer::new(batch));
+        let event = ProcessedEvent {
+            index: "logs".into(),
+            bulk_action: BulkAction::Index,
+            log,
+            document_metadata: DocumentMetadata::WithoutId,
+            lane: Default::default(),
+            encryption_key: None,
+            headers: Vec::new(),
+            query: Vec::new(),
+        };
+        (event, receiver)
+    };
+
+    let (rendered, _receiver) = event(Some("alias/tenant-a"));
+    let rendered = render_templates(rendered, Some(&encryption), None, None).unwrap();
+    assert_eq!(rendered.encryption_key.as_deref(), Some("alias/tenant-a"));
+
+    let (unrendered, receiver) = event(None);
+    assert!(render_templates(unrendered, Some(&encryption), None, None).is_none());
+    assert_eq!(receiver.await, BatchStatus::Rejected);
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vrl::path::PathPrefix;
+
+use crate::{
+    config::log_schema,
+    event::{LogEvent, Value},
+};
+
+/// A field the timestamp of logs may be read from.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TimestampField {
+    /// The name of the field.
+    #[configurable(metadata(docs::examples = "time"))]
+    #[configurable(metadata(docs::examples = "event.created"))]
+    pub field: ConfigValuePath,
+
+    /// The format of the field.
+    ///
+    /// `unix` and `unix_ms` read numbers of seconds and milliseconds since the Unix epoch. Other
+    /// formats are [strftime specifiers][strftime] for strings, read as UTC unless they include an
+    /// offset. By default, strings are read as RFC 3339 timestamps, and numbers as seconds since
+    /// the Unix epoch.
+    ///
+    /// [strftime]: https://docs.rs/chrono/latest/chrono/format/strftime/index.html#specifiers
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "unix_ms"))]
+    #[configurable(metadata(docs::examples = "%d/%b/%Y:%H:%M:%S %z"))]
+    pub format: Option<String>,
+}
+
+impl TimestampField {
+    fn parse(&self, value: &Value) -> Option<DateTime<Utc>> {
+        match (value, self.format.as_deref()) {
+            (Value::Timestamp(timestamp), _) => Some(*timestamp),
+            (Value::Integer(millis), Some("unix_ms")) => Utc.timestamp_millis_opt(*millis).single(),
+            (Value::Float(millis), Some("unix_ms")) => Utc
+                .timestamp_millis_opt(millis.into_inner() as i64)
+                .single(),
+            (Value::Integer(secs), None | Some("unix")) => Utc.timestamp_opt(*secs, 0).single(),
+            (Value::Float(secs), None | Some("unix")) => {
+                let secs = secs.into_inner();
+                Utc.timestamp_opt(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+                    .single()
+            }
+            (Value::Bytes(bytes), format) => {
+                let text = std::str::from_utf8(bytes).ok()?.trim();
+                match format {
+                    None => DateTime::parse_from_rfc3339(text)
+                        .ok()
+                        .map(|timestamp| timestamp.with_timezone(&Utc)),
+                    Some("unix") | Some("unix_ms") => {
+                        let number = Value::from(text.parse::<i64>().ok()?);
+                        self.parse(&number)
+                    }
+                    Some(format) => DateTime::parse_from_str(text, format)
+                        .map(|timestamp| timestamp.with_timezone(&Utc))
+                        .or_else(|_| {
+                            NaiveDateTime::parse_from_str(text, format)
+                                .map(|timestamp| timestamp.and_utc())
+                        })
+                        .ok(),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Sets the timestamp of `log` from the first of `fields` holding a valid timestamp, or to the
+/// current time if none does.
+pub(super) fn resolve_timestamp(log: &mut LogEvent, fields: &[TimestampField]) {
+    let timestamp = fields
+        .iter()
+        .find_map(|field| {
+            log.get((PathPrefix::Event, &field.field))
+                .and_then(|value| field.parse(value))
+        })
+        .unwrap_or_else(Utc::now);
+
+    if let Some(path) = log
+        .timestamp_path()
+        .or_else(|| log_schema().timestamp_key_target_path())
+        .cloned()
+    {
+        log.insert(&path, timestamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(field: &str, format: Option<&str>) -> TimestampField {
+        TimestampField {
+            field: field.into(),
+            format: format.map(ToOwned::to_owned),
+        }
+    }
+
+    fn timestamp(log: &LogEvent) -> DateTime<Utc> {
+        *log.get_timestamp().unwrap().as_timestamp().unwrap()
+    }
+
+    #[test]
+    fn parses_formats() {
+        let expected = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        for (value, format) in [
+            (Value::from(expected), None),
+            (Value::from("2024-01-02T03:04:05Z"), None),
+            (Value::from("2024-01-02T04:04:05+01:00"), None),
+            (Value::from(1_704_164_645), None),
+            (Value::from(1_704_164_645), Some("unix")),
+            (Value::from(1_704_164_645_000_i64), Some("unix_ms")),
+            (Value::from("1704164645000"), Some("unix_ms")),
+            (
+                Value::from("02/Jan/2024:04:04:05 +0100"),
+                Some("%d/%b/%Y:%H:%M:%S %z"),
+            ),
+            (
+                Value::from("2024-01-02 03:04:05"),
+                Some("%Y-%m-%d %H:%M:%S"),
+            ),
+        ] {
+            assert_eq!(
+                field("time", format).parse(&value),
+                Some(expected),
+                "{value:?} {format:?}"
+            );
+        }
+        assert_eq!(field("time", None).parse(&Value::from("yesterday")), None);
+    }
+
+    #[test]
+    fn tries_fields_in_order() {
+        let fields = [field("ts", Some("unix_ms")), field("time", None)];
+
+        let mut log = LogEvent::default();
+        log.insert("ts", "not a timestamp");
+        log.insert("time", "2024-01-02T03:04:05Z");
+        resolve_timestamp(&mut log, &fields);
+        assert_eq!(
+            timestamp(&log),
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+        );
+
+        let before = Utc::now();
+        let mut log = LogEvent::default();
+        resolve_timestamp(&mut log, &fields);
+        assert!(timestamp(&log) >= before);
+    }
+}
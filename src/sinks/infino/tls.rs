@@ -0,0 +1,500 @@
+use std::{fmt, num::NonZeroU64, sync::Arc};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use hyper::{
+    body::HttpBody,
+    client::{self, Client},
+};
+use openssl::{
+    ssl::{SslContextBuilder, SslVerifyMode, SslVersion},
+    x509::X509Ref,
+};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use snafu::ResultExt;
+use vector_lib::{config::proxy::ProxyConfig, configurable::configurable_component};
+
+use super::diagnostics::suggest_field;
+use crate::{
+    http::{BuildTlsConnectorSnafu, HttpClient, HttpError, MakeHttpsConnectorSnafu},
+    sinks::infino::ParseError,
+    tls::{tls_connector_builder, TlsConfig, TlsSettings},
+};
+
+/// The TLS 1.2 cipher suites approved by FIPS 140, in OpenSSL notation.
+const FIPS_CIPHER_LIST: &str = "ECDHE-ECDSA-AES128-GCM-SHA256:ECDHE-RSA-AES128-GCM-SHA256:\
+                                ECDHE-ECDSA-AES256-GCM-SHA384:ECDHE-RSA-AES256-GCM-SHA384";
+
+/// The TLS 1.3 cipher suites approved by FIPS 140.
+const FIPS_CIPHERSUITES: &str = "TLS_AES_128_GCM_SHA256:TLS_AES_256_GCM_SHA384";
+
+/// The key exchange groups approved by FIPS 140.
+const FIPS_GROUPS: &str = "P-256:P-384:P-521";
+
+/// TLS configuration of the Infino sink.
+#[configurable_component]
+#[derive(Clone, Debug, Default)]
+#[serde(remote = "Self")]
+pub struct InfinoTlsConfig {
+    #[serde(flatten)]
+    pub options: TlsConfig,
+
+    /// Whether or not to restrict TLS to the algorithms approved by FIPS 140.
+    ///
+    /// Connections to Infino then use TLS 1.2 or later, with AES-GCM cipher suites and NIST
+    /// curves only, and the sink fails to start if certificate or hostname verification is
+    /// disabled. This restricts the algorithms negotiated, while the certification of the
+    /// cryptographic module depends on the OpenSSL build Vector runs with.
+    #[serde(default)]
+    pub fips: bool,
+
+    /// The oldest TLS version to connect with.
+    ///
+    /// By default, the oldest version supported by OpenSSL is allowed, or TLS 1.2 with `fips`.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "1.3"))]
+    pub min_version: Option<TlsVersion>,
+
+    /// The latest TLS version to connect with.
+    ///
+    /// By default, the latest version supported by OpenSSL is allowed.
+    #[serde(default)]
+    pub max_version: Option<TlsVersion>,
+
+    /// The cipher suites to connect with, in order of preference.
+    ///
+    /// TLS 1.3 suites are named as in the TLS standard, such as `TLS_AES_256_GCM_SHA384`, and
+    /// older ones as in OpenSSL, such as `ECDHE-RSA-AES256-GCM-SHA384`. By default, the suites
+    /// enabled by OpenSSL are allowed, or the ones approved by FIPS 140 with `fips`.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "TLS_AES_256_GCM_SHA384"))]
+    #[configurable(metadata(docs::examples = "ECDHE-RSA-AES256-GCM-SHA384"))]
+    pub cipher_suites: Vec<String>,
+
+    /// The pins of the public keys Infino endpoints may present, as base64 SHA-256 hashes.
+    ///
+    /// Each pin is the hash of the DER-encoded SubjectPublicKeyInfo of a certificate, as in HTTP
+    /// Public Key Pinning. Connections are refused unless the certificate chain presented by the
+    /// endpoint holds a pinned key, which protects against certificates issued by compromised
+    /// intermediate authorities. Pin a backup key along with the current one, so that the
+    /// certificate can be renewed without an outage.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "jQJTbIh0grw0/1TkHSumWb+Fs0Ggogr621gT3PvPKG0="))]
+    pub pins: Vec<String>,
+
+    /// The interval at which `crt_file` and `key_file` are read again, in seconds.
+    ///
+    /// When the client certificate or key changes on disk, such as when the certificate is
+    /// renewed, the endpoints open new connections with it, without Vector being restarted. By
+    /// default, the files are only read when the sink is built.
+    #[serde(default)]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 60))]
+    pub reload_interval_secs: Option<NonZeroU64>,
+}
+
+/// The options of `InfinoTlsConfig` besides those of `TlsConfig`.
+const OPTIONS: [&str; 6] = [
+    "fips",
+    "min_version",
+    "max_version",
+    "cipher_suites",
+    "pins",
+    "reload_interval_secs",
+];
+
+// The derived implementations are generated as inherent functions by `remote = "Self"`, so that
+// unknown options are rejected, which `#[serde(flatten)]` does not support.
+impl Serialize for InfinoTlsConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InfinoTlsConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut options = JsonMap::deserialize(deserializer)?;
+        let own = OPTIONS
+            .iter()
+            .filter_map(|option| options.remove_entry(*option))
+            .collect();
+        let mut config = Self::deserialize(JsonValue::Object(own)).map_err(D::Error::custom)?;
+        // `TlsConfig` rejects unknown fields, which are all left once the own options are removed.
+        let options = TlsConfig::deserialize(JsonValue::Object(options));
+        config.options = options.map_err(|error| match suggest_field(&error.to_string()) {
+            Some(message) => D::Error::custom(message),
+            None => D::Error::custom(error),
+        })?;
+        Ok(config)
+    }
+}
+
+/// The SHA-256 hash of a public key.
+type Pin = [u8; 32];
+
+/// A version of TLS.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum TlsVersion {
+    /// TLS 1.0.
+    #[serde(rename = "1.0")]
+    V1_0,
+
+    /// TLS 1.1.
+    #[serde(rename = "1.1")]
+    V1_1,
+
+    /// TLS 1.2.
+    #[serde(rename = "1.2")]
+    V1_2,
+
+    /// TLS 1.3.
+    #[serde(rename = "1.3")]
+    V1_3,
+}
+
+impl TlsVersion {
+    const fn ssl_version(self) -> SslVersion {
+        match self {
+            Self::V1_0 => SslVersion::TLS1,
+            Self::V1_1 => SslVersion::TLS1_1,
+            Self::V1_2 => SslVersion::TLS1_2,
+            Self::V1_3 => SslVersion::TLS1_3,
+        }
+    }
+}
+
+impl InfinoTlsConfig {
+    /// Checks that the options are consistent, and do not conflict with `fips`.
+    fn validate(&self) -> Result<(), ParseError> {
+        if let (Some(min), Some(max)) = (self.min_version, self.max_version) {
+            if min > max {
+                return Err(ParseError::InvalidTlsVersions);
+            }
+        }
+        if self.fips {
+            if self.min_version.is_some_and(|min| min < TlsVersion::V1_2) {
+                return Err(ParseError::FipsViolation {
+                    reason: "`tls.min_version` is older than TLS 1.2",
+                });
+            }
+            let approved = |suite: &String| {
+                FIPS_CIPHER_LIST
+                    .split(':')
+                    .chain(FIPS_CIPHERSUITES.split(':'))
+                    .any(|approved| approved == suite)
+            };
+            if !self.cipher_suites.iter().all(approved) {
+                return Err(ParseError::FipsViolation {
+                    reason: "`tls.cipher_suites` allows suites that are not approved",
+                });
+            }
+            if self.options.verify_certificate == Some(false) {
+                return Err(ParseError::FipsViolation {
+                    reason: "`tls.verify_certificate` is disabled",
+                });
+            }
+            if self.options.verify_hostname == Some(false) {
+                return Err(ParseError::FipsViolation {
+                    reason: "`tls.verify_hostname` is disabled",
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_pins(&self) -> Result<Vec<Pin>, ParseError> {
+        self.pins
+            .iter()
+            .map(|pin| {
+                BASE64_STANDARD
+                    .decode(pin)
+                    .ok()
+                    .and_then(|hash| Pin::try_from(hash).ok())
+                    .ok_or_else(|| ParseError::InvalidTlsPin { pin: pin.clone() })
+            })
+            .collect()
+    }
+
+    /// Whether or not the options change the TLS context built from `options`.
+    fn customizes_context(&self) -> bool {
+        self.fips
+            || self.min_version.is_some()
+            || self.max_version.is_some()
+            || !self.cipher_suites.is_empty()
+            || !self.pins.is_empty()
+    }
+
+    fn apply(
+        &self,
+        context: &mut SslContextBuilder,
+        pins: &[Pin],
+    ) -> Result<(), openssl::error::ErrorStack> {
+        let min_version = self
+            .min_version
+            .or_else(|| self.fips.then_some(TlsVersion::V1_2));
+        if let Some(version) = min_version {
+            context.set_min_proto_version(Some(version.ssl_version()))?;
+        }
+        if let Some(version) = self.max_version {
+            context.set_max_proto_version(Some(version.ssl_version()))?;
+        }
+
+        let (suites, ciphers): (Vec<&str>, Vec<&str>) = self
+            .cipher_suites
+            .iter()
+            .map(String::as_str)
+            .partition(|suite| suite.starts_with("TLS_"));
+        if !ciphers.is_empty() {
+            context.set_cipher_list(&ciphers.join(":"))?;
+        } else if self.fips {
+            context.set_cipher_list(FIPS_CIPHER_LIST)?;
+        }
+        if !suites.is_empty() {
+            context.set_ciphersuites(&suites.join(":"))?;
+        } else if self.fips {
+            context.set_ciphersuites(FIPS_CIPHERSUITES)?;
+        }
+
+        if self.fips {
+            context.set_groups_list(FIPS_GROUPS)?;
+        }
+
+        if !pins.is_empty() {
+            let pins = pins.to_vec();
+            let verify_certificate = self.options.verify_certificate != Some(false);
+            // The callback is called for each certificate of the chain, from the root down to the
+            // leaf, once the whole chain is known.
+            context.set_verify_callback(SslVerifyMode::PEER, move |verified, store| {
+                if !verified && verify_certificate {
+                    return false;
+                }
+                if store.error_depth() > 0 {
+                    return true;
+                }
+                let pinned = store.chain().map_or(false, |chain| {
+                    chain.iter().any(|certificate| {
+                        spki_hash(certificate).map_or(false, |hash| pins.contains(&hash))
+                    })
+                });
+                if !pinned {
+                    error!(message = "Infino endpoint presented no pinned public key.");
+                }
+                pinned
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the SHA-256 hash of the SubjectPublicKeyInfo of `certificate`.
+fn spki_hash(certificate: &X509Ref) -> Option<Pin> {
+    let spki = certificate.public_key().ok()?.public_key_to_der().ok()?;
+    Some(Sha256::digest(spki).into())
+}
+
+/// The settings of the connections to Infino.
+#[derive(Clone, Debug, Default)]
+pub struct InfinoTlsSettings {
+    settings: TlsSettings,
+    context: Option<Arc<TlsContext>>,
+}
+
+/// The options applied to the TLS context on top of `TlsSettings`.
+#[derive(Debug)]
+struct TlsContext {
+    config: InfinoTlsConfig,
+    pins: Vec<Pin>,
+}
+
+impl InfinoTlsSettings {
+    /// Builds a client connecting to Infino with these settings.
+    pub fn http_client<B>(&self, proxy: &ProxyConfig) -> Result<HttpClient<B>, HttpError>
+    where
+        B: fmt::Debug + HttpBody + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<crate::Error>,
+    {
+        self.http_client_with(proxy, &mut Client::builder())
+    }
+
+    /// Builds a client connecting to Infino with these settings, and the options of
+    /// `client_builder`.
+    pub fn http_client_with<B>(
+        &self,
+        proxy: &ProxyConfig,
+        client_builder: &mut client::Builder,
+    ) -> Result<HttpClient<B>, HttpError>
+    where
+        B: fmt::Debug + HttpBody + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<crate::Error>,
+    {
+        let Some(context) = self.context.clone() else {
+            return HttpClient::new_with_custom_client(
+                self.settings.clone(),
+                proxy,
+                client_builder,
+            );
+        };
+        let tls_connector = move |settings: &_| {
+            let mut builder = tls_connector_builder(settings).context(BuildTlsConnectorSnafu)?;
+            context
+                .config
+                .apply(&mut builder, &context.pins)
+                .context(MakeHttpsConnectorSnafu)?;
+            Ok(builder)
+        };
+        HttpClient::new_with_tls_connector(
+            self.settings.clone(),
+            tls_connector,
+            proxy,
+            client_builder,
+        )
+    }
+}
+
+/// Builds the settings of the connections to Infino.
+pub fn tls_settings(config: &Option<InfinoTlsConfig>) -> crate::Result<InfinoTlsSettings> {
+    let Some(config) = config else {
+        return Ok(InfinoTlsSettings {
+            settings: TlsSettings::from_options(&None)?,
+            context: None,
+        });
+    };
+    config.validate()?;
+    let pins = config.decode_pins()?;
+
+    let settings = TlsSettings::from_options(&Some(config.options.clone()))?;
+    let context = config.customizes_context().then(|| {
+        Arc::new(TlsContext {
+            config: config.clone(),
+            pins,
+        })
+    });
+    Ok(InfinoTlsSettings { settings, context })
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::ssl::{SslConnector, SslMethod};
+
+    use super::*;
+
+    #[test]
+    fn fips_rejects_disabled_verification() {
+        let config = InfinoTlsConfig {
+            options: TlsConfig {
+                verify_certificate: Some(false),
+                ..Default::default()
+            },
+            fips: true,
+            ..Default::default()
+        };
+        assert!(tls_settings(&Some(config)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        let config = serde_json::from_str::<InfinoTlsConfig>(
+            r#"{ "verify_certificate": false, "fips": true, "ca_path": "/ca.crt" }"#,
+        )
+        .unwrap();
+        assert_eq!(config.options.verify_certificate, Some(false));
+        assert_eq!(config.options.ca_file, Some("/ca.crt".into()));
+        assert!(config.fips);
+
+        let error = serde_json::from_str::<InfinoTlsConfig>(r#"{ "verify_certficate": false }"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("did you mean `verify_certificate`?"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn fips_restricts_the_context() {
+        let config = InfinoTlsConfig {
+            fips: true,
+            ..Default::default()
+        };
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        config.apply(&mut builder, &[]).unwrap();
+        assert!(builder.min_proto_version() == Some(SslVersion::TLS1_2));
+    }
+
+    #[test]
+    fn fips_rejects_unapproved_options() {
+        let config = InfinoTlsConfig {
+            fips: true,
+            cipher_suites: vec!["TLS_CHACHA20_POLY1305_SHA256".into()],
+            ..Default::default()
+        };
+        assert!(tls_settings(&Some(config)).is_err());
+
+        let config = InfinoTlsConfig {
+            fips: true,
+            min_version: Some(TlsVersion::V1_1),
+            ..Default::default()
+        };
+        assert!(tls_settings(&Some(config)).is_err());
+    }
+
+    #[test]
+    fn applies_versions_and_suites() {
+        let config = InfinoTlsConfig {
+            min_version: Some(TlsVersion::V1_3),
+            max_version: Some(TlsVersion::V1_3),
+            cipher_suites: vec!["TLS_AES_256_GCM_SHA384".into()],
+            ..Default::default()
+        };
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        config.apply(&mut builder, &[]).unwrap();
+        assert!(builder.min_proto_version() == Some(SslVersion::TLS1_3));
+        assert!(builder.max_proto_version() == Some(SslVersion::TLS1_3));
+
+        let config = InfinoTlsConfig {
+            min_version: Some(TlsVersion::V1_3),
+            max_version: Some(TlsVersion::V1_2),
+            ..Default::default()
+        };
+        assert!(tls_settings(&Some(config)).is_err());
+    }
+
+    #[test]
+    fn decodes_pins() {
+        let pin = "jQJTbIh0grw0/1TkHSumWb+Fs0Ggogr621gT3PvPKG0=";
+        let config = InfinoTlsConfig {
+            pins: vec![pin.into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.decode_pins().unwrap(),
+            [Pin::try_from(BASE64_STANDARD.decode(pin).unwrap()).unwrap()]
+        );
+        assert!(tls_settings(&Some(config)).is_ok());
+
+        let config = InfinoTlsConfig {
+            pins: vec!["c2hvcnQ=".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.decode_pins(),
+            Err(ParseError::InvalidTlsPin { .. })
+        ));
+    }
+
+    #[test]
+    fn hashes_public_keys() {
+        let pem = std::fs::read(crate::tls::TEST_PEM_CA_PATH).unwrap();
+        let certificate = openssl::x509::X509::from_pem(&pem).unwrap();
+        let hash = spki_hash(&certificate).unwrap();
+        assert_eq!(
+            BASE64_STANDARD.encode(hash),
+            "SlWF2/gnMPSLvQXR+u97eXm1JLrT5aBSRIYsY45pEkM="
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{TimeZone, Utc};
+use vector_lib::configurable::configurable_component;
+
+use crate::{
+    event::{LogEvent, ObjectMap, TraceEvent, Value},
+    internal_events::TemplateRenderingError,
+    template::Template,
+};
+
+/// Trace configuration.
+///
+/// Traces are indexed into a dedicated index, one document per trace, with the fields of their
+/// spans mapped to the fields of Infino traces. The sink only accepts traces when this is set.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TraceConfig {
+    /// The index traces are written to.
+    #[serde(default = "default_index")]
+    #[configurable(metadata(docs::examples = "traces-{{ service }}-%Y.%m.%d"))]
+    pub index: Template,
+}
+
+fn default_index() -> Template {
+    Template::try_from("traces-%Y.%m.%d").expect("valid template")
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            index: default_index(),
+        }
+    }
+}
+
+impl TraceConfig {
+    /// Returns the index of `trace`, or `None` if the trace has to be dropped.
+    pub fn index(&self, trace: &LogEvent) -> Option<String> {
+        self.index
+            .render_string(trace)
+            .map_err(|error| {
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("traces.index"),
+                    drop_event: true,
+                });
+            })
+            .ok()
+    }
+}
+
+/// The fields of spans, and the fields of Infino spans they are written to.
+const SPAN_FIELDS: [(&str, &str); 10] = [
+    ("trace_id", "trace_id"),
+    ("span_id", "span_id"),
+    ("parent_id", "parent_span_id"),
+    ("service", "service_name"),
+    ("name", "name"),
+    ("resource", "resource"),
+    ("type", "kind"),
+    ("duration", "duration_ns"),
+    ("meta", "attributes"),
+    ("metrics", "measures"),
+];
+
+/// Converts a trace into an Infino trace document.
+///
+/// The spans of the trace have their fields renamed as Infino expects them, and their start time,
+/// in nanoseconds since the epoch, converted into a timestamp. Other fields are kept as they are.
+pub fn convert_trace_to_ingest_trace(trace: TraceEvent) -> LogEvent {
+    let (mut fields, metadata) = trace.into_parts();
+    if let Some(Value::Array(spans)) = fields.get_mut("spans") {
+        for span in spans.iter_mut() {
+            if let Value::Object(span) = span {
+                map_span(span);
+            }
+        }
+    }
+    LogEvent::from_map(fields, metadata)
+}
+
+fn map_span(span: &mut ObjectMap) {
+    let mut mapped = ObjectMap::new();
+    for (from, to) in SPAN_FIELDS {
+        if let Some(value) = span.remove(from) {
+            mapped.insert(to.into(), value);
+        }
+    }
+    if let Some(start) = span.remove("start") {
+        let start = match start {
+            Value::Integer(nanos) => Value::Timestamp(Utc.timestamp_nanos(nanos)),
+            start => start,
+        };
+        mapped.insert("start_time".into(), start);
+    }
+    span.extend(mapped);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_span_fields() {
+        let mut span = ObjectMap::new();
+        span.insert("trace_id".into(), Value::from(1));
+        span.insert("span_id".into(), Value::from(2));
+        span.insert("parent_id".into(), Value::from(0));
+        span.insert("service".into(), Value::from("checkout"));
+        span.insert("start".into(), Value::from(1_700_000_000_000_000_000_i64));
+        span.insert("duration".into(), Value::from(5_000));
+        span.insert("custom".into(), Value::from("kept"));
+        let mut fields = ObjectMap::new();
+        fields.insert("spans".into(), Value::Array(vec![Value::Object(span)]));
+        fields.insert("env".into(), Value::from("prod"));
+
+        let log = convert_trace_to_ingest_trace(TraceEvent::from_parts(fields, Default::default()));
+        assert_eq!(log.get("env"), Some(&Value::from("prod")));
+        assert_eq!(log.get("spans[0].trace_id"), Some(&Value::from(1)));
+        assert_eq!(log.get("spans[0].parent_span_id"), Some(&Value::from(0)));
+        assert_eq!(
+            log.get("spans[0].service_name"),
+            Some(&Value::from("checkout"))
+        );
+        assert_eq!(log.get("spans[0].duration_ns"), Some(&Value::from(5_000)));
+        assert_eq!(log.get("spans[0].custom"), Some(&Value::from("kept")));
+        assert_eq!(
+            log.get("spans[0].start_time"),
+            Some(&Value::Timestamp(
+                Utc.timestamp_nanos(1_700_000_000_000_000_000)
+            ))
+        );
+        assert!(log.get("spans[0].service").is_none());
+        assert!(log.get("spans[0].start").is_none());
+    }
+
+    #[test]
+    fn renders_indexes() {
+        let config = TraceConfig {
+            index: Template::try_from("traces-{{ service }}").unwrap(),
+        };
+        let mut trace = LogEvent::default();
+        trace.insert("service", "checkout");
+        assert_eq!(config.index(&trace).as_deref(), Some("traces-checkout"));
+        assert_eq!(config.index(&LogEvent::default()), None);
+    }
+}
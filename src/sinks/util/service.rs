@@ -1,6 +1,6 @@
 use std::{hash::Hash, marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
 
-use futures_util::stream::{self, BoxStream};
+use futures_util::stream::{self, BoxStream, StreamExt};
 use serde_with::serde_as;
 use tower::{
     balance::p2c::Balance,
@@ -325,35 +325,75 @@ impl TowerRequestSettings {
         S::Error: Into<crate::Error> + Send + Sync + 'static,
         S::Response: Send,
         S::Future: Send + 'static,
+    {
+        let services = services
+            .into_iter()
+            .enumerate()
+            .map(|(i, service)| Change::Insert(i, service))
+            .collect::<Vec<_>>();
+        self.discovered_service(
+            retry_logic,
+            Box::pin(stream::iter(services)),
+            health_config,
+            health_logic,
+            buffer_bound,
+        )
+    }
+
+    /// Distributes requests to the services [(Endpoint, service)] that `changes` inserts, until
+    /// it removes them.
+    ///
+    /// The services are only ever those given by `changes`, so the stream should stay pending
+    /// once it has no more changes, rather than end, for services to be added later on.
+    pub fn discovered_service<Req, RL, HL, S, K>(
+        self,
+        retry_logic: RL,
+        changes: BoxStream<'static, Change<K, (String, S)>>,
+        health_config: HealthConfig,
+        health_logic: HL,
+        buffer_bound: usize,
+    ) -> DistributedService<S, RL, HL, K, Req>
+    where
+        Req: Clone + Send + 'static,
+        RL: RetryLogic<Response = S::Response>,
+        HL: HealthLogic<Response = S::Response, Error = crate::Error>,
+        S: Service<Req> + Clone + Send + 'static,
+        S::Error: Into<crate::Error> + Send + Sync + 'static,
+        S::Response: Send,
+        S::Future: Send + 'static,
+        K: Hash + Eq + Clone + Send + 'static,
     {
         let policy = self.retry_policy(retry_logic.clone());
 
         // Build services
         let open = OpenGauge::new();
-        let services = services
-            .into_iter()
-            .map(|(endpoint, inner)| {
-                // Build individual service
-                ServiceBuilder::new()
-                    .layer(AdaptiveConcurrencyLimitLayer::new(
-                        self.concurrency,
-                        self.adaptive_concurrency,
-                        retry_logic.clone(),
-                    ))
-                    .service(
-                        health_config.build(
-                            health_logic.clone(),
-                            ServiceBuilder::new().timeout(self.timeout).service(inner),
-                            open.clone(),
-                            endpoint,
-                        ), // NOTE: there is a version conflict for crate `tracing` between `tracing_tower` crate
-                           // and Vector. Once that is resolved, this can be used instead of passing endpoint everywhere.
-                           // .trace_service(|_| info_span!("endpoint", %endpoint)),
-                    )
+        let (concurrency, adaptive_concurrency, timeout) =
+            (self.concurrency, self.adaptive_concurrency, self.timeout);
+        let services = changes.map(move |change| {
+            Ok(match change {
+                Change::Insert(key, (endpoint, inner)) => {
+                    // Build individual service
+                    let service = ServiceBuilder::new()
+                        .layer(AdaptiveConcurrencyLimitLayer::new(
+                            concurrency,
+                            adaptive_concurrency,
+                            retry_logic.clone(),
+                        ))
+                        .service(
+                            health_config.build(
+                                health_logic.clone(),
+                                ServiceBuilder::new().timeout(timeout).service(inner),
+                                open.clone(),
+                                endpoint,
+                            ), // NOTE: there is a version conflict for crate `tracing` between `tracing_tower` crate
+                               // and Vector. Once that is resolved, this can be used instead of passing endpoint everywhere.
+                               // .trace_service(|_| info_span!("endpoint", %endpoint)),
+                        );
+                    Change::Insert(key, service)
+                }
+                Change::Remove(key) => Change::Remove(key),
             })
-            .enumerate()
-            .map(|(i, service)| Ok(Change::Insert(i, service)))
-            .collect::<Vec<_>>();
+        });
 
         // Build sink service
         ServiceBuilder::new()
@@ -361,7 +401,7 @@ impl TowerRequestSettings {
             .retry(policy)
             // [Balance] must be wrapped with a [BufferLayer] so that the overall service implements Clone.
             .layer(BufferLayer::new(buffer_bound))
-            .service(Balance::new(Box::pin(stream::iter(services)) as Pin<Box<_>>))
+            .service(Balance::new(Box::pin(services) as Pin<Box<_>>))
     }
 }
 
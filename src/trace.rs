@@ -338,6 +338,25 @@ where
 #[derive(Default, Debug)]
 struct SpanFields(HashMap<&'static str, Value>);
 
+/// Returns the span field `name`, such as `component_id`, of the current span or of its closest
+/// parent holding it.
+///
+/// Only the `component_*` fields are recorded, and only once tracing is initialized.
+pub fn current_component_field(name: &str) -> Option<Value> {
+    tracing::Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<tracing_subscriber::Registry>()?;
+            let span = registry.span(id)?;
+            let value = span.scope().find_map(|span| {
+                span.extensions()
+                    .get::<SpanFields>()
+                    .and_then(|fields| fields.0.get(name).cloned())
+            });
+            value
+        })
+        .flatten()
+}
+
 impl SpanFields {
     fn record(&mut self, field: &tracing_core::Field, value: impl Into<Value>) {
         let name = field.name();
@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    pin::Pin,
+    time::Duration,
+};
+
+use async_stream::stream;
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use vector_lib::config::LogNamespace;
+use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
+use vector_lib::EstimatedJsonEncodedSizeOf;
+use vrl::path::PathPrefix;
+
+use crate::{
+    config::{DataType, Input, OutputId, TransformConfig, TransformContext, TransformOutput},
+    event::{Event, LogEvent, ObjectMap, Value},
+    internal_events::TemplateRenderingError,
+    schema,
+    template::Template,
+    transforms::{TaskTransform, Transform},
+};
+
+/// Configuration for the `infino_summary` transform.
+#[configurable_component(transform(
+    "infino_summary",
+    "Summarize the ingest statistics of the events passing through a topology."
+))]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InfinoSummaryConfig {
+    /// The interval between summaries, in seconds.
+    ///
+    /// Each summary covers the events received since the previous one. Intervals without events
+    /// are not summarized.
+    #[serde(default = "default_interval_secs")]
+    #[configurable(metadata(docs::human_name = "Summary Interval"))]
+    pub interval_secs: u64,
+
+    /// The index events are counted for, as rendered by the `bulk.index` of the `infino` sink.
+    ///
+    /// Events for which the template fails to render are counted as errors, with the
+    /// `index_template_failed` reason.
+    #[serde(default = "default_index")]
+    #[configurable(metadata(docs::examples = "application-{{ application_id }}-%Y-%m-%d"))]
+    pub index: Template,
+
+    /// The field holding the reason events are errors, such as the field set by a route handling
+    /// failed events.
+    ///
+    /// Events holding the field are counted as errors, by its value, rather than as ingested.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "error.reason"))]
+    pub reason_field: Option<ConfigValuePath>,
+}
+
+const fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_index() -> Template {
+    Template::try_from("vector-%Y.%m.%d").expect("can't parse template")
+}
+
+impl Default for InfinoSummaryConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_interval_secs(),
+            index: default_index(),
+            reason_field: None,
+        }
+    }
+}
+
+impl_generate_config_from_default!(InfinoSummaryConfig);
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "infino_summary")]
+impl TransformConfig for InfinoSummaryConfig {
+    async fn build(&self, _context: &TransformContext) -> crate::Result<Transform> {
+        InfinoSummary::new(self).map(Transform::event_task)
+    }
+
+    fn input(&self) -> Input {
+        Input::new(DataType::Log | DataType::Metric)
+    }
+
+    fn outputs(
+        &self,
+        _: vector_lib::enrichment::TableRegistry,
+        _: &[(OutputId, schema::Definition)],
+        _: LogNamespace,
+    ) -> Vec<TransformOutput> {
+        vec![TransformOutput::new(DataType::Log, HashMap::new())]
+    }
+}
+
+/// The statistics of an interval.
+#[derive(Debug, Default)]
+struct Summary {
+    events: u64,
+    bytes: u64,
+    indexes: HashSet<String>,
+    errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug)]
+pub struct InfinoSummary {
+    interval: Duration,
+    index: Template,
+    reason_field: Option<ConfigValuePath>,
+    summary: Summary,
+}
+
+impl InfinoSummary {
+    pub fn new(config: &InfinoSummaryConfig) -> crate::Result<Self> {
+        if config.interval_secs == 0 {
+            return Err("`interval_secs` must be greater than zero".into());
+        }
+        Ok(Self {
+            interval: Duration::from_secs(config.interval_secs),
+            index: config.index.clone(),
+            reason_field: config.reason_field.clone(),
+            summary: Summary::default(),
+        })
+    }
+
+    fn reason(&self, event: &Event) -> Option<String> {
+        let field = self.reason_field.as_ref()?;
+        let Event::Log(log) = event else {
+            return None;
+        };
+        log.get((PathPrefix::Event, field))
+            .map(|reason| match reason {
+                Value::Bytes(reason) => String::from_utf8_lossy(reason).into_owned(),
+                reason => reason.to_string(),
+            })
+    }
+
+    fn record(&mut self, event: Event) {
+        if let Some(reason) = self.reason(&event) {
+            *self.summary.errors.entry(reason).or_default() += 1;
+            return;
+        }
+        match self.index.render_string(&event) {
+            Ok(index) => {
+                self.summary.events += 1;
+                self.summary.bytes += event.estimated_json_encoded_size_of().get() as u64;
+                self.summary.indexes.insert(index);
+            }
+            Err(error) => {
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("index"),
+                    drop_event: false,
+                });
+                *self
+                    .summary
+                    .errors
+                    .entry("index_template_failed".to_owned())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    fn flush_into(&mut self, output: &mut Vec<Event>) {
+        let summary = std::mem::take(&mut self.summary);
+        if summary.events == 0 && summary.errors.is_empty() {
+            return;
+        }
+
+        let errors = summary
+            .errors
+            .into_iter()
+            .map(|(reason, count)| (reason.into(), Value::from(count as i64)))
+            .collect::<ObjectMap>();
+        let mut log = LogEvent::default();
+        log.insert("timestamp", Utc::now());
+        log.insert("interval_secs", self.interval.as_secs() as i64);
+        log.insert("events", summary.events as i64);
+        log.insert("bytes", summary.bytes as i64);
+        log.insert("indexes", summary.indexes.len() as i64);
+        log.insert("errors", Value::Object(errors));
+        output.push(Event::Log(log));
+    }
+}
+
+impl TaskTransform<Event> for InfinoSummary {
+    fn transform(
+        mut self: Box<Self>,
+        mut input_rx: Pin<Box<dyn Stream<Item = Event> + Send>>,
+    ) -> Pin<Box<dyn Stream<Item = Event> + Send>>
+    where
+        Self: 'static,
+    {
+        let mut flush_stream = tokio::time::interval(self.interval);
+
+        Box::pin(stream! {
+            let mut output = Vec::new();
+            let mut done = false;
+            while !done {
+                tokio::select! {
+                    _ = flush_stream.tick() => {
+                        self.flush_into(&mut output);
+                    },
+                    maybe_event = input_rx.next() => {
+                        match maybe_event {
+                            None => {
+                                self.flush_into(&mut output);
+                                done = true;
+                            }
+                            Some(event) => self.record(event),
+                        }
+                    }
+                };
+                for event in output.drain(..) {
+                    yield event;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_config() {
+        crate::test_util::test_generate_config::<InfinoSummaryConfig>();
+    }
+
+    fn summarize(config: InfinoSummaryConfig, events: Vec<Event>) -> Option<LogEvent> {
+        let mut summary = InfinoSummary::new(&config).unwrap();
+        for event in events {
+            summary.record(event);
+        }
+        let mut output = Vec::new();
+        summary.flush_into(&mut output);
+        assert!(output.len() <= 1);
+        output.pop().map(Event::into_log)
+    }
+
+    fn log(fields: &[(&str, &str)]) -> Event {
+        let mut log = LogEvent::from("message");
+        for (field, value) in fields {
+            log.insert(*field, *value);
+        }
+        Event::Log(log)
+    }
+
+    #[test]
+    fn summarizes_events() {
+        let config = InfinoSummaryConfig {
+            index: Template::try_from("logs-{{ service }}").unwrap(),
+            reason_field: Some("error.reason".into()),
+            ..Default::default()
+        };
+        let summary = summarize(
+            config,
+            vec![
+                log(&[("service", "api")]),
+                log(&[("service", "api")]),
+                log(&[("service", "web")]),
+                log(&[]),
+                log(&[("service", "api"), ("error.reason", "mapping_conflict")]),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(summary["events"], Value::Integer(3));
+        assert_eq!(summary["indexes"], Value::Integer(2));
+        assert!(summary["bytes"].as_integer().unwrap() > 0);
+        assert_eq!(summary["errors.index_template_failed"], Value::Integer(1));
+        assert_eq!(summary["errors.mapping_conflict"], Value::Integer(1));
+    }
+
+    #[test]
+    fn skips_empty_intervals() {
+        assert!(summarize(InfinoSummaryConfig::default(), Vec::new()).is_none());
+    }
+
+    #[test]
+    fn rejects_zero_interval() {
+        let config = InfinoSummaryConfig {
+            interval_secs: 0,
+            ..Default::default()
+        };
+        assert!(InfinoSummary::new(&config).is_err());
+    }
+}